@@ -1,11 +1,19 @@
+use core::fmt;
+
+use group::GroupEncoding;
 use memuse::DynamicUsage;
+use pasta_curves::pallas;
 
 use crate::{
     note::{ExtractedNoteCommitment, Nullifier, Rho, TransmittedNoteCiphertext},
+    note_encryption_v3::ENC_CIPHERTEXT_SIZE_V3,
     primitives::redpallas::{self, SpendAuth},
     value::ValueCommitment,
 };
 
+/// The size of the outgoing ciphertext of an action.
+pub(crate) const OUT_CIPHERTEXT_SIZE: usize = 80;
+
 /// An action applied to the global ledger.
 ///
 /// This both creates a note (adding a commitment to the global ledger), and consumes
@@ -46,6 +54,57 @@ impl<T> Action<T> {
         }
     }
 
+    /// Constructs an `Action` from the encodings of its constituent parts, checking that
+    /// each of them is valid.
+    ///
+    /// The value commitment, randomized validating key and ephemeral key must be
+    /// encodings of Pallas points, the nullifier and note commitment must be canonical
+    /// encodings of field elements, and the ciphertexts must have the sizes of
+    /// [`OrchardDomainV3`] ciphertexts.
+    ///
+    /// [`OrchardDomainV3`]: crate::note_encryption_v3::OrchardDomainV3
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_from_parts(
+        nf: &[u8; 32],
+        rk: &[u8; 32],
+        cmx: &[u8; 32],
+        epk_bytes: &[u8; 32],
+        enc_ciphertext: &[u8],
+        out_ciphertext: &[u8],
+        cv_net: &[u8; 32],
+        authorization: T,
+    ) -> Result<Self, ActionError> {
+        let nf = Option::from(Nullifier::from_bytes(nf)).ok_or(ActionError::InvalidNullifier)?;
+        let rk = redpallas::VerificationKey::try_from(*rk)
+            .map_err(|_| ActionError::InvalidRandomizedKey)?;
+        let cmx = Option::from(ExtractedNoteCommitment::from_bytes(cmx))
+            .ok_or(ActionError::InvalidNoteCommitment)?;
+        if bool::from(pallas::Affine::from_bytes(epk_bytes).is_none()) {
+            return Err(ActionError::InvalidEphemeralKey);
+        }
+        let enc_ciphertext = enc_ciphertext
+            .try_into()
+            .map_err(|_| ActionError::EncCiphertextSize(enc_ciphertext.len()))?;
+        let out_ciphertext = out_ciphertext
+            .try_into()
+            .map_err(|_| ActionError::OutCiphertextSize(out_ciphertext.len()))?;
+        let cv_net = Option::from(ValueCommitment::from_bytes(cv_net))
+            .ok_or(ActionError::InvalidValueCommitment)?;
+
+        Ok(Action::from_parts(
+            nf,
+            rk,
+            cmx,
+            TransmittedNoteCiphertext {
+                epk_bytes: *epk_bytes,
+                enc_ciphertext,
+                out_ciphertext,
+            },
+            cv_net,
+            authorization,
+        ))
+    }
+
     /// Returns the nullifier of the note being spent.
     pub fn nullifier(&self) -> &Nullifier {
         &self.nf
@@ -66,6 +125,32 @@ impl<T> Action<T> {
         &self.encrypted_note
     }
 
+    /// Returns the encoding of the ephemeral public key of the new note.
+    ///
+    /// These are the bytes of the [`EphemeralKeyBytes`] of the note in
+    /// [`OrchardDomainV3`].
+    ///
+    /// [`EphemeralKeyBytes`]: zcash_note_encryption_zsa::EphemeralKeyBytes
+    /// [`OrchardDomainV3`]: crate::note_encryption_v3::OrchardDomainV3
+    pub fn epk_bytes(&self) -> &[u8; 32] {
+        &self.encrypted_note.epk_bytes
+    }
+
+    /// Returns the encrypted note ciphertext of the new note.
+    ///
+    /// These are the bytes of the [`NoteCiphertextBytes`] of the note.
+    ///
+    /// [`NoteCiphertextBytes`]: crate::note_encryption_v3::NoteCiphertextBytes
+    pub fn enc_ciphertext_bytes(&self) -> &[u8; 612] {
+        &self.encrypted_note.enc_ciphertext
+    }
+
+    /// Returns the ciphertext from which the holder of the outgoing viewing key of the
+    /// new note can recover it.
+    pub fn out_ciphertext_bytes(&self) -> &[u8; 80] {
+        &self.encrypted_note.out_ciphertext
+    }
+
     /// Obtains the [`Rho`] value that was used to construct the new note being created.
     pub fn rho(&self) -> Rho {
         Rho::from_nf_old(self.nf)
@@ -106,6 +191,52 @@ impl<T> Action<T> {
     }
 }
 
+/// An error returned by [`Action::try_from_parts`] when a part of an action is invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionError {
+    /// The nullifier is not a canonical encoding of a field element.
+    InvalidNullifier,
+    /// The randomized validating key is not a valid encoding of a point.
+    InvalidRandomizedKey,
+    /// The note commitment is not a canonical encoding of a field element.
+    InvalidNoteCommitment,
+    /// The ephemeral key is not a valid encoding of a point.
+    InvalidEphemeralKey,
+    /// The encrypted note ciphertext has the contained size instead of
+    /// [`ENC_CIPHERTEXT_SIZE_V3`].
+    EncCiphertextSize(usize),
+    /// The outgoing ciphertext has the contained size instead of 80 bytes.
+    OutCiphertextSize(usize),
+    /// The value commitment is not a valid encoding of a point.
+    InvalidValueCommitment,
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionError::InvalidNullifier => f.write_str("Invalid nullifier encoding"),
+            ActionError::InvalidRandomizedKey => {
+                f.write_str("Invalid randomized validating key encoding")
+            }
+            ActionError::InvalidNoteCommitment => f.write_str("Invalid note commitment encoding"),
+            ActionError::InvalidEphemeralKey => f.write_str("Invalid ephemeral key encoding"),
+            ActionError::EncCiphertextSize(size) => write!(
+                f,
+                "Encrypted note ciphertext has {} bytes instead of {}",
+                size, ENC_CIPHERTEXT_SIZE_V3
+            ),
+            ActionError::OutCiphertextSize(size) => write!(
+                f,
+                "Outgoing ciphertext has {} bytes instead of {}",
+                size, OUT_CIPHERTEXT_SIZE
+            ),
+            ActionError::InvalidValueCommitment => f.write_str("Invalid value commitment encoding"),
+        }
+    }
+}
+
+impl std::error::Error for ActionError {}
+
 impl DynamicUsage for Action<redpallas::Signature<SpendAuth>> {
     #[inline(always)]
     fn dynamic_usage(&self) -> usize {
@@ -210,3 +341,46 @@ pub(crate) mod testing {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{testing::arb_action, Action, ActionError};
+    use crate::value::NoteValue;
+
+    proptest! {
+        #[test]
+        fn action_from_encoded_parts(
+            action in arb_action(NoteValue::from_raw(10), NoteValue::from_raw(5))
+        ) {
+            let (epk, enc, out) = action.encrypted_note().as_parts();
+            let parts = |nf: &[u8; 32], enc: &[u8]| {
+                Action::try_from_parts(
+                    nf,
+                    &<[u8; 32]>::from(action.rk()),
+                    &action.cmx().to_bytes(),
+                    epk,
+                    enc,
+                    out,
+                    &action.cv_net().to_bytes(),
+                    (),
+                )
+            };
+
+            let parsed = parts(&action.nullifier().to_bytes(), enc).unwrap();
+            prop_assert_eq!(parsed.nullifier(), action.nullifier());
+            prop_assert_eq!(parsed.cmx(), action.cmx());
+            prop_assert_eq!(parsed.enc_ciphertext_bytes(), action.enc_ciphertext_bytes());
+
+            prop_assert_eq!(
+                parts(&[0xff; 32], enc).unwrap_err(),
+                ActionError::InvalidNullifier
+            );
+            prop_assert_eq!(
+                parts(&action.nullifier().to_bytes(), &enc[1..]).unwrap_err(),
+                ActionError::EncCiphertextSize(611)
+            );
+        }
+    }
+}
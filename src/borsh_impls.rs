@@ -0,0 +1,115 @@
+//! Borsh encodings of the public value types.
+//!
+//! The encodings match the consensus encodings of the types: Borsh is only used for the
+//! framing of composite values such as burn entries, which are encoded as
+//! `(AssetBase, u64)` tuples as burnt amounts are non-negative. Decoding enforces the same canonicity rules as the
+//! `from_bytes` constructors of the types.
+
+use std::io;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::{
+    bundle::Flags,
+    note::{AssetBase, ExtractedNoteCommitment, Note, Nullifier, NOTE_STORAGE_SIZE},
+};
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_array<R: io::Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+macro_rules! impl_borsh_32_bytes {
+    ($ty:ty, $name:expr) => {
+        impl BorshSerialize for $ty {
+            fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+                writer.write_all(&self.to_bytes())
+            }
+        }
+
+        impl BorshDeserialize for $ty {
+            fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+                Option::from(<$ty>::from_bytes(&read_array(reader)?))
+                    .ok_or_else(|| invalid_data(concat!("Invalid ", $name)))
+            }
+        }
+    };
+}
+
+impl_borsh_32_bytes!(AssetBase, "asset base");
+impl_borsh_32_bytes!(Nullifier, "nullifier");
+impl_borsh_32_bytes!(ExtractedNoteCommitment, "note commitment");
+
+impl BorshSerialize for Flags {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[self.to_byte()])
+    }
+}
+
+impl BorshDeserialize for Flags {
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let [byte] = read_array(reader)?;
+        Flags::from_byte(byte).ok_or_else(|| invalid_data("Invalid flags"))
+    }
+}
+
+/// Notes have no consensus encoding, and use their storage encoding instead.
+impl BorshSerialize for Note {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_storage_bytes())
+    }
+}
+
+impl BorshDeserialize for Note {
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let bytes: [u8; NOTE_STORAGE_SIZE] = read_array(reader)?;
+        Note::from_storage_bytes(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use proptest::prelude::*;
+
+    use crate::{
+        bundle::{testing::arb_flags, Flags},
+        note::{
+            asset_base::testing::arb_asset_base, nullifier::testing::arb_nullifier,
+            testing::arb_note, AssetBase, Note, Nullifier,
+        },
+        value::testing::arb_note_value,
+    };
+
+    proptest! {
+        #[test]
+        fn borsh_round_trip(
+            asset in arb_asset_base(),
+            value in prop::num::u64::ANY,
+            nf in arb_nullifier(),
+            flags in arb_flags(),
+            note in arb_note_value().prop_flat_map(arb_note),
+        ) {
+            let burn = (asset, value).try_to_vec().unwrap();
+            prop_assert_eq!(&burn[..32], &asset.to_bytes()[..]);
+            prop_assert_eq!(&burn[32..], &value.to_le_bytes()[..]);
+            prop_assert_eq!(<(AssetBase, u64)>::try_from_slice(&burn).unwrap(), (asset, value));
+
+            let encoded = nf.try_to_vec().unwrap();
+            prop_assert_eq!(&encoded[..], &nf.to_bytes()[..]);
+            prop_assert_eq!(Nullifier::try_from_slice(&encoded).unwrap(), nf);
+
+            let encoded = flags.try_to_vec().unwrap();
+            prop_assert_eq!(encoded, vec![flags.to_byte()]);
+            prop_assert!(Flags::try_from_slice(&[0xff]).is_err());
+
+            let encoded = note.try_to_vec().unwrap();
+            prop_assert_eq!(Note::try_from_slice(&encoded).unwrap(), note);
+        }
+    }
+}
@@ -2,31 +2,61 @@
 
 use core::fmt;
 use core::iter;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Display;
 
-use ff::Field;
+use blake2b_simd::Params;
+use ff::{Field, PrimeField};
 use nonempty::NonEmpty;
 use pasta_curves::pallas;
-use rand::{prelude::SliceRandom, CryptoRng, RngCore};
+use rand::{prelude::SliceRandom, CryptoRng, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 
 use crate::{
     action::Action,
     address::Address,
-    bundle::{derive_bvk, Authorization, Authorized, Bundle, Flags},
-    circuit::{Circuit, Instance, Proof, ProvingKey},
+    bundle::{
+        commitments::hash_bundle_auth_data_from_parts, derive_bvk, Authorization, Authorized,
+        Bundle, BundleAuthorizingCommitment, Flags, ValueOpening, ValueOpenings,
+    },
+    circuit::{Circuit, Instance, Proof, ProverOptions, ProvingKey},
     keys::{
-        FullViewingKey, OutgoingViewingKey, Scope, SpendAuthorizingKey, SpendValidatingKey,
-        SpendingKey,
+        FullViewingKey, NetworkType, OutgoingViewingKey, Scope, SpendAuthorizingKey,
+        SpendValidatingKey, SpendingKey, ViewingKeyEncodingError,
     },
-    note::{AssetBase, Note, Rho, TransmittedNoteCiphertext},
+    note::{AssetBase, Note, RandomSeed, Rho, TransmittedNoteCiphertext},
     note_encryption_v3::OrchardNoteEncryption,
     primitives::redpallas::{self, Binding, SpendAuth},
+    swap_bundle::{ActionGroup, ActionGroupAuthorized},
     tree::{Anchor, MerklePath},
     value::{self, NoteValue, OverflowError, ValueCommitTrapdoor, ValueCommitment, ValueSum},
 };
 
-const MIN_ACTIONS: usize = 2;
+mod audit;
+pub use audit::{ActionRandomness, AuditError, AuditTrail, AuditingRng, EncryptedAuditLog};
+
+mod background;
+pub use background::ProofHandle;
+
+mod batch_payer;
+pub use batch_payer::{
+    BatchPayError, BatchPayReport, BatchPayer, BundleReport, NoteSelector, SelectedNote,
+};
+
+mod payment_request;
+pub use payment_request::{PaymentItemError, PaymentRequestItem};
+
+mod planner;
+pub use planner::{BundlePlanner, PlanMetadata};
+
+mod redaction;
+pub use redaction::{RedactedBundleMetadata, RedactionPolicy};
+
+pub(crate) const MIN_ACTIONS: usize = 2;
+
+/// The personalization of the seed of the generator used by
+/// [`Builder::build_deterministic`].
+const BUILDER_PRG_PERSONALIZATION: &[u8; 16] = b"Zcash_OrchBldPRG";
 
 /// An enumeration of rules for Orchard bundle construction.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -117,60 +147,259 @@ impl BundleType {
 /// An error type for the kinds of errors that can occur during bundle construction.
 #[derive(Debug)]
 pub enum BuildError {
-    /// Spends are disabled for the provided bundle type.
+    /// Spends were requested, but are disabled for the requested bundle type.
     SpendsDisabled,
-    /// Spends are disabled for the provided bundle type.
+    /// Outputs were requested, but are disabled for the requested bundle type.
     OutputsDisabled,
-    /// The anchor provided to this builder doesn't match the Merkle path used to add a spend.
-    AnchorMismatch,
-    /// A bundle could not be built because required signatures were missing.
-    MissingSignatures,
+    /// The spend at the given index could not be included in the bundle.
+    Spend {
+        /// The index of the spend, in the order the spends were added.
+        index: usize,
+        /// The reason the spend could not be included.
+        error: SpendError,
+    },
+    /// The output at the given index could not be included in the bundle.
+    Output {
+        /// The index of the output, in the order the outputs were added.
+        index: usize,
+        /// The reason the output could not be included.
+        error: OutputError,
+    },
+    /// The pre-built action at the given index could not be included in the bundle.
+    PrebuiltAction {
+        /// The index of the pre-built action, in the order the actions were added.
+        index: usize,
+        /// The reason the action could not be included.
+        error: PrebuiltActionError,
+    },
     /// An error occurred in the process of producing a proof for a bundle.
-    Proof(halo2_proofs::plonk::Error),
-    /// An overflow error occurred while attempting to construct the value
-    /// for a bundle.
-    ValueSum(value::OverflowError),
-    /// External signature is not valid.
-    InvalidExternalSignature,
-    /// A signature is valid for more than one input. This should never happen if `alpha`
-    /// is sampled correctly, and indicates a critical failure in randomness generation.
-    DuplicateSignature,
+    Proof(ProofError),
+    /// The value balance or the burns of the bundle could not be computed.
+    Balance(BalanceError),
+    /// The bundle could not be authorized.
+    Signature(SignatureError),
     /// The bundle being constructed violated the construction rules for the requested bundle type.
     BundleTypeNotSatisfiable,
+    /// The spends or outputs of the given asset need more actions than fit in one bundle.
+    AssetGroupTooLarge(AssetBase),
+    /// The bundle would exceed a limit of the builder's [`BuilderConfig`].
+    Limit(LimitError),
 }
 
 impl Display for BuildError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use BuildError::*;
         match self {
-            MissingSignatures => f.write_str("Required signatures were missing during build"),
-            Proof(e) => f.write_str(&format!("Could not create proof: {}", e)),
-            ValueSum(_) => f.write_str("Overflow occurred during value construction"),
-            InvalidExternalSignature => f.write_str("External signature was invalid"),
-            DuplicateSignature => f.write_str("Signature valid for more than one input"),
+            SpendsDisabled => f.write_str("Spends are not enabled for the requested bundle type."),
+            OutputsDisabled => {
+                f.write_str("Outputs are not enabled for the requested bundle type.")
+            }
+            Spend { index, error } => write!(f, "Spend {} is invalid: {}", index, error),
+            Output { index, error } => write!(f, "Output {} is invalid: {}", index, error),
+            PrebuiltAction { index, error } => {
+                write!(f, "Pre-built action {} is invalid: {}", index, error)
+            }
+            Proof(e) => e.fmt(f),
+            Balance(e) => e.fmt(f),
+            Signature(e) => e.fmt(f),
             BundleTypeNotSatisfiable => {
                 f.write_str("Bundle structure did not conform to requested bundle type.")
             }
-            SpendsDisabled => f.write_str("Spends are not enabled for the requested bundle type."),
-            OutputsDisabled => f.write_str("Spends are not enabled for the requested bundle type."),
-            AnchorMismatch => {
-                f.write_str("All spends must share the anchor requested for the transaction.")
+            AssetGroupTooLarge(_) => {
+                f.write_str("The actions for an asset do not fit in a single bundle.")
             }
+            Limit(e) => e.fmt(f),
         }
     }
 }
 
-impl std::error::Error for BuildError {}
+impl std::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BuildError::Spend { error, .. } => Some(error),
+            BuildError::Output { error, .. } => Some(error),
+            BuildError::PrebuiltAction { error, .. } => Some(error),
+            BuildError::Proof(e) => Some(e),
+            BuildError::Balance(e) => Some(e),
+            BuildError::Signature(e) => Some(e),
+            BuildError::Limit(e) => Some(e),
+            BuildError::SpendsDisabled
+            | BuildError::OutputsDisabled
+            | BuildError::BundleTypeNotSatisfiable
+            | BuildError::AssetGroupTooLarge(_) => None,
+        }
+    }
+}
 
 impl From<halo2_proofs::plonk::Error> for BuildError {
     fn from(e: halo2_proofs::plonk::Error) -> Self {
-        BuildError::Proof(e)
+        BuildError::Proof(ProofError::Prover(e))
     }
 }
 
 impl From<value::OverflowError> for BuildError {
     fn from(e: value::OverflowError) -> Self {
-        BuildError::ValueSum(e)
+        BuildError::Balance(BalanceError::Overflow(e))
+    }
+}
+
+/// An error type for producing the proof of a bundle.
+#[derive(Debug)]
+pub enum ProofError {
+    /// The prover failed to create the proof.
+    Prover(halo2_proofs::plonk::Error),
+    /// Creating the proof is estimated to need more memory than the
+    /// [`ProverOptions::max_memory_hint`].
+    WouldExceedBudget {
+        /// The estimated memory needed to create the proof, in bytes.
+        required: usize,
+        /// The memory budget of the prover, in bytes.
+        budget: usize,
+    },
+}
+
+impl Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofError::Prover(e) => write!(f, "Could not create proof: {}", e),
+            ProofError::WouldExceedBudget { required, budget } => write!(
+                f,
+                "Creating the proof needs about {} bytes, over the budget of {} bytes",
+                required, budget
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ProofError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProofError::Prover(e) => Some(e),
+            ProofError::WouldExceedBudget { .. } => None,
+        }
+    }
+}
+
+/// An error type for computing the value balance and the burns of a bundle.
+#[derive(Debug)]
+pub enum BalanceError {
+    /// An overflow occurred while summing the values of the bundle.
+    Overflow(value::OverflowError),
+    /// The value balance of the bundle is out of range for the value balance type.
+    ValueBalanceOutOfRange,
+    /// The value burned for the given asset is out of range for the value balance type.
+    BurnOutOfRange(AssetBase),
+}
+
+impl Display for BalanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BalanceError::Overflow(_) => f.write_str("Overflow occurred during value construction"),
+            BalanceError::ValueBalanceOutOfRange => f.write_str("Value balance is out of range"),
+            BalanceError::BurnOutOfRange(_) => f.write_str("Burned value is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for BalanceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BalanceError::Overflow(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// An error type for authorizing a bundle.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The action at the given index is missing its spend authorization signature.
+    Missing(usize),
+    /// An external signature is not valid for any unsigned action.
+    Invalid,
+    /// A signature is valid for more than one input. This should never happen if `alpha`
+    /// is sampled correctly, and indicates a critical failure in randomness generation.
+    Duplicate,
+    /// The bundle does not contain an action at the given index.
+    ActionIndexOutOfBounds(usize),
+    /// The action at the given index has already been signed.
+    ActionAlreadySigned(usize),
+}
+
+impl Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::Missing(index) => {
+                write!(f, "The signature for action {} is missing", index)
+            }
+            SignatureError::Invalid => f.write_str("External signature was invalid"),
+            SignatureError::Duplicate => f.write_str("Signature valid for more than one input"),
+            SignatureError::ActionIndexOutOfBounds(index) => {
+                write!(f, "There is no action at index {}", index)
+            }
+            SignatureError::ActionAlreadySigned(index) => {
+                write!(f, "The action at index {} is already signed", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+/// An error type for adding a pre-built action to the builder.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PrebuiltActionError {
+    /// The spend of the action is invalid.
+    Spend(SpendError),
+    /// The output of the action is invalid.
+    Output(OutputError),
+    /// The spent note and the output of the action have different asset types.
+    AssetMismatch,
+    /// Adding the action would exceed a limit of the builder's [`BuilderConfig`].
+    Limit(LimitError),
+}
+
+impl Display for PrebuiltActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrebuiltActionError::Spend(e) => e.fmt(f),
+            PrebuiltActionError::Output(e) => e.fmt(f),
+            PrebuiltActionError::AssetMismatch => {
+                f.write_str("The spend and output of a pre-built action must have the same asset.")
+            }
+            PrebuiltActionError::Limit(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PrebuiltActionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PrebuiltActionError::Spend(e) => Some(e),
+            PrebuiltActionError::Output(e) => Some(e),
+            PrebuiltActionError::Limit(e) => Some(e),
+            PrebuiltActionError::AssetMismatch => None,
+        }
+    }
+}
+
+/// Checks that a pre-built action can be included in a bundle with the given flags and
+/// anchor.
+fn check_prebuilt_action(
+    action: &ActionInfo,
+    flags: Flags,
+    anchor: &Anchor,
+) -> Result<(), PrebuiltActionError> {
+    if !flags.spends_enabled() && action.spend.dummy_sk.is_none() {
+        Err(PrebuiltActionError::Spend(SpendError::SpendsDisabled))
+    } else if !flags.outputs_enabled() && action.output.value != NoteValue::zero() {
+        Err(PrebuiltActionError::Output(OutputError::OutputsDisabled))
+    } else if !action.spend.has_matching_anchor(anchor) {
+        Err(PrebuiltActionError::Spend(SpendError::AnchorMismatch))
+    } else if action.spend.note.asset() != action.output.asset {
+        Err(PrebuiltActionError::AssetMismatch)
+    } else {
+        Ok(())
     }
 }
 
@@ -183,6 +412,8 @@ pub enum SpendError {
     AnchorMismatch,
     /// The full viewing key provided didn't match the note provided
     FvkMismatch,
+    /// Adding the spend would exceed a limit of the builder's [`BuilderConfig`].
+    Limit(LimitError),
 }
 
 impl Display for SpendError {
@@ -192,23 +423,221 @@ impl Display for SpendError {
             SpendsDisabled => "Spends are not enabled for this builder",
             AnchorMismatch => "All anchors must be equal.",
             FvkMismatch => "FullViewingKey does not correspond to the given note",
+            Limit(e) => return e.fmt(f),
+        })
+    }
+}
+
+impl std::error::Error for SpendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SpendError::Limit(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// An error type for adding a burn to the builder.
+#[derive(Debug, PartialEq, Eq)]
+pub enum BurnError {
+    /// Only non-native assets can be burnt.
+    NativeAsset,
+    /// A zero value cannot be burnt.
+    ZeroValue,
+    /// The total burnt value of the asset overflows.
+    Overflow,
+    /// Adding the burn would exceed a limit of the builder's [`BuilderConfig`].
+    Limit(LimitError),
+}
+
+impl Display for BurnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use BurnError::*;
+        f.write_str(match self {
+            NativeAsset => "Burning is only possible for non-native assets",
+            ZeroValue => "Burning is not possible for zero values",
+            Overflow => "Orchard ValueSum operation overflowed",
+            Limit(e) => return e.fmt(f),
         })
     }
 }
 
-impl std::error::Error for SpendError {}
+impl std::error::Error for BurnError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BurnError::Limit(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// An error indicating that a bundle would exceed one of the limits of its
+/// [`BuilderConfig`]. Each variant holds the limit that would be exceeded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitError {
+    /// The bundle would contain too many actions.
+    TooManyActions(usize),
+    /// The bundle would burn too many assets.
+    TooManyBurns(usize),
+    /// The bundle would involve too many distinct assets.
+    TooManyAssets(usize),
+}
+
+impl Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::TooManyActions(limit) => {
+                write!(f, "The bundle would contain more than {} actions", limit)
+            }
+            LimitError::TooManyBurns(limit) => {
+                write!(f, "The bundle would burn more than {} assets", limit)
+            }
+            LimitError::TooManyAssets(limit) => {
+                write!(f, "The bundle would involve more than {} assets", limit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+/// Limits on the contents of a bundle, enforced by the [`Builder`].
+///
+/// Chain rules may restrict bundles further than the Orchard protocol itself. Configuring
+/// the builder with those restrictions makes it fail as soon as a spend, output or burn
+/// would break them, instead of producing a bundle that cannot be relayed. A limit of
+/// `None` is not enforced.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BuilderConfig {
+    /// The maximum number of actions in the bundle, including padding.
+    pub max_actions: Option<usize>,
+    /// The maximum number of distinct assets burnt by the bundle.
+    pub max_burns: Option<usize>,
+    /// The maximum number of distinct assets spent or received in the bundle.
+    pub max_assets: Option<usize>,
+}
+
+impl BuilderConfig {
+    fn check(
+        &self,
+        num_actions: usize,
+        num_burns: usize,
+        num_assets: usize,
+    ) -> Result<(), LimitError> {
+        match (self.max_actions, self.max_burns, self.max_assets) {
+            (Some(limit), _, _) if num_actions > limit => Err(LimitError::TooManyActions(limit)),
+            (_, Some(limit), _) if num_burns > limit => Err(LimitError::TooManyBurns(limit)),
+            (_, _, Some(limit)) if num_assets > limit => Err(LimitError::TooManyAssets(limit)),
+            _ => Ok(()),
+        }
+    }
+}
 
-/// The only error that can occur here is if outputs are disabled for this builder.
+/// An error type for adding an output to the builder.
 #[derive(Debug, PartialEq, Eq)]
-pub struct OutputError;
+pub enum OutputError {
+    /// Outputs aren't enabled for this builder.
+    OutputsDisabled,
+    /// Adding the output would exceed a limit of the builder's [`BuilderConfig`].
+    Limit(LimitError),
+    /// The value of the output is below the minimum of the builder's [`DustPolicy`],
+    /// which is held by this variant.
+    DustOutput(NoteValue),
+    /// The encoded recipient is invalid, or is an address on another network than that
+    /// of the builder.
+    InvalidAddress(ViewingKeyEncodingError),
+}
 
 impl Display for OutputError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str("Outputs are not enabled for this builder")
+        match self {
+            OutputError::OutputsDisabled => f.write_str("Outputs are not enabled for this builder"),
+            OutputError::Limit(e) => e.fmt(f),
+            OutputError::DustOutput(minimum) => write!(
+                f,
+                "The output value is below the minimum of {}",
+                minimum.inner()
+            ),
+            OutputError::InvalidAddress(e) => write!(f, "Invalid recipient: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for OutputError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OutputError::Limit(e) => Some(e),
+            OutputError::InvalidAddress(e) => Some(e),
+            OutputError::OutputsDisabled | OutputError::DustOutput(_) => None,
+        }
+    }
+}
+
+/// The minimum values of the outputs of a bundle, enforced by the [`Builder`].
+///
+/// Relay policies may reject transactions with outputs of very low value. Configuring
+/// the builder with the same minimums makes it reject such outputs before the bundle is
+/// proven. The default policy accepts outputs of any value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DustPolicy {
+    /// The minimum value of an output of the native asset.
+    pub min_native_value: NoteValue,
+    /// The minimum value of an output of any other asset.
+    pub min_zsa_value: NoteValue,
+}
+
+impl DustPolicy {
+    /// Returns the minimum value of an output of `asset`.
+    fn minimum(&self, asset: AssetBase) -> NoteValue {
+        if bool::from(asset.is_native()) {
+            self.min_native_value
+        } else {
+            self.min_zsa_value
+        }
+    }
+
+    fn check(&self, value: NoteValue, asset: AssetBase) -> Result<(), OutputError> {
+        let minimum = self.minimum(asset);
+        if value.inner() < minimum.inner() {
+            Err(OutputError::DustOutput(minimum))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The result of comparing the root derived by the Merkle path of a spent note with the
+/// anchor of a builder, as returned by [`Builder::diagnose_spend_anchor`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AnchorDiagnostics {
+    expected: Anchor,
+    computed: Anchor,
+    position: u32,
+}
+
+impl AnchorDiagnostics {
+    /// Returns the anchor of the builder.
+    pub fn expected(&self) -> Anchor {
+        self.expected
+    }
+
+    /// Returns the root derived by the Merkle path for the note.
+    pub fn computed(&self) -> Anchor {
+        self.computed
+    }
+
+    /// Returns the position of the note according to the Merkle path.
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    /// Returns `true` if the Merkle path has the anchor of the builder.
+    pub fn is_match(&self) -> bool {
+        self.expected == self.computed
     }
 }
 
-impl std::error::Error for OutputError {}
+pub use crate::tree::WitnessSource;
 
 /// Information about a specific note to be spent in an [`Action`].
 #[derive(Debug, Clone)]
@@ -340,16 +769,91 @@ impl OutputInfo {
     }
 }
 
+/// A policy for splitting change into several notes of randomized denominations.
+///
+/// A single large change note is easy to tell apart from payments by its value. Splitting
+/// it into a random number of notes with random values reduces the linkability of change
+/// notes, at the cost of additional actions in the bundle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChangeSplitPolicy {
+    threshold: NoteValue,
+    max_notes: usize,
+}
+
+impl ChangeSplitPolicy {
+    /// Constructs a policy under which change with a value of at least `threshold` is
+    /// split into between 2 and `max_notes` notes.
+    ///
+    /// Returns `None` if `max_notes` is less than 2.
+    pub fn new(threshold: NoteValue, max_notes: usize) -> Option<Self> {
+        (max_notes >= 2).then_some(ChangeSplitPolicy {
+            threshold,
+            max_notes,
+        })
+    }
+
+    /// Returns the minimum value of change that will be split.
+    pub fn threshold(&self) -> NoteValue {
+        self.threshold
+    }
+
+    /// Returns the maximum number of notes a change output will be split into.
+    pub fn max_notes(&self) -> usize {
+        self.max_notes
+    }
+
+    /// Splits `value` into randomized non-zero denominations of at least `minimum`
+    /// according to this policy.
+    ///
+    /// If `value` cannot be split into two such notes, it is returned as is.
+    fn split(
+        &self,
+        value: NoteValue,
+        minimum: NoteValue,
+        rng: &mut impl RngCore,
+    ) -> Vec<NoteValue> {
+        // Each note must receive a non-zero value of at least `minimum`.
+        let unit = minimum.inner().max(1);
+        let max_notes = self
+            .max_notes
+            .min(usize::try_from(value.inner() / unit).unwrap_or(usize::MAX));
+        if value.inner() < self.threshold.inner() || max_notes < 2 {
+            return vec![value];
+        }
+
+        // Distribute the value in excess of the minimums of the notes at random.
+        let num_notes = rng.gen_range(2..=max_notes);
+        let excess = value.inner() - unit * num_notes as u64;
+        let mut cuts: Vec<_> = (1..num_notes).map(|_| rng.gen_range(0..=excess)).collect();
+        cuts.sort_unstable();
+
+        cuts.into_iter()
+            .chain(iter::once(excess))
+            .scan(0, |prev, cut| {
+                let part = cut - *prev;
+                *prev = cut;
+                Some(NoteValue::from_raw(unit + part))
+            })
+            .collect()
+    }
+}
+
 /// Information about a specific [`Action`] we plan to build.
 #[derive(Debug)]
-struct ActionInfo {
+pub struct ActionInfo {
     spend: SpendInfo,
     output: OutputInfo,
     rcv: ValueCommitTrapdoor,
 }
 
 impl ActionInfo {
-    fn new(spend: SpendInfo, output: OutputInfo, rng: impl RngCore) -> Self {
+    /// This constructor is public to enable creation of custom builders.
+    /// If you are not creating a custom builder, use [`Builder::add_spend`] and
+    /// [`Builder::add_output`] instead, and let the builder pair them up.
+    ///
+    /// Creates an `ActionInfo` from a spend and an output that must be placed in the
+    /// same [`Action`], sampling a fresh value commitment trapdoor.
+    pub fn new(spend: SpendInfo, output: OutputInfo, rng: impl RngCore) -> Self {
         ActionInfo {
             spend,
             output,
@@ -375,20 +879,22 @@ impl ActionInfo {
     ///
     /// [orchardsend]: https://zips.z.cash/protocol/nu5.pdf#orchardsend
     ///
+    /// `cv_net` must be the commitment to [`ActionInfo::value_sum`] under `self.rcv`.
+    ///
     /// # Panics
     ///
     /// Panics if the asset types of the spent and output notes do not match.
-    fn build(self, mut rng: impl RngCore) -> (Action<SigningMetadata>, Circuit) {
+    fn build(
+        self,
+        cv_net: ValueCommitment,
+        mut rng: impl RngCore,
+    ) -> (Action<SigningMetadata>, Circuit, RandomSeed) {
         assert_eq!(
             self.spend.note.asset(),
             self.output.asset,
             "spend and recipient note types must be equal"
         );
 
-        let v_net = self.value_sum();
-        let asset = self.output.asset;
-        let cv_net = ValueCommitment::derive(v_net, self.rcv, asset);
-
         let nf_old = self.spend.note.nullifier(&self.spend.fvk);
         let rho = Rho::from_nf_old(nf_old);
         let ak: SpendValidatingKey = self.spend.fvk.clone().into();
@@ -426,6 +932,7 @@ impl ActionInfo {
                 },
             ),
             Circuit::from_action_context_unchecked(self.spend, note, alpha, self.rcv),
+            *note.rseed(),
         )
     }
 }
@@ -441,23 +948,41 @@ pub type UnauthorizedBundle<V> = Bundle<InProgress<Unproven, Unauthorized>, V>;
 /// This includes information about how [`Action`]s within the bundle are ordered (after
 /// padding and randomization) relative to the order in which spends and outputs were
 /// provided (to [`bundle`]), or the order in which [`Builder`] mutations were performed.
+///
+/// This undoes the shuffling of the actions, so it should not be persisted as is. Use
+/// [`BundleMetadata::redact`] to keep only the parts that are needed after the bundle
+/// has been built.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BundleMetadata {
+    action_spends: Vec<ActionSpend>,
     spend_indices: Vec<usize>,
     output_indices: Vec<usize>,
+    prebuilt_indices: Vec<usize>,
+    change_indices: Vec<Vec<usize>>,
+    value_openings: Option<Vec<ValueOpening>>,
+    action_randomness: Option<Vec<ActionRandomness>>,
 }
 
 impl BundleMetadata {
-    fn new(num_requested_spends: usize, num_requested_outputs: usize) -> Self {
+    fn new(
+        num_requested_spends: usize,
+        num_requested_outputs: usize,
+        num_prebuilt_actions: usize,
+    ) -> Self {
         BundleMetadata {
+            action_spends: vec![],
             spend_indices: vec![0; num_requested_spends],
             output_indices: vec![0; num_requested_outputs],
+            prebuilt_indices: vec![0; num_prebuilt_actions],
+            change_indices: vec![],
+            value_openings: None,
+            action_randomness: None,
         }
     }
 
     /// Returns the metadata for a [`Bundle`] that contains only dummy actions, if any.
     pub fn empty() -> Self {
-        Self::new(0, 0)
+        Self::new(0, 0, 0)
     }
 
     /// Returns the index within the bundle of the [`Action`] corresponding to the `n`-th
@@ -483,6 +1008,76 @@ impl BundleMetadata {
     pub fn output_action_index(&self, n: usize) -> Option<usize> {
         self.output_indices.get(n).copied()
     }
+
+    /// Returns the index within the bundle of the [`Action`] corresponding to the `n`-th
+    /// pre-built action specified in bundle construction. If a [`Builder`] was used, this
+    /// refers to the action added by the `n`-th call to [`Builder::add_prebuilt_action`].
+    ///
+    /// Pre-built actions are inserted at random positions among the other actions.
+    pub fn prebuilt_action_index(&self, n: usize) -> Option<usize> {
+        self.prebuilt_indices.get(n).copied()
+    }
+
+    /// Returns the indices within the bundle of the [`Action`]s holding the notes that
+    /// the `n`-th change output was split into, where `n` counts the calls to
+    /// [`Builder::add_change_output`].
+    ///
+    /// If no [`ChangeSplitPolicy`] applied to the change output, the returned slice
+    /// contains a single index.
+    pub fn change_action_indices(&self, n: usize) -> Option<&[usize]> {
+        self.change_indices.get(n).map(|indices| &indices[..])
+    }
+
+    /// Returns the kind of note spent by the action at the given index within the bundle.
+    ///
+    /// Wallets should only treat the nullifiers of actions spending
+    /// [requested](ActionSpend::Requested) notes as spends of their notes.
+    pub fn action_spend(&self, action_idx: usize) -> Option<ActionSpend> {
+        self.action_spends.get(action_idx).copied()
+    }
+
+    /// Returns the indices within the bundle of the [`Action`]s spending split notes of
+    /// the `n`-th spend specified in bundle construction, in ascending order.
+    ///
+    /// The builder pads the actions of a non-native asset by splitting the first spent
+    /// note of that asset, so this is empty unless the `n`-th spend was split.
+    pub fn split_action_indices(&self, n: usize) -> Vec<usize> {
+        self.action_spends
+            .iter()
+            .enumerate()
+            .filter(|(_, spend)| **spend == ActionSpend::Split(n))
+            .map(|(action_idx, _)| action_idx)
+            .collect()
+    }
+
+    /// Returns the openings of the value commitments of all the actions of `bundle`,
+    /// bound to its commitment, if they were recorded when building it.
+    ///
+    /// See [`Builder::record_value_openings`].
+    pub fn value_openings<T: Authorization, V: Copy + Into<i64>>(
+        &self,
+        bundle: &Bundle<T, V>,
+    ) -> Option<ValueOpenings> {
+        self.value_openings.as_ref().map(|openings| {
+            ValueOpenings::new(bundle, openings.iter().copied().enumerate().collect())
+        })
+    }
+
+    /// Records the locations of the notes that change outputs were split into, given
+    /// the output indices of each part, and forgets the outputs that were not
+    /// requested by the caller.
+    fn record_change_parts(&mut self, change_parts: Vec<Vec<usize>>, num_requested_outputs: usize) {
+        self.change_indices = change_parts
+            .into_iter()
+            .map(|parts| {
+                parts
+                    .into_iter()
+                    .map(|output_idx| self.output_indices[output_idx])
+                    .collect()
+            })
+            .collect();
+        self.output_indices.truncate(num_requested_outputs);
+    }
 }
 
 /// A builder that constructs a [`Bundle`] from a set of notes to be spent, and outputs
@@ -491,21 +1086,73 @@ impl BundleMetadata {
 pub struct Builder {
     spends: Vec<SpendInfo>,
     outputs: Vec<OutputInfo>,
+    prebuilt_actions: Vec<ActionInfo>,
+    change_outputs: Vec<usize>,
+    change_split_policies: HashMap<AssetBase, ChangeSplitPolicy>,
     burn: HashMap<AssetBase, ValueSum>,
     bundle_type: BundleType,
     anchor: Anchor,
+    config: BuilderConfig,
+    dust_policy: DustPolicy,
+    network: Option<NetworkType>,
+    record_value_openings: bool,
 }
 
 impl Builder {
     /// Constructs a new empty builder for an Orchard bundle.
     pub fn new(bundle_type: BundleType, anchor: Anchor) -> Self {
+        Self::with_config(bundle_type, anchor, BuilderConfig::default())
+    }
+
+    /// Constructs a new empty builder for an Orchard bundle, which enforces the limits of
+    /// the given [`BuilderConfig`].
+    pub fn with_config(bundle_type: BundleType, anchor: Anchor, config: BuilderConfig) -> Self {
         Builder {
             spends: vec![],
             outputs: vec![],
+            prebuilt_actions: vec![],
+            change_outputs: vec![],
+            change_split_policies: HashMap::new(),
             burn: HashMap::new(),
             bundle_type,
             anchor,
+            config,
+            dust_policy: DustPolicy::default(),
+            network: None,
+            record_value_openings: false,
+        }
+    }
+
+    /// Checks the limits of the [`BuilderConfig`] against a bundle spending notes of the
+    /// `spent` assets and creating outputs of the `received` assets, in addition to the
+    /// pre-built actions and burns of this builder.
+    fn check_limits(
+        &self,
+        spent: impl Iterator<Item = AssetBase>,
+        received: impl Iterator<Item = AssetBase>,
+    ) -> Result<(), LimitError> {
+        let mut per_asset: HashMap<AssetBase, (usize, usize)> = HashMap::new();
+        for asset in spent {
+            per_asset.entry(asset).or_default().0 += 1;
+        }
+        for asset in received {
+            per_asset.entry(asset).or_default().1 += 1;
         }
+
+        let num_actions = per_asset
+            .values()
+            .map(|(num_spends, num_outputs)| *num_spends.max(num_outputs))
+            .sum::<usize>()
+            + self.prebuilt_actions.len();
+
+        let num_assets = per_asset
+            .keys()
+            .chain(self.prebuilt_actions.iter().map(|a| &a.output.asset))
+            .collect::<HashSet<_>>()
+            .len();
+
+        self.config
+            .check(num_actions.max(MIN_ACTIONS), self.burn.len(), num_assets)
     }
 
     /// Adds a note to be spent in this transaction.
@@ -531,6 +1178,15 @@ impl Builder {
             return Err(SpendError::SpendsDisabled);
         }
 
+        self.check_limits(
+            self.spends
+                .iter()
+                .map(|s| s.note.asset())
+                .chain(iter::once(note.asset())),
+            self.outputs.iter().map(|o| o.asset),
+        )
+        .map_err(SpendError::Limit)?;
+
         let spend = SpendInfo::new(fvk, note, merkle_path, false).ok_or(SpendError::FvkMismatch)?;
 
         // Consistency check: all anchors must be equal.
@@ -543,6 +1199,42 @@ impl Builder {
         Ok(())
     }
 
+    /// Compares the anchor of this builder with the root that `merkle_path` derives for
+    /// `note`, to diagnose [`SpendError::AnchorMismatch`] errors such as stale witnesses.
+    pub fn diagnose_spend_anchor(
+        &self,
+        note: &Note,
+        merkle_path: &MerklePath,
+    ) -> AnchorDiagnostics {
+        AnchorDiagnostics {
+            expected: self.anchor,
+            computed: merkle_path.root(note.commitment().into()),
+            position: merkle_path.position(),
+        }
+    }
+
+    /// Adds a note to be spent in this transaction, as in [`Builder::add_spend`].
+    ///
+    /// If `merkle_path` does not have the anchor of this builder, for example because the
+    /// wallet's witness is older than the anchor, a witness for the same position is
+    /// requested from `witnesses` and used instead.
+    pub fn add_spend_with_witness_source(
+        &mut self,
+        fvk: FullViewingKey,
+        note: Note,
+        merkle_path: MerklePath,
+        witnesses: &impl WitnessSource,
+    ) -> Result<(), SpendError> {
+        let merkle_path = if self.diagnose_spend_anchor(&note, &merkle_path).is_match() {
+            merkle_path
+        } else {
+            witnesses
+                .witness_at(merkle_path.position(), &self.anchor)
+                .unwrap_or(merkle_path)
+        };
+        self.add_spend(fvk, note, merkle_path)
+    }
+
     /// Adds an address which will receive funds in this transaction.
     pub fn add_output(
         &mut self,
@@ -554,27 +1246,176 @@ impl Builder {
     ) -> Result<(), OutputError> {
         let flags = self.bundle_type.flags();
         if !flags.outputs_enabled() {
-            return Err(OutputError);
+            return Err(OutputError::OutputsDisabled);
         }
 
+        self.check_limits(
+            self.spends.iter().map(|s| s.note.asset()),
+            self.outputs
+                .iter()
+                .map(|o| o.asset)
+                .chain(iter::once(asset)),
+        )
+        .map_err(OutputError::Limit)?;
+        self.dust_policy.check(value, asset)?;
+
         self.outputs
             .push(OutputInfo::new(ovk, recipient, value, asset, memo));
 
         Ok(())
     }
 
-    /// Add an instruction to burn a given amount of a specific asset.
-    pub fn add_burn(&mut self, asset: AssetBase, value: NoteValue) -> Result<(), &'static str> {
-        if asset.is_native().into() {
-            return Err("Burning is only possible for non-native assets");
-        }
+    /// Adds an address which will receive change in this transaction.
+    ///
+    /// This behaves like [`Builder::add_output`], and the change output is counted as an
+    /// output for the purpose of [`BundleMetadata::output_action_index`]. In addition, if
+    /// a [`ChangeSplitPolicy`] has been set for `asset`, the change may be split into
+    /// several notes when the bundle is built; use
+    /// [`BundleMetadata::change_action_indices`] to locate them.
+    pub fn add_change_output(
+        &mut self,
+        ovk: Option<OutgoingViewingKey>,
+        recipient: Address,
+        value: NoteValue,
+        asset: AssetBase,
+        memo: Option<[u8; 512]>,
+    ) -> Result<(), OutputError> {
+        self.add_output(ovk, recipient, value, asset, memo)?;
+        self.change_outputs.push(self.outputs.len() - 1);
 
-        if value.inner() == 0 {
-            return Err("Burning is not possible for zero values");
-        }
+        Ok(())
+    }
+
+    /// Adds an output sent from the wallet of `fvk`, encrypting it to the outgoing
+    /// viewing key of `scope`, so that the wallet can recover it.
+    ///
+    /// An output with [`Scope::External`] is a payment, and is added as in
+    /// [`Builder::add_output`]. An output with [`Scope::Internal`] is change, and is added
+    /// as in [`Builder::add_change_output`].
+    pub fn add_output_with_scope(
+        &mut self,
+        fvk: &FullViewingKey,
+        scope: Scope,
+        recipient: Address,
+        value: NoteValue,
+        asset: AssetBase,
+        memo: Option<[u8; 512]>,
+    ) -> Result<(), OutputError> {
+        let ovk = Some(fvk.to_ovk(scope));
+        match scope {
+            Scope::External => self.add_output(ovk, recipient, value, asset, memo),
+            Scope::Internal => self.add_change_output(ovk, recipient, value, asset, memo),
+        }
+    }
+
+    /// Adds a recipient given by its encoded address, as parsed by [`Address::decode`].
+    ///
+    /// The address must be encoded for the network of the builder, as set by
+    /// [`Builder::set_network`]. If no network has been set, the network of the first
+    /// encoded recipient becomes that of the builder, so that all of the encoded
+    /// recipients of a bundle are on the same network. The output is otherwise added as
+    /// in [`Builder::add_output`].
+    pub fn add_output_to_encoded_address(
+        &mut self,
+        ovk: Option<OutgoingViewingKey>,
+        recipient: &str,
+        value: NoteValue,
+        asset: AssetBase,
+        memo: Option<[u8; 512]>,
+    ) -> Result<(), OutputError> {
+        let (network, recipient) = match self.network {
+            Some(network) => Address::decode(network, recipient).map(|a| (network, a)),
+            None => Address::decode_any(recipient),
+        }
+        .map_err(OutputError::InvalidAddress)?;
+        self.add_output(ovk, recipient, value, asset, memo)?;
+        self.network = Some(network);
+
+        Ok(())
+    }
+
+    /// Sets the network that the encoded recipients of this builder must be on.
+    pub fn set_network(&mut self, network: NetworkType) {
+        self.network = Some(network);
+    }
+
+    /// Returns the network of the encoded recipients of this builder, if it is known.
+    pub fn network(&self) -> Option<NetworkType> {
+        self.network
+    }
+
+    /// Sets the minimum values of the outputs of the bundle.
+    ///
+    /// Outputs added after this call are checked when they are added, and all of the
+    /// outputs are checked again when the bundle is built. Change outputs are only split
+    /// into notes of at least the minimum value.
+    pub fn set_dust_policy(&mut self, policy: DustPolicy) {
+        self.dust_policy = policy;
+    }
+
+    /// Sets the policy used to split change outputs of the given asset.
+    ///
+    /// The policy only applies to outputs added with [`Builder::add_change_output`].
+    pub fn set_change_split_policy(&mut self, asset: AssetBase, policy: ChangeSplitPolicy) {
+        self.change_split_policies.insert(asset, policy);
+    }
+
+    /// Records the openings of the value commitments of the built actions in the
+    /// [`BundleMetadata`], so that the amounts they move can later be disclosed to an
+    /// auditor with [`BundleMetadata::value_openings`].
+    ///
+    /// The openings reveal the value and asset of every action, and should be handled
+    /// with the same care as viewing keys.
+    pub fn record_value_openings(&mut self) {
+        self.record_value_openings = true;
+    }
+
+    /// Adds an action whose spend and output pairing was decided outside of the builder.
+    ///
+    /// This is intended for advanced constructors (such as swap coordinators) that need
+    /// control over which spend and which output share an [`Action`], while still reusing
+    /// the proving and signing plumbing of the builder. Pre-built actions are never split
+    /// or padded, and are inserted at random positions among the other actions of the
+    /// bundle; use [`BundleMetadata::prebuilt_action_index`] to locate them.
+    ///
+    /// Returns an error if spends or outputs are disabled for this builder while the
+    /// action requires them, if the Merkle path of the spent note does not have the
+    /// required anchor, if the spent note and the output have different asset types, or
+    /// if the action would exceed a limit of the builder.
+    pub fn add_prebuilt_action(&mut self, action: ActionInfo) -> Result<(), PrebuiltActionError> {
+        check_prebuilt_action(&action, self.bundle_type.flags(), &self.anchor)?;
+
+        self.prebuilt_actions.push(action);
+        if let Err(e) = self.check_limits(
+            self.spends.iter().map(|s| s.note.asset()),
+            self.outputs.iter().map(|o| o.asset),
+        ) {
+            self.prebuilt_actions.pop();
+            return Err(PrebuiltActionError::Limit(e));
+        }
+
+        Ok(())
+    }
+
+    /// Add an instruction to burn a given amount of a specific asset.
+    pub fn add_burn(&mut self, asset: AssetBase, value: NoteValue) -> Result<(), BurnError> {
+        if asset.is_native().into() {
+            return Err(BurnError::NativeAsset);
+        }
+
+        if value.inner() == 0 {
+            return Err(BurnError::ZeroValue);
+        }
+
+        match self.config.max_burns {
+            Some(limit) if !self.burn.contains_key(&asset) && self.burn.len() >= limit => {
+                return Err(BurnError::Limit(LimitError::TooManyBurns(limit)));
+            }
+            _ => (),
+        }
 
         let cur = *self.burn.get(&asset).unwrap_or(&ValueSum::zero());
-        let sum = (cur + value).ok_or("Orchard ValueSum operation overflowed")?;
+        let sum = (cur + value).ok_or(BurnError::Overflow)?;
         self.burn.insert(asset, sum);
         Ok(())
     }
@@ -611,6 +1452,11 @@ impl Builder {
                     .iter()
                     .map(|output| NoteValue::zero() - output.value),
             )
+            .chain(
+                self.prebuilt_actions
+                    .iter()
+                    .map(|action| action.value_sum()),
+            )
             .fold(Some(ValueSum::zero()), |acc, note_value| acc? + note_value)
             .ok_or(OverflowError)?;
         i64::try_from(value_balance).and_then(|i| V::try_from(i).map_err(|_| value::OverflowError))
@@ -624,37 +1470,183 @@ impl Builder {
         self,
         rng: impl RngCore,
     ) -> Result<Option<(UnauthorizedBundle<V>, BundleMetadata)>, BuildError> {
-        bundle(
-            rng,
+        self.build_inner(rng, true, false)
+    }
+
+    /// Builds a bundle as in [`Builder::build`], drawing all of its randomness from a
+    /// generator seeded with `seed`.
+    ///
+    /// This covers the dummy spends and outputs, the shuffling of the actions and the
+    /// randomness of each action, so the same builder and seed always produce the same
+    /// bundle with a given version of this crate. The generator is domain-separated from
+    /// other uses of `seed`.
+    ///
+    /// This is intended for tests and reproducible builds: the privacy of the bundle
+    /// relies on `seed` being secret and never reused.
+    pub fn build_deterministic<V: TryFrom<i64>>(
+        self,
+        seed: &[u8; 32],
+    ) -> Result<Option<(UnauthorizedBundle<V>, BundleMetadata)>, BuildError> {
+        self.build_inner(deterministic_rng(seed), true, false)
+    }
+
+    /// Builds a bundle as in [`Builder::build`], additionally returning an [`AuditTrail`]
+    /// of the randomness consumed while building it.
+    ///
+    /// The trail records the value commitment trapdoor, spend authorization randomizer
+    /// and note seed randomness of each action, along with all of the randomness drawn
+    /// from `rng`. It can be encrypted to an auditor with [`AuditTrail::encrypt`], bound
+    /// to the commitment of the bundle, so that the way in which the transaction was
+    /// formed can later be proven.
+    pub fn build_audited<V: TryFrom<i64>, R: RngCore + CryptoRng>(
+        self,
+        rng: R,
+    ) -> Result<Option<(UnauthorizedBundle<V>, BundleMetadata, AuditTrail)>, BuildError> {
+        let mut rng = AuditingRng::new(rng);
+        let result = self.build_inner(&mut rng, true, true)?;
+        Ok(result.map(|(bundle, mut bundle_meta)| {
+            let actions = bundle_meta
+                .action_randomness
+                .take()
+                .expect("action randomness was recorded");
+            (
+                bundle,
+                bundle_meta,
+                AuditTrail::from_parts(actions, rng.into_consumed()),
+            )
+        }))
+    }
+
+    /// Builds a bundle meant to be used as an action group of a swap, whose non-native
+    /// assets are balanced by the other action groups of the swap.
+    pub(crate) fn build_unbalanced<V: TryFrom<i64>>(
+        self,
+        rng: impl RngCore,
+    ) -> Result<Option<(UnauthorizedBundle<V>, BundleMetadata)>, BuildError> {
+        self.build_inner(rng, false, false)
+    }
+
+    fn build_inner<V: TryFrom<i64>>(
+        mut self,
+        mut rng: impl RngCore,
+        balanced: bool,
+        record_randomness: bool,
+    ) -> Result<Option<(UnauthorizedBundle<V>, BundleMetadata)>, BuildError> {
+        let num_requested_outputs = self.outputs.len();
+        let change_parts = self.split_change(&mut rng);
+
+        // Splitting change may have added outputs.
+        self.check_limits(
+            self.spends.iter().map(|s| s.note.asset()),
+            self.outputs.iter().map(|o| o.asset),
+        )
+        .map_err(BuildError::Limit)?;
+        for (output_idx, output) in self.outputs.iter().enumerate() {
+            self.dust_policy
+                .check(output.value, output.asset)
+                .map_err(|error| BuildError::Output {
+                    // Report the parts of a split change output as the change output.
+                    index: change_parts
+                        .iter()
+                        .find(|parts| parts.contains(&output_idx))
+                        .map_or(output_idx, |parts| parts[0]),
+                    error,
+                })?;
+        }
+
+        let mut result = build_bundle(
+            &mut rng,
             self.anchor,
             self.bundle_type,
             self.spends,
             self.outputs,
+            self.prebuilt_actions,
             self.burn,
-        )
+            balanced,
+            self.record_value_openings,
+            record_randomness,
+        )?;
+
+        if let Some((_, bundle_meta)) = result.as_mut() {
+            bundle_meta.record_change_parts(change_parts, num_requested_outputs);
+        }
+
+        Ok(result)
+    }
+
+    /// Splits the change outputs according to the configured [`ChangeSplitPolicy`]s.
+    ///
+    /// The first part of each change output replaces the original output, and the other
+    /// parts are appended to the list of outputs. Returns the output indices of the parts
+    /// of each change output.
+    fn split_change(&mut self, rng: &mut impl RngCore) -> Vec<Vec<usize>> {
+        let mut change_parts = Vec::with_capacity(self.change_outputs.len());
+        for output_idx in self.change_outputs.clone() {
+            let output = self.outputs[output_idx].clone();
+            let mut parts = vec![output_idx];
+            if let Some(policy) = self.change_split_policies.get(&output.asset) {
+                let minimum = self.dust_policy.minimum(output.asset);
+                let mut values = policy.split(output.value, minimum, rng).into_iter();
+                // `split` always returns at least one value.
+                self.outputs[output_idx].value = values.next().unwrap();
+                for value in values {
+                    parts.push(self.outputs.len());
+                    self.outputs.push(OutputInfo {
+                        value,
+                        ..output.clone()
+                    });
+                }
+            }
+            change_parts.push(parts);
+        }
+        change_parts
     }
 }
 
+/// The kind of note spent by an [`Action`] of a bundle, as recorded in its
+/// [`BundleMetadata`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionSpend {
+    /// The action spends the `n`-th spend specified in bundle construction.
+    Requested(usize),
+    /// The action spends a split note of the `n`-th spend specified in bundle
+    /// construction. Its nullifier is not the nullifier of the spent note.
+    Split(usize),
+    /// The action spends a dummy note.
+    Dummy,
+    /// The action is the `n`-th pre-built action specified in bundle construction.
+    Prebuilt(usize),
+}
+
 /// The index of the attached spend or output in the bundle.
 /// None indicates a dummy note.
 /// The index is used to track the position of the note in the bundle.
 type MetadataIdx = Option<usize>;
 
-/// Partition a list of spends and recipients by note types.
+/// Returns the generator used by [`Builder::build_deterministic`] for the given seed.
+fn deterministic_rng(seed: &[u8; 32]) -> ChaCha20Rng {
+    let prg_seed = Params::new()
+        .hash_length(32)
+        .personal(BUILDER_PRG_PERSONALIZATION)
+        .hash(seed);
+    ChaCha20Rng::from_seed(prg_seed.as_bytes().try_into().unwrap())
+}
+
+/// Partition a list of spends and recipients by note types, in the order of the assets.
 /// Method creates single dummy ZEC note if spends and recipients are both empty.
 #[allow(clippy::type_complexity)]
 fn partition_by_asset(
     spends: &[SpendInfo],
     outputs: &[OutputInfo],
     rng: &mut impl RngCore,
-) -> HashMap<
+) -> BTreeMap<
     AssetBase,
     (
         Vec<(SpendInfo, MetadataIdx)>,
         Vec<(OutputInfo, MetadataIdx)>,
     ),
 > {
-    let mut hm = HashMap::new();
+    let mut hm = BTreeMap::new();
 
     for (i, s) in spends.iter().enumerate() {
         hm.entry(s.note.asset())
@@ -696,17 +1688,83 @@ fn pad_spend(spend: Option<&SpendInfo>, asset: AssetBase, mut rng: impl RngCore)
     }
 }
 
+/// The origin of an [`ActionInfo`] within the bundle under construction, used to fill in
+/// the [`BundleMetadata`].
+enum ActionSource {
+    /// An action pairing the given spend with the given output, if it is not a dummy.
+    Paired(ActionSpend, MetadataIdx),
+    /// The action added by the given call to [`Builder::add_prebuilt_action`].
+    Prebuilt(usize),
+}
+
 /// Builds a bundle containing the given spent notes and outputs.
 ///
 /// The returned bundle will have no proof or signatures; these can be applied with
 /// [`Bundle::create_proof`] and [`Bundle::apply_signatures`] respectively.
 pub fn bundle<V: TryFrom<i64>>(
+    rng: impl RngCore,
+    anchor: Anchor,
+    bundle_type: BundleType,
+    spends: Vec<SpendInfo>,
+    outputs: Vec<OutputInfo>,
+    burn: HashMap<AssetBase, ValueSum>,
+) -> Result<Option<(UnauthorizedBundle<V>, BundleMetadata)>, BuildError> {
+    bundle_with_prebuilt_actions(rng, anchor, bundle_type, spends, outputs, vec![], burn)
+}
+
+/// Builds a bundle containing the given spent notes and outputs, along with actions
+/// whose spend and output pairing has already been decided by the caller.
+///
+/// The pre-built actions are inserted at random positions among the actions built from
+/// `spends` and `outputs`; their locations are recorded in the returned
+/// [`BundleMetadata`].
+///
+/// The returned bundle will have no proof or signatures; these can be applied with
+/// [`Bundle::create_proof`] and [`Bundle::apply_signatures`] respectively.
+pub fn bundle_with_prebuilt_actions<V: TryFrom<i64>>(
+    rng: impl RngCore,
+    anchor: Anchor,
+    bundle_type: BundleType,
+    spends: Vec<SpendInfo>,
+    outputs: Vec<OutputInfo>,
+    prebuilt_actions: Vec<ActionInfo>,
+    burn: HashMap<AssetBase, ValueSum>,
+) -> Result<Option<(UnauthorizedBundle<V>, BundleMetadata)>, BuildError> {
+    build_bundle(
+        rng,
+        anchor,
+        bundle_type,
+        spends,
+        outputs,
+        prebuilt_actions,
+        burn,
+        true,
+        false,
+        false,
+    )
+}
+
+/// Builds a bundle as in [`bundle_with_prebuilt_actions`].
+///
+/// If `balanced` is `false`, the non-native assets of the bundle are not required to
+/// balance; this is the case for the action groups of a swap, which only balance once
+/// combined.
+///
+/// If `record_value_openings` is `true`, the openings of the value commitments of the
+/// actions are recorded in the returned [`BundleMetadata`]. Likewise, if
+/// `record_randomness` is `true`, the randomness used for each action is recorded.
+#[allow(clippy::too_many_arguments)]
+fn build_bundle<V: TryFrom<i64>>(
     mut rng: impl RngCore,
     anchor: Anchor,
     bundle_type: BundleType,
     spends: Vec<SpendInfo>,
     outputs: Vec<OutputInfo>,
+    prebuilt_actions: Vec<ActionInfo>,
     burn: HashMap<AssetBase, ValueSum>,
+    balanced: bool,
+    record_value_openings: bool,
+    record_randomness: bool,
 ) -> Result<Option<(UnauthorizedBundle<V>, BundleMetadata)>, BuildError> {
     let flags = bundle_type.flags();
 
@@ -715,10 +1773,14 @@ pub fn bundle<V: TryFrom<i64>>(
         return Err(BuildError::SpendsDisabled);
     }
 
-    for spend in &spends {
-        if !spend.has_matching_anchor(&anchor) {
-            return Err(BuildError::AnchorMismatch);
-        }
+    if let Some(index) = spends
+        .iter()
+        .position(|spend| !spend.has_matching_anchor(&anchor))
+    {
+        return Err(BuildError::Spend {
+            index,
+            error: SpendError::AnchorMismatch,
+        });
     }
 
     let num_requested_outputs = outputs.len();
@@ -726,25 +1788,47 @@ pub fn bundle<V: TryFrom<i64>>(
         return Err(BuildError::OutputsDisabled);
     }
 
+    let num_prebuilt_actions = prebuilt_actions.len();
+    for (index, action) in prebuilt_actions.iter().enumerate() {
+        check_prebuilt_action(action, flags, &anchor)
+            .map_err(|error| BuildError::PrebuiltAction { index, error })?;
+    }
+
     // Pair up the spends and outputs, extending with dummy values as necessary.
-    let (pre_actions, bundle_meta) = {
+    let (pre_actions, mut bundle_meta) = {
         // Use Vec::with_capacity().extend(...) instead of .collect() to avoid reallocations,
         // as we can estimate the vector size beforehand.
         let mut indexed_spends_outputs =
             Vec::with_capacity(spends.len().max(outputs.len()).max(MIN_ACTIONS));
 
+        // The assets are partitioned in their canonical order, so that the same
+        // randomness is used for the same asset.
         indexed_spends_outputs.extend(
             partition_by_asset(&spends, &outputs, &mut rng)
                 .into_iter()
                 .flat_map(|(asset, (spends, outputs))| {
                     let num_asset_pre_actions = spends.len().max(outputs.len());
 
-                    let first_spend = spends.first().map(|(s, _)| s.clone());
+                    let first_spend = spends.first().map(|(s, idx)| (s.clone(), *idx));
 
                     let mut indexed_spends = spends
                         .into_iter()
+                        .map(|(spend, idx)| {
+                            (
+                                spend,
+                                idx.map_or(ActionSpend::Dummy, ActionSpend::Requested),
+                            )
+                        })
                         .chain(iter::repeat_with(|| {
-                            (pad_spend(first_spend.as_ref(), asset, &mut rng), None)
+                            let spend =
+                                pad_spend(first_spend.as_ref().map(|(s, _)| s), asset, &mut rng);
+                            let kind = match &first_spend {
+                                Some((_, Some(idx))) if spend.split_flag => {
+                                    ActionSpend::Split(*idx)
+                                }
+                                _ => ActionSpend::Dummy,
+                            };
+                            (spend, kind)
                         }))
                         .take(num_asset_pre_actions)
                         .collect::<Vec<_>>();
@@ -772,32 +1856,82 @@ pub fn bundle<V: TryFrom<i64>>(
         indexed_spends_outputs.extend(
             iter::repeat_with(|| {
                 (
-                    (pad_spend(None, AssetBase::native(), &mut rng), None),
+                    (
+                        pad_spend(None, AssetBase::native(), &mut rng),
+                        ActionSpend::Dummy,
+                    ),
                     (OutputInfo::dummy(&mut rng, AssetBase::native()), None),
                 )
             })
-            .take(MIN_ACTIONS.saturating_sub(indexed_spends_outputs.len())),
+            .take(MIN_ACTIONS.saturating_sub(indexed_spends_outputs.len() + num_prebuilt_actions)),
         );
 
-        let mut bundle_meta = BundleMetadata::new(num_requested_spends, num_requested_outputs);
-        let pre_actions = indexed_spends_outputs
+        let mut sourced_actions = indexed_spends_outputs
             .into_iter()
-            .enumerate()
-            .map(|(action_idx, ((spend, spend_idx), (output, out_idx)))| {
-                // Record the post-randomization spend location
-                if let Some(spend_idx) = spend_idx {
-                    bundle_meta.spend_indices[spend_idx] = action_idx;
-                }
+            .map(|((spend, spend_kind), (output, out_idx))| {
+                (
+                    ActionInfo::new(spend, output, &mut rng),
+                    ActionSource::Paired(spend_kind, out_idx),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // Insert the pre-built actions at random positions, so that their location does
+        // not reveal which actions were constructed externally.
+        for (prebuilt_idx, action) in prebuilt_actions.into_iter().enumerate() {
+            let position = rng.gen_range(0..=sourced_actions.len());
+            sourced_actions.insert(position, (action, ActionSource::Prebuilt(prebuilt_idx)));
+        }
 
-                // Record the post-randomization output location
-                if let Some(out_idx) = out_idx {
-                    bundle_meta.output_indices[out_idx] = action_idx;
+        let mut bundle_meta = BundleMetadata::new(
+            num_requested_spends,
+            num_requested_outputs,
+            num_prebuilt_actions,
+        );
+        let pre_actions = sourced_actions
+            .into_iter()
+            .enumerate()
+            .map(|(action_idx, (action, source))| {
+                match source {
+                    ActionSource::Paired(spend_kind, out_idx) => {
+                        // Record the post-randomization spend location
+                        if let ActionSpend::Requested(spend_idx) = spend_kind {
+                            bundle_meta.spend_indices[spend_idx] = action_idx;
+                        }
+                        bundle_meta.action_spends.push(spend_kind);
+
+                        // Record the post-randomization output location
+                        if let Some(out_idx) = out_idx {
+                            bundle_meta.output_indices[out_idx] = action_idx;
+                        }
+                    }
+                    ActionSource::Prebuilt(prebuilt_idx) => {
+                        bundle_meta.prebuilt_indices[prebuilt_idx] = action_idx;
+                        bundle_meta
+                            .action_spends
+                            .push(ActionSpend::Prebuilt(prebuilt_idx));
+                    }
                 }
 
-                ActionInfo::new(spend, output, &mut rng)
+                action
             })
             .collect::<Vec<_>>();
 
+        if record_value_openings {
+            bundle_meta.value_openings = Some(
+                pre_actions
+                    .iter()
+                    .map(|action| {
+                        ValueOpening::from_parts(
+                            action.value_sum(),
+                            action.spend.note.asset(),
+                            action.rcv,
+                        )
+                    })
+                    .collect(),
+            );
+        }
+
         (pre_actions, bundle_meta)
     };
 
@@ -812,7 +1946,7 @@ pub fn bundle<V: TryFrom<i64>>(
         .into()?;
 
     let result_value_balance = V::try_from(native_value_balance)
-        .map_err(|_| BuildError::ValueSum(value::OverflowError))?;
+        .map_err(|_| BuildError::Balance(BalanceError::ValueBalanceOutOfRange))?;
 
     // Compute the transaction binding signing key.
     let bsk = pre_actions
@@ -821,22 +1955,65 @@ pub fn bundle<V: TryFrom<i64>>(
         .sum::<ValueCommitTrapdoor>()
         .into_bsk();
 
-    // Create the actions.
-    let (actions, circuits): (Vec<_>, Vec<_>) =
-        pre_actions.into_iter().map(|a| a.build(&mut rng)).unzip();
+    // Derive the value commitments of all actions together, then create the actions.
+    let cv_nets = ValueCommitment::derive_batch(
+        pre_actions
+            .iter()
+            .map(|a| (a.value_sum(), a.rcv, a.output.asset)),
+    );
+    let rcvs = pre_actions.iter().map(|a| a.rcv).collect::<Vec<_>>();
+    let mut rseeds = Vec::with_capacity(pre_actions.len());
+    let (actions, circuits): (Vec<_>, Vec<_>) = pre_actions
+        .into_iter()
+        .zip(cv_nets)
+        .map(|(a, cv_net)| {
+            let (action, circuit, rseed) = a.build(cv_net, &mut rng);
+            rseeds.push(rseed);
+            (action, circuit)
+        })
+        .unzip();
+
+    if record_randomness {
+        bundle_meta.action_randomness = Some(
+            actions
+                .iter()
+                .zip(rcvs)
+                .zip(rseeds)
+                .map(|((action, rcv), rseed)| {
+                    ActionRandomness::from_parts(
+                        rcv.inner().to_repr(),
+                        action.authorization().parts.alpha.to_repr(),
+                        *rseed.as_bytes(),
+                    )
+                })
+                .collect(),
+        );
+    }
 
     // Verify that bsk and bvk are consistent.
-    let bvk = derive_bvk(
-        &actions,
-        native_value_balance,
-        burn.iter()
-            .flat_map(|(asset, value)| -> Result<_, BuildError> { Ok((*asset, (*value).into()?)) }),
-    );
-    assert_eq!(redpallas::VerificationKey::from(&bsk), bvk);
+    if balanced {
+        let bvk = derive_bvk(
+            &actions,
+            native_value_balance,
+            burn.iter()
+                .flat_map(|(asset, value)| -> Result<_, BuildError> {
+                    Ok((*asset, (*value).into()?))
+                }),
+        );
+        assert_eq!(redpallas::VerificationKey::from(&bsk), bvk);
+    }
 
+    // Emit the burns in the canonical order required by the consensus rules.
+    let mut burn: Vec<_> = burn.into_iter().collect();
+    burn.sort_unstable_by_key(|(asset, _)| asset.to_bytes());
     let burn = burn
         .into_iter()
-        .map(|(asset, value)| Ok((asset, value.into()?)))
+        .map(|(asset, value)| {
+            value
+                .into()
+                .map(|value| (asset, value))
+                .map_err(|_| BuildError::Balance(BalanceError::BurnOutOfRange(asset)))
+        })
         .collect::<Result<Vec<(AssetBase, V)>, BuildError>>()?;
 
     Ok(NonEmpty::from_vec(actions).map(|actions| {
@@ -892,6 +2069,24 @@ impl<S: InProgressSignatures> InProgress<Unproven, S> {
     ) -> Result<Proof, halo2_proofs::plonk::Error> {
         Proof::create(pk, &self.proof.circuits, instances, rng)
     }
+
+    /// Creates the proof for this bundle, with the given options.
+    pub fn create_proof_with_options(
+        &self,
+        pk: &ProvingKey,
+        instances: &[Instance],
+        options: &ProverOptions,
+        rng: impl RngCore + Send,
+    ) -> Result<Proof, ProofError> {
+        if let Some(required) = options.exceeded_budget(self.proof.circuits.len()) {
+            return Err(ProofError::WouldExceedBudget {
+                required,
+                budget: options.max_memory_hint.unwrap_or_default(),
+            });
+        }
+        Proof::create_with_options(pk, &self.proof.circuits, instances, options, rng)
+            .map_err(ProofError::Prover)
+    }
 }
 
 impl<S: InProgressSignatures, V> Bundle<InProgress<Unproven, S>, V> {
@@ -918,6 +2113,39 @@ impl<S: InProgressSignatures, V> Bundle<InProgress<Unproven, S>, V> {
             },
         )
     }
+
+    /// Creates the proof for this bundle, with the given options.
+    ///
+    /// If creating the proof is estimated to need more memory than the
+    /// [`ProverOptions::max_memory_hint`], this fails with
+    /// [`ProofError::WouldExceedBudget`] before the prover starts. As all of the actions
+    /// of a bundle are proven together, a bundle that exceeds the budget must be built
+    /// with fewer actions, which [`ProverOptions::max_actions`] gives a limit for.
+    pub fn create_proof_with_options(
+        self,
+        pk: &ProvingKey,
+        options: &ProverOptions,
+        mut rng: impl RngCore + Send,
+    ) -> Result<Bundle<InProgress<Proof, S>, V>, BuildError> {
+        let instances: Vec<_> = self
+            .actions()
+            .iter()
+            .map(|a| a.to_instance(*self.flags(), *self.anchor()))
+            .collect();
+        self.try_map_authorization(
+            &mut (),
+            |_, _, a| Ok(a),
+            |_, auth| {
+                let proof = auth
+                    .create_proof_with_options(pk, &instances, options, &mut rng)
+                    .map_err(BuildError::Proof)?;
+                Ok(InProgress {
+                    proof,
+                    sigs: auth.sigs,
+                })
+            },
+        )
+    }
 }
 
 /// The parts needed to sign an [`Action`].
@@ -957,6 +2185,40 @@ pub struct SigningMetadata {
 pub struct PartiallyAuthorized {
     binding_signature: redpallas::Signature<Binding>,
     sighash: [u8; 32],
+    /// The indices of the actions spending dummy notes, which were signed by the builder.
+    dummy_actions: Vec<usize>,
+}
+
+/// A summary of the spend authorization signatures collected for a bundle.
+///
+/// Actions spending dummy notes are signed by the builder itself, and are not counted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SigningProgress {
+    collected: usize,
+    required: usize,
+}
+
+impl SigningProgress {
+    /// Returns the number of signatures that have been collected for non-dummy spends.
+    pub fn collected(&self) -> usize {
+        self.collected
+    }
+
+    /// Returns the total number of signatures required for non-dummy spends.
+    pub fn required(&self) -> usize {
+        self.required
+    }
+
+    /// Returns `true` if all the required signatures have been collected.
+    pub fn is_complete(&self) -> bool {
+        self.collected == self.required
+    }
+
+    /// Returns `true` if signatures are still required, and the only signatures present
+    /// in the bundle are those the builder created for dummy spends.
+    pub fn only_dummies_signed(&self) -> bool {
+        self.collected == 0 && self.required > 0
+    }
 }
 
 impl InProgressSignatures for PartiallyAuthorized {
@@ -975,11 +2237,14 @@ pub enum MaybeSigned {
 }
 
 impl MaybeSigned {
-    fn finalize(self) -> Result<redpallas::Signature<SpendAuth>, BuildError> {
-        match self {
+    /// Returns the signature of the action at `index`, or an error if it is missing.
+    fn finalize(self, index: &mut usize) -> Result<redpallas::Signature<SpendAuth>, BuildError> {
+        let result = match self {
             Self::Signature(sig) => Ok(sig),
-            _ => Err(BuildError::MissingSignatures),
-        }
+            _ => Err(BuildError::Signature(SignatureError::Missing(*index))),
+        };
+        *index += 1;
+        result
     }
 }
 
@@ -992,20 +2257,26 @@ impl<P: fmt::Debug, V> Bundle<InProgress<P, Unauthorized>, V> {
         mut rng: R,
         sighash: [u8; 32],
     ) -> Bundle<InProgress<P, PartiallyAuthorized>, V> {
+        let mut dummy_actions = vec![];
         self.map_authorization(
-            &mut rng,
-            |rng, _, SigningMetadata { dummy_ask, parts }| {
+            &mut (&mut rng, 0usize, &mut dummy_actions),
+            |(rng, index, dummy_actions), _, SigningMetadata { dummy_ask, parts }| {
+                if dummy_ask.is_some() {
+                    dummy_actions.push(*index);
+                }
+                *index += 1;
                 // We can create signatures for dummy spends immediately.
                 dummy_ask
                     .map(|ask| ask.randomize(&parts.alpha).sign(rng, &sighash))
                     .map(MaybeSigned::Signature)
                     .unwrap_or(MaybeSigned::SigningMetadata(parts))
             },
-            |rng, auth| InProgress {
+            |(rng, _, dummy_actions), auth| InProgress {
                 proof: auth.proof,
                 sigs: PartiallyAuthorized {
                     binding_signature: auth.sigs.bsk.sign(rng, &sighash),
                     sighash,
+                    dummy_actions: core::mem::take(*dummy_actions),
                 },
             },
         )
@@ -1032,6 +2303,39 @@ impl<V> Bundle<InProgress<Proof, Unauthorized>, V> {
     }
 }
 
+impl<V: Copy + Into<i64>> Bundle<InProgress<Proof, Unauthorized>, V> {
+    /// Authorizes this bundle as an action group of a [`SwapBundle`].
+    ///
+    /// The spends are signed over the commitment of this action group, rather than over
+    /// a transaction sighash, so that the action group can be authorized without knowing
+    /// the other action groups of the swap. The binding signing key is kept in the
+    /// returned [`ActionGroup`], to sign the swap bundle once all action groups are known.
+    ///
+    /// Returns an error if any signatures are missing.
+    ///
+    /// [`SwapBundle`]: crate::swap_bundle::SwapBundle
+    pub fn build_action_group<R: RngCore + CryptoRng>(
+        self,
+        mut rng: R,
+        signing_keys: &[SpendAuthorizingKey],
+    ) -> Result<ActionGroup<V>, BuildError> {
+        let bsk = self.authorization().sigs.bsk;
+        let sighash = self.commitment().into();
+        let action_group = signing_keys
+            .iter()
+            .fold(self.prepare(&mut rng, sighash), |partial, ask| {
+                partial.sign(&mut rng, ask)
+            })
+            .try_map_authorization(
+                &mut 0usize,
+                |index, _, maybe| maybe.finalize(index),
+                |_, partial| Ok(ActionGroupAuthorized::from_parts(partial.proof)),
+            )?;
+
+        Ok(ActionGroup::from_parts(action_group, bsk))
+    }
+}
+
 impl<P: fmt::Debug, V> Bundle<InProgress<P, PartiallyAuthorized>, V> {
     /// Signs this bundle with the given [`SpendAuthorizingKey`].
     ///
@@ -1088,42 +2392,168 @@ impl<P: fmt::Debug, V> Bundle<InProgress<P, PartiallyAuthorized>, V> {
             |_, partial| partial,
         );
         match signature_valid_for {
-            0 => Err(BuildError::InvalidExternalSignature),
+            0 => Err(BuildError::Signature(SignatureError::Invalid)),
             1 => Ok(bundle),
-            _ => Err(BuildError::DuplicateSignature),
+            _ => Err(BuildError::Signature(SignatureError::Duplicate)),
         }
     }
-}
 
-impl<V> Bundle<InProgress<Proof, PartiallyAuthorized>, V> {
-    /// Finalizes this bundle, enabling it to be included in a transaction.
+    /// Appends an externally computed [`Signature`] to the action at `action_index`.
     ///
-    /// Returns an error if any signatures are missing.
-    pub fn finalize(self) -> Result<Bundle<Authorized, V>, BuildError> {
-        self.try_map_authorization(
-            &mut (),
-            |_, _, maybe| maybe.finalize(),
-            |_, partial| {
-                Ok(Authorized::from_parts(
-                    partial.proof,
-                    partial.sigs.binding_signature,
+    /// Unlike [`Bundle::append_signatures`], the signature is only checked against the
+    /// given action, which lets coordinators that know where each signature belongs
+    /// avoid trial-verifying it against every unsigned action.
+    ///
+    /// [`Signature`]: redpallas::Signature
+    pub fn append_signature_at(
+        self,
+        action_index: usize,
+        signature: &redpallas::Signature<SpendAuth>,
+    ) -> Result<Self, BuildError> {
+        match self.actions().get(action_index).map(|a| a.authorization()) {
+            None => {
+                return Err(BuildError::Signature(
+                    SignatureError::ActionIndexOutOfBounds(action_index),
                 ))
+            }
+            Some(MaybeSigned::Signature(_)) => {
+                return Err(BuildError::Signature(SignatureError::ActionAlreadySigned(
+                    action_index,
+                )))
+            }
+            Some(MaybeSigned::SigningMetadata(parts)) => {
+                let rk = parts.ak.randomize(&parts.alpha);
+                if rk
+                    .verify(&self.authorization().sigs.sighash[..], signature)
+                    .is_err()
+                {
+                    return Err(BuildError::Signature(SignatureError::Invalid));
+                }
+            }
+        }
+
+        let mut index = 0usize;
+        Ok(self.map_authorization(
+            &mut index,
+            |index, _, maybe| {
+                let maybe = if *index == action_index {
+                    MaybeSigned::Signature(signature.clone())
+                } else {
+                    maybe
+                };
+                *index += 1;
+                maybe
             },
-        )
+            |_, partial| partial,
+        ))
     }
-}
 
-/// A trait that provides a minimized view of an Orchard input suitable for use in
-/// fee and change calculation.
-pub trait InputView<NoteRef> {
-    /// An identifier for the input being spent.
-    fn note_id(&self) -> &NoteRef;
-    /// The value of the input being spent.
-    fn value<V: From<u64>>(&self) -> V;
-}
+    /// Returns the indices of the actions that have a spend authorization signature,
+    /// including the actions spending dummy notes.
+    pub fn signed_actions(&self) -> Vec<usize> {
+        self.actions()
+            .iter()
+            .enumerate()
+            .filter(|(_, action)| matches!(action.authorization(), MaybeSigned::Signature(_)))
+            .map(|(index, _)| index)
+            .collect()
+    }
 
-impl InputView<()> for SpendInfo {
-    fn note_id(&self) -> &() {
+    /// Returns `true` if the action at `index` spends a dummy note that was signed by
+    /// the builder.
+    pub fn is_dummy_action(&self, index: usize) -> bool {
+        self.authorization().sigs.dummy_actions.contains(&index)
+    }
+
+    /// Returns the distinct spend validating keys whose signatures are still required,
+    /// in the order of the actions they authorize.
+    pub fn required_signers(&self) -> Vec<SpendValidatingKey> {
+        let mut signers: Vec<SpendValidatingKey> = vec![];
+        for (_, ak, _) in self.unsigned_actions() {
+            if !signers.contains(&ak) {
+                signers.push(ak);
+            }
+        }
+        signers
+    }
+
+    /// Returns how many of the signatures for non-dummy spends have been collected.
+    pub fn signing_progress(&self) -> SigningProgress {
+        let num_dummies = self.authorization().sigs.dummy_actions.len();
+        SigningProgress {
+            collected: self.signed_actions().len() - num_dummies,
+            required: self.actions().len() - num_dummies,
+        }
+    }
+
+    /// Returns the actions that are still waiting for a spend authorization signature.
+    ///
+    /// Each entry contains the index of the action within the bundle, the spend validating
+    /// key that must authorize it, and the randomizer `alpha` with which the corresponding
+    /// spend authorizing key must be randomized to sign it.
+    pub fn unsigned_actions(&self) -> Vec<(usize, SpendValidatingKey, pallas::Scalar)> {
+        self.actions()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, action)| match action.authorization() {
+                MaybeSigned::SigningMetadata(parts) => Some((index, parts.ak.clone(), parts.alpha)),
+                MaybeSigned::Signature(_) => None,
+            })
+            .collect()
+    }
+}
+
+impl<V> Bundle<InProgress<Proof, PartiallyAuthorized>, V> {
+    /// Computes the commitment to the authorizing data of the bundle that this bundle
+    /// finalizes into, as in [`Bundle::authorizing_commitment`].
+    ///
+    /// Returns `None` if any signatures are missing.
+    pub fn authorizing_commitment(&self) -> Option<BundleAuthorizingCommitment> {
+        let spend_auth_sigs = self
+            .actions()
+            .iter()
+            .map(|action| match action.authorization() {
+                MaybeSigned::Signature(sig) => Some(sig),
+                MaybeSigned::SigningMetadata(_) => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(BundleAuthorizingCommitment(
+            hash_bundle_auth_data_from_parts(
+                self.authorization().proof.as_ref(),
+                spend_auth_sigs,
+                &self.authorization().sigs.binding_signature,
+            ),
+        ))
+    }
+
+    /// Finalizes this bundle, enabling it to be included in a transaction.
+    ///
+    /// Returns an error if any signatures are missing.
+    pub fn finalize(self) -> Result<Bundle<Authorized, V>, BuildError> {
+        self.try_map_authorization(
+            &mut 0usize,
+            |index, _, maybe| maybe.finalize(index),
+            |_, partial| {
+                Ok(Authorized::from_parts(
+                    partial.proof,
+                    partial.sigs.binding_signature,
+                ))
+            },
+        )
+    }
+}
+
+/// A trait that provides a minimized view of an Orchard input suitable for use in
+/// fee and change calculation.
+pub trait InputView<NoteRef> {
+    /// An identifier for the input being spent.
+    fn note_id(&self) -> &NoteRef;
+    /// The value of the input being spent.
+    fn value<V: From<u64>>(&self) -> V;
+}
+
+impl InputView<()> for SpendInfo {
+    fn note_id(&self) -> &() {
         // The builder does not make use of note identifiers, so we can just return the unit value.
         &()
     }
@@ -1293,16 +2723,25 @@ pub mod testing {
 mod tests {
     use rand::rngs::OsRng;
 
-    use super::Builder;
+    use super::{
+        ActionInfo, ActionSpend, AuditError, BuildError, Builder, BuilderConfig, BurnError,
+        ChangeSplitPolicy, DustPolicy, LimitError, OutputError, OutputInfo, PrebuiltActionError,
+        ProofError, SignatureError, SpendError, SpendInfo, WitnessSource,
+    };
     use crate::note::AssetBase;
     use crate::{
         builder::BundleType,
-        bundle::{Authorized, Bundle},
-        circuit::ProvingKey,
+        bundle::{burn_validation::validate_burn_list, Authorized, Bundle, Flags},
+        circuit::{ProverOptions, ProvingKey},
         constants::MERKLE_DEPTH_ORCHARD,
-        keys::{FullViewingKey, Scope, SpendingKey},
-        tree::EMPTY_ROOTS,
-        value::NoteValue,
+        keys::{
+            FullViewingKey, NetworkType, Scope, SpendAuthorizingKey, SpendValidatingKey,
+            SpendingKey, ViewingKeyEncodingError,
+        },
+        note::{ExtractedNoteCommitment, Note, Nullifier, Rho},
+        primitives::redpallas,
+        tree::{Anchor, MerklePath, WitnessTracker, EMPTY_ROOTS},
+        value::{NoteValue, ValueCommitTrapdoor},
     };
 
     #[test]
@@ -1343,4 +2782,912 @@ mod tests {
             .unwrap();
         assert_eq!(bundle.value_balance(), &(-5000))
     }
+
+    #[test]
+    fn in_progress_commitments_match_authorized_bundle() {
+        let pk = ProvingKey::build();
+        let mut rng = OsRng;
+
+        let recipient =
+            FullViewingKey::from(&SpendingKey::random(&mut rng)).address_at(0u32, Scope::External);
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_output(
+                None,
+                recipient,
+                NoteValue::from_raw(5000),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+
+        let unauthorized = builder.build::<i64>(&mut rng).unwrap().unwrap().0;
+        let commitment = unauthorized.commitment();
+        let partial = unauthorized
+            .create_proof(&pk, &mut rng)
+            .unwrap()
+            .prepare(rng, [0; 32]);
+        let auth_commitment = partial.authorizing_commitment().unwrap();
+
+        let bundle = partial.finalize().unwrap();
+        assert_eq!(bundle.commitment().0, commitment.0);
+        assert_eq!(bundle.authorizing_commitment().0, auth_commitment.0);
+    }
+
+    #[test]
+    fn proving_fails_fast_over_memory_budget() {
+        let pk = ProvingKey::build();
+        let mut rng = OsRng;
+
+        let recipient =
+            FullViewingKey::from(&SpendingKey::random(&mut rng)).address_at(0u32, Scope::External);
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_output(
+                None,
+                recipient,
+                NoteValue::from_raw(5000),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+        let unauthorized = builder.build::<i64>(&mut rng).unwrap().unwrap().0;
+
+        let required = ProverOptions::estimated_memory(unauthorized.actions().len());
+        let options = ProverOptions {
+            max_memory_hint: Some(required - 1),
+            threads: None,
+        };
+        assert!(matches!(
+            unauthorized.create_proof_with_options(&pk, &options, &mut rng),
+            Err(BuildError::Proof(ProofError::WouldExceedBudget { required: r, budget }))
+                if r == required && budget == required - 1
+        ));
+    }
+
+    #[test]
+    fn prover_budget_limits_the_actions_of_a_bundle() {
+        let mut rng = OsRng;
+
+        let recipient =
+            FullViewingKey::from(&SpendingKey::random(&mut rng)).address_at(0u32, Scope::External);
+
+        assert!(matches!(ProverOptions::default().max_actions(), Ok(None)));
+        let options = ProverOptions {
+            max_memory_hint: Some(ProverOptions::estimated_memory(3) - 1),
+            threads: None,
+        };
+        assert!(matches!(options.max_actions(), Ok(Some(2))));
+
+        // A budget that cannot fit the padded minimum of two actions allows no bundle.
+        let required = ProverOptions::estimated_memory(2);
+        assert!(matches!(
+            ProverOptions {
+                max_memory_hint: Some(required - 1),
+                threads: None,
+            }
+            .max_actions(),
+            Err(ProofError::WouldExceedBudget { required: r, budget })
+                if r == required && budget == required - 1
+        ));
+
+        let mut builder = Builder::with_config(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+            BuilderConfig {
+                max_actions: options.max_actions().unwrap(),
+                ..Default::default()
+            },
+        );
+        let mut add_output = || {
+            builder.add_output(
+                None,
+                recipient,
+                NoteValue::from_raw(1),
+                AssetBase::native(),
+                None,
+            )
+        };
+        assert_eq!(add_output(), Ok(()));
+        assert_eq!(add_output(), Ok(()));
+        assert_eq!(
+            add_output(),
+            Err(OutputError::Limit(LimitError::TooManyActions(2)))
+        );
+    }
+
+    #[test]
+    fn audited_build_records_action_randomness() {
+        let mut rng = OsRng;
+
+        let sk = SpendingKey::random(&mut rng);
+        let fvk = FullViewingKey::from(&sk);
+        let recipient = fvk.address_at(0u32, Scope::External);
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_output(
+                None,
+                recipient,
+                NoteValue::from_raw(5000),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+
+        let (bundle, _, trail) = builder.build_audited::<i64, _>(&mut rng).unwrap().unwrap();
+        assert_eq!(trail.actions().len(), bundle.actions().len());
+        assert!(!trail.consumed().is_empty());
+
+        // The recorded trapdoors are those from which the binding signing key is derived.
+        let bsk = trail
+            .actions()
+            .iter()
+            .map(|action| action.rcv())
+            .collect::<Vec<_>>()
+            .iter()
+            .sum::<ValueCommitTrapdoor>()
+            .into_bsk();
+        assert_eq!(
+            redpallas::VerificationKey::from(&bsk),
+            bundle.binding_validating_key()
+        );
+
+        let key = [7; 32];
+        let log = trail.encrypt(&key, &bundle.commitment());
+        assert_eq!(log.decrypt(&key, &bundle.commitment()), Ok(trail));
+        assert_eq!(
+            log.decrypt(&[8; 32], &bundle.commitment()),
+            Err(AuditError::Decryption)
+        );
+    }
+
+    #[test]
+    fn deterministic_build_is_reproducible() {
+        let sk = SpendingKey::from_bytes([7; 32]).unwrap();
+        let fvk = FullViewingKey::from(&sk);
+        let recipient = fvk.address_at(0u32, Scope::External);
+
+        let build = |seed: &[u8; 32]| {
+            let mut builder = Builder::new(
+                BundleType::DEFAULT_VANILLA,
+                EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+            );
+            builder
+                .add_output(
+                    None,
+                    recipient,
+                    NoteValue::from_raw(5000),
+                    AssetBase::native(),
+                    None,
+                )
+                .unwrap();
+            let (bundle, _) = builder.build_deterministic::<i64>(seed).unwrap().unwrap();
+            <[u8; 32]>::from(bundle.commitment())
+        };
+
+        assert_eq!(build(&[1; 32]), build(&[1; 32]));
+        assert_ne!(build(&[1; 32]), build(&[2; 32]));
+    }
+
+    #[test]
+    fn prebuilt_action_is_recorded_in_metadata() {
+        let mut rng = OsRng;
+
+        let sk = SpendingKey::random(&mut rng);
+        let fvk = FullViewingKey::from(&sk);
+        let recipient = fvk.address_at(0u32, Scope::External);
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_output(
+                None,
+                recipient,
+                NoteValue::from_raw(5000),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+
+        let (_, spend_fvk, spend_note) = Note::dummy(&mut rng, None, AssetBase::native());
+        let spend =
+            SpendInfo::new(spend_fvk, spend_note, MerklePath::dummy(&mut rng), false).unwrap();
+        let output = OutputInfo::new(
+            None,
+            recipient,
+            NoteValue::from_raw(1000),
+            AssetBase::native(),
+            None,
+        );
+        builder
+            .add_prebuilt_action(ActionInfo::new(spend, output, &mut rng))
+            .unwrap();
+
+        let balance: i64 = builder.value_balance().unwrap();
+        assert_eq!(balance, -6000);
+
+        let (bundle, meta) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+        assert_eq!(bundle.actions().len(), 2);
+        assert_eq!(bundle.value_balance(), &(-6000));
+
+        let prebuilt_idx = meta.prebuilt_action_index(0).unwrap();
+        let output_idx = meta.output_action_index(0).unwrap();
+        assert!(prebuilt_idx < 2);
+        assert_ne!(prebuilt_idx, output_idx);
+        assert_eq!(meta.prebuilt_action_index(1), None);
+    }
+
+    #[test]
+    fn build_errors_identify_the_invalid_action() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+        let prebuilt = |asset| {
+            let (_, spend_fvk, spend_note) = Note::dummy(&mut OsRng, None, AssetBase::native());
+            let spend = SpendInfo::new(spend_fvk, spend_note, MerklePath::dummy(&mut OsRng), false)
+                .unwrap();
+            let output = OutputInfo::new(None, recipient, NoteValue::from_raw(0), asset, None);
+            ActionInfo::new(spend, output, &mut OsRng)
+        };
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_ZSA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_prebuilt_action(prebuilt(AssetBase::native()))
+            .unwrap();
+        assert_eq!(
+            builder.add_prebuilt_action(prebuilt(AssetBase::random())),
+            Err(PrebuiltActionError::AssetMismatch)
+        );
+
+        let err = BuildError::PrebuiltAction {
+            index: 1,
+            error: PrebuiltActionError::AssetMismatch,
+        };
+        assert!(std::error::Error::source(&err).is_some());
+        assert_eq!(
+            err.to_string(),
+            "Pre-built action 1 is invalid: The spend and output of a pre-built action must have the same asset."
+        );
+    }
+
+    #[test]
+    fn prebuilt_actions_respect_bundle_type() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+        let (_, spend_fvk, spend_note) = Note::dummy(&mut rng, None, AssetBase::native());
+        let spend =
+            SpendInfo::new(spend_fvk, spend_note, MerklePath::dummy(&mut rng), false).unwrap();
+        let output = OutputInfo::new(
+            None,
+            recipient,
+            NoteValue::from_raw(0),
+            AssetBase::native(),
+            None,
+        );
+
+        let result = super::bundle_with_prebuilt_actions::<i64>(
+            &mut rng,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+            BundleType::Transactional {
+                flags: Flags::SPENDS_DISABLED,
+                bundle_required: false,
+            },
+            vec![],
+            vec![],
+            vec![ActionInfo::new(spend, output, &mut rng)],
+            Default::default(),
+        );
+        assert!(matches!(
+            result,
+            Err(BuildError::PrebuiltAction {
+                index: 0,
+                error: PrebuiltActionError::Spend(SpendError::SpendsDisabled),
+            })
+        ));
+    }
+
+    #[test]
+    fn disabled_spends_and_outputs_are_reported() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+        let (_, spend_fvk, spend_note) = Note::dummy(&mut rng, None, AssetBase::native());
+        let spend =
+            SpendInfo::new(spend_fvk, spend_note, MerklePath::dummy(&mut rng), false).unwrap();
+        let output = OutputInfo::new(
+            None,
+            recipient,
+            NoteValue::from_raw(10),
+            AssetBase::native(),
+            None,
+        );
+
+        let result = super::bundle::<i64>(
+            &mut rng,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+            BundleType::Coinbase,
+            vec![spend],
+            vec![],
+            Default::default(),
+        );
+        assert!(matches!(result, Err(BuildError::SpendsDisabled)));
+
+        let result = super::bundle::<i64>(
+            &mut rng,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+            BundleType::Transactional {
+                flags: Flags::OUTPUTS_DISABLED,
+                bundle_required: false,
+            },
+            vec![],
+            vec![output],
+            Default::default(),
+        );
+        assert!(matches!(result, Err(BuildError::OutputsDisabled)));
+    }
+
+    #[test]
+    fn stale_witnesses_are_diagnosed_and_refreshed() {
+        struct Tree(MerklePath);
+        impl WitnessSource for Tree {
+            fn witness_at(&self, position: u32, _: &Anchor) -> Option<MerklePath> {
+                (position == self.0.position()).then(|| self.0.clone())
+            }
+        }
+
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let note = Note::new(
+            fvk.address_at(0u32, Scope::External),
+            NoteValue::from_raw(10),
+            AssetBase::native(),
+            Rho::from_nf_old(Nullifier::dummy(&mut rng)),
+            &mut rng,
+        );
+        let current = MerklePath::dummy(&mut rng);
+        let stale =
+            MerklePath::from_parts(current.position(), MerklePath::dummy(&mut rng).auth_path());
+        let anchor = current.root(note.commitment().into());
+
+        let mut builder = Builder::new(BundleType::DEFAULT_VANILLA, anchor);
+
+        let diagnostics = builder.diagnose_spend_anchor(&note, &stale);
+        assert!(!diagnostics.is_match());
+        assert_eq!(diagnostics.expected(), anchor);
+        assert_eq!(diagnostics.computed(), stale.root(note.commitment().into()));
+        assert_eq!(diagnostics.position(), current.position());
+        assert!(builder.diagnose_spend_anchor(&note, &current).is_match());
+
+        assert_eq!(
+            builder.add_spend(fvk.clone(), note, stale.clone()),
+            Err(SpendError::AnchorMismatch)
+        );
+        assert_eq!(
+            builder.add_spend_with_witness_source(fvk, note, stale, &Tree(current)),
+            Ok(())
+        );
+        assert_eq!(builder.spends().len(), 1);
+    }
+
+    #[test]
+    fn append_signature_at_index() {
+        let mut rng = OsRng;
+
+        let (sk, fvk, note) = Note::dummy(&mut rng, None, AssetBase::native());
+        let merkle_path = MerklePath::dummy(&mut rng);
+        let anchor = merkle_path.root(note.commitment().into());
+
+        let mut builder = Builder::new(BundleType::DEFAULT_VANILLA, anchor);
+        builder.add_spend(fvk, note, merkle_path).unwrap();
+
+        let sighash = [7u8; 32];
+        let (bundle, _) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+        let bundle = bundle.prepare(&mut rng, sighash);
+
+        let unsigned = bundle.unsigned_actions();
+        assert_eq!(unsigned.len(), 1);
+        assert_eq!(bundle.signed_actions().len(), 1);
+        assert_eq!(bundle.required_signers(), vec![unsigned[0].1.clone()]);
+        assert!(bundle.signing_progress().only_dummies_signed());
+        assert!(bundle.is_dummy_action(bundle.signed_actions()[0]));
+        let (index, ak, alpha) = unsigned[0].clone();
+
+        let ask = SpendAuthorizingKey::from(&sk);
+        assert_eq!(SpendValidatingKey::from(&ask), ak);
+        let signature = ask.randomize(&alpha).sign(&mut rng, &sighash);
+
+        let bundle = bundle.append_signature_at(index, &signature).unwrap();
+        assert!(bundle.unsigned_actions().is_empty());
+        assert!(!bundle.is_dummy_action(index));
+        let progress = bundle.signing_progress();
+        assert_eq!((progress.collected(), progress.required()), (1, 1));
+        assert!(progress.is_complete());
+        assert!(matches!(
+            bundle.append_signature_at(index, &signature),
+            Err(BuildError::Signature(SignatureError::ActionAlreadySigned(i))) if i == index
+        ));
+    }
+
+    #[test]
+    fn builder_config_limits_are_enforced() {
+        let mut rng = OsRng;
+
+        let sk = SpendingKey::random(&mut rng);
+        let fvk = FullViewingKey::from(&sk);
+        let recipient = fvk.address_at(0u32, Scope::External);
+
+        let config = BuilderConfig {
+            max_actions: Some(2),
+            max_burns: Some(1),
+            max_assets: Some(2),
+        };
+        let mut builder = Builder::with_config(
+            BundleType::DEFAULT_ZSA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+            config,
+        );
+
+        for _ in 0..2 {
+            builder
+                .add_output(
+                    None,
+                    recipient,
+                    NoteValue::from_raw(1),
+                    AssetBase::native(),
+                    None,
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            builder.add_output(
+                None,
+                recipient,
+                NoteValue::from_raw(1),
+                AssetBase::native(),
+                None,
+            ),
+            Err(OutputError::Limit(LimitError::TooManyActions(2)))
+        );
+
+        let asset = AssetBase::random();
+        assert_eq!(builder.add_burn(asset, NoteValue::from_raw(1)), Ok(()));
+        assert_eq!(
+            builder.add_burn(AssetBase::random(), NoteValue::from_raw(1)),
+            Err(BurnError::Limit(LimitError::TooManyBurns(1)))
+        );
+    }
+
+    #[test]
+    fn dust_outputs_are_rejected() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+        let asset = AssetBase::random();
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_ZSA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        // The change output is added before the policy, so it is only rejected when the
+        // bundle is built.
+        builder
+            .add_change_output(
+                None,
+                recipient,
+                NoteValue::from_raw(500),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+        builder.set_dust_policy(DustPolicy {
+            min_native_value: NoteValue::from_raw(600),
+            min_zsa_value: NoteValue::from_raw(10),
+        });
+
+        assert_eq!(
+            builder.add_output(
+                None,
+                recipient,
+                NoteValue::from_raw(599),
+                AssetBase::native(),
+                None
+            ),
+            Err(OutputError::DustOutput(NoteValue::from_raw(600)))
+        );
+        assert_eq!(
+            builder.add_output(None, recipient, NoteValue::from_raw(9), asset, None),
+            Err(OutputError::DustOutput(NoteValue::from_raw(10)))
+        );
+        builder
+            .add_output(None, recipient, NoteValue::from_raw(10), asset, None)
+            .unwrap();
+
+        assert!(matches!(
+            builder.build::<i64>(&mut rng),
+            Err(BuildError::Output {
+                index: 0,
+                error: OutputError::DustOutput(minimum),
+            }) if minimum == NoteValue::from_raw(600)
+        ));
+    }
+
+    #[test]
+    fn change_is_not_split_into_dust() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let change_address = fvk.address_at(0u32, Scope::Internal);
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder.set_dust_policy(DustPolicy {
+            min_native_value: NoteValue::from_raw(600),
+            min_zsa_value: NoteValue::zero(),
+        });
+        builder.set_change_split_policy(
+            AssetBase::native(),
+            ChangeSplitPolicy::new(NoteValue::from_raw(1), 4).unwrap(),
+        );
+        builder
+            .add_change_output(
+                None,
+                change_address,
+                NoteValue::from_raw(1000),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+
+        // The change cannot be split into two notes of at least the minimum value.
+        let (_, meta) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+        assert_eq!(meta.change_action_indices(0).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn encoded_recipients_are_on_one_network() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+        let value = NoteValue::from_raw(10);
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        assert_eq!(builder.network(), None);
+        builder
+            .add_output_to_encoded_address(
+                None,
+                &recipient.encode(NetworkType::Test),
+                value,
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(builder.network(), Some(NetworkType::Test));
+
+        assert_eq!(
+            builder.add_output_to_encoded_address(
+                None,
+                &recipient.encode(NetworkType::Main),
+                value,
+                AssetBase::native(),
+                None,
+            ),
+            Err(OutputError::InvalidAddress(
+                ViewingKeyEncodingError::WrongNetwork(NetworkType::Main)
+            ))
+        );
+        assert_eq!(
+            builder.add_output_to_encoded_address(
+                None,
+                "not an address",
+                value,
+                AssetBase::native(),
+                None,
+            ),
+            Err(OutputError::InvalidAddress(
+                ViewingKeyEncodingError::InvalidEncoding
+            ))
+        );
+        assert_eq!(builder.outputs.len(), 1);
+    }
+
+    #[test]
+    fn change_is_split_according_to_policy() {
+        let mut rng = OsRng;
+
+        let sk = SpendingKey::random(&mut rng);
+        let fvk = FullViewingKey::from(&sk);
+        let recipient = fvk.address_at(0u32, Scope::External);
+        let change_address = fvk.address_at(0u32, Scope::Internal);
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder.set_change_split_policy(
+            AssetBase::native(),
+            ChangeSplitPolicy::new(NoteValue::from_raw(1000), 4).unwrap(),
+        );
+        builder
+            .add_output(
+                None,
+                recipient,
+                NoteValue::from_raw(5000),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+        builder
+            .add_change_output(
+                None,
+                change_address,
+                NoteValue::from_raw(3000),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+
+        let (bundle, meta) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+        assert_eq!(bundle.value_balance(), &(-8000));
+
+        let change_indices = meta.change_action_indices(0).unwrap();
+        assert!((2..=4).contains(&change_indices.len()));
+        assert_eq!(meta.output_action_index(1), Some(change_indices[0]));
+        assert_eq!(meta.output_action_index(2), None);
+        assert!(!change_indices.contains(&meta.output_action_index(0).unwrap()));
+    }
+
+    #[test]
+    fn change_split_produces_nonzero_parts() {
+        let mut rng = OsRng;
+        let policy = ChangeSplitPolicy::new(NoteValue::from_raw(2), 5).unwrap();
+
+        for value in [1u64, 2, 3, 10, 1_000_000] {
+            let parts = policy.split(NoteValue::from_raw(value), NoteValue::zero(), &mut rng);
+            assert!(parts.iter().all(|part| part.inner() > 0));
+            assert_eq!(parts.iter().map(|part| part.inner()).sum::<u64>(), value);
+        }
+
+        // Each part is at least the minimum, and there are at most as many parts as the
+        // minimum fits in the value.
+        for _ in 0..100 {
+            let parts = policy.split(
+                NoteValue::from_raw(1000),
+                NoteValue::from_raw(300),
+                &mut rng,
+            );
+            assert!((2..=3).contains(&parts.len()));
+            assert!(parts.iter().all(|part| part.inner() >= 300));
+            assert_eq!(parts.iter().map(|part| part.inner()).sum::<u64>(), 1000);
+        }
+        assert!(ChangeSplitPolicy::new(NoteValue::from_raw(2), 1).is_none());
+    }
+
+    #[test]
+    fn split_spends_are_recorded_in_metadata() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+        let asset = AssetBase::random();
+
+        let note = Note::new(
+            recipient,
+            NoteValue::from_raw(1000),
+            asset,
+            Rho::from_nf_old(Nullifier::dummy(&mut rng)),
+            &mut rng,
+        );
+        let mut tree = WitnessTracker::new();
+        tree.append(ExtractedNoteCommitment::from(note.commitment()))
+            .unwrap();
+        tree.mark().unwrap();
+
+        let mut builder = Builder::new(BundleType::DEFAULT_ZSA, tree.root());
+        builder
+            .add_spend(fvk, note, tree.witness(0).unwrap())
+            .unwrap();
+        for value in [600, 300, 100] {
+            builder
+                .add_output(None, recipient, NoteValue::from_raw(value), asset, None)
+                .unwrap();
+        }
+        let (bundle, meta) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+        assert_eq!(bundle.actions().len(), 3);
+
+        let spend_idx = meta.spend_action_index(0).unwrap();
+        assert_eq!(
+            meta.action_spend(spend_idx),
+            Some(ActionSpend::Requested(0))
+        );
+        let split_indices = meta.split_action_indices(0);
+        assert_eq!(split_indices.len(), 2);
+        for idx in split_indices {
+            assert_ne!(idx, spend_idx);
+            assert_eq!(meta.action_spend(idx), Some(ActionSpend::Split(0)));
+        }
+        assert_eq!(meta.action_spend(3), None);
+    }
+
+    #[test]
+    fn burns_are_emitted_in_canonical_order() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+
+        let notes: Vec<_> = (0..4)
+            .map(|_| {
+                Note::new(
+                    recipient,
+                    NoteValue::from_raw(10),
+                    AssetBase::random(),
+                    Rho::from_nf_old(Nullifier::dummy(&mut rng)),
+                    &mut rng,
+                )
+            })
+            .collect();
+        let mut tree = WitnessTracker::new();
+        for note in &notes {
+            tree.append(ExtractedNoteCommitment::from(note.commitment()))
+                .unwrap();
+            tree.mark().unwrap();
+        }
+
+        let mut builder = Builder::new(BundleType::DEFAULT_ZSA, tree.root());
+        for (position, note) in (0..).zip(&notes) {
+            builder
+                .add_spend(fvk.clone(), *note, tree.witness(position).unwrap())
+                .unwrap();
+            builder
+                .add_output(None, recipient, NoteValue::from_raw(7), note.asset(), None)
+                .unwrap();
+            builder
+                .add_burn(note.asset(), NoteValue::from_raw(3))
+                .unwrap();
+        }
+        let (bundle, _) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+
+        let burn: Vec<_> = bundle
+            .burn()
+            .iter()
+            .map(|(asset, value)| (*asset, NoteValue::from_raw(*value as u64)))
+            .collect();
+        assert_eq!(burn.len(), 4);
+        assert_eq!(validate_burn_list(&burn), Ok(()));
+    }
+
+    #[test]
+    fn deterministic_build_with_burns_is_reproducible() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+
+        let notes: Vec<_> = (0..3)
+            .map(|_| {
+                Note::new(
+                    recipient,
+                    NoteValue::from_raw(10),
+                    AssetBase::random(),
+                    Rho::from_nf_old(Nullifier::dummy(&mut rng)),
+                    &mut rng,
+                )
+            })
+            .collect();
+        let mut tree = WitnessTracker::new();
+        for note in &notes {
+            tree.append(ExtractedNoteCommitment::from(note.commitment()))
+                .unwrap();
+            tree.mark().unwrap();
+        }
+
+        let build = |seed: &[u8; 32]| {
+            let mut builder = Builder::new(BundleType::DEFAULT_ZSA, tree.root());
+            for (position, note) in (0..).zip(&notes) {
+                builder
+                    .add_spend(fvk.clone(), *note, tree.witness(position).unwrap())
+                    .unwrap();
+                builder
+                    .add_output(None, recipient, NoteValue::from_raw(7), note.asset(), None)
+                    .unwrap();
+                builder
+                    .add_burn(note.asset(), NoteValue::from_raw(3))
+                    .unwrap();
+            }
+            let (bundle, _) = builder.build_deterministic::<i64>(seed).unwrap().unwrap();
+            <[u8; 32]>::from(bundle.commitment())
+        };
+
+        assert_eq!(build(&[1; 32]), build(&[1; 32]));
+        assert_ne!(build(&[1; 32]), build(&[2; 32]));
+    }
+
+    #[test]
+    fn scoped_outputs_are_recoverable() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let payee =
+            FullViewingKey::from(&SpendingKey::random(&mut rng)).address_at(0u32, Scope::External);
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_output_with_scope(
+                &fvk,
+                Scope::External,
+                payee,
+                NoteValue::from_raw(3000),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+        builder
+            .add_output_with_scope(
+                &fvk,
+                Scope::Internal,
+                fvk.address_at(0u32, Scope::Internal),
+                NoteValue::from_raw(2000),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+        let (bundle, meta) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+        let payment_idx = meta.output_action_index(0).unwrap();
+        let change_idx = meta.output_action_index(1).unwrap();
+        assert_eq!(meta.change_action_indices(0), Some(&[change_idx][..]));
+
+        let external_ovk = fvk.to_ovk(Scope::External);
+        let internal_ovk = fvk.to_ovk(Scope::Internal);
+        assert!(bundle
+            .recover_output_with_ovk(payment_idx, &external_ovk)
+            .is_some());
+        assert!(bundle
+            .recover_output_with_ovk(change_idx, &internal_ovk)
+            .is_some());
+        assert!(bundle
+            .recover_output_with_ovk(change_idx, &external_ovk)
+            .is_none());
+        assert!(bundle.is_change_output(change_idx, &fvk));
+        assert!(!bundle.is_change_output(payment_idx, &fvk));
+
+        let ock = bundle.output_ock(change_idx, &internal_ovk).unwrap();
+        assert_eq!(
+            bundle
+                .recover_output_with_ock(change_idx, &ock)
+                .map(|(note, _, _)| note.value()),
+            Some(NoteValue::from_raw(2000))
+        );
+        assert!(bundle.recover_output_with_ock(payment_idx, &ock).is_none());
+    }
 }
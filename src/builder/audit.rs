@@ -0,0 +1,270 @@
+//! Audit trails of the randomness consumed while building a bundle.
+
+use core::fmt;
+
+use blake2b_simd::Params;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Nonce,
+};
+use rand::{CryptoRng, RngCore};
+
+use crate::{bundle::BundleCommitment, value::ValueCommitTrapdoor};
+
+const AUDIT_KDF_PERSONALIZATION: &[u8; 16] = b"Zcash_OrchAudKDF";
+
+/// The size of the encoding of an [`ActionRandomness`].
+const ACTION_RANDOMNESS_SIZE: usize = 96;
+
+/// A random number generator that records all of the randomness drawn from it.
+///
+/// [`Builder::build_audited`] draws its randomness through an `AuditingRng`, so that all
+/// of the randomness consumed by the builder is reported in the [`AuditTrail`] of the
+/// bundle.
+///
+/// [`Builder::build_audited`]: super::Builder::build_audited
+#[derive(Debug)]
+pub struct AuditingRng<R> {
+    inner: R,
+    consumed: Vec<u8>,
+}
+
+impl<R: RngCore + CryptoRng> AuditingRng<R> {
+    /// Wraps the given random number generator.
+    pub fn new(inner: R) -> Self {
+        AuditingRng {
+            inner,
+            consumed: vec![],
+        }
+    }
+
+    /// Returns the randomness drawn so far, in the order in which it was drawn.
+    pub fn consumed(&self) -> &[u8] {
+        &self.consumed
+    }
+
+    /// Returns the randomness drawn from this generator, discarding the generator.
+    pub fn into_consumed(self) -> Vec<u8> {
+        self.consumed
+    }
+}
+
+impl<R: RngCore> RngCore for AuditingRng<R> {
+    fn next_u32(&mut self) -> u32 {
+        let value = self.inner.next_u32();
+        self.consumed.extend_from_slice(&value.to_le_bytes());
+        value
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let value = self.inner.next_u64();
+        self.consumed.extend_from_slice(&value.to_le_bytes());
+        value
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.inner.fill_bytes(dest);
+        self.consumed.extend_from_slice(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.inner.try_fill_bytes(dest)?;
+        self.consumed.extend_from_slice(dest);
+        Ok(())
+    }
+}
+
+impl<R: CryptoRng> CryptoRng for AuditingRng<R> {}
+
+/// The randomness used to construct a single action of a bundle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActionRandomness {
+    rcv: [u8; 32],
+    alpha: [u8; 32],
+    rseed: [u8; 32],
+}
+
+impl ActionRandomness {
+    pub(crate) fn from_parts(rcv: [u8; 32], alpha: [u8; 32], rseed: [u8; 32]) -> Self {
+        ActionRandomness { rcv, alpha, rseed }
+    }
+
+    /// Returns the trapdoor of the value commitment of the action.
+    pub fn rcv(&self) -> ValueCommitTrapdoor {
+        ValueCommitTrapdoor::from_bytes(self.rcv).unwrap()
+    }
+
+    /// Returns the encoding of the randomizer of the spend validating key of the action.
+    pub fn alpha(&self) -> &[u8; 32] {
+        &self.alpha
+    }
+
+    /// Returns the seed randomness of the note created by the action.
+    pub fn rseed(&self) -> &[u8; 32] {
+        &self.rseed
+    }
+
+    fn to_bytes(&self) -> [u8; ACTION_RANDOMNESS_SIZE] {
+        let mut bytes = [0; ACTION_RANDOMNESS_SIZE];
+        bytes[..32].copy_from_slice(&self.rcv);
+        bytes[32..64].copy_from_slice(&self.alpha);
+        bytes[64..].copy_from_slice(&self.rseed);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let rcv: [u8; 32] = bytes[..32].try_into().unwrap();
+        Option::<ValueCommitTrapdoor>::from(ValueCommitTrapdoor::from_bytes(rcv))?;
+        Some(ActionRandomness {
+            rcv,
+            alpha: bytes[32..64].try_into().unwrap(),
+            rseed: bytes[64..].try_into().unwrap(),
+        })
+    }
+}
+
+/// A record of how a bundle was formed.
+///
+/// The trail holds the randomness used for each action of the bundle, in the order of
+/// the actions in the bundle, along with all of the randomness that the builder drew
+/// from its random number generator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditTrail {
+    actions: Vec<ActionRandomness>,
+    consumed: Vec<u8>,
+}
+
+impl AuditTrail {
+    pub(crate) fn from_parts(actions: Vec<ActionRandomness>, consumed: Vec<u8>) -> Self {
+        AuditTrail { actions, consumed }
+    }
+
+    /// Returns the randomness used for each action of the bundle.
+    pub fn actions(&self) -> &[ActionRandomness] {
+        &self.actions
+    }
+
+    /// Returns all of the randomness drawn by the builder, in the order in which it was
+    /// drawn.
+    pub fn consumed(&self) -> &[u8] {
+        &self.consumed
+    }
+
+    /// Encrypts this trail to `key`, binding it to the commitment of the bundle that it
+    /// describes.
+    ///
+    /// The encrypted log can only be decrypted with the same key and bundle commitment.
+    pub fn encrypt(&self, key: &[u8; 32], commitment: &BundleCommitment) -> EncryptedAuditLog {
+        let mut plaintext = Vec::with_capacity(
+            4 + self.actions.len() * ACTION_RANDOMNESS_SIZE + self.consumed.len(),
+        );
+        plaintext.extend_from_slice(&(self.actions.len() as u32).to_le_bytes());
+        for action in &self.actions {
+            plaintext.extend_from_slice(&action.to_bytes());
+        }
+        plaintext.extend_from_slice(&self.consumed);
+
+        let ciphertext = cipher(key, commitment)
+            .encrypt(
+                &Nonce::default(),
+                Payload {
+                    msg: &plaintext,
+                    aad: commitment.0.as_bytes(),
+                },
+            )
+            .expect("plaintext is not too long");
+
+        EncryptedAuditLog { ciphertext }
+    }
+}
+
+/// Derives the cipher for the audit log of the bundle with the given commitment.
+///
+/// As the encryption key is unique to the bundle, the log can be encrypted with the
+/// all-zero nonce.
+fn cipher(key: &[u8; 32], commitment: &BundleCommitment) -> ChaCha20Poly1305 {
+    let encryption_key = Params::new()
+        .hash_length(32)
+        .personal(AUDIT_KDF_PERSONALIZATION)
+        .to_state()
+        .update(key)
+        .update(commitment.0.as_bytes())
+        .finalize();
+    ChaCha20Poly1305::new_from_slice(encryption_key.as_bytes()).unwrap()
+}
+
+/// An [`AuditTrail`] encrypted to the key of an auditor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedAuditLog {
+    ciphertext: Vec<u8>,
+}
+
+impl EncryptedAuditLog {
+    /// Parses an encrypted audit log from its byte encoding.
+    pub fn from_bytes(ciphertext: Vec<u8>) -> Self {
+        EncryptedAuditLog { ciphertext }
+    }
+
+    /// Returns the byte encoding of this log.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.ciphertext
+    }
+
+    /// Decrypts this log with `key`, checking that it describes the bundle with the
+    /// given commitment.
+    pub fn decrypt(
+        &self,
+        key: &[u8; 32],
+        commitment: &BundleCommitment,
+    ) -> Result<AuditTrail, AuditError> {
+        let plaintext = cipher(key, commitment)
+            .decrypt(
+                &Nonce::default(),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: commitment.0.as_bytes(),
+                },
+            )
+            .map_err(|_| AuditError::Decryption)?;
+
+        if plaintext.len() < 4 {
+            return Err(AuditError::Malformed);
+        }
+        let (num_actions, rest) = plaintext.split_at(4);
+        let num_actions = u32::from_le_bytes(num_actions.try_into().unwrap()) as usize;
+        let actions_len = num_actions
+            .checked_mul(ACTION_RANDOMNESS_SIZE)
+            .filter(|len| *len <= rest.len())
+            .ok_or(AuditError::Malformed)?;
+        let (actions, consumed) = rest.split_at(actions_len);
+
+        Ok(AuditTrail {
+            actions: actions
+                .chunks_exact(ACTION_RANDOMNESS_SIZE)
+                .map(ActionRandomness::from_bytes)
+                .collect::<Option<_>>()
+                .ok_or(AuditError::Malformed)?,
+            consumed: consumed.to_vec(),
+        })
+    }
+}
+
+/// An error that can occur when decrypting an [`EncryptedAuditLog`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditError {
+    /// The log was not encrypted with the given key for the given bundle.
+    Decryption,
+    /// The decrypted log is not a valid encoding of an [`AuditTrail`].
+    Malformed,
+}
+
+impl fmt::Display for AuditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditError::Decryption => f.write_str("Failed to decrypt the audit log"),
+            AuditError::Malformed => f.write_str("Malformed audit log"),
+        }
+    }
+}
+
+impl std::error::Error for AuditError {}
@@ -0,0 +1,254 @@
+//! Creating the proofs of bundles on background threads.
+
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use rand::RngCore;
+
+use super::{BuildError, InProgress, InProgressSignatures, Unproven};
+use crate::{bundle::Bundle, circuit::ProvingKey, Proof};
+
+/// The result of creating a proof, or the panic of the prover.
+type ProofResult<S, V> =
+    Result<Result<Bundle<InProgress<Proof, S>, V>, BuildError>, Box<dyn Any + Send>>;
+
+/// The state of a proof being created, shared between its handle and the prover thread.
+struct State<S: InProgressSignatures, V> {
+    result: Option<ProofResult<S, V>>,
+    taken: bool,
+    waker: Option<Waker>,
+}
+
+impl<S: InProgressSignatures, V> State<S, V> {
+    fn take(&mut self) -> Option<ProofResult<S, V>> {
+        let result = self.result.take();
+        self.taken |= result.is_some();
+        result
+    }
+}
+
+struct Shared<S: InProgressSignatures, V> {
+    state: Mutex<State<S, V>>,
+    ready: Condvar,
+}
+
+/// A handle to the proof of a bundle being created on a background thread, as returned
+/// by [`Bundle::create_proof_spawned`].
+///
+/// The proven bundle can be polled for with [`ProofHandle::try_wait`], waited for with
+/// [`ProofHandle::wait`], or awaited, as the handle is a [`Future`]. If the prover
+/// panics, the panic is propagated to the caller when the result is taken.
+///
+/// The prover cannot be interrupted: dropping the handle discards the proof, but the
+/// background thread runs until the proof is created.
+pub struct ProofHandle<S: InProgressSignatures, V> {
+    shared: Arc<Shared<S, V>>,
+}
+
+impl<S: InProgressSignatures, V> fmt::Debug for ProofHandle<S, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProofHandle")
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+impl<S: InProgressSignatures, V> ProofHandle<S, V> {
+    /// Returns `true` if the proof has been created, or the prover has failed, including
+    /// once the result has been returned.
+    pub fn is_finished(&self) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        state.result.is_some() || state.taken
+    }
+
+    /// Returns the proven bundle if the proof has been created, without blocking.
+    ///
+    /// The result is only returned once: subsequent calls return `None`.
+    pub fn try_wait(&mut self) -> Option<Result<Bundle<InProgress<Proof, S>, V>, BuildError>> {
+        self.shared.state.lock().unwrap().take().map(unwrap_panic)
+    }
+
+    /// Blocks until the proof has been created, and returns the proven bundle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the result has already been returned by [`ProofHandle::try_wait`].
+    pub fn wait(self) -> Result<Bundle<InProgress<Proof, S>, V>, BuildError> {
+        let mut state = self.shared.state.lock().unwrap();
+        loop {
+            assert!(!state.taken, "the proven bundle has already been returned");
+            match state.take() {
+                Some(result) => return unwrap_panic(result),
+                None => state = self.shared.ready.wait(state).unwrap(),
+            }
+        }
+    }
+}
+
+impl<S: InProgressSignatures, V> Future for ProofHandle<S, V> {
+    type Output = Result<Bundle<InProgress<Proof, S>, V>, BuildError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.shared.state.lock().unwrap();
+        assert!(!state.taken, "the proven bundle has already been returned");
+        match state.take() {
+            Some(result) => Poll::Ready(unwrap_panic(result)),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+fn unwrap_panic<T>(result: Result<T, Box<dyn Any + Send>>) -> T {
+    result.unwrap_or_else(|e| panic::resume_unwind(e))
+}
+
+impl<S, V> Bundle<InProgress<Unproven, S>, V>
+where
+    S: InProgressSignatures + Send + 'static,
+    S::SpendAuth: Send,
+    V: Send + 'static,
+{
+    /// Creates the proof for this bundle on a new background thread.
+    ///
+    /// This behaves like [`Bundle::create_proof`], without blocking the calling thread
+    /// while the proof is created, which takes seconds on most devices.
+    pub fn create_proof_spawned(
+        self,
+        pk: Arc<ProvingKey>,
+        rng: impl RngCore + Send + 'static,
+    ) -> ProofHandle<S, V> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                result: None,
+                taken: false,
+                waker: None,
+            }),
+            ready: Condvar::new(),
+        });
+
+        let prover = shared.clone();
+        thread::spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| self.create_proof(&pk, rng)));
+
+            let mut state = prover.state.lock().unwrap();
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+            prover.ready.notify_all();
+        });
+
+        ProofHandle { shared }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+    use std::thread;
+    use std::time::Duration;
+
+    use rand::rngs::OsRng;
+
+    use crate::{
+        builder::{Builder, BundleType, UnauthorizedBundle},
+        circuit::ProvingKey,
+        constants::MERKLE_DEPTH_ORCHARD,
+        keys::{FullViewingKey, Scope, SpendingKey},
+        note::AssetBase,
+        tree::EMPTY_ROOTS,
+        value::NoteValue,
+    };
+
+    fn unproven_bundle() -> UnauthorizedBundle<i64> {
+        let mut rng = OsRng;
+        let recipient =
+            FullViewingKey::from(&SpendingKey::random(&mut rng)).address_at(0u32, Scope::External);
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_output(
+                None,
+                recipient,
+                NoteValue::from_raw(5000),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+        builder.build::<i64>(&mut rng).unwrap().unwrap().0
+    }
+
+    #[test]
+    fn spawned_proof_matches_bundle() {
+        let pk = Arc::new(ProvingKey::build());
+        let unauthorized = unproven_bundle();
+        let commitment = unauthorized.commitment();
+
+        let proven = unauthorized.create_proof_spawned(pk, OsRng).wait().unwrap();
+        assert_eq!(proven.commitment().0, commitment.0);
+    }
+
+    #[test]
+    fn spawned_proof_can_be_polled() {
+        let pk = Arc::new(ProvingKey::build());
+        let unauthorized = unproven_bundle();
+        let commitment = unauthorized.commitment();
+
+        let mut handle = unauthorized.create_proof_spawned(pk, OsRng);
+        let proven = loop {
+            match handle.try_wait() {
+                Some(result) => break result.unwrap(),
+                None => thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        assert_eq!(proven.commitment().0, commitment.0);
+
+        // The result is only returned once, but the proof remains finished.
+        assert!(handle.is_finished());
+        assert!(handle.try_wait().is_none());
+    }
+
+    /// Wakes a thread parked in [`block_on`].
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn spawned_proof_can_be_awaited() {
+        let pk = Arc::new(ProvingKey::build());
+        let unauthorized = unproven_bundle();
+        let commitment = unauthorized.commitment();
+
+        let proven = block_on(unauthorized.create_proof_spawned(pk, OsRng)).unwrap();
+        assert_eq!(proven.commitment().0, commitment.0);
+    }
+}
@@ -0,0 +1,428 @@
+//! Batching of many payments into as many bundles as needed.
+
+use core::fmt;
+
+use rand::RngCore;
+
+use super::{
+    BuildError, Builder, BundleType, OutputError, PaymentRequestItem, SpendError,
+    UnauthorizedBundle, MIN_ACTIONS,
+};
+use crate::{
+    address::Address,
+    keys::{FullViewingKey, OutgoingViewingKey},
+    note::{AssetBase, Note},
+    tree::{Anchor, MerklePath},
+    value::NoteValue,
+};
+
+/// A note chosen by a [`NoteSelector`] to fund payments, along with what is needed to
+/// spend it.
+#[derive(Clone, Debug)]
+pub struct SelectedNote {
+    /// The full viewing key that owns the note.
+    pub fvk: FullViewingKey,
+    /// The note to spend.
+    pub note: Note,
+    /// The Merkle path of the note to the anchor of the bundles.
+    pub merkle_path: MerklePath,
+}
+
+/// A source of spendable notes, such as a wallet.
+pub trait NoteSelector {
+    /// Selects unspent notes of `asset` whose total value is at least `target`, or
+    /// returns `None` if there are not enough funds.
+    ///
+    /// The selected notes must not be returned by later calls.
+    fn select_notes(&mut self, asset: AssetBase, target: NoteValue) -> Option<Vec<SelectedNote>>;
+}
+
+/// An error that can occur when building the bundles of a [`BatchPayer`].
+#[derive(Debug)]
+pub enum BatchPayError {
+    /// The total amount of the payments of a bundle is out of range once the payment at
+    /// the given index is added.
+    AmountOutOfRange(usize),
+    /// The note selector did not have enough funds for the payments of the asset.
+    InsufficientFunds(AssetBase),
+    /// The note selector selected more notes for the payments of the bundle at the given
+    /// index than fit in a bundle.
+    TooManyNotes(usize),
+    /// A selected note could not be spent.
+    Spend(SpendError),
+    /// A payment or change output could not be added.
+    Output(OutputError),
+    /// A bundle could not be built.
+    Build(BuildError),
+}
+
+impl fmt::Display for BatchPayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchPayError::AmountOutOfRange(index) => {
+                write!(f, "Total amount is out of range at payment {}", index)
+            }
+            BatchPayError::InsufficientFunds(_) => f.write_str("Insufficient funds"),
+            BatchPayError::TooManyNotes(bundle) => {
+                write!(f, "Too many notes were selected for bundle {}", bundle)
+            }
+            BatchPayError::Spend(e) => e.fmt(f),
+            BatchPayError::Output(e) => e.fmt(f),
+            BatchPayError::Build(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for BatchPayError {}
+
+/// A builder of the bundles paying a large number of recipients, such as the
+/// withdrawals processed by an exchange.
+///
+/// The payments are grouped by asset and split into bundles of at most `max_actions`
+/// actions. Each bundle pays a single asset, spends notes selected by a [`NoteSelector`]
+/// for exactly the payments it contains, and returns any excess to a change address, so
+/// that its value balance is zero. Transaction fees must be paid by other means.
+#[derive(Debug)]
+pub struct BatchPayer {
+    bundle_type: BundleType,
+    anchor: Anchor,
+    max_actions: usize,
+    ovk: Option<OutgoingViewingKey>,
+    change_address: Address,
+    payments: Vec<PaymentRequestItem>,
+}
+
+impl BatchPayer {
+    /// Constructs a new batch payer producing bundles of the given type, all sharing
+    /// `anchor`, and each containing at most `max_actions` actions.
+    ///
+    /// Returns `None` if `max_actions` does not leave room in each bundle for a payment
+    /// and a change output.
+    pub fn new(
+        bundle_type: BundleType,
+        anchor: Anchor,
+        max_actions: usize,
+        ovk: Option<OutgoingViewingKey>,
+        change_address: Address,
+    ) -> Option<Self> {
+        (max_actions >= MIN_ACTIONS).then(|| BatchPayer {
+            bundle_type,
+            anchor,
+            max_actions,
+            ovk,
+            change_address,
+            payments: vec![],
+        })
+    }
+
+    /// Adds a payment.
+    pub fn add_payment(&mut self, payment: PaymentRequestItem) {
+        self.payments.push(payment);
+    }
+
+    /// Adds several payments, in order.
+    pub fn add_payments(&mut self, payments: impl IntoIterator<Item = PaymentRequestItem>) {
+        self.payments.extend(payments);
+    }
+
+    /// Selects the notes to spend and builds the bundles.
+    ///
+    /// The returned bundles are ready to be proven and signed. Use the returned
+    /// [`BatchPayReport`] to reconcile the payments with the bundles.
+    pub fn build<V: TryFrom<i64>>(
+        self,
+        selector: &mut impl NoteSelector,
+        mut rng: impl RngCore,
+    ) -> Result<(Vec<UnauthorizedBundle<V>>, BatchPayReport), BatchPayError> {
+        // Group the payments by asset, in the order in which each asset first appears.
+        let mut groups: Vec<(AssetBase, Vec<usize>)> = vec![];
+        for (index, payment) in self.payments.iter().enumerate() {
+            let asset = payment.asset();
+            match groups.iter_mut().find(|(a, _)| *a == asset) {
+                Some((_, indices)) => indices.push(index),
+                None => groups.push((asset, vec![index])),
+            }
+        }
+
+        let mut bundles = vec![];
+        let mut report = BatchPayReport {
+            bundles: vec![],
+            payment_locations: vec![(0, 0); self.payments.len()],
+        };
+
+        // Leave room for the change output of each bundle.
+        let payments_per_bundle = self.max_actions - 1;
+        for (asset, indices) in groups {
+            for chunk in indices.chunks(payments_per_bundle) {
+                let bundle_idx = bundles.len();
+                let paid = chunk.iter().try_fold(0u64, |total, &index| {
+                    total
+                        .checked_add(self.payments[index].amount)
+                        .filter(|total| *total <= i64::MAX as u64)
+                        .ok_or(BatchPayError::AmountOutOfRange(index))
+                })?;
+
+                let notes = selector
+                    .select_notes(asset, NoteValue::from_raw(paid))
+                    .ok_or(BatchPayError::InsufficientFunds(asset))?;
+                if notes.len() > self.max_actions {
+                    return Err(BatchPayError::TooManyNotes(bundle_idx));
+                }
+                let selected = notes
+                    .iter()
+                    .try_fold(0u64, |total, selected| {
+                        total.checked_add(selected.note.value().inner())
+                    })
+                    .filter(|selected| *selected >= paid)
+                    .ok_or(BatchPayError::InsufficientFunds(asset))?;
+
+                let mut builder = Builder::new(self.bundle_type, self.anchor);
+                for selected in notes {
+                    builder
+                        .add_spend(selected.fvk, selected.note, selected.merkle_path)
+                        .map_err(BatchPayError::Spend)?;
+                }
+                for &index in chunk {
+                    let payment = &self.payments[index];
+                    builder
+                        .add_output(
+                            self.ovk.clone(),
+                            payment.recipient,
+                            NoteValue::from_raw(payment.amount),
+                            asset,
+                            payment.memo,
+                        )
+                        .map_err(BatchPayError::Output)?;
+                }
+                let change = selected - paid;
+                if change > 0 {
+                    builder
+                        .add_change_output(
+                            self.ovk.clone(),
+                            self.change_address,
+                            NoteValue::from_raw(change),
+                            asset,
+                            None,
+                        )
+                        .map_err(BatchPayError::Output)?;
+                }
+
+                let (bundle, bundle_meta) = builder
+                    .build(&mut rng)
+                    .map_err(BatchPayError::Build)?
+                    .expect("bundles are padded to contain at least MIN_ACTIONS actions");
+                for (n, &index) in chunk.iter().enumerate() {
+                    let action_idx = bundle_meta
+                        .output_action_index(n)
+                        .expect("every payment has an output");
+                    report.payment_locations[index] = (bundle_idx, action_idx);
+                }
+                report.bundles.push(BundleReport {
+                    asset,
+                    num_payments: chunk.len(),
+                    paid,
+                    selected,
+                    change,
+                    change_action_indices: bundle_meta
+                        .change_action_indices(0)
+                        .map_or_else(Vec::new, |indices| indices.to_vec()),
+                });
+                bundles.push(bundle);
+            }
+        }
+
+        Ok((bundles, report))
+    }
+}
+
+/// A summary of a bundle built by a [`BatchPayer`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BundleReport {
+    asset: AssetBase,
+    num_payments: usize,
+    paid: u64,
+    selected: u64,
+    change: u64,
+    change_action_indices: Vec<usize>,
+}
+
+impl BundleReport {
+    /// Returns the asset paid by the bundle.
+    pub fn asset(&self) -> AssetBase {
+        self.asset
+    }
+
+    /// Returns the number of payments in the bundle.
+    pub fn num_payments(&self) -> usize {
+        self.num_payments
+    }
+
+    /// Returns the total amount paid by the bundle.
+    pub fn paid(&self) -> u64 {
+        self.paid
+    }
+
+    /// Returns the total value of the notes spent by the bundle.
+    pub fn selected(&self) -> u64 {
+        self.selected
+    }
+
+    /// Returns the value returned to the change address.
+    pub fn change(&self) -> u64 {
+        self.change
+    }
+
+    /// Returns the indices of the actions holding the change, if any.
+    pub fn change_action_indices(&self) -> &[usize] {
+        &self.change_action_indices
+    }
+}
+
+/// A reconciliation of the payments of a [`BatchPayer`] with the bundles built for them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchPayReport {
+    bundles: Vec<BundleReport>,
+    payment_locations: Vec<(usize, usize)>,
+}
+
+impl BatchPayReport {
+    /// Returns the summaries of the bundles, in the order in which they were returned.
+    pub fn bundles(&self) -> &[BundleReport] {
+        &self.bundles
+    }
+
+    /// Returns the index of the bundle, and the index of the [`Action`] within that
+    /// bundle, that pays the `n`-th payment added to the [`BatchPayer`].
+    ///
+    /// [`Action`]: crate::action::Action
+    pub fn payment_location(&self, n: usize) -> Option<(usize, usize)> {
+        self.payment_locations.get(n).copied()
+    }
+
+    /// Returns the total amount paid of the given asset.
+    pub fn total_paid(&self, asset: AssetBase) -> u64 {
+        self.bundles
+            .iter()
+            .filter(|bundle| bundle.asset == asset)
+            .map(|bundle| bundle.paid)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::{BatchPayError, BatchPayer, NoteSelector, SelectedNote};
+    use crate::{
+        builder::{BundleType, PaymentRequestItem},
+        keys::{FullViewingKey, Scope, SpendingKey},
+        note::{AssetBase, ExtractedNoteCommitment, Note, Nullifier, Rho},
+        tree::WitnessTracker,
+        value::NoteValue,
+    };
+
+    /// Selects the first note that covers the target on its own.
+    struct FirstFitSelector {
+        notes: Vec<SelectedNote>,
+    }
+
+    impl NoteSelector for FirstFitSelector {
+        fn select_notes(
+            &mut self,
+            asset: AssetBase,
+            target: NoteValue,
+        ) -> Option<Vec<SelectedNote>> {
+            let position = self.notes.iter().position(|selected| {
+                selected.note.asset() == asset && selected.note.value().inner() >= target.inner()
+            })?;
+            Some(vec![self.notes.remove(position)])
+        }
+    }
+
+    #[test]
+    fn payments_are_split_across_bundles() {
+        let mut rng = OsRng;
+
+        let sk = SpendingKey::random(&mut rng);
+        let fvk = FullViewingKey::from(&sk);
+        let recipient = fvk.address_at(0u32, Scope::External);
+        let change_address = fvk.address_at(0u32, Scope::Internal);
+
+        let mut tree = WitnessTracker::new();
+        let notes = (0..2)
+            .map(|_| {
+                let note = Note::new(
+                    recipient,
+                    NoteValue::from_raw(1000),
+                    AssetBase::native(),
+                    Rho::from_nf_old(Nullifier::dummy(&mut rng)),
+                    &mut rng,
+                );
+                tree.append(ExtractedNoteCommitment::from(note.commitment()))
+                    .unwrap();
+                tree.mark().unwrap();
+                note
+            })
+            .collect::<Vec<_>>();
+        let mut selector = FirstFitSelector {
+            notes: notes
+                .into_iter()
+                .enumerate()
+                .map(|(position, note)| SelectedNote {
+                    fvk: fvk.clone(),
+                    note,
+                    merkle_path: tree.witness(position as u32).unwrap(),
+                })
+                .collect(),
+        };
+
+        let payments = |count| {
+            (1..=count).map(move |amount| PaymentRequestItem {
+                recipient,
+                amount,
+                asset: None,
+                memo: None,
+            })
+        };
+
+        // With room for three payments per bundle, five payments need two bundles.
+        let mut payer = BatchPayer::new(
+            BundleType::DEFAULT_VANILLA,
+            tree.root(),
+            4,
+            None,
+            change_address,
+        )
+        .unwrap();
+        payer.add_payments(payments(5));
+
+        let (bundles, report) = payer.build::<i64>(&mut selector, &mut rng).unwrap();
+        assert_eq!(bundles.len(), 2);
+        assert!(bundles.iter().all(|b| b.actions().len() <= 4));
+        assert!(bundles.iter().all(|b| *b.value_balance() == 0));
+        assert_eq!(report.total_paid(AssetBase::native()), 15);
+        assert_eq!(report.bundles()[0].change(), 994);
+        assert_eq!(report.bundles()[1].change(), 991);
+        for n in 0..5 {
+            let (bundle_idx, action_idx) = report.payment_location(n).unwrap();
+            assert_eq!(bundle_idx, n / 3);
+            assert!(action_idx < bundles[bundle_idx].actions().len());
+        }
+
+        // Both notes have been spent.
+        let mut payer = BatchPayer::new(
+            BundleType::DEFAULT_VANILLA,
+            tree.root(),
+            4,
+            None,
+            change_address,
+        )
+        .unwrap();
+        payer.add_payments(payments(1));
+        assert!(matches!(
+            payer.build::<i64>(&mut selector, &mut rng),
+            Err(BatchPayError::InsufficientFunds(_))
+        ));
+    }
+}
@@ -0,0 +1,234 @@
+//! Population of a builder from the items of a payment request.
+
+use core::fmt;
+use core::iter;
+use std::collections::HashMap;
+
+use super::{Builder, OutputError};
+use crate::{address::Address, keys::OutgoingViewingKey, note::AssetBase, value::NoteValue};
+
+/// The maximum amount of the native asset that can be requested in a single payment.
+const MAX_MONEY: u64 = 21_000_000 * 100_000_000;
+
+/// A payment to an Orchard address, as parsed from a [ZIP 321] payment request.
+///
+/// [ZIP 321]: https://zips.z.cash/zip-0321
+#[derive(Clone, Debug)]
+pub struct PaymentRequestItem {
+    /// The recipient of the payment.
+    pub recipient: Address,
+    /// The requested amount, in the smallest unit of `asset`.
+    pub amount: u64,
+    /// The requested asset, or `None` for the native asset.
+    pub asset: Option<AssetBase>,
+    /// The memo to attach to the payment, if any.
+    pub memo: Option<[u8; 512]>,
+}
+
+impl PaymentRequestItem {
+    /// Returns the requested asset.
+    pub fn asset(&self) -> AssetBase {
+        self.asset.unwrap_or_else(AssetBase::native)
+    }
+}
+
+/// An error for an invalid item of a payment request.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PaymentItemError {
+    /// The requested asset is not known to the wallet.
+    UnknownAsset(AssetBase),
+    /// The requested amount, or the total requested amount of its asset, is out of range.
+    AmountOutOfRange(u64),
+    /// The output for the item could not be added to the builder.
+    Output(OutputError),
+}
+
+impl fmt::Display for PaymentItemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentItemError::UnknownAsset(_) => f.write_str("Unknown asset"),
+            PaymentItemError::AmountOutOfRange(amount) => {
+                write!(f, "Amount {} is out of range", amount)
+            }
+            PaymentItemError::Output(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PaymentItemError {}
+
+impl Builder {
+    /// Adds an output for each item of a payment request.
+    ///
+    /// Each item must request either the native asset or one of the `known_assets`, and
+    /// the total amount requested for each asset must be within range. The outputs are
+    /// only added if every item is valid; otherwise the index of each invalid item is
+    /// returned along with its error, and the builder is left unchanged.
+    pub fn add_payment_request(
+        &mut self,
+        ovk: Option<OutgoingViewingKey>,
+        items: &[PaymentRequestItem],
+        known_assets: &[AssetBase],
+    ) -> Result<(), Vec<(usize, PaymentItemError)>> {
+        let mut errors = vec![];
+        let mut totals: HashMap<AssetBase, u64> = HashMap::new();
+        for (index, item) in items.iter().enumerate() {
+            let asset = item.asset();
+            let is_native = bool::from(asset.is_native());
+            if !is_native && !known_assets.contains(&asset) {
+                errors.push((index, PaymentItemError::UnknownAsset(asset)));
+                continue;
+            }
+
+            let max_amount = if is_native {
+                MAX_MONEY
+            } else {
+                i64::MAX as u64
+            };
+            let total = totals.entry(asset).or_default();
+            match total.checked_add(item.amount) {
+                Some(new_total) if new_total <= max_amount => *total = new_total,
+                _ => {
+                    errors.push((index, PaymentItemError::AmountOutOfRange(item.amount)));
+                    continue;
+                }
+            }
+
+            if let Err(e) = self
+                .dust_policy
+                .check(NoteValue::from_raw(item.amount), asset)
+            {
+                errors.push((index, PaymentItemError::Output(e)));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // Find the first item that cannot be added, so that the builder is left unchanged
+        // on failure.
+        if !self.bundle_type.flags().outputs_enabled() && !items.is_empty() {
+            return Err(vec![(
+                0,
+                PaymentItemError::Output(OutputError::OutputsDisabled),
+            )]);
+        }
+        for index in 0..items.len() {
+            self.check_limits(
+                self.spends.iter().map(|s| s.note.asset()),
+                self.outputs
+                    .iter()
+                    .map(|o| o.asset)
+                    .chain(items[..=index].iter().map(|item| item.asset())),
+            )
+            .map_err(|e| vec![(index, PaymentItemError::Output(OutputError::Limit(e)))])?;
+        }
+
+        for item in items {
+            self.add_output(
+                ovk.clone(),
+                item.recipient,
+                NoteValue::from_raw(item.amount),
+                item.asset(),
+                item.memo,
+            )
+            .expect("the outputs have been checked");
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::{PaymentItemError, PaymentRequestItem, MAX_MONEY};
+    use crate::{
+        builder::{Builder, BundleType, DustPolicy, OutputError},
+        constants::MERKLE_DEPTH_ORCHARD,
+        keys::{FullViewingKey, Scope, SpendingKey},
+        note::AssetBase,
+        tree::EMPTY_ROOTS,
+        value::NoteValue,
+    };
+
+    #[test]
+    fn payment_request_items_are_validated() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+        let known = AssetBase::random();
+        let unknown = AssetBase::random();
+
+        let item = |amount, asset| PaymentRequestItem {
+            recipient,
+            amount,
+            asset,
+            memo: None,
+        };
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_ZSA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+
+        assert_eq!(
+            builder.add_payment_request(
+                None,
+                &[
+                    item(5, None),
+                    item(3, Some(unknown)),
+                    item(MAX_MONEY, None),
+                    item(7, Some(known)),
+                ],
+                &[known],
+            ),
+            Err(vec![
+                (1, PaymentItemError::UnknownAsset(unknown)),
+                (2, PaymentItemError::AmountOutOfRange(MAX_MONEY)),
+            ])
+        );
+        assert!(builder.outputs().is_empty());
+
+        assert_eq!(
+            builder.add_payment_request(None, &[item(5, None), item(7, Some(known))], &[known]),
+            Ok(())
+        );
+        assert_eq!(builder.outputs().len(), 2);
+    }
+
+    #[test]
+    fn dust_payment_request_items_are_rejected() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+
+        let item = |amount| PaymentRequestItem {
+            recipient,
+            amount,
+            asset: None,
+            memo: None,
+        };
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder.set_dust_policy(DustPolicy {
+            min_native_value: NoteValue::from_raw(600),
+            min_zsa_value: NoteValue::zero(),
+        });
+
+        assert_eq!(
+            builder.add_payment_request(None, &[item(600), item(599)], &[]),
+            Err(vec![(
+                1,
+                PaymentItemError::Output(OutputError::DustOutput(NoteValue::from_raw(600)))
+            )])
+        );
+        assert!(builder.outputs().is_empty());
+    }
+}
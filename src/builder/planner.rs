@@ -0,0 +1,341 @@
+//! Planning of transfers that do not fit into a single bundle.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use rand::RngCore;
+
+use super::{
+    BuildError, Builder, BundleMetadata, BundleType, BurnError, OutputError, OutputInfo,
+    SpendError, SpendInfo, UnauthorizedBundle, MIN_ACTIONS,
+};
+use crate::{
+    address::Address,
+    keys::{FullViewingKey, OutgoingViewingKey},
+    note::{AssetBase, Note},
+    tree::{Anchor, MerklePath},
+    value::NoteValue,
+};
+
+/// A planner that distributes spends and outputs over as many bundles as needed to
+/// respect a limit on the number of actions per bundle.
+///
+/// The spends and outputs of a non-native asset are always placed in the same bundle, so
+/// that every bundle balances for each non-native asset. Native spends and outputs may be
+/// spread over several bundles, in which case each bundle has its own value balance and is
+/// expected to be included in a separate transaction.
+#[derive(Debug)]
+pub struct BundlePlanner {
+    builder: Builder,
+    max_actions: usize,
+}
+
+impl BundlePlanner {
+    /// Constructs a new empty planner producing bundles of the given type, all sharing
+    /// `anchor`, and each containing at most `max_actions` actions.
+    ///
+    /// Returns `None` if `max_actions` is smaller than the minimum number of actions in
+    /// a bundle.
+    pub fn new(bundle_type: BundleType, anchor: Anchor, max_actions: usize) -> Option<Self> {
+        (max_actions >= MIN_ACTIONS).then(|| BundlePlanner {
+            builder: Builder::new(bundle_type, anchor),
+            max_actions,
+        })
+    }
+
+    /// Adds a note to be spent. See [`Builder::add_spend`].
+    pub fn add_spend(
+        &mut self,
+        fvk: FullViewingKey,
+        note: Note,
+        merkle_path: MerklePath,
+    ) -> Result<(), SpendError> {
+        self.builder.add_spend(fvk, note, merkle_path)
+    }
+
+    /// Adds an address which will receive funds. See [`Builder::add_output`].
+    pub fn add_output(
+        &mut self,
+        ovk: Option<OutgoingViewingKey>,
+        recipient: Address,
+        value: NoteValue,
+        asset: AssetBase,
+        memo: Option<[u8; 512]>,
+    ) -> Result<(), OutputError> {
+        self.builder.add_output(ovk, recipient, value, asset, memo)
+    }
+
+    /// Adds a burn of a non-native asset. See [`Builder::add_burn`].
+    ///
+    /// The burn is placed in the bundle that contains the spends of `asset`.
+    pub fn add_burn(&mut self, asset: AssetBase, value: NoteValue) -> Result<(), BurnError> {
+        self.builder.add_burn(asset, value)
+    }
+
+    /// Distributes the spends, outputs and burns over as few [`Builder`]s as possible.
+    ///
+    /// Returns the builders along with, for each builder, the indices of the spends and
+    /// outputs that were assigned to it, in the order in which they were added to it.
+    fn plan(self) -> Result<Vec<PlannedBuilder>, BuildError> {
+        let Builder {
+            spends,
+            outputs,
+            burn,
+            bundle_type,
+            anchor,
+            ..
+        } = self.builder;
+        let max_actions = self.max_actions;
+
+        let mut groups: HashMap<AssetBase, (Vec<usize>, Vec<usize>)> = HashMap::new();
+        for (i, spend) in spends.iter().enumerate() {
+            groups.entry(spend.note.asset()).or_default().0.push(i);
+        }
+        for (i, output) in outputs.iter().enumerate() {
+            groups.entry(output.asset).or_default().1.push(i);
+        }
+        let (native_spends, native_outputs) =
+            groups.remove(&AssetBase::native()).unwrap_or_default();
+
+        let new_slot = || PlannedBuilder::new(Builder::new(bundle_type, anchor));
+        let mut slots: Vec<PlannedBuilder> = vec![];
+
+        // Place the largest asset groups first, each in the first bundle with enough room.
+        let mut groups = groups.into_iter().collect::<Vec<_>>();
+        groups.sort_by_key(|(_, (spends, outputs))| Reverse(spends.len().max(outputs.len())));
+        for (asset, (spend_idxs, output_idxs)) in groups {
+            let num_actions = spend_idxs.len().max(output_idxs.len());
+            if num_actions > max_actions {
+                return Err(BuildError::AssetGroupTooLarge(asset));
+            }
+
+            let slot_idx = match slots
+                .iter()
+                .position(|slot| slot.num_actions + num_actions <= max_actions)
+            {
+                Some(slot_idx) => slot_idx,
+                None => {
+                    slots.push(new_slot());
+                    slots.len() - 1
+                }
+            };
+            slots[slot_idx].assign(&spends, &outputs, spend_idxs, output_idxs);
+        }
+
+        // Native spends and outputs fill up the remaining room.
+        let mut native_spends = native_spends.into_iter();
+        let mut native_outputs = native_outputs.into_iter();
+        let mut slot_idx = 0;
+        while native_spends.len() > 0 || native_outputs.len() > 0 {
+            if slot_idx == slots.len() {
+                slots.push(new_slot());
+            }
+            let slot = &mut slots[slot_idx];
+            let room = max_actions - slot.num_actions;
+            let spend_idxs = native_spends.by_ref().take(room).collect();
+            let output_idxs = native_outputs.by_ref().take(room).collect();
+            slot.assign(&spends, &outputs, spend_idxs, output_idxs);
+            slot_idx += 1;
+        }
+
+        for (asset, value) in burn {
+            let slot_idx = match slots.iter().position(|slot| slot.contains_asset(&asset)) {
+                Some(slot_idx) => slot_idx,
+                None if slots.is_empty() => {
+                    slots.push(new_slot());
+                    0
+                }
+                None => 0,
+            };
+            slots[slot_idx].builder.burn.insert(asset, value);
+        }
+
+        Ok(slots)
+    }
+
+    /// Builds the planned bundles.
+    ///
+    /// The returned bundles are ready to be proven and signed. Use the returned
+    /// [`PlanMetadata`] to locate each spend and output among them.
+    pub fn build<V: TryFrom<i64>>(
+        self,
+        mut rng: impl RngCore,
+    ) -> Result<(Vec<UnauthorizedBundle<V>>, PlanMetadata), BuildError> {
+        let num_spends = self.builder.spends.len();
+        let num_outputs = self.builder.outputs.len();
+
+        let mut bundles = vec![];
+        let mut plan_meta = PlanMetadata {
+            bundles: vec![],
+            spend_locations: vec![(0, 0); num_spends],
+            output_locations: vec![(0, 0); num_outputs],
+        };
+
+        for (bundle_idx, slot) in self.plan()?.into_iter().enumerate() {
+            for (n, spend_idx) in slot.spend_idxs.into_iter().enumerate() {
+                plan_meta.spend_locations[spend_idx] = (bundle_idx, n);
+            }
+            for (n, output_idx) in slot.output_idxs.into_iter().enumerate() {
+                plan_meta.output_locations[output_idx] = (bundle_idx, n);
+            }
+
+            let (bundle, bundle_meta) = slot
+                .builder
+                .build(&mut rng)?
+                .expect("bundles are padded to contain at least MIN_ACTIONS actions");
+            bundles.push(bundle);
+            plan_meta.bundles.push(bundle_meta);
+        }
+
+        Ok((bundles, plan_meta))
+    }
+}
+
+/// A [`Builder`] with the spends and outputs assigned to it by a [`BundlePlanner`].
+#[derive(Debug)]
+struct PlannedBuilder {
+    builder: Builder,
+    spend_idxs: Vec<usize>,
+    output_idxs: Vec<usize>,
+    num_actions: usize,
+}
+
+impl PlannedBuilder {
+    fn new(builder: Builder) -> Self {
+        PlannedBuilder {
+            builder,
+            spend_idxs: vec![],
+            output_idxs: vec![],
+            num_actions: 0,
+        }
+    }
+
+    fn assign(
+        &mut self,
+        spends: &[SpendInfo],
+        outputs: &[OutputInfo],
+        spend_idxs: Vec<usize>,
+        output_idxs: Vec<usize>,
+    ) {
+        self.num_actions += spend_idxs.len().max(output_idxs.len());
+        self.builder
+            .spends
+            .extend(spend_idxs.iter().map(|&i| spends[i].clone()));
+        self.builder
+            .outputs
+            .extend(output_idxs.iter().map(|&i| outputs[i].clone()));
+        self.spend_idxs.extend(spend_idxs);
+        self.output_idxs.extend(output_idxs);
+    }
+
+    fn contains_asset(&self, asset: &AssetBase) -> bool {
+        self.builder.spends.iter().any(|s| &s.note.asset() == asset)
+            || self.builder.outputs.iter().any(|o| &o.asset == asset)
+    }
+}
+
+/// Metadata about the bundles produced by a [`BundlePlanner`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlanMetadata {
+    bundles: Vec<BundleMetadata>,
+    spend_locations: Vec<(usize, usize)>,
+    output_locations: Vec<(usize, usize)>,
+}
+
+impl PlanMetadata {
+    /// Returns the number of bundles in the plan.
+    pub fn num_bundles(&self) -> usize {
+        self.bundles.len()
+    }
+
+    /// Returns the metadata of the `i`-th bundle in the plan.
+    pub fn bundle_metadata(&self, i: usize) -> Option<&BundleMetadata> {
+        self.bundles.get(i)
+    }
+
+    /// Returns the index of the bundle, and the index of the [`Action`] within that
+    /// bundle, corresponding to the `n`-th spend added to the [`BundlePlanner`].
+    ///
+    /// [`Action`]: crate::action::Action
+    pub fn spend_action_index(&self, n: usize) -> Option<(usize, usize)> {
+        let (bundle_idx, local_n) = *self.spend_locations.get(n)?;
+        self.bundles[bundle_idx]
+            .spend_action_index(local_n)
+            .map(|action_idx| (bundle_idx, action_idx))
+    }
+
+    /// Returns the index of the bundle, and the index of the [`Action`] within that
+    /// bundle, corresponding to the `n`-th output added to the [`BundlePlanner`].
+    ///
+    /// [`Action`]: crate::action::Action
+    pub fn output_action_index(&self, n: usize) -> Option<(usize, usize)> {
+        let (bundle_idx, local_n) = *self.output_locations.get(n)?;
+        self.bundles[bundle_idx]
+            .output_action_index(local_n)
+            .map(|action_idx| (bundle_idx, action_idx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::BundlePlanner;
+    use crate::{
+        builder::BundleType,
+        constants::MERKLE_DEPTH_ORCHARD,
+        keys::{FullViewingKey, Scope, SpendingKey},
+        note::AssetBase,
+        tree::EMPTY_ROOTS,
+        value::NoteValue,
+    };
+
+    #[test]
+    fn outputs_are_split_across_bundles() {
+        let mut rng = OsRng;
+
+        let sk = SpendingKey::random(&mut rng);
+        let fvk = FullViewingKey::from(&sk);
+        let recipient = fvk.address_at(0u32, Scope::External);
+
+        let mut planner = BundlePlanner::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+            3,
+        )
+        .unwrap();
+        for value in 1..=7 {
+            planner
+                .add_output(
+                    None,
+                    recipient,
+                    NoteValue::from_raw(value),
+                    AssetBase::native(),
+                    None,
+                )
+                .unwrap();
+        }
+
+        let (bundles, meta) = planner.build::<i64>(&mut rng).unwrap();
+        assert_eq!(bundles.len(), 3);
+        assert_eq!(meta.num_bundles(), 3);
+        assert!(bundles.iter().all(|b| b.actions().len() <= 3));
+        assert_eq!(bundles.iter().map(|b| b.value_balance()).sum::<i64>(), -28);
+
+        for n in 0..7 {
+            let (bundle_idx, action_idx) = meta.output_action_index(n).unwrap();
+            assert!(action_idx < bundles[bundle_idx].actions().len());
+        }
+        assert_eq!(meta.output_action_index(7), None);
+    }
+
+    #[test]
+    fn max_actions_must_allow_a_bundle() {
+        assert!(BundlePlanner::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+            1,
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,245 @@
+//! Redaction of bundle metadata before it is persisted.
+
+use std::io::{self, Read, Write};
+
+use super::BundleMetadata;
+
+/// The parts of a [`BundleMetadata`] that are kept by [`BundleMetadata::redact`].
+///
+/// Each part maps the spends or outputs given to the builder to the positions of their
+/// actions in the bundle, which undoes the shuffling of the actions. A wallet should only
+/// keep the parts that it needs once the bundle has been built.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RedactionPolicy {
+    /// Whether to keep the action indices of the spends.
+    pub spends: bool,
+    /// Whether to keep the action indices of the outputs.
+    pub outputs: bool,
+    /// Whether to keep the action indices of the pre-built actions.
+    pub prebuilt_actions: bool,
+    /// Whether to keep the action indices of the notes that the change outputs were
+    /// split into.
+    pub change: bool,
+}
+
+impl RedactionPolicy {
+    /// Removes all of the action indices.
+    pub const REDACT_ALL: RedactionPolicy = RedactionPolicy {
+        spends: false,
+        outputs: false,
+        prebuilt_actions: false,
+        change: false,
+    };
+
+    /// Keeps only the action indices of the change outputs, which a wallet needs in
+    /// order to track its change notes.
+    pub const CHANGE_ONLY: RedactionPolicy = RedactionPolicy {
+        change: true,
+        ..RedactionPolicy::REDACT_ALL
+    };
+
+    /// Keeps all of the action indices.
+    pub const KEEP_ALL: RedactionPolicy = RedactionPolicy {
+        spends: true,
+        outputs: true,
+        prebuilt_actions: true,
+        change: true,
+    };
+}
+
+/// The metadata of a bundle, reduced to the parts selected by a [`RedactionPolicy`].
+///
+/// This is the form in which the metadata of a bundle can be persisted: unlike
+/// [`BundleMetadata`], it can be serialized, and it never holds the openings of the value
+/// commitments or the randomness of the actions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RedactedBundleMetadata {
+    spend_indices: Option<Vec<usize>>,
+    output_indices: Option<Vec<usize>>,
+    prebuilt_indices: Option<Vec<usize>>,
+    change_indices: Option<Vec<Vec<usize>>>,
+}
+
+impl BundleMetadata {
+    /// Returns the parts of this metadata selected by `policy`.
+    pub fn redact(&self, policy: RedactionPolicy) -> RedactedBundleMetadata {
+        RedactedBundleMetadata {
+            spend_indices: policy.spends.then(|| self.spend_indices.clone()),
+            output_indices: policy.outputs.then(|| self.output_indices.clone()),
+            prebuilt_indices: policy
+                .prebuilt_actions
+                .then(|| self.prebuilt_indices.clone()),
+            change_indices: policy.change.then(|| self.change_indices.clone()),
+        }
+    }
+}
+
+impl RedactedBundleMetadata {
+    /// Returns the policy that this metadata was redacted with.
+    pub fn policy(&self) -> RedactionPolicy {
+        RedactionPolicy {
+            spends: self.spend_indices.is_some(),
+            outputs: self.output_indices.is_some(),
+            prebuilt_actions: self.prebuilt_indices.is_some(),
+            change: self.change_indices.is_some(),
+        }
+    }
+
+    /// Returns the index of the action corresponding to the `n`-th spend, as in
+    /// [`BundleMetadata::spend_action_index`], or `None` if it was redacted.
+    pub fn spend_action_index(&self, n: usize) -> Option<usize> {
+        self.spend_indices.as_ref()?.get(n).copied()
+    }
+
+    /// Returns the index of the action corresponding to the `n`-th output, as in
+    /// [`BundleMetadata::output_action_index`], or `None` if it was redacted.
+    pub fn output_action_index(&self, n: usize) -> Option<usize> {
+        self.output_indices.as_ref()?.get(n).copied()
+    }
+
+    /// Returns the index of the action corresponding to the `n`-th pre-built action, as
+    /// in [`BundleMetadata::prebuilt_action_index`], or `None` if it was redacted.
+    pub fn prebuilt_action_index(&self, n: usize) -> Option<usize> {
+        self.prebuilt_indices.as_ref()?.get(n).copied()
+    }
+
+    /// Returns the indices of the actions holding the parts of the `n`-th change output,
+    /// as in [`BundleMetadata::change_action_indices`], or `None` if they were redacted.
+    pub fn change_action_indices(&self, n: usize) -> Option<&[usize]> {
+        self.change_indices
+            .as_ref()?
+            .get(n)
+            .map(|indices| &indices[..])
+    }
+
+    /// Serializes this metadata.
+    ///
+    /// Each part is encoded as a presence byte, followed if present by the number of
+    /// indices and the indices themselves, as little-endian 32-bit integers. The indices
+    /// of each change output are encoded as a list of their own.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_optional(&mut writer, &self.spend_indices, |writer, indices| {
+            write_indices(writer, indices)
+        })?;
+        write_optional(&mut writer, &self.output_indices, |writer, indices| {
+            write_indices(writer, indices)
+        })?;
+        write_optional(&mut writer, &self.prebuilt_indices, |writer, indices| {
+            write_indices(writer, indices)
+        })?;
+        write_optional(&mut writer, &self.change_indices, |writer, parts| {
+            write_u32(writer, parts.len())?;
+            parts
+                .iter()
+                .try_for_each(|indices| write_indices(writer, indices))
+        })
+    }
+
+    /// Parses metadata serialized with [`RedactedBundleMetadata::write`].
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        Ok(RedactedBundleMetadata {
+            spend_indices: read_optional(&mut reader, read_indices)?,
+            output_indices: read_optional(&mut reader, read_indices)?,
+            prebuilt_indices: read_optional(&mut reader, read_indices)?,
+            change_indices: read_optional(&mut reader, |reader| {
+                (0..read_u32(reader)?)
+                    .map(|_| read_indices(reader))
+                    .collect()
+            })?,
+        })
+    }
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: usize) -> io::Result<()> {
+    let value = u32::try_from(value)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Index out of range"))?;
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn write_indices<W: Write>(writer: &mut W, indices: &[usize]) -> io::Result<()> {
+    write_u32(writer, indices.len())?;
+    indices
+        .iter()
+        .try_for_each(|index| write_u32(writer, *index))
+}
+
+fn read_indices<R: Read>(reader: &mut R) -> io::Result<Vec<usize>> {
+    (0..read_u32(reader)?)
+        .map(|_| read_u32(reader).map(|index| index as usize))
+        .collect()
+}
+
+fn write_optional<W: Write, T>(
+    writer: &mut W,
+    value: &Option<T>,
+    write: impl FnOnce(&mut W, &T) -> io::Result<()>,
+) -> io::Result<()> {
+    match value {
+        None => writer.write_all(&[0]),
+        Some(value) => {
+            writer.write_all(&[1])?;
+            write(writer, value)
+        }
+    }
+}
+
+fn read_optional<R: Read, T>(
+    reader: &mut R,
+    read: impl FnOnce(&mut R) -> io::Result<T>,
+) -> io::Result<Option<T>> {
+    let mut present = [0];
+    reader.read_exact(&mut present)?;
+    match present[0] {
+        0 => Ok(None),
+        1 => read(reader).map(Some),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid presence flag in bundle metadata",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RedactedBundleMetadata, RedactionPolicy};
+    use crate::builder::BundleMetadata;
+
+    #[test]
+    fn redaction_round_trip() {
+        let mut metadata = BundleMetadata::new(2, 3, 1);
+        metadata.spend_indices = vec![3, 0];
+        metadata.output_indices = vec![1, 2, 4];
+        metadata.prebuilt_indices = vec![5];
+        metadata.record_change_parts(vec![vec![1, 2]], 1);
+
+        let redacted = metadata.redact(RedactionPolicy::CHANGE_ONLY);
+        assert_eq!(redacted.policy(), RedactionPolicy::CHANGE_ONLY);
+        assert_eq!(redacted.spend_action_index(0), None);
+        assert_eq!(redacted.output_action_index(0), None);
+        assert_eq!(redacted.change_action_indices(0), Some(&[2, 4][..]));
+
+        for policy in [
+            RedactionPolicy::REDACT_ALL,
+            RedactionPolicy::CHANGE_ONLY,
+            RedactionPolicy::KEEP_ALL,
+        ] {
+            let redacted = metadata.redact(policy);
+            let mut bytes = vec![];
+            redacted.write(&mut bytes).unwrap();
+            assert_eq!(RedactedBundleMetadata::read(&bytes[..]).unwrap(), redacted);
+        }
+
+        let full = metadata.redact(RedactionPolicy::KEEP_ALL);
+        assert_eq!(full.spend_action_index(0), Some(3));
+        assert_eq!(full.output_action_index(0), Some(1));
+        assert_eq!(full.prebuilt_action_index(0), Some(5));
+
+        assert!(RedactedBundleMetadata::read(&[2, 0, 0, 0][..]).is_err());
+    }
+}
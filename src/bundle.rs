@@ -1,32 +1,64 @@
 //! Structs related to bundles of Orchard actions.
 
+#[cfg(feature = "circuit")]
 mod batch;
+mod burn_receipt;
 pub mod burn_validation;
 pub mod commitments;
-
-pub use batch::BatchValidator;
+mod flows;
+mod openings;
+pub mod parse;
+mod scanning;
+#[cfg(feature = "circuit")]
+mod self_check;
+pub mod sighash;
+mod size;
+mod witness;
+
+#[cfg(feature = "circuit")]
+pub use batch::{BatchFailure, BatchValidator, FailedCheck, ParallelVerifier, PendingBundle};
+pub use burn_receipt::{BurnReceipt, BurnReceiptError};
+pub use flows::AssetFlow;
+pub use openings::{OpeningError, ValueOpening, ValueOpenings};
+pub(crate) use parse::{read_compact_size, write_compact_size};
+pub use scanning::{OutputKind, ReceivedOutput};
+#[cfg(feature = "circuit")]
+pub use self_check::{SelfCheckReport, StructuralError};
+pub(crate) use size::compact_size_len;
+pub use size::VERIFICATION_WEIGHT_FACTOR;
+pub use witness::{ActionWitnessHash, WitnessHashes};
 
 use core::fmt;
 
 use blake2b_simd::Hash as Blake2bHash;
 use memuse::DynamicUsage;
 use nonempty::NonEmpty;
-use zcash_note_encryption_zsa::{try_note_decryption, try_output_recovery_with_ovk};
+use zcash_note_encryption_zsa::{
+    try_compact_note_decryption, try_note_decryption, try_output_recovery_with_ock,
+    try_output_recovery_with_ovk, EphemeralKeyBytes,
+};
 
-use crate::note::AssetBase;
+use crate::note::{AssetBase, NullifierIndex};
 use crate::{
     action::Action,
     address::Address,
     bundle::commitments::{hash_bundle_auth_data, hash_bundle_txid_data},
-    circuit::{Instance, Proof, VerifyingKey},
-    keys::{IncomingViewingKey, OutgoingViewingKey, PreparedIncomingViewingKey},
+    keys::{
+        FullViewingKey, IncomingViewingKey, OutgoingViewingKey, PreparedIncomingViewingKey,
+        ScanningKeys, Scope,
+    },
     note::Note,
-    note_encryption_v3::OrchardDomainV3,
+    note_encryption_v3::{prf_ock_orchard, OrchardDomainV3, OutgoingCipherKey},
     primitives::redpallas::{self, Binding, SpendAuth},
     tree::Anchor,
     value::{ValueCommitTrapdoor, ValueCommitment, ValueSum},
+    Proof,
 };
 
+#[cfg(feature = "circuit")]
+use crate::circuit::{Instance, VerifyingKey};
+
+#[cfg(feature = "circuit")]
 impl<T> Action<T> {
     /// Prepares the public instance for this action, for creating and verifying the
     /// bundle proof.
@@ -340,6 +372,7 @@ impl<T: Authorization, V> Bundle<T, V> {
         })
     }
 
+    #[cfg(feature = "circuit")]
     pub(crate) fn to_instances(&self) -> Vec<Instance> {
         self.actions
             .iter()
@@ -347,6 +380,36 @@ impl<T: Authorization, V> Bundle<T, V> {
             .collect()
     }
 
+    /// Returns the index of each action in the bundle that spends a note in `index`,
+    /// along with the identifier of the spent note.
+    pub fn spent_nullifiers_matching<'a, Id>(
+        &self,
+        index: &'a NullifierIndex<Id>,
+    ) -> Vec<(usize, &'a Id)> {
+        self.actions
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, action)| index.get(action.nullifier()).map(|id| (idx, id)))
+            .collect()
+    }
+
+    /// Checks that each note is committed to by the action at the given index, as when
+    /// the notes were decrypted from the actions of this bundle.
+    ///
+    /// Returns the index of the first action whose note commitment does not match its
+    /// note, or that is not in the bundle.
+    pub fn verify_note_commitments<'a>(
+        &self,
+        notes: impl IntoIterator<Item = (usize, &'a Note)>,
+    ) -> Result<(), usize> {
+        notes
+            .into_iter()
+            .try_for_each(|(idx, note)| match self.actions.get(idx) {
+                Some(action) if note.verify_commitment(action.cmx()) => Ok(()),
+                _ => Err(idx),
+            })
+    }
+
     /// Performs trial decryption of each action in the bundle with each of the
     /// specified incoming viewing keys, and returns a vector of each decrypted
     /// note plaintext contents along with the index of the action from which it
@@ -355,19 +418,30 @@ impl<T: Authorization, V> Bundle<T, V> {
         &self,
         keys: &[IncomingViewingKey],
     ) -> Vec<(usize, IncomingViewingKey, Note, Address, [u8; 512])> {
-        let prepared_keys: Vec<_> = keys
-            .iter()
-            .map(|ivk| (ivk, PreparedIncomingViewingKey::new(ivk)))
-            .collect();
+        self.decrypt_outputs_with_scanning_keys(&ScanningKeys::new(keys, &[]))
+    }
+
+    /// Performs trial decryption of each action in the bundle with each of the
+    /// incoming viewing keys in `keys`, and returns a vector of each decrypted note
+    /// plaintext contents along with the index of the action from which it was derived.
+    ///
+    /// Unlike [`Bundle::decrypt_outputs_with_keys`], this reuses the prepared keys across
+    /// calls.
+    pub fn decrypt_outputs_with_scanning_keys(
+        &self,
+        keys: &ScanningKeys,
+    ) -> Vec<(usize, IncomingViewingKey, Note, Address, [u8; 512])> {
         self.actions
             .iter()
             .enumerate()
             .filter_map(|(idx, action)| {
                 let domain = OrchardDomainV3::for_action(action);
-                prepared_keys.iter().find_map(|(ivk, prepared_ivk)| {
-                    try_note_decryption(&domain, prepared_ivk, action)
-                        .map(|(n, a, m)| (idx, (*ivk).clone(), n, a, m))
-                })
+                keys.prepared_ivks()
+                    .iter()
+                    .find_map(|(ivk, _, prepared_ivk)| {
+                        try_note_decryption(&domain, prepared_ivk, action)
+                            .map(|(n, a, m)| (idx, ivk.clone(), n, a, m))
+                    })
             })
             .collect()
     }
@@ -387,6 +461,50 @@ impl<T: Authorization, V> Bundle<T, V> {
         })
     }
 
+    /// Performs trial decryption of the compact prefix of each action in the bundle with
+    /// each of the incoming viewing keys in `keys`, and returns a vector of each
+    /// decrypted note along with the index of the action from which it was derived.
+    ///
+    /// This is cheaper than [`Bundle::decrypt_outputs_with_scanning_keys`], as it neither
+    /// decrypts the memos nor authenticates the full ciphertexts. The notes are still
+    /// checked against the note commitments of the actions.
+    pub fn decrypt_outputs_compact_with_scanning_keys(
+        &self,
+        keys: &ScanningKeys,
+    ) -> Vec<(usize, IncomingViewingKey, Note, Address)> {
+        self.actions
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, action)| {
+                let domain = OrchardDomainV3::for_action(action);
+                keys.prepared_ivks()
+                    .iter()
+                    .find_map(|(ivk, _, prepared_ivk)| {
+                        try_compact_note_decryption(&domain, prepared_ivk, action)
+                            .map(|(n, a)| (idx, ivk.clone(), n, a))
+                    })
+            })
+            .collect()
+    }
+
+    /// Performs trial decryption of the compact prefix of the action at `action_idx` in
+    /// the bundle with the specified incoming viewing key, and returns the decrypted note
+    /// if successful.
+    ///
+    /// As in [`Bundle::decrypt_outputs_compact_with_scanning_keys`], the memo is not
+    /// decrypted.
+    pub fn decrypt_output_compact_with_key(
+        &self,
+        action_idx: usize,
+        key: &IncomingViewingKey,
+    ) -> Option<(Note, Address)> {
+        let prepared_ivk = PreparedIncomingViewingKey::new(key);
+        self.actions.get(action_idx).and_then(move |action| {
+            let domain = OrchardDomainV3::for_action(action);
+            try_compact_note_decryption(&domain, &prepared_ivk, action)
+        })
+    }
+
     /// Performs trial decryption of each action in the bundle with each of the
     /// specified outgoing viewing keys, and returns a vector of each decrypted
     /// note plaintext contents along with the index of the action from which it
@@ -414,6 +532,16 @@ impl<T: Authorization, V> Bundle<T, V> {
             .collect()
     }
 
+    /// Performs trial decryption of each action in the bundle with each of the
+    /// outgoing viewing keys in `keys`, and returns a vector of each decrypted note
+    /// plaintext contents along with the index of the action from which it was derived.
+    pub fn recover_outputs_with_scanning_keys(
+        &self,
+        keys: &ScanningKeys,
+    ) -> Vec<(usize, OutgoingViewingKey, Note, Address, [u8; 512])> {
+        self.recover_outputs_with_ovks(keys.ovks())
+    }
+
     /// Attempts to decrypt the action at the specified index with the specified
     /// outgoing viewing key, and returns the decrypted note plaintext contents
     /// if successful.
@@ -433,18 +561,77 @@ impl<T: Authorization, V> Bundle<T, V> {
             )
         })
     }
+
+    /// Returns `true` if the action at the specified index creates change for the wallet
+    /// of `fvk`.
+    ///
+    /// This holds if the output can be recovered with the internal outgoing viewing key of
+    /// `fvk`, and is sent to an internal address of `fvk`.
+    pub fn is_change_output(&self, action_idx: usize, fvk: &FullViewingKey) -> bool {
+        self.recover_output_with_ovk(action_idx, &fvk.to_ovk(Scope::Internal))
+            .map_or(false, |(note, _, _)| fvk.is_change(&note))
+    }
+
+    /// Derives the outgoing cipher key of the action at the specified index under the
+    /// specified outgoing viewing key.
+    ///
+    /// The key can be given to an auditor, who can then recover the output of that action
+    /// with [`Bundle::recover_output_with_ock`] without learning the outgoing viewing key.
+    pub fn output_ock(
+        &self,
+        action_idx: usize,
+        key: &OutgoingViewingKey,
+    ) -> Option<OutgoingCipherKey> {
+        self.actions.get(action_idx).map(|action| {
+            prf_ock_orchard(
+                key,
+                action.cv_net(),
+                &action.cmx().to_bytes(),
+                &EphemeralKeyBytes(action.encrypted_note().epk_bytes),
+            )
+        })
+    }
+
+    /// Attempts to decrypt the action at the specified index with the specified
+    /// outgoing cipher key, and returns the decrypted note plaintext contents if
+    /// successful.
+    pub fn recover_output_with_ock(
+        &self,
+        action_idx: usize,
+        ock: &OutgoingCipherKey,
+    ) -> Option<(Note, Address, [u8; 512])> {
+        self.actions.get(action_idx).and_then(move |action| {
+            let domain = OrchardDomainV3::for_action(action);
+            try_output_recovery_with_ock(
+                &domain,
+                ock,
+                action,
+                &action.encrypted_note().out_ciphertext,
+            )
+        })
+    }
 }
 
-pub(crate) fn derive_bvk<'a, A: 'a, V: Clone + Into<i64>>(
+/// Derives the binding validating key of a bundle from its actions, its native value
+/// balance, and the values of the assets it burns.
+///
+/// `bvk` is the sum of the value commitments of the actions, minus commitments with zero
+/// trapdoors to the value balance and to each burned value. The binding signature of the
+/// bundle must verify under this key, which holds if and only if the values committed to
+/// by the actions balance with the value balance and the burns. Custom bundle
+/// constructors can use this to check the binding signing key they derive from the
+/// trapdoors of the actions.
+///
+/// Defined in [Zcash Protocol Spec § 4.14: Balance and Binding Signature (Orchard)][orchardbalance].
+///
+/// [orchardbalance]: https://zips.z.cash/protocol/nu5.pdf#orchardbalance
+pub fn derive_bvk<'a, A: 'a, V: Clone + Into<i64>>(
     actions: impl IntoIterator<Item = &'a Action<A>>,
     value_balance: V,
     burn: impl Iterator<Item = (AssetBase, V)>,
 ) -> redpallas::VerificationKey<Binding> {
     // https://p.z.cash/TCR:bad-txns-orchard-binding-signature-invalid?partial
-    (actions
-        .into_iter()
-        .map(|a| a.cv_net())
-        .sum::<ValueCommitment>()
+    (ValueCommitment::sum(actions.into_iter().map(|a| a.cv_net()))
         - ValueCommitment::derive(
             ValueSum::from_raw(value_balance.into()),
             ValueCommitTrapdoor::zero(),
@@ -518,6 +705,8 @@ impl<V> Bundle<Authorized, V> {
     }
 
     /// Verifies the proof for this bundle.
+    #[cfg(feature = "circuit")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "circuit")))]
     pub fn verify_proof(&self, vk: &VerifyingKey) -> Result<(), halo2_proofs::plonk::Error> {
         self.authorization()
             .proof()
@@ -525,6 +714,21 @@ impl<V> Bundle<Authorized, V> {
     }
 }
 
+/// Verifies the binding signature of the given bundle over `sighash`.
+///
+/// The binding validating key is derived from the value commitments of the actions, the
+/// value balance and the burns of the bundle, as in [`Bundle::binding_validating_key`].
+/// This checks the binding signature in isolation, without verifying the proof or the
+/// spend authorization signatures of the bundle.
+pub fn verify_binding_signature<V: Copy + Into<i64>>(
+    bundle: &Bundle<Authorized, V>,
+    sighash: &[u8; 32],
+) -> Result<(), reddsa::Error> {
+    bundle
+        .binding_validating_key()
+        .verify(sighash, bundle.authorization().binding_signature())
+}
+
 impl<V: DynamicUsage> DynamicUsage for Bundle<Authorized, V> {
     fn dynamic_usage(&self) -> usize {
         self.actions.dynamic_usage()
@@ -582,10 +786,9 @@ pub mod testing {
     use proptest::prelude::*;
 
     use crate::{
-        circuit::Proof,
         primitives::redpallas::{self, testing::arb_binding_signing_key},
         value::{testing::arb_note_value_bounded, NoteValue, ValueSum, MAX_NOTE_VALUE},
-        Anchor,
+        Anchor, Proof,
     };
 
     use super::{Action, Authorization, Authorized, Bundle, Flags};
@@ -741,4 +944,211 @@ pub mod testing {
             )
         }
     }
+
+    /// Generate an arbitrary bundle as [`arb_bundle`] does, with `i64` values as in a
+    /// transaction. The value balance is clamped to the range of `i64`, and the burns are
+    /// in canonical order, with burnt values clamped to the positive range of `i64`.
+    pub fn arb_bundle_i64(n_actions: usize) -> impl Strategy<Value = Bundle<Authorized, i64>> {
+        arb_bundle(n_actions).prop_map(|bundle| {
+            let max = i128::from(i64::MAX);
+            let mut burn: Vec<_> = bundle
+                .burn
+                .into_iter()
+                .map(|(asset, value)| (asset, i128::from(value).abs().clamp(1, max) as i64))
+                .collect();
+            burn.sort_by_key(|(asset, _)| *asset);
+            burn.dedup_by_key(|(asset, _)| *asset);
+
+            Bundle {
+                actions: bundle.actions,
+                flags: bundle.flags,
+                value_balance: i128::from(bundle.value_balance).clamp(-max, max) as i64,
+                burn,
+                anchor: bundle.anchor,
+                authorization: bundle.authorization,
+            }
+        })
+    }
+}
+
+#[cfg(all(test, feature = "circuit"))]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::{batch::tests::bundles_with_failures, verify_binding_signature, Bundle};
+    use crate::{
+        builder::{Builder, BundleType},
+        constants::MERKLE_DEPTH_ORCHARD,
+        keys::{FullViewingKey, ScanningKeys, Scope, SpendingKey},
+        note::AssetBase,
+        tree::EMPTY_ROOTS,
+        value::NoteValue,
+    };
+
+    #[test]
+    fn binding_signature_is_verified() {
+        let (_, bundles) = bundles_with_failures();
+        let (valid, sighash) = &bundles[0];
+        assert_eq!(verify_binding_signature(valid, sighash), Ok(()));
+        assert!(verify_binding_signature(valid, &[0; 32]).is_err());
+
+        // The bundle with an invalid binding signature.
+        let (invalid, _) = &bundles[3];
+        assert!(verify_binding_signature(invalid, sighash).is_err());
+
+        // A tampered value balance changes the binding validating key.
+        let tampered = Bundle::from_parts(
+            valid.actions().clone(),
+            *valid.flags(),
+            *valid.value_balance() + 1,
+            valid.burn().clone(),
+            *valid.anchor(),
+            valid.authorization().clone(),
+        );
+        assert!(verify_binding_signature(&tampered, sighash).is_err());
+    }
+
+    #[test]
+    fn scanning_keys_find_outputs() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let other = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::Internal);
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_output(
+                Some(fvk.to_ovk(Scope::Internal)),
+                recipient,
+                NoteValue::from_raw(5000),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+        let (bundle, meta) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+        let idx = meta.output_action_index(0).unwrap();
+
+        let keys = ScanningKeys::from_full_viewing_keys(&[other.clone(), fvk.clone()]);
+        assert_eq!(keys.ivks().count(), 4);
+
+        let decrypted = bundle.decrypt_outputs_with_scanning_keys(&keys);
+        assert_eq!(decrypted.len(), 1);
+        assert_eq!(decrypted[0].0, idx);
+        assert_eq!(decrypted[0].1, fvk.to_ivk(Scope::Internal));
+        assert_eq!(decrypted[0].3, recipient);
+
+        let recovered = bundle.recover_outputs_with_scanning_keys(&keys);
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].0, idx);
+        assert_eq!(recovered[0].2, decrypted[0].2);
+
+        let keys = ScanningKeys::from_full_viewing_keys(&[other]);
+        assert!(bundle.decrypt_outputs_with_scanning_keys(&keys).is_empty());
+        assert!(bundle.recover_outputs_with_scanning_keys(&keys).is_empty());
+    }
+
+    #[test]
+    fn decrypted_notes_match_their_commitments() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_output(
+                None,
+                recipient,
+                NoteValue::from_raw(5000),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+        let (bundle, _) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+
+        let decrypted = bundle.decrypt_outputs_with_keys(&[fvk.to_ivk(Scope::External)]);
+        assert_eq!(decrypted.len(), 1);
+        let (idx, _, note, _, _) = &decrypted[0];
+        assert!(note.verify_commitment(bundle.actions()[*idx].cmx()));
+        assert_eq!(
+            bundle
+                .verify_note_commitments(decrypted.iter().map(|(idx, _, note, _, _)| (*idx, note))),
+            Ok(())
+        );
+
+        // The note is not committed to by the other action, nor by a missing action.
+        let other_idx = (idx + 1) % bundle.actions().len();
+        assert!(!note.verify_commitment(bundle.actions()[other_idx].cmx()));
+        assert_eq!(
+            bundle.verify_note_commitments([(other_idx, note)]),
+            Err(other_idx)
+        );
+        let missing_idx = bundle.actions().len();
+        assert_eq!(
+            bundle.verify_note_commitments([(missing_idx, note)]),
+            Err(missing_idx)
+        );
+    }
+
+    #[test]
+    fn compact_decryption_matches_full_decryption() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let other = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_output(
+                None,
+                recipient,
+                NoteValue::from_raw(5000),
+                AssetBase::native(),
+                Some([7; 512]),
+            )
+            .unwrap();
+        let (bundle, meta) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+        let idx = meta.output_action_index(0).unwrap();
+        let ivk = fvk.to_ivk(Scope::External);
+
+        let (note, address, _) = bundle.decrypt_output_with_key(idx, &ivk).unwrap();
+        assert_eq!(
+            bundle.decrypt_output_compact_with_key(idx, &ivk),
+            Some((note, address))
+        );
+        let other_idx = (idx + 1) % bundle.actions().len();
+        assert_eq!(
+            bundle.decrypt_output_compact_with_key(other_idx, &ivk),
+            None
+        );
+        assert_eq!(
+            bundle.decrypt_output_compact_with_key(bundle.actions().len(), &ivk),
+            None
+        );
+        assert_eq!(
+            bundle.decrypt_output_compact_with_key(idx, &other.to_ivk(Scope::External)),
+            None
+        );
+
+        let keys = ScanningKeys::from_full_viewing_keys(&[other.clone(), fvk]);
+        assert_eq!(
+            bundle.decrypt_outputs_compact_with_scanning_keys(&keys),
+            vec![(idx, ivk, note, address)]
+        );
+        let keys = ScanningKeys::from_full_viewing_keys(&[other]);
+        assert!(bundle
+            .decrypt_outputs_compact_with_scanning_keys(&keys)
+            .is_empty());
+    }
 }
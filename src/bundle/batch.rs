@@ -1,19 +1,188 @@
+mod parallel;
+
+use core::fmt;
+
 use halo2_proofs::plonk;
 use pasta_curves::vesta;
 use rand::{CryptoRng, RngCore};
 use tracing::debug;
 
+pub use parallel::{ParallelVerifier, PendingBundle};
+
 use super::{Authorized, Bundle};
 use crate::{
-    circuit::VerifyingKey,
+    circuit::{Instance, VerifyingKey},
     primitives::redpallas::{self, Binding, SpendAuth},
+    swap_bundle::SwapBundle,
+    Proof,
 };
 
+/// A check of a bundle that failed validation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailedCheck {
+    /// The proof of the bundle, or of the action group at the given index of a swap
+    /// bundle. The index is always 0 for other bundles.
+    Proof(usize),
+    /// The spend authorization signature of the action at the given index.
+    SpendAuthSignature(usize),
+    /// The binding signature of the bundle.
+    BindingSignature,
+}
+
+/// A failure of a bundle within a batch, as identified by
+/// [`BatchValidator::validate_with_attribution`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchFailure {
+    /// The index of the bundle, in the order in which bundles were added to the batch.
+    pub bundle: usize,
+    /// The check that failed.
+    pub check: FailedCheck,
+}
+
+impl fmt::Display for BatchFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.check {
+            FailedCheck::Proof(index) => {
+                write!(f, "Bundle {}: invalid proof {}", self.bundle, index)
+            }
+            FailedCheck::SpendAuthSignature(index) => write!(
+                f,
+                "Bundle {}: invalid spend authorization signature for action {}",
+                self.bundle, index
+            ),
+            FailedCheck::BindingSignature => {
+                write!(f, "Bundle {}: invalid binding signature", self.bundle)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BatchFailure {}
+
 /// A signature within an authorized Orchard bundle.
 #[derive(Debug)]
 struct BundleSignature {
     /// The signature item for validation.
     signature: redpallas::batch::Item<SpendAuth, Binding>,
+    /// The check that the signature is part of.
+    check: FailedCheck,
+}
+
+/// A proof within an authorized Orchard bundle.
+#[derive(Debug)]
+struct BundleProof {
+    proof: Proof,
+    instances: Vec<Instance>,
+    /// The index of the proof within the bundle.
+    index: usize,
+}
+
+/// The proofs and signatures of a single bundle.
+#[derive(Debug)]
+struct BundleItems {
+    /// The index of the bundle, in the order in which bundles were added to a validator.
+    bundle: usize,
+    signatures: Vec<BundleSignature>,
+    proofs: Vec<BundleProof>,
+}
+
+impl BundleItems {
+    fn from_bundle<V: Copy + Into<i64>>(
+        bundle_index: usize,
+        bundle: &Bundle<Authorized, V>,
+        sighash: [u8; 32],
+    ) -> Self {
+        let mut signatures: Vec<_> = bundle
+            .actions()
+            .iter()
+            .enumerate()
+            .map(|(index, action)| BundleSignature {
+                signature: action
+                    .rk()
+                    .create_batch_item(action.authorization().clone(), &sighash),
+                check: FailedCheck::SpendAuthSignature(index),
+            })
+            .collect();
+
+        signatures.push(BundleSignature {
+            signature: bundle
+                .binding_validating_key()
+                .create_batch_item(bundle.authorization().binding_signature().clone(), &sighash),
+            check: FailedCheck::BindingSignature,
+        });
+
+        BundleItems {
+            bundle: bundle_index,
+            signatures,
+            proofs: vec![BundleProof {
+                proof: bundle.authorization().proof().clone(),
+                instances: bundle.to_instances(),
+                index: 0,
+            }],
+        }
+    }
+
+    fn from_swap_bundle<V: Copy + Into<i64>>(
+        bundle_index: usize,
+        bundle: &SwapBundle<V>,
+        sighash: [u8; 32],
+    ) -> Self {
+        let mut signatures = vec![];
+        let mut proofs = vec![];
+        for (group_index, action_group) in bundle.action_groups().iter().enumerate() {
+            let action_group_sighash: [u8; 32] = action_group.commitment().into();
+            for action in action_group.actions().iter() {
+                signatures.push(BundleSignature {
+                    signature: action
+                        .rk()
+                        .create_batch_item(action.authorization().clone(), &action_group_sighash),
+                    check: FailedCheck::SpendAuthSignature(signatures.len()),
+                });
+            }
+
+            proofs.push(BundleProof {
+                proof: action_group.authorization().proof().clone(),
+                instances: action_group.to_instances(),
+                index: group_index,
+            });
+        }
+
+        signatures.push(BundleSignature {
+            signature: bundle
+                .binding_validating_key()
+                .create_batch_item(bundle.binding_signature().clone(), &sighash),
+            check: FailedCheck::BindingSignature,
+        });
+
+        BundleItems {
+            bundle: bundle_index,
+            signatures,
+            proofs,
+        }
+    }
+
+    /// Checks the signatures and proofs of the bundle individually, and returns the
+    /// checks that failed.
+    fn failures(&self, vk: &VerifyingKey) -> Vec<BatchFailure> {
+        let failed_signatures = self
+            .signatures
+            .iter()
+            .filter(|sig| sig.signature.clone().verify_single().is_err())
+            .map(|sig| sig.check);
+        let failed_proofs = self
+            .proofs
+            .iter()
+            .filter(|proof| proof.proof.verify(vk, &proof.instances).is_err())
+            .map(|proof| FailedCheck::Proof(proof.index));
+
+        failed_signatures
+            .chain(failed_proofs)
+            .map(|check| BatchFailure {
+                bundle: self.bundle,
+                check,
+            })
+            .collect()
+    }
 }
 
 /// Batch validation context for Orchard.
@@ -22,7 +191,8 @@ struct BundleSignature {
 #[derive(Debug, Default)]
 pub struct BatchValidator {
     proofs: plonk::BatchVerifier<vesta::Affine>,
-    signatures: Vec<BundleSignature>,
+    bundles: Vec<BundleItems>,
+    attribution: bool,
 }
 
 impl BatchValidator {
@@ -30,34 +200,65 @@ impl BatchValidator {
     pub fn new() -> Self {
         BatchValidator {
             proofs: plonk::BatchVerifier::new(),
-            signatures: vec![],
+            bundles: vec![],
+            attribution: false,
         }
     }
 
+    /// Constructs a new batch validation context that keeps a copy of each proof, so
+    /// that the failed checks can be identified with
+    /// [`BatchValidator::validate_with_attribution`].
+    pub fn with_attribution() -> Self {
+        BatchValidator {
+            attribution: true,
+            ..Self::new()
+        }
+    }
+
+    fn add_items(&mut self, mut items: BundleItems) {
+        if self.attribution {
+            for proof in &items.proofs {
+                proof
+                    .proof
+                    .add_to_batch(&mut self.proofs, proof.instances.clone());
+            }
+        } else {
+            // The proofs are only needed again to attribute failures.
+            for proof in items.proofs.drain(..) {
+                proof.proof.into_batch(&mut self.proofs, proof.instances);
+            }
+        }
+        self.bundles.push(items);
+    }
+
     /// Adds the proof and RedPallas signatures from the given bundle to the validator.
     pub fn add_bundle<V: Copy + Into<i64>>(
         &mut self,
         bundle: &Bundle<Authorized, V>,
         sighash: [u8; 32],
     ) {
-        for action in bundle.actions().iter() {
-            self.signatures.push(BundleSignature {
-                signature: action
-                    .rk()
-                    .create_batch_item(action.authorization().clone(), &sighash),
-            });
-        }
-
-        self.signatures.push(BundleSignature {
-            signature: bundle
-                .binding_validating_key()
-                .create_batch_item(bundle.authorization().binding_signature().clone(), &sighash),
-        });
+        self.add_items(BundleItems::from_bundle(
+            self.bundles.len(),
+            bundle,
+            sighash,
+        ));
+    }
 
-        bundle
-            .authorization()
-            .proof()
-            .add_to_batch(&mut self.proofs, bundle.to_instances());
+    /// Adds the proofs and RedPallas signatures from the given swap bundle to the
+    /// validator.
+    ///
+    /// The spend authorization signatures of each action group are checked against the
+    /// commitment of that action group, and the binding signature against `sighash`.
+    pub fn add_swap_bundle<V: Copy + Into<i64>>(
+        &mut self,
+        bundle: &SwapBundle<V>,
+        sighash: [u8; 32],
+    ) {
+        self.add_items(BundleItems::from_swap_bundle(
+            self.bundles.len(),
+            bundle,
+            sighash,
+        ));
     }
 
     /// Batch-validates the accumulated bundles.
@@ -65,11 +266,20 @@ impl BatchValidator {
     /// Returns `true` if every proof and signature in every bundle added to the batch
     /// validator is valid, or `false` if one or more are invalid. No attempt is made to
     /// figure out which of the accumulated bundles might be invalid; if that information
-    /// is desired, construct separate [`BatchValidator`]s for sub-batches of the bundles.
+    /// is desired, use [`BatchValidator::validate_with_attribution`].
     pub fn validate<R: RngCore + CryptoRng>(self, vk: &VerifyingKey, rng: R) -> bool {
+        Self::validate_batch(self.proofs, &self.bundles, vk, rng)
+    }
+
+    fn validate_batch<R: RngCore + CryptoRng>(
+        proofs: plonk::BatchVerifier<vesta::Affine>,
+        bundles: &[BundleItems],
+        vk: &VerifyingKey,
+        rng: R,
+    ) -> bool {
         // https://p.z.cash/TCR:bad-txns-orchard-binding-signature-invalid?partial
 
-        if self.signatures.is_empty() {
+        if bundles.is_empty() {
             // An empty batch is always valid, but is not free to run; skip it.
             // Note that a transaction has at least a binding signature, so if
             // there are no signatures, there are also no proofs.
@@ -77,17 +287,202 @@ impl BatchValidator {
         }
 
         let mut validator = redpallas::batch::Verifier::new();
-        for sig in self.signatures.iter() {
+        for sig in bundles.iter().flat_map(|bundle| bundle.signatures.iter()) {
             validator.queue(sig.signature.clone());
         }
 
         match validator.verify(rng) {
             // If signatures are valid, check the proofs.
-            Ok(()) => self.proofs.finalize(&vk.params, &vk.vk),
+            Ok(()) => proofs.finalize(&vk.params, &vk.vk),
             Err(e) => {
                 debug!("RedPallas batch validation failed: {}", e);
                 false
             }
         }
     }
+
+    /// Batch-validates the accumulated bundles, identifying the failed checks if the
+    /// batch is invalid.
+    ///
+    /// If the batch is invalid, the proofs and signatures of the bundles are checked
+    /// individually, in the order in which the bundles were added, and the failed checks
+    /// are returned. If `early_abort` is `true`, only the first failure is returned,
+    /// and the bundles following the first invalid bundle are not checked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this validator was not constructed with
+    /// [`BatchValidator::with_attribution`].
+    pub fn validate_with_attribution<R: RngCore + CryptoRng>(
+        self,
+        vk: &VerifyingKey,
+        rng: R,
+        early_abort: bool,
+    ) -> Result<(), Vec<BatchFailure>> {
+        assert!(
+            self.attribution,
+            "the validator must be constructed with BatchValidator::with_attribution"
+        );
+        if Self::validate_batch(self.proofs, &self.bundles, vk, rng) {
+            return Ok(());
+        }
+
+        let mut failures = vec![];
+        for bundle in &self.bundles {
+            failures.extend(bundle.failures(vk));
+            if early_abort && !failures.is_empty() {
+                failures.truncate(1);
+                break;
+            }
+        }
+
+        Err(failures)
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use rand::rngs::OsRng;
+
+    use super::{BatchFailure, BatchValidator, FailedCheck};
+    use crate::{
+        builder::{Builder, BundleType},
+        bundle::{Authorized, Bundle},
+        circuit::{ProvingKey, VerifyingKey},
+        constants::MERKLE_DEPTH_ORCHARD,
+        keys::{FullViewingKey, Scope, SpendingKey},
+        note::AssetBase,
+        tree::EMPTY_ROOTS,
+        value::NoteValue,
+    };
+
+    /// Builds and authorizes a bundle with a single output, and returns it along with the
+    /// sighash it is authorized for.
+    fn authorized_bundle(pk: &ProvingKey) -> (Bundle<Authorized, i64>, [u8; 32]) {
+        let mut rng = OsRng;
+
+        let recipient =
+            FullViewingKey::from(&SpendingKey::random(&mut rng)).address_at(0u32, Scope::External);
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_output(
+                None,
+                recipient,
+                NoteValue::from_raw(5000),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+        let unauthorized = builder.build::<i64>(&mut rng).unwrap().unwrap().0;
+        let sighash: [u8; 32] = unauthorized.commitment().into();
+        let bundle = unauthorized
+            .create_proof(pk, &mut rng)
+            .unwrap()
+            .apply_signatures(rng, sighash, &[])
+            .unwrap();
+        (bundle, sighash)
+    }
+
+    /// An authorized bundle along with its sighash.
+    pub(in crate::bundle) type SignedBundle = (Bundle<Authorized, i64>, [u8; 32]);
+
+    /// Returns a valid bundle, followed by copies of it with an invalid proof, an invalid
+    /// spend authorization signature for the first action, and an invalid binding
+    /// signature.
+    pub(in crate::bundle) fn bundles_with_failures() -> (VerifyingKey, Vec<SignedBundle>) {
+        let pk = ProvingKey::build();
+        let (valid, sighash) = authorized_bundle(&pk);
+        let (other, _) = authorized_bundle(&pk);
+
+        let bad_proof = valid.clone().map_authorization(
+            &mut (),
+            |_, _, sig| sig,
+            |_, auth| {
+                Authorized::from_parts(
+                    other.authorization().proof().clone(),
+                    auth.binding_signature().clone(),
+                )
+            },
+        );
+        let mut first = true;
+        let bad_spend_auth = valid.clone().map_authorization(
+            &mut (),
+            |_, _, sig| {
+                if std::mem::take(&mut first) {
+                    other.actions().first().authorization().clone()
+                } else {
+                    sig
+                }
+            },
+            |_, auth| auth,
+        );
+        let bad_binding = valid.clone().map_authorization(
+            &mut (),
+            |_, _, sig| sig,
+            |_, auth| {
+                Authorized::from_parts(
+                    auth.proof().clone(),
+                    other.authorization().binding_signature().clone(),
+                )
+            },
+        );
+
+        (
+            VerifyingKey::build(),
+            vec![
+                (valid, sighash),
+                (bad_proof, sighash),
+                (bad_spend_auth, sighash),
+                (bad_binding, sighash),
+            ],
+        )
+    }
+
+    #[test]
+    fn failures_are_attributed() {
+        let (vk, bundles) = bundles_with_failures();
+        let validator = |bundles: &[SignedBundle]| {
+            let mut validator = BatchValidator::with_attribution();
+            for (bundle, sighash) in bundles {
+                validator.add_bundle(bundle, *sighash);
+            }
+            validator
+        };
+        let failure = |bundle, check| BatchFailure { bundle, check };
+
+        assert_eq!(
+            validator(&bundles[..1]).validate_with_attribution(&vk, OsRng, false),
+            Ok(())
+        );
+        assert_eq!(
+            validator(&bundles).validate_with_attribution(&vk, OsRng, false),
+            Err(vec![
+                failure(1, FailedCheck::Proof(0)),
+                failure(2, FailedCheck::SpendAuthSignature(0)),
+                failure(3, FailedCheck::BindingSignature),
+            ])
+        );
+        assert_eq!(
+            validator(&bundles).validate_with_attribution(&vk, OsRng, true),
+            Err(vec![failure(1, FailedCheck::Proof(0))])
+        );
+    }
+
+    #[test]
+    fn batches_without_attribution_only_report_validity() {
+        let (vk, bundles) = bundles_with_failures();
+
+        let mut validator = BatchValidator::new();
+        validator.add_bundle(&bundles[0].0, bundles[0].1);
+        assert!(validator.validate(&vk, OsRng));
+
+        let mut validator = BatchValidator::new();
+        for (bundle, sighash) in &bundles {
+            validator.add_bundle(bundle, *sighash);
+        }
+        assert!(!validator.validate(&vk, OsRng));
+    }
 }
@@ -0,0 +1,313 @@
+//! Verification of individual bundles in parallel on background threads.
+
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use super::{BatchFailure, BundleItems};
+use crate::{
+    bundle::{Authorized, Bundle},
+    circuit::VerifyingKey,
+    swap_bundle::SwapBundle,
+};
+
+/// The outcome of validating a single bundle.
+#[derive(Clone, Debug)]
+enum Outcome {
+    /// The bundle was checked, with the given result.
+    Checked(Result<(), Vec<BatchFailure>>),
+    /// The worker panicked while checking the bundle, or exited before checking it.
+    Panicked,
+}
+
+/// The outcome of validating a single bundle, set by a worker once it is known.
+#[derive(Debug, Default)]
+struct SharedResult {
+    outcome: Mutex<Option<Outcome>>,
+    ready: Condvar,
+}
+
+impl SharedResult {
+    fn set(&self, outcome: Outcome) {
+        *self.outcome.lock().unwrap() = Some(outcome);
+        self.ready.notify_all();
+    }
+}
+
+/// A bundle queued for validation.
+struct Job {
+    items: BundleItems,
+    result: Arc<SharedResult>,
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        // A job is only dropped unchecked if every worker has panicked, in which case
+        // nothing will check its bundle.
+        if self.result.outcome.lock().unwrap().is_none() {
+            self.result.set(Outcome::Panicked);
+        }
+    }
+}
+
+/// A handle to the result of validating a bundle added to a
+/// [`ParallelVerifier`].
+#[derive(Clone, Debug)]
+pub struct PendingBundle {
+    bundle: usize,
+    result: Arc<SharedResult>,
+}
+
+impl PendingBundle {
+    /// Returns the index of the bundle, in the order in which bundles were added to the
+    /// validator.
+    pub fn bundle_index(&self) -> usize {
+        self.bundle
+    }
+
+    /// Returns the result of validating the bundle, if it is already known.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a worker panicked while validating the bundle.
+    pub fn try_result(&self) -> Option<Result<(), Vec<BatchFailure>>> {
+        self.result
+            .outcome
+            .lock()
+            .unwrap()
+            .clone()
+            .map(|outcome| self.unwrap_outcome(outcome))
+    }
+
+    /// Waits for the bundle to be validated, and returns the failed checks if it is
+    /// invalid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a worker panicked while validating the bundle.
+    pub fn wait(&self) -> Result<(), Vec<BatchFailure>> {
+        let mut outcome = self.result.outcome.lock().unwrap();
+        loop {
+            match &*outcome {
+                Some(outcome) => return self.unwrap_outcome(outcome.clone()),
+                None => outcome = self.result.ready.wait(outcome).unwrap(),
+            }
+        }
+    }
+
+    fn unwrap_outcome(&self, outcome: Outcome) -> Result<(), Vec<BatchFailure>> {
+        match outcome {
+            Outcome::Checked(result) => result,
+            Outcome::Panicked => panic!("the validation of bundle {} panicked", self.bundle),
+        }
+    }
+}
+
+/// A per-bundle verifier that checks bundles on a pool of background threads as they
+/// are added.
+///
+/// Unlike [`BatchValidator`], which defers all work until the batch is validated, this
+/// starts checking each bundle as soon as it is added, so that proof verification
+/// overlaps with the parsing of the remaining bundles of a block. This is not a batch
+/// validator: the proof and each signature of a bundle are verified individually, which
+/// costs more in total than batch validation but always identifies the failed checks.
+///
+/// [`BatchValidator`]: super::BatchValidator
+#[derive(Debug)]
+pub struct ParallelVerifier {
+    jobs: Option<mpsc::Sender<Job>>,
+    pending: Vec<PendingBundle>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ParallelVerifier {
+    /// Constructs a validation context with one worker thread per available CPU.
+    pub fn new(vk: Arc<VerifyingKey>) -> Self {
+        let threads =
+            thread::available_parallelism().unwrap_or_else(|_| NonZeroUsize::new(1).unwrap());
+        Self::with_threads(vk, threads)
+    }
+
+    /// Constructs a validation context with the given number of worker threads.
+    pub fn with_threads(vk: Arc<VerifyingKey>, threads: NonZeroUsize) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..threads.get())
+            .map(|_| {
+                let vk = vk.clone();
+                let receiver = receiver.clone();
+                thread::spawn(move || loop {
+                    // The queue is unlocked before the job is run.
+                    let job = receiver.lock().unwrap().recv();
+                    let job = match job {
+                        Ok(job) => job,
+                        // The queue has been closed by `finalize`.
+                        Err(_) => break,
+                    };
+
+                    let failures =
+                        panic::catch_unwind(AssertUnwindSafe(|| job.items.failures(&vk)));
+                    match failures {
+                        Ok(failures) => job.result.set(Outcome::Checked(if failures.is_empty() {
+                            Ok(())
+                        } else {
+                            Err(failures)
+                        })),
+                        // Waiters are woken before the panic is raised again, to be
+                        // propagated by `finalize`.
+                        Err(e) => {
+                            job.result.set(Outcome::Panicked);
+                            panic::resume_unwind(e);
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        ParallelVerifier {
+            jobs: Some(jobs),
+            pending: vec![],
+            workers,
+        }
+    }
+
+    fn enqueue(&mut self, items: BundleItems) -> PendingBundle {
+        let pending = PendingBundle {
+            bundle: items.bundle,
+            result: Arc::new(SharedResult::default()),
+        };
+        // If every worker has panicked, the job is dropped, which marks its bundle as
+        // unchecked.
+        let _ = self
+            .jobs
+            .as_ref()
+            .expect("only closed by finalize")
+            .send(Job {
+                items,
+                result: pending.result.clone(),
+            });
+        self.pending.push(pending.clone());
+        pending
+    }
+
+    /// Queues the proof and RedPallas signatures of the given bundle for validation.
+    ///
+    /// Returns a handle that can be used to wait for the result of validating this
+    /// bundle alone.
+    pub fn add_bundle<V: Copy + Into<i64>>(
+        &mut self,
+        bundle: &Bundle<Authorized, V>,
+        sighash: [u8; 32],
+    ) -> PendingBundle {
+        let items = BundleItems::from_bundle(self.pending.len(), bundle, sighash);
+        self.enqueue(items)
+    }
+
+    /// Queues the proofs and RedPallas signatures of the given swap bundle for
+    /// validation.
+    ///
+    /// The spend authorization signatures of each action group are checked against the
+    /// commitment of that action group, and the binding signature against `sighash`.
+    pub fn add_swap_bundle<V: Copy + Into<i64>>(
+        &mut self,
+        bundle: &SwapBundle<V>,
+        sighash: [u8; 32],
+    ) -> PendingBundle {
+        let items = BundleItems::from_swap_bundle(self.pending.len(), bundle, sighash);
+        self.enqueue(items)
+    }
+
+    /// Waits for all of the queued bundles to be validated.
+    ///
+    /// Returns the failed checks of all of the bundles, in the order in which the
+    /// bundles were added, if any bundle is invalid.
+    pub fn finalize(mut self) -> Result<(), Vec<BatchFailure>> {
+        // Closing the queue lets the workers exit once it is drained.
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            if let Err(e) = worker.join() {
+                panic::resume_unwind(e);
+            }
+        }
+
+        let failures: Vec<_> = self
+            .pending
+            .iter()
+            .filter_map(|pending| pending.wait().err())
+            .flatten()
+            .collect();
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::Arc;
+
+    use super::{Job, ParallelVerifier, PendingBundle, SharedResult};
+    use crate::bundle::batch::{
+        tests::bundles_with_failures, BatchFailure, BundleItems, FailedCheck,
+    };
+
+    #[test]
+    fn each_bundle_is_checked() {
+        let (vk, bundles) = bundles_with_failures();
+        let mut validator =
+            ParallelVerifier::with_threads(Arc::new(vk), NonZeroUsize::new(2).unwrap());
+
+        let pending: Vec<_> = bundles
+            .iter()
+            .map(|(bundle, sighash)| validator.add_bundle(bundle, *sighash))
+            .collect();
+        for (index, pending) in pending.iter().enumerate() {
+            assert_eq!(pending.bundle_index(), index);
+        }
+
+        let failure = |bundle, check| BatchFailure { bundle, check };
+        assert_eq!(pending[0].wait(), Ok(()));
+        assert_eq!(
+            pending[1].wait(),
+            Err(vec![failure(1, FailedCheck::Proof(0))])
+        );
+        assert_eq!(
+            pending[2].wait(),
+            Err(vec![failure(2, FailedCheck::SpendAuthSignature(0))])
+        );
+        assert_eq!(pending[3].try_result(), Some(pending[3].wait()));
+
+        assert_eq!(
+            validator.finalize(),
+            Err(vec![
+                failure(1, FailedCheck::Proof(0)),
+                failure(2, FailedCheck::SpendAuthSignature(0)),
+                failure(3, FailedCheck::BindingSignature),
+            ])
+        );
+    }
+
+    #[test]
+    fn unchecked_bundles_do_not_block_waiters() {
+        let (_, bundles) = bundles_with_failures();
+        let (bundle, sighash) = &bundles[0];
+        let pending = PendingBundle {
+            bundle: 0,
+            result: Arc::new(SharedResult::default()),
+        };
+        assert!(pending.try_result().is_none());
+
+        // A job dropped without being run, as when every worker has panicked.
+        drop(Job {
+            items: BundleItems::from_bundle(0, bundle, *sighash),
+            result: pending.result.clone(),
+        });
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| pending.wait())).is_err());
+    }
+}
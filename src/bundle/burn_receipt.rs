@@ -0,0 +1,186 @@
+//! Receipts proving that an asset was burnt by a bundle.
+
+use core::fmt;
+
+use super::{Authorized, Bundle};
+use crate::{note::AssetBase, value::NoteValue};
+
+/// A receipt for the burn of an asset by an authorized bundle.
+///
+/// The receipt holds the burnt asset and amount, the commitment to the bundle, and the
+/// sighash of the transaction containing the bundle, which its binding signature signs.
+/// The binding signature can only be created by the builder of the bundle, and commits
+/// to its burns through the binding validating key.
+///
+/// A third party can fetch the transaction that the receipt refers to from the chain,
+/// and check with [`BurnReceipt::verify`] that its bundle burns the asset and that the
+/// binding signature of the bundle is valid for the sighash of the receipt. The receipt
+/// does not itself prove that the transaction was mined: the third party must still
+/// check that the sighash is that of a transaction on the chain. For a v6 transaction
+/// whose only effects are shielded, the sighash is its transaction identifier digest, as
+/// computed by [`V6Sighash`].
+///
+/// [`V6Sighash`]: super::sighash::V6Sighash
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BurnReceipt {
+    asset: AssetBase,
+    amount: NoteValue,
+    bundle_commitment: [u8; 32],
+    sighash: [u8; 32],
+}
+
+impl BurnReceipt {
+    /// The size of the byte encoding of a receipt.
+    pub const SIZE: usize = 104;
+
+    /// Returns the receipt for the burn of `asset` by `bundle`, which is contained in the
+    /// transaction with the given sighash, or `None` if the bundle does not burn the
+    /// asset.
+    pub fn new<V: Copy + Into<i64>>(
+        bundle: &Bundle<Authorized, V>,
+        asset: &AssetBase,
+        sighash: &[u8; 32],
+    ) -> Option<Self> {
+        bundle
+            .burn()
+            .iter()
+            .find(|(burnt, _)| burnt == asset)
+            .and_then(|(asset, amount)| {
+                let amount = u64::try_from(Into::<i64>::into(*amount)).ok()?;
+                Some(BurnReceipt {
+                    asset: *asset,
+                    amount: NoteValue::from_raw(amount),
+                    bundle_commitment: bundle.commitment().into(),
+                    sighash: *sighash,
+                })
+            })
+    }
+
+    /// Returns the burnt asset.
+    pub fn asset(&self) -> AssetBase {
+        self.asset
+    }
+
+    /// Returns the burnt amount of the asset.
+    pub fn amount(&self) -> NoteValue {
+        self.amount
+    }
+
+    /// Returns the commitment to the bundle that burnt the asset, as used in the
+    /// transaction identifier.
+    pub fn bundle_commitment(&self) -> &[u8; 32] {
+        &self.bundle_commitment
+    }
+
+    /// Returns the sighash of the transaction containing the bundle that burnt the asset.
+    pub fn sighash(&self) -> &[u8; 32] {
+        &self.sighash
+    }
+
+    /// Checks this receipt against the bundle that burnt the asset.
+    pub fn verify<V: Copy + Into<i64>>(
+        &self,
+        bundle: &Bundle<Authorized, V>,
+    ) -> Result<(), BurnReceiptError> {
+        if <[u8; 32]>::from(bundle.commitment()) != self.bundle_commitment {
+            return Err(BurnReceiptError::CommitmentMismatch);
+        }
+        if !bundle.burn().iter().any(|(asset, amount)| {
+            *asset == self.asset && Into::<i64>::into(*amount) == self.amount.inner() as i64
+        }) {
+            return Err(BurnReceiptError::BurnNotFound);
+        }
+        bundle
+            .binding_validating_key()
+            .verify(&self.sighash, bundle.authorization().binding_signature())
+            .map_err(|_| BurnReceiptError::InvalidBindingSignature)
+    }
+
+    /// Returns the byte encoding of this receipt.
+    ///
+    /// The encoding is the asset, the amount as a little-endian 64-bit integer, the
+    /// bundle commitment and the sighash.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[..32].copy_from_slice(&self.asset.to_bytes());
+        bytes[32..40].copy_from_slice(&self.amount.to_bytes());
+        bytes[40..72].copy_from_slice(&self.bundle_commitment);
+        bytes[72..].copy_from_slice(&self.sighash);
+        bytes
+    }
+
+    /// Parses a receipt from its byte encoding.
+    ///
+    /// Returns `None` if the encoding of the asset is invalid.
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Option<Self> {
+        let asset = Option::from(AssetBase::from_bytes(bytes[..32].try_into().unwrap()))?;
+        Some(BurnReceipt {
+            asset,
+            amount: NoteValue::from_bytes(bytes[32..40].try_into().unwrap()),
+            bundle_commitment: bytes[40..72].try_into().unwrap(),
+            sighash: bytes[72..].try_into().unwrap(),
+        })
+    }
+}
+
+impl<V: Copy + Into<i64>> Bundle<Authorized, V> {
+    /// Returns a [`BurnReceipt`] for each of the burns of this bundle, in order, given
+    /// the sighash of the transaction containing it.
+    pub fn burn_receipts(&self, sighash: &[u8; 32]) -> Vec<BurnReceipt> {
+        self.burn()
+            .iter()
+            .filter_map(|(asset, _)| BurnReceipt::new(self, asset, sighash))
+            .collect()
+    }
+}
+
+/// An error returned when a [`BurnReceipt`] does not match a bundle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BurnReceiptError {
+    /// The commitment of the bundle is not the one in the receipt.
+    CommitmentMismatch,
+    /// The bundle does not burn the amount of the asset in the receipt.
+    BurnNotFound,
+    /// The binding signature of the bundle is not valid for the sighash in the receipt.
+    InvalidBindingSignature,
+}
+
+impl fmt::Display for BurnReceiptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BurnReceiptError::CommitmentMismatch => {
+                "The bundle commitment does not match the receipt"
+            }
+            BurnReceiptError::BurnNotFound => "The bundle does not burn the receipted amount",
+            BurnReceiptError::InvalidBindingSignature => "The binding signature is invalid",
+        })
+    }
+}
+
+impl std::error::Error for BurnReceiptError {}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{BurnReceipt, BurnReceiptError};
+    use crate::bundle::testing::arb_bundle_i64;
+
+    proptest! {
+        #[test]
+        fn burn_receipts(bundle in arb_bundle_i64(2), other in arb_bundle_i64(2)) {
+            let receipts = bundle.burn_receipts(&[7; 32]);
+            prop_assert_eq!(receipts.len(), bundle.burn().len());
+            for (receipt, (asset, amount)) in receipts.iter().zip(bundle.burn()) {
+                prop_assert_eq!(receipt.asset(), *asset);
+                prop_assert_eq!(receipt.amount().inner() as i64, *amount);
+                prop_assert_eq!(receipt.sighash(), &[7; 32]);
+                prop_assert_eq!(BurnReceipt::from_bytes(&receipt.to_bytes()), Some(*receipt));
+                prop_assert_eq!(
+                    receipt.verify(&other),
+                    Err(BurnReceiptError::CommitmentMismatch)
+                );
+            }
+        }
+    }
+}
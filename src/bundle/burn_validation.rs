@@ -1,14 +1,18 @@
 //! Validating burn operations on asset bundles.
 //!
-//! The module provides a function `validate_bundle_burn` that can be used to validate the burn values for the bundle.
+//! The module provides a function `validate_bundle_burn` that can be used to validate the burn values for the bundle,
+//! and a function `validate_burn_list` that additionally enforces the canonical order of the burn list.
+//! The function `burn_totals` sums the burns of many bundles, such as those of a block.
 //!
+use std::collections::HashMap;
 use std::fmt;
 
+use crate::bundle::{Authorization, Bundle};
 use crate::note::AssetBase;
+use crate::value::NoteValue;
 
 /// Possible errors that can occur during bundle burn validation.
-#[derive(Debug)]
-#[cfg_attr(test, derive(PartialEq, Eq))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BurnError {
     /// Encountered a duplicate asset to burn.
     DuplicateAsset,
@@ -50,6 +54,137 @@ pub fn validate_bundle_burn(bundle_burn: &Vec<(AssetBase, i64)>) -> Result<(), B
     Ok(())
 }
 
+/// Possible errors that can occur during burn list validation, with the indices of the
+/// offending entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BurnListError {
+    /// The asset at `index` was already burned by the entry at `first`.
+    DuplicateAsset {
+        /// The index of the first entry burning the asset.
+        first: usize,
+        /// The index of the duplicate entry.
+        index: usize,
+    },
+    /// The entry at the given index burns the native asset.
+    NativeAsset(usize),
+    /// The entry at the given index burns a zero value.
+    ZeroAmount(usize),
+    /// The asset of the entry at the given index is not greater than the asset of the
+    /// previous entry.
+    Unsorted(usize),
+}
+
+/// Validates a burn list against the consensus rules for burns.
+///
+/// The assets must be distinct and non-native, each burned value must be non-zero, and
+/// the entries must be sorted in increasing order of the encodings of their assets.
+///
+/// # Errors
+///
+/// Returns the first error encountered, in the order of the entries of `burn`.
+pub fn validate_burn_list(burn: &[(AssetBase, NoteValue)]) -> Result<(), BurnListError> {
+    let mut seen = HashMap::<AssetBase, usize>::new();
+
+    for (index, (asset, value)) in burn.iter().enumerate() {
+        if asset.is_native().into() {
+            return Err(BurnListError::NativeAsset(index));
+        }
+        if value.inner() == 0 {
+            return Err(BurnListError::ZeroAmount(index));
+        }
+        if let Some(first) = seen.insert(*asset, index) {
+            return Err(BurnListError::DuplicateAsset { first, index });
+        }
+        if index > 0 && burn[index - 1].0.to_bytes() >= asset.to_bytes() {
+            return Err(BurnListError::Unsorted(index));
+        }
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for BurnListError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BurnListError::DuplicateAsset { first, index } => write!(
+                f,
+                "Burn entry {} duplicates the asset of entry {}.",
+                index, first
+            ),
+            BurnListError::NativeAsset(index) => {
+                write!(f, "Burn entry {} burns the native asset.", index)
+            }
+            BurnListError::ZeroAmount(index) => {
+                write!(f, "Burn entry {} burns a zero value.", index)
+            }
+            BurnListError::Unsorted(index) => {
+                write!(f, "Burn entry {} is not in canonical order.", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BurnListError {}
+
+/// Possible errors that can occur when summing the burns of many bundles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BurnTotalError {
+    /// The bundle at `bundle` burns a negative value of `asset`.
+    NegativeAmount {
+        /// The index of the bundle.
+        bundle: usize,
+        /// The asset burned with a negative value.
+        asset: AssetBase,
+    },
+    /// The total burned value of the asset overflows.
+    Overflow(AssetBase),
+}
+
+/// Sums the values burned for each asset by the given bundles.
+///
+/// The result is the total decrease of the supply of each asset, for example across the
+/// bundles of a block. The bundles are expected to have passed [`validate_bundle_burn`].
+///
+/// # Errors
+///
+/// Returns a `BurnTotalError` if:
+/// * A bundle burns a negative value (`BurnTotalError::NegativeAmount`).
+/// * The total value burned for an asset overflows a `u128` (`BurnTotalError::Overflow`).
+pub fn burn_totals<'a, T: Authorization + 'a, V: Copy + Into<i64> + 'a>(
+    bundles: impl IntoIterator<Item = &'a Bundle<T, V>>,
+) -> Result<HashMap<AssetBase, u128>, BurnTotalError> {
+    let mut totals = HashMap::<AssetBase, u128>::new();
+
+    for (index, bundle) in bundles.into_iter().enumerate() {
+        for (asset, value) in bundle.burn() {
+            let value =
+                u128::try_from((*value).into()).map_err(|_| BurnTotalError::NegativeAmount {
+                    bundle: index,
+                    asset: *asset,
+                })?;
+            let total = totals.entry(*asset).or_default();
+            *total = total
+                .checked_add(value)
+                .ok_or(BurnTotalError::Overflow(*asset))?;
+        }
+    }
+
+    Ok(totals)
+}
+
+impl fmt::Display for BurnTotalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BurnTotalError::NegativeAmount { bundle, .. } => {
+                write!(f, "Bundle {} burns a negative value.", bundle)
+            }
+            BurnTotalError::Overflow(_) => write!(f, "The total burned value overflows."),
+        }
+    }
+}
+
+impl std::error::Error for BurnTotalError {}
+
 impl fmt::Display for BurnError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -62,9 +197,14 @@ impl fmt::Display for BurnError {
     }
 }
 
+impl std::error::Error for BurnError {}
+
 #[cfg(test)]
 mod tests {
+    use proptest::{collection::vec, prelude::*};
+
     use super::*;
+    use crate::bundle::testing::arb_bundle_i64;
 
     /// Creates an item of bundle burn list for a given asset description and value.
     ///
@@ -142,4 +282,65 @@ mod tests {
 
         assert_eq!(result, Err(BurnError::NonPositiveAmount));
     }
+
+    #[test]
+    fn validate_burn_list_errors() {
+        let mut burn: Vec<_> = ["Asset 1", "Asset 2", "Asset 3"]
+            .iter()
+            .map(|desc| {
+                let (asset, value) = get_burn_tuple(desc, 10);
+                (asset, NoteValue::from_raw(value as u64))
+            })
+            .collect();
+        burn.sort_by_key(|(asset, _)| asset.to_bytes());
+        assert_eq!(validate_burn_list(&burn), Ok(()));
+
+        let mut unsorted = burn.clone();
+        unsorted.swap(1, 2);
+        assert_eq!(
+            validate_burn_list(&unsorted),
+            Err(BurnListError::Unsorted(2))
+        );
+
+        let mut duplicate = burn.clone();
+        duplicate[2].0 = duplicate[0].0;
+        assert_eq!(
+            validate_burn_list(&duplicate),
+            Err(BurnListError::DuplicateAsset { first: 0, index: 2 })
+        );
+
+        let mut zero = burn.clone();
+        zero[1].1 = NoteValue::from_raw(0);
+        assert_eq!(validate_burn_list(&zero), Err(BurnListError::ZeroAmount(1)));
+
+        let mut native = burn;
+        native[0].0 = AssetBase::native();
+        assert_eq!(
+            validate_burn_list(&native),
+            Err(BurnListError::NativeAsset(0))
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn burn_totals_sum_bundles(
+            bundles in vec(arb_bundle_i64(1), 1..4),
+        ) {
+            let mut expected = HashMap::<AssetBase, u128>::new();
+            for (asset, value) in bundles.iter().flat_map(|bundle| bundle.burn()) {
+                *expected.entry(*asset).or_default() += *value as u128;
+            }
+            prop_assert_eq!(burn_totals(&bundles), Ok(expected));
+
+            let negated: Vec<_> = bundles
+                .into_iter()
+                .map(|bundle| bundle.try_map_value_balance(|v| Ok::<_, ()>(-v - 1)).unwrap())
+                .collect();
+            let asset = negated[0].burn()[0].0;
+            prop_assert_eq!(
+                burn_totals(&negated),
+                Err(BurnTotalError::NegativeAmount { bundle: 0, asset })
+            );
+        }
+    }
 }
@@ -2,64 +2,246 @@
 
 use blake2b_simd::{Hash as Blake2bHash, Params, State};
 
-use crate::bundle::{Authorization, Authorized, Bundle};
+use crate::action::Action;
+use crate::bundle::{Authorization, Authorized, Bundle, Flags};
 use crate::issuance::{IssueAuth, IssueBundle, Signed};
+use crate::primitives::redpallas::{self, Binding, SpendAuth};
+#[cfg(feature = "circuit")]
+use crate::swap_bundle::SwapBundle;
+use crate::tree::Anchor;
 
-const ZCASH_ORCHARD_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxIdOrchardHash";
-const ZCASH_ORCHARD_ACTIONS_COMPACT_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxIdOrcActCHash";
-const ZCASH_ORCHARD_ACTIONS_MEMOS_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxIdOrcActMHash";
-const ZCASH_ORCHARD_ACTIONS_NONCOMPACT_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxIdOrcActNHash";
-const ZCASH_ORCHARD_SIGS_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxAuthOrchaHash";
-const ZCASH_ORCHARD_ZSA_ISSUE_PERSONALIZATION: &[u8; 16] = b"ZTxIdSAIssueHash";
-const ZCASH_ORCHARD_ZSA_ISSUE_ACTION_PERSONALIZATION: &[u8; 16] = b"ZTxIdIssuActHash";
-const ZCASH_ORCHARD_ZSA_ISSUE_NOTE_PERSONALIZATION: &[u8; 16] = b"ZTxIdIAcNoteHash";
-const ZCASH_ORCHARD_ZSA_ISSUE_SIG_PERSONALIZATION: &[u8; 16] = b"ZTxAuthZSAOrHash";
+/// Personalization of the commitment to a bundle.
+pub const ZCASH_ORCHARD_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxIdOrchardHash";
+/// Personalization of the commitment to the compact parts of the actions of a bundle.
+pub const ZCASH_ORCHARD_ACTIONS_COMPACT_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxIdOrcActCHash";
+/// Personalization of the commitment to the memos of the actions of a bundle.
+pub const ZCASH_ORCHARD_ACTIONS_MEMOS_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxIdOrcActMHash";
+/// Personalization of the commitment to the non-compact parts of the actions of a bundle.
+pub const ZCASH_ORCHARD_ACTIONS_NONCOMPACT_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxIdOrcActNHash";
+/// Personalization of the commitment to the authorizing data of a bundle.
+pub const ZCASH_ORCHARD_SIGS_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxAuthOrchaHash";
+/// Personalization of the commitment to the action groups of a swap bundle.
+pub const ZCASH_ORCHARD_ACTION_GROUPS_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxIdOrcActGHash";
+/// Personalization of the commitment to an issue bundle.
+pub const ZCASH_ORCHARD_ZSA_ISSUE_PERSONALIZATION: &[u8; 16] = b"ZTxIdSAIssueHash";
+/// Personalization of the commitment to the actions of an issue bundle.
+pub const ZCASH_ORCHARD_ZSA_ISSUE_ACTION_PERSONALIZATION: &[u8; 16] = b"ZTxIdIssuActHash";
+/// Personalization of the commitment to the notes of an issue action.
+pub const ZCASH_ORCHARD_ZSA_ISSUE_NOTE_PERSONALIZATION: &[u8; 16] = b"ZTxIdIAcNoteHash";
+/// Personalization of the commitment to the authorizing data of an issue bundle.
+pub const ZCASH_ORCHARD_ZSA_ISSUE_SIG_PERSONALIZATION: &[u8; 16] = b"ZTxAuthZSAOrHash";
+
+/// The digests computed by this module, as used in the transaction identifier and the
+/// authorizing data commitment of a transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrchardDigest {
+    /// The commitment to a bundle.
+    Bundle,
+    /// The commitment to the compact parts of the actions of a bundle.
+    ActionsCompact,
+    /// The commitment to the memos of the actions of a bundle.
+    ActionsMemos,
+    /// The commitment to the non-compact parts of the actions of a bundle.
+    ActionsNoncompact,
+    /// The commitment to the authorizing data of a bundle.
+    BundleAuth,
+    /// The commitment to the action groups of a swap bundle.
+    ActionGroups,
+    /// The commitment to an issue bundle.
+    IssueBundle,
+    /// The commitment to the actions of an issue bundle.
+    IssueActions,
+    /// The commitment to the notes of an issue action.
+    IssueNotes,
+    /// The commitment to the authorizing data of an issue bundle.
+    IssueBundleAuth,
+}
+
+impl OrchardDigest {
+    /// Returns the BLAKE2b personalization of this digest.
+    pub fn personalization(&self) -> &'static [u8; 16] {
+        match self {
+            OrchardDigest::Bundle => ZCASH_ORCHARD_HASH_PERSONALIZATION,
+            OrchardDigest::ActionsCompact => ZCASH_ORCHARD_ACTIONS_COMPACT_HASH_PERSONALIZATION,
+            OrchardDigest::ActionsMemos => ZCASH_ORCHARD_ACTIONS_MEMOS_HASH_PERSONALIZATION,
+            OrchardDigest::ActionsNoncompact => {
+                ZCASH_ORCHARD_ACTIONS_NONCOMPACT_HASH_PERSONALIZATION
+            }
+            OrchardDigest::BundleAuth => ZCASH_ORCHARD_SIGS_HASH_PERSONALIZATION,
+            OrchardDigest::ActionGroups => ZCASH_ORCHARD_ACTION_GROUPS_HASH_PERSONALIZATION,
+            OrchardDigest::IssueBundle => ZCASH_ORCHARD_ZSA_ISSUE_PERSONALIZATION,
+            OrchardDigest::IssueActions => ZCASH_ORCHARD_ZSA_ISSUE_ACTION_PERSONALIZATION,
+            OrchardDigest::IssueNotes => ZCASH_ORCHARD_ZSA_ISSUE_NOTE_PERSONALIZATION,
+            OrchardDigest::IssueBundleAuth => ZCASH_ORCHARD_ZSA_ISSUE_SIG_PERSONALIZATION,
+        }
+    }
+}
+
+/// The revision of the layout of the digests of the actions of a bundle.
+///
+/// The revisions differ in how the encrypted note ciphertext of each action is split
+/// between its compact, memo and non-compact parts. Only the layout of the actions of
+/// this crate, whose note ciphertexts include the asset, is supported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DigestVersion {
+    /// The layout of [ZIP 226] for v6 transactions, whose note plaintexts include the
+    /// asset of the note.
+    ///
+    /// [ZIP 226]: https://qed-it.github.io/zips/zip-0226
+    #[default]
+    V6,
+}
+
+impl DigestVersion {
+    /// Returns the size of the prefix of the encrypted note ciphertext that is part of the
+    /// compact digest.
+    pub fn compact_ciphertext_size(&self) -> usize {
+        match self {
+            DigestVersion::V6 => 84,
+        }
+    }
+
+    /// Returns the end of the memo ciphertext within the encrypted note ciphertext.
+    pub fn memo_ciphertext_end(&self) -> usize {
+        self.compact_ciphertext_size() + 512
+    }
+}
 
 fn hasher(personal: &[u8; 16]) -> State {
     Params::new().hash_length(32).personal(personal).to_state()
 }
 
-/// Write disjoint parts of each Orchard shielded action as 3 separate hashes:
-/// * \[(nullifier, cmx, ephemeral_key, enc_ciphertext\[..52\])*\] personalized
+/// An incremental hasher computing the commitment of a bundle, as defined in
+/// [ZIP-244: Transaction Identifier Non-Malleability][zip244], from its actions as they
+/// are produced.
+///
+/// The disjoint parts of each Orchard shielded action are written to 3 separate hashes,
+/// where `c` is the [compact ciphertext size](DigestVersion::compact_ciphertext_size):
+/// * \[(nullifier, cmx, ephemeral_key, enc_ciphertext\[..c\])*\] personalized
 ///   with ZCASH_ORCHARD_ACTIONS_COMPACT_HASH_PERSONALIZATION
-/// * \[enc_ciphertext\[52..564\]*\] (memo ciphertexts) personalized
+/// * \[enc_ciphertext\[c..c+512\]*\] (memo ciphertexts) personalized
 ///   with ZCASH_ORCHARD_ACTIONS_MEMOS_HASH_PERSONALIZATION
-/// * \[(cv, rk, enc_ciphertext\[564..\], out_ciphertext)*\] personalized
+/// * \[(cv, rk, enc_ciphertext\[c+512..\], out_ciphertext)*\] personalized
 ///   with ZCASH_ORCHARD_ACTIONS_NONCOMPACT_HASH_PERSONALIZATION
-/// as defined in [ZIP-244: Transaction Identifier Non-Malleability][zip244]
 ///
-/// Then, hash these together along with (flags, value_balance_orchard, anchor_orchard),
-/// personalized with ZCASH_ORCHARD_ACTIONS_HASH_PERSONALIZATION
+/// Then, these are hashed together along with (flags, value_balance_orchard,
+/// anchor_orchard), personalized with ZCASH_ORCHARD_ACTIONS_HASH_PERSONALIZATION.
+///
+/// Hashing the actions of a bundle in order with [`DigestVersion::V6`], and finalizing
+/// with its other fields, produces the same digest as [`Bundle::commitment`].
 ///
 /// [zip244]: https://zips.z.cash/zip-0244
-pub(crate) fn hash_bundle_txid_data<A: Authorization, V: Copy + Into<i64>>(
+#[derive(Clone, Debug)]
+pub struct BundleTxidHasher {
+    version: DigestVersion,
+    compact: State,
+    memos: State,
+    noncompact: State,
+}
+
+impl Default for BundleTxidHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BundleTxidHasher {
+    /// Constructs a hasher for a bundle with no actions yet, using the latest
+    /// [`DigestVersion`].
+    pub fn new() -> Self {
+        Self::with_version(DigestVersion::default())
+    }
+
+    /// Constructs a hasher for a bundle with no actions yet, using the given
+    /// [`DigestVersion`].
+    pub fn with_version(version: DigestVersion) -> Self {
+        BundleTxidHasher {
+            version,
+            compact: hasher(ZCASH_ORCHARD_ACTIONS_COMPACT_HASH_PERSONALIZATION),
+            memos: hasher(ZCASH_ORCHARD_ACTIONS_MEMOS_HASH_PERSONALIZATION),
+            noncompact: hasher(ZCASH_ORCHARD_ACTIONS_NONCOMPACT_HASH_PERSONALIZATION),
+        }
+    }
+
+    /// Absorbs the next action of the bundle.
+    ///
+    /// The authorization of the action is not committed to.
+    pub fn add_action<A>(&mut self, action: &Action<A>) -> &mut Self {
+        let enc_ciphertext = &action.encrypted_note().enc_ciphertext;
+
+        let compact_end = self.version.compact_ciphertext_size();
+        let memo_end = self.version.memo_ciphertext_end();
+
+        self.compact.update(&action.nullifier().to_bytes());
+        self.compact.update(&action.cmx().to_bytes());
+        self.compact.update(&action.encrypted_note().epk_bytes);
+        self.compact.update(&enc_ciphertext[..compact_end]);
+
+        self.memos.update(&enc_ciphertext[compact_end..memo_end]);
+
+        self.noncompact.update(&action.cv_net().to_bytes());
+        self.noncompact.update(&<[u8; 32]>::from(action.rk()));
+        self.noncompact.update(&enc_ciphertext[memo_end..]);
+        self.noncompact
+            .update(&action.encrypted_note().out_ciphertext);
+        self
+    }
+
+    /// Completes the commitment with the remaining fields of the bundle.
+    pub fn finalize(&self, flags: &Flags, value_balance: i64, anchor: &Anchor) -> Blake2bHash {
+        let mut h = hasher(ZCASH_ORCHARD_HASH_PERSONALIZATION);
+        h.update(self.compact.finalize().as_bytes());
+        h.update(self.memos.finalize().as_bytes());
+        h.update(self.noncompact.finalize().as_bytes());
+        h.update(&[flags.to_byte()]);
+        h.update(&value_balance.to_le_bytes());
+        h.update(&anchor.to_bytes());
+        h.finalize()
+    }
+}
+
+/// Construct the commitment of a bundle as defined in
+/// [ZIP-244: Transaction Identifier Non-Malleability][zip244], as described in
+/// [`BundleTxidHasher`].
+///
+/// [zip244]: https://zips.z.cash/zip-0244
+pub fn hash_bundle_txid_data<A: Authorization, V: Copy + Into<i64>>(
     bundle: &Bundle<A, V>,
 ) -> Blake2bHash {
-    let mut h = hasher(ZCASH_ORCHARD_HASH_PERSONALIZATION);
-    let mut ch = hasher(ZCASH_ORCHARD_ACTIONS_COMPACT_HASH_PERSONALIZATION);
-    let mut mh = hasher(ZCASH_ORCHARD_ACTIONS_MEMOS_HASH_PERSONALIZATION);
-    let mut nh = hasher(ZCASH_ORCHARD_ACTIONS_NONCOMPACT_HASH_PERSONALIZATION);
+    hash_bundle_txid_data_with_value_balance(bundle, (*bundle.value_balance()).into())
+}
 
+/// Construct the commitment of a bundle as in [`hash_bundle_txid_data`], with the given
+/// value balance in place of the value balance of the bundle.
+///
+/// The commitment does not depend on the authorization of the bundle, so it can be
+/// computed for a bundle that is still being proven or signed, whatever the type of its
+/// value balance. Parties to a bundle can use it to agree on its effects before any of
+/// them signs it.
+pub fn hash_bundle_txid_data_with_value_balance<A: Authorization, V>(
+    bundle: &Bundle<A, V>,
+    value_balance: i64,
+) -> Blake2bHash {
+    let mut h = BundleTxidHasher::new();
     for action in bundle.actions().iter() {
-        ch.update(&action.nullifier().to_bytes());
-        ch.update(&action.cmx().to_bytes());
-        ch.update(&action.encrypted_note().epk_bytes);
-        ch.update(&action.encrypted_note().enc_ciphertext[..84]); // TODO: make sure it is backward compatible with Orchard [..52]
-
-        mh.update(&action.encrypted_note().enc_ciphertext[84..596]);
-
-        nh.update(&action.cv_net().to_bytes());
-        nh.update(&<[u8; 32]>::from(action.rk()));
-        nh.update(&action.encrypted_note().enc_ciphertext[596..]);
-        nh.update(&action.encrypted_note().out_ciphertext);
+        h.add_action(action);
     }
+    h.finalize(bundle.flags(), value_balance, bundle.anchor())
+}
 
-    h.update(ch.finalize().as_bytes());
-    h.update(mh.finalize().as_bytes());
-    h.update(nh.finalize().as_bytes());
-    h.update(&[bundle.flags().to_byte()]);
+/// Construct the commitment for a swap bundle, by hashing the commitments of each of its
+/// action groups (computed as in [`hash_bundle_txid_data`]) along with the value balance
+/// of the swap bundle, personalized with ZCASH_ORCHARD_ACTION_GROUPS_HASH_PERSONALIZATION.
+#[cfg(feature = "circuit")]
+pub(crate) fn hash_swap_bundle_txid_data<V: Copy + Into<i64>>(
+    bundle: &SwapBundle<V>,
+) -> Blake2bHash {
+    let mut h = hasher(ZCASH_ORCHARD_ACTION_GROUPS_HASH_PERSONALIZATION);
+    for action_group in bundle.action_groups() {
+        h.update(hash_bundle_txid_data(action_group).as_bytes());
+    }
     h.update(&(*bundle.value_balance()).into().to_le_bytes());
-    h.update(&bundle.anchor().to_bytes());
     h.finalize()
 }
 
@@ -76,15 +258,31 @@ pub fn hash_bundle_txid_empty() -> Blake2bHash {
 /// Identifier Non-Malleability][zip244]
 ///
 /// [zip244]: https://zips.z.cash/zip-0244
-pub(crate) fn hash_bundle_auth_data<V>(bundle: &Bundle<Authorized, V>) -> Blake2bHash {
+pub fn hash_bundle_auth_data<V>(bundle: &Bundle<Authorized, V>) -> Blake2bHash {
+    hash_bundle_auth_data_from_parts(
+        bundle.authorization().proof().as_ref(),
+        bundle.actions().iter().map(|action| action.authorization()),
+        bundle.authorization().binding_signature(),
+    )
+}
+
+/// Construct the commitment to the authorizing data of a bundle as in
+/// [`hash_bundle_auth_data`], from the proof, the spend authorization signatures of the
+/// actions in order, and the binding signature of the bundle.
+///
+/// This allows the commitment to be computed for a bundle whose authorization is held
+/// in a different form, such as a bundle that is still being signed.
+pub fn hash_bundle_auth_data_from_parts<'a>(
+    proof: &[u8],
+    spend_auth_sigs: impl IntoIterator<Item = &'a redpallas::Signature<SpendAuth>>,
+    binding_signature: &redpallas::Signature<Binding>,
+) -> Blake2bHash {
     let mut h = hasher(ZCASH_ORCHARD_SIGS_HASH_PERSONALIZATION);
-    h.update(bundle.authorization().proof().as_ref());
-    for action in bundle.actions().iter() {
-        h.update(&<[u8; 64]>::from(action.authorization()));
+    h.update(proof);
+    for sig in spend_auth_sigs {
+        h.update(&<[u8; 64]>::from(sig));
     }
-    h.update(&<[u8; 64]>::from(
-        bundle.authorization().binding_signature(),
-    ));
+    h.update(&<[u8; 64]>::from(binding_signature));
     h.finalize()
 }
 
@@ -142,3 +340,52 @@ pub(crate) fn hash_issue_bundle_auth_data(bundle: &IssueBundle<Signed>) -> Blake
     h.update(&<[u8; 64]>::from(bundle.authorization().signature()));
     h.finalize()
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{
+        hash_bundle_auth_data_from_parts, hash_bundle_txid_data_with_value_balance,
+        BundleTxidHasher, OrchardDigest,
+    };
+    use crate::bundle::testing::arb_bundle_i64;
+
+    proptest! {
+        #[test]
+        fn incremental_txid_matches_bundle_commitment(bundle in arb_bundle_i64(3)) {
+            let mut hasher = BundleTxidHasher::new();
+            for action in bundle.actions() {
+                hasher.add_action(action);
+            }
+            let digest = hasher.finalize(bundle.flags(), *bundle.value_balance(), bundle.anchor());
+
+            prop_assert_eq!(digest, bundle.commitment().0);
+        }
+
+        #[test]
+        fn commitments_from_parts(bundle in arb_bundle_i64(3)) {
+            prop_assert_eq!(
+                hash_bundle_txid_data_with_value_balance(&bundle, *bundle.value_balance()),
+                bundle.commitment().0
+            );
+            prop_assert_eq!(
+                hash_bundle_auth_data_from_parts(
+                    bundle.authorization().proof().as_ref(),
+                    bundle.actions().iter().map(|action| action.authorization()),
+                    bundle.authorization().binding_signature(),
+                ),
+                bundle.authorizing_commitment().0
+            );
+        }
+    }
+
+    #[test]
+    fn personalizations() {
+        assert_eq!(OrchardDigest::Bundle.personalization(), b"ZTxIdOrchardHash");
+        assert_eq!(
+            OrchardDigest::IssueBundleAuth.personalization(),
+            b"ZTxAuthZSAOrHash"
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Per-asset summaries of the value moved by a bundle.
+
+use zcash_note_encryption_zsa::{try_note_decryption, try_output_recovery_with_ovk};
+
+use super::{Authorization, Bundle};
+use crate::{
+    keys::{FullViewingKey, PreparedIncomingViewingKey, Scope},
+    note::AssetBase,
+    note_encryption_v3::OrchardDomainV3,
+    value::{OverflowError, ValueSum},
+};
+
+/// The value of a single asset moved by a bundle, from the point of view of the holder of
+/// a set of viewing keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AssetFlow {
+    asset: AssetBase,
+    received: ValueSum,
+    sent: ValueSum,
+    burnt: ValueSum,
+}
+
+impl AssetFlow {
+    fn new(asset: AssetBase) -> Self {
+        AssetFlow {
+            asset,
+            received: ValueSum::zero(),
+            sent: ValueSum::zero(),
+            burnt: ValueSum::zero(),
+        }
+    }
+
+    /// Returns the asset this flow refers to.
+    pub fn asset(&self) -> AssetBase {
+        self.asset
+    }
+
+    /// Returns the total value of the outputs received by the viewing keys, including
+    /// change.
+    pub fn received(&self) -> ValueSum {
+        self.received
+    }
+
+    /// Returns the total value of the outputs that the viewing keys can recover as
+    /// their sender, and that are not received by the viewing keys themselves.
+    pub fn sent(&self) -> ValueSum {
+        self.sent
+    }
+
+    /// Returns the value of this asset burnt by the bundle.
+    pub fn burnt(&self) -> ValueSum {
+        self.burnt
+    }
+}
+
+impl<T: Authorization, V: Copy + Into<i64>> Bundle<T, V> {
+    /// Summarizes, for each asset, the value received, sent and burnt by this bundle
+    /// from the point of view of the holder of the given full viewing keys.
+    ///
+    /// Outputs are decrypted with the incoming viewing keys of both scopes of each key,
+    /// and recovered with the outgoing viewing keys of both scopes. The value of the notes
+    /// spent by the bundle cannot be learnt from the bundle alone, and is not included.
+    ///
+    /// Assets appear in the order in which they are first encountered in the actions of
+    /// the bundle, followed by the assets that are only burnt.
+    pub fn asset_flows(&self, keys: &[FullViewingKey]) -> Result<Vec<AssetFlow>, OverflowError> {
+        let scopes = [Scope::External, Scope::Internal];
+        let ivks: Vec<_> = keys
+            .iter()
+            .flat_map(|fvk| scopes.iter().map(move |scope| fvk.to_ivk(*scope)))
+            .map(|ivk| PreparedIncomingViewingKey::new(&ivk))
+            .collect();
+        let ovks: Vec<_> = keys
+            .iter()
+            .flat_map(|fvk| scopes.iter().map(move |scope| fvk.to_ovk(*scope)))
+            .collect();
+
+        let mut flows: Vec<AssetFlow> = vec![];
+        let mut flow_for = |asset: AssetBase| -> usize {
+            match flows.iter().position(|flow| flow.asset == asset) {
+                Some(i) => i,
+                None => {
+                    flows.push(AssetFlow::new(asset));
+                    flows.len() - 1
+                }
+            }
+        };
+        let mut updates = vec![];
+
+        for action in self.actions() {
+            let domain = OrchardDomainV3::for_action(action);
+            if let Some((note, _, _)) = ivks
+                .iter()
+                .find_map(|ivk| try_note_decryption(&domain, ivk, action))
+            {
+                updates.push((flow_for(note.asset()), note.value(), false));
+            } else if let Some((note, _, _)) = ovks.iter().find_map(|ovk| {
+                try_output_recovery_with_ovk(
+                    &domain,
+                    ovk,
+                    action,
+                    action.cv_net(),
+                    &action.encrypted_note().out_ciphertext,
+                )
+            }) {
+                updates.push((flow_for(note.asset()), note.value(), true));
+            }
+        }
+        let burns: Vec<_> = self
+            .burn()
+            .iter()
+            .map(|(asset, value)| (flow_for(*asset), ValueSum::from_raw((*value).into())))
+            .collect();
+
+        for (i, value, sent) in updates {
+            let flow = &mut flows[i];
+            if sent {
+                flow.sent = (flow.sent + value).ok_or(OverflowError)?;
+            } else {
+                flow.received = (flow.received + value).ok_or(OverflowError)?;
+            }
+        }
+        for (i, value) in burns {
+            flows[i].burnt = (flows[i].burnt + value).ok_or(OverflowError)?;
+        }
+
+        Ok(flows)
+    }
+}
+
+#[cfg(all(test, feature = "circuit"))]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use crate::{
+        builder::{Builder, BundleType},
+        constants::MERKLE_DEPTH_ORCHARD,
+        keys::{FullViewingKey, Scope, SpendingKey},
+        note::AssetBase,
+        tree::EMPTY_ROOTS,
+        value::{NoteValue, ValueSum},
+    };
+
+    #[test]
+    fn asset_flows_distinguish_received_and_sent() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let other_fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_output(
+                Some(fvk.to_ovk(Scope::External)),
+                other_fvk.address_at(0u32, Scope::External),
+                NoteValue::from_raw(3),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+        builder
+            .add_output(
+                Some(fvk.to_ovk(Scope::Internal)),
+                fvk.address_at(0u32, Scope::Internal),
+                NoteValue::from_raw(5),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+        let (bundle, _) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+
+        let flows = bundle.asset_flows(&[fvk]).unwrap();
+        assert_eq!(flows.len(), 1);
+        assert_eq!(flows[0].asset(), AssetBase::native());
+        assert_eq!(flows[0].received(), ValueSum::from_raw(5));
+        assert_eq!(flows[0].sent(), ValueSum::from_raw(3));
+        assert_eq!(flows[0].burnt(), ValueSum::zero());
+
+        let flows = bundle.asset_flows(&[other_fvk]).unwrap();
+        assert_eq!(flows[0].received(), ValueSum::from_raw(3));
+        assert_eq!(flows[0].sent(), ValueSum::zero());
+    }
+}
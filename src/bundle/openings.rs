@@ -0,0 +1,226 @@
+//! Openings of the value commitments of a bundle, for selective disclosure to auditors.
+
+use core::fmt;
+
+use super::{Authorization, Bundle};
+use crate::{
+    note::AssetBase,
+    value::{ValueCommitTrapdoor, ValueCommitment, ValueSum},
+};
+
+/// The opening of the value commitment `cv_net` of a single action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValueOpening {
+    value: ValueSum,
+    asset: AssetBase,
+    rcv: ValueCommitTrapdoor,
+}
+
+impl ValueOpening {
+    pub(crate) fn from_parts(value: ValueSum, asset: AssetBase, rcv: ValueCommitTrapdoor) -> Self {
+        ValueOpening { value, asset, rcv }
+    }
+
+    /// Returns the net value of the action: the value of the spent note minus the value
+    /// of the output note.
+    pub fn value(&self) -> ValueSum {
+        self.value
+    }
+
+    /// Returns the asset moved by the action.
+    pub fn asset(&self) -> AssetBase {
+        self.asset
+    }
+
+    /// Returns the randomness of the value commitment.
+    pub fn rcv(&self) -> ValueCommitTrapdoor {
+        self.rcv
+    }
+
+    /// Recomputes the value commitment that this opening opens.
+    pub fn commitment(&self) -> ValueCommitment {
+        ValueCommitment::derive(self.value, self.rcv, self.asset)
+    }
+}
+
+/// An error that can occur when checking [`ValueOpenings`] against a bundle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpeningError {
+    /// The openings were made for a bundle with a different commitment.
+    BundleMismatch,
+    /// The bundle does not contain an action at the given index.
+    ActionIndexOutOfBounds(usize),
+    /// The opening for the action at the given index does not match its `cv_net`.
+    InvalidOpening(usize),
+}
+
+impl fmt::Display for OpeningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpeningError::BundleMismatch => {
+                f.write_str("The openings were made for a different bundle")
+            }
+            OpeningError::ActionIndexOutOfBounds(index) => {
+                write!(f, "There is no action at index {}", index)
+            }
+            OpeningError::InvalidOpening(index) => {
+                write!(f, "Invalid value commitment opening for action {}", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OpeningError {}
+
+/// Openings of the value commitments of some of the actions of a bundle, bound to the
+/// commitment of that bundle.
+///
+/// These disclose the amounts and assets moved by the opened actions to an auditor,
+/// without giving them any viewing key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValueOpenings {
+    bundle_commitment: [u8; 32],
+    openings: Vec<(usize, ValueOpening)>,
+}
+
+impl ValueOpenings {
+    /// Binds the openings of the actions of `bundle` to its commitment.
+    ///
+    /// `openings` contains the index of each opened action along with its opening.
+    pub fn new<T: Authorization, V: Copy + Into<i64>>(
+        bundle: &Bundle<T, V>,
+        openings: Vec<(usize, ValueOpening)>,
+    ) -> Self {
+        ValueOpenings {
+            bundle_commitment: bundle.commitment().into(),
+            openings,
+        }
+    }
+
+    /// Returns the commitment of the bundle these openings were made for.
+    pub fn bundle_commitment(&self) -> &[u8; 32] {
+        &self.bundle_commitment
+    }
+
+    /// Returns the index of each opened action along with its opening.
+    pub fn openings(&self) -> &[(usize, ValueOpening)] {
+        &self.openings
+    }
+
+    /// Restricts these openings to the actions for which `disclose` returns `true`, in
+    /// order to only disclose part of the bundle.
+    pub fn select(&self, mut disclose: impl FnMut(usize) -> bool) -> Self {
+        ValueOpenings {
+            bundle_commitment: self.bundle_commitment,
+            openings: self
+                .openings
+                .iter()
+                .filter(|(index, _)| disclose(*index))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Checks these openings against the `cv_net` of the actions of `bundle`.
+    pub fn verify<T: Authorization, V: Copy + Into<i64>>(
+        &self,
+        bundle: &Bundle<T, V>,
+    ) -> Result<(), OpeningError> {
+        if <[u8; 32]>::from(bundle.commitment()) != self.bundle_commitment {
+            return Err(OpeningError::BundleMismatch);
+        }
+
+        for (index, opening) in &self.openings {
+            let action = bundle
+                .actions()
+                .get(*index)
+                .ok_or(OpeningError::ActionIndexOutOfBounds(*index))?;
+            if action.cv_net().to_bytes() != opening.commitment().to_bytes() {
+                return Err(OpeningError::InvalidOpening(*index));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "circuit"))]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::OpeningError;
+    use crate::{
+        builder::{Builder, BundleType},
+        constants::MERKLE_DEPTH_ORCHARD,
+        keys::{FullViewingKey, Scope, SpendingKey},
+        note::AssetBase,
+        tree::EMPTY_ROOTS,
+        value::{NoteValue, ValueSum},
+    };
+
+    #[test]
+    fn value_openings_match_bundle() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+
+        let build = |rng: &mut OsRng| {
+            let mut builder = Builder::new(
+                BundleType::DEFAULT_VANILLA,
+                EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+            );
+            builder.record_value_openings();
+            builder
+                .add_output(
+                    None,
+                    recipient,
+                    NoteValue::from_raw(7),
+                    AssetBase::native(),
+                    None,
+                )
+                .unwrap();
+            builder.build::<i64>(rng).unwrap().unwrap()
+        };
+        let (bundle, meta) = build(&mut rng);
+        let (other_bundle, _) = build(&mut rng);
+
+        let openings = meta.value_openings(&bundle).unwrap();
+        assert_eq!(openings.openings().len(), bundle.actions().len());
+        assert_eq!(openings.verify(&bundle), Ok(()));
+        assert_eq!(
+            openings.verify(&other_bundle),
+            Err(OpeningError::BundleMismatch)
+        );
+
+        // Only disclose the action holding the output.
+        let output_idx = meta.output_action_index(0).unwrap();
+        let disclosed = openings.select(|index| index == output_idx);
+        assert_eq!(disclosed.openings().len(), 1);
+        assert_eq!(disclosed.openings()[0].1.value(), ValueSum::from_raw(-7));
+        assert_eq!(disclosed.verify(&bundle), Ok(()));
+    }
+
+    #[test]
+    fn value_openings_are_opt_in() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_output(
+                None,
+                fvk.address_at(0u32, Scope::External),
+                NoteValue::from_raw(7),
+                AssetBase::native(),
+                None,
+            )
+            .unwrap();
+        let (bundle, meta) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+        assert!(meta.value_openings(&bundle).is_none());
+    }
+}
@@ -0,0 +1,859 @@
+//! Encoding and parsing of authorized bundles.
+//!
+//! Bundles are encoded as in the v6 transaction format:
+//!
+//! - `nActionsOrchard` as a `CompactSize`, followed by the actions without their
+//!   signatures: `cv_net`, `nf`, `rk`, `cmx`, `epk`, `enc_ciphertext`, `out_ciphertext`;
+//! - `flagsOrchard` as a single byte;
+//! - `valueBalanceOrchard` as a little-endian `i64`;
+//! - `nAssetBurn` as a `CompactSize`, followed by the burns: an asset base and a
+//!   little-endian `u64` value;
+//! - `anchorOrchard`;
+//! - `sizeProofsOrchard` as a `CompactSize`, followed by the proof;
+//! - the spend authorization signatures of the actions;
+//! - `bindingSigOrchard`.
+
+use core::fmt;
+use std::io::{self, Read, Write};
+
+use ff::PrimeField;
+use group::GroupEncoding;
+use nonempty::NonEmpty;
+use pasta_curves::pallas;
+
+use super::{
+    burn_validation::{validate_burn_list, BurnListError},
+    size::{ACTION_FIXED_SIZE, BURN_ENTRY_SIZE, SIGNATURE_SIZE},
+    Authorized, Bundle, Flags,
+};
+use crate::{
+    action::Action,
+    keys::EphemeralPublicKey,
+    note::{AssetBase, ExtractedNoteCommitment, Nullifier, TransmittedNoteCiphertext},
+    note_encryption_v3::ENC_CIPHERTEXT_SIZE_V3,
+    primitives::redpallas::{self, SpendAuth},
+    tree::Anchor,
+    value::{NoteValue, ValueCommitment},
+    Proof,
+};
+
+/// The size of the encoding of an action, without its signature.
+const ACTION_SIZE: usize = ACTION_FIXED_SIZE + ENC_CIPHERTEXT_SIZE_V3;
+
+/// The maximum size of a block, which bounds the size of any bundle.
+const MAX_BLOCK_SIZE: usize = 2_000_000;
+
+/// Limits on the bundles accepted by the parser, protecting it against encodings that
+/// would require excessive memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// The maximum number of actions in a bundle.
+    pub max_actions: usize,
+    /// The maximum number of burns in a bundle.
+    pub max_burns: usize,
+    /// The maximum size of the proof of a bundle.
+    pub max_proof_len: usize,
+}
+
+impl Default for ParseLimits {
+    /// Returns the limits implied by the maximum size of a block.
+    fn default() -> Self {
+        ParseLimits {
+            max_actions: MAX_BLOCK_SIZE / (ACTION_SIZE + SIGNATURE_SIZE),
+            max_burns: MAX_BLOCK_SIZE / BURN_ENTRY_SIZE,
+            max_proof_len: MAX_BLOCK_SIZE,
+        }
+    }
+}
+
+/// An error that can occur when parsing a bundle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The encoding ended before the end of the bundle.
+    UnexpectedEnd,
+    /// A `CompactSize` was not encoded in its shortest form.
+    NonCanonicalCompactSize,
+    /// The bundle contains no actions.
+    NoActions,
+    /// The bundle has more actions than allowed by the [`ParseLimits`].
+    TooManyActions(u64),
+    /// The bundle has more burns than allowed by the [`ParseLimits`].
+    TooManyBurns(u64),
+    /// The proof of the bundle is larger than allowed by the [`ParseLimits`].
+    ProofTooLarge(u64),
+    /// The given field of the bundle is not a valid encoding.
+    InvalidEncoding(&'static str),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedEnd => f.write_str("Unexpected end of bundle encoding"),
+            ParseError::NonCanonicalCompactSize => f.write_str("Non-canonical CompactSize"),
+            ParseError::NoActions => f.write_str("Bundle contains no actions"),
+            ParseError::TooManyActions(n) => write!(f, "Too many actions: {}", n),
+            ParseError::TooManyBurns(n) => write!(f, "Too many burns: {}", n),
+            ParseError::ProofTooLarge(n) => write!(f, "Proof too large: {} bytes", n),
+            ParseError::InvalidEncoding(field) => write!(f, "Invalid encoding of {}", field),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A field of a bundle that is not canonically encoded, as found by
+/// [`check_encodings`].
+///
+/// Bundles with such fields may still be valid by consensus, but relayers may choose not
+/// to propagate them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodingViolation {
+    /// The given field of the action at `index` is not a canonical encoding.
+    Action {
+        /// The index of the action within the bundle.
+        index: usize,
+        /// The name of the field.
+        field: &'static str,
+    },
+    /// The flags of the bundle have reserved bits set.
+    ReservedFlagBits(u8),
+    /// The asset of the burn entry at the given index is not a canonical encoding of a
+    /// valid asset base.
+    BurnAsset(usize),
+    /// The value of the burn entry at the given index does not fit in an `i64`.
+    BurnValue(usize),
+    /// The burn entries are not in canonical form.
+    BurnList(BurnListError),
+    /// The anchor is not a canonical encoding of a base field element.
+    Anchor,
+    /// The binding signature is not a canonical encoding.
+    BindingSignature,
+}
+
+impl fmt::Display for EncodingViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodingViolation::Action { index, field } => {
+                write!(f, "Non-canonical {} in action {}", field, index)
+            }
+            EncodingViolation::ReservedFlagBits(flags) => {
+                write!(f, "Reserved flag bits set: {:#010b}", flags)
+            }
+            EncodingViolation::BurnAsset(index) => {
+                write!(f, "Non-canonical asset in burn entry {}", index)
+            }
+            EncodingViolation::BurnValue(index) => {
+                write!(f, "Burn value out of range in burn entry {}", index)
+            }
+            EncodingViolation::BurnList(e) => write!(f, "Non-canonical burn list: {}", e),
+            EncodingViolation::Anchor => f.write_str("Non-canonical anchor"),
+            EncodingViolation::BindingSignature => f.write_str("Non-canonical binding signature"),
+        }
+    }
+}
+
+/// Returns `true` if `bytes` is a canonical encoding of a RedPallas signature, that is, a
+/// canonical point encoding followed by a canonical scalar encoding.
+fn is_canonical_signature(bytes: &[u8; 64]) -> bool {
+    let r: [u8; 32] = bytes[..32].try_into().unwrap();
+    let s: [u8; 32] = bytes[32..].try_into().unwrap();
+    bool::from(pallas::Point::from_bytes(&r).is_some())
+        && bool::from(pallas::Scalar::from_repr(s).is_some())
+}
+
+impl From<ParseError> for io::Error {
+    fn from(e: ParseError) -> Self {
+        let kind = match e {
+            ParseError::UnexpectedEnd => io::ErrorKind::UnexpectedEof,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+/// Returns the number of bytes following the first byte of a `CompactSize`.
+fn compact_size_tail_len(first: u8) -> usize {
+    match first {
+        0xfd => 2,
+        0xfe => 4,
+        0xff => 8,
+        _ => 0,
+    }
+}
+
+/// Decodes a `CompactSize` from its first byte and the bytes following it.
+fn decode_compact_size(first: u8, tail: &[u8]) -> Result<u64, ParseError> {
+    let mut buf = [0u8; 8];
+    buf[..tail.len()].copy_from_slice(tail);
+    let n = u64::from_le_bytes(buf);
+    let min = match first {
+        0xfd => 0xfd,
+        0xfe => 0x1_0000,
+        0xff => 0x1_0000_0000,
+        _ => return Ok(first.into()),
+    };
+    if n < min {
+        Err(ParseError::NonCanonicalCompactSize)
+    } else {
+        Ok(n)
+    }
+}
+
+/// Checks a count against a limit.
+fn check_limit(
+    n: u64,
+    limit: usize,
+    err: impl FnOnce(u64) -> ParseError,
+) -> Result<usize, ParseError> {
+    usize::try_from(n)
+        .ok()
+        .filter(|n| *n <= limit)
+        .ok_or_else(|| err(n))
+}
+
+/// Returns the size of `count` entries of `size` bytes followed by `extra` bytes, or
+/// `err(count)` if it overflows.
+///
+/// The limits are chosen by the caller, so a count within them may still be too large
+/// for the size of its entries to be representable.
+fn encoded_size(
+    count: usize,
+    size: usize,
+    extra: usize,
+    err: impl FnOnce(u64) -> ParseError,
+) -> Result<usize, ParseError> {
+    count
+        .checked_mul(size)
+        .and_then(|n| n.checked_add(extra))
+        .ok_or_else(|| err(count as u64))
+}
+
+/// A cursor over the bytes of an encoding.
+struct Cursor<'a>(&'a [u8]);
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ParseError> {
+        if self.0.len() < n {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<&'a [u8; N], ParseError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn take_compact_size(&mut self) -> Result<u64, ParseError> {
+        let first = self.take(1)?[0];
+        let tail = self.take(compact_size_tail_len(first))?;
+        decode_compact_size(first, tail)
+    }
+}
+
+/// A view of the encoding of an action, borrowing its bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct ActionView<'a> {
+    bytes: &'a [u8; ACTION_SIZE],
+    spend_auth_sig: &'a [u8; 64],
+}
+
+impl<'a> ActionView<'a> {
+    fn field<const N: usize>(&self, offset: usize) -> &'a [u8; N] {
+        self.bytes[offset..offset + N].try_into().unwrap()
+    }
+
+    /// Returns the encoding of the value commitment of the action.
+    pub fn cv_net_bytes(&self) -> &'a [u8; 32] {
+        self.field(0)
+    }
+
+    /// Returns the encoding of the nullifier of the note spent by the action.
+    pub fn nullifier_bytes(&self) -> &'a [u8; 32] {
+        self.field(32)
+    }
+
+    /// Returns the encoding of the randomized verification key of the action.
+    pub fn rk_bytes(&self) -> &'a [u8; 32] {
+        self.field(64)
+    }
+
+    /// Returns the encoding of the commitment to the note created by the action.
+    pub fn cmx_bytes(&self) -> &'a [u8; 32] {
+        self.field(96)
+    }
+
+    /// Returns the encoding of the ephemeral public key of the action.
+    pub fn epk_bytes(&self) -> &'a [u8; 32] {
+        self.field(128)
+    }
+
+    /// Returns the encrypted note ciphertext of the action.
+    pub fn enc_ciphertext(&self) -> &'a [u8; ENC_CIPHERTEXT_SIZE_V3] {
+        self.field(160)
+    }
+
+    /// Returns the outgoing ciphertext of the action.
+    pub fn out_ciphertext(&self) -> &'a [u8; 80] {
+        self.field(160 + ENC_CIPHERTEXT_SIZE_V3)
+    }
+
+    /// Returns the encoding of the spend authorization signature of the action.
+    pub fn spend_auth_sig_bytes(&self) -> &'a [u8; 64] {
+        self.spend_auth_sig
+    }
+
+    /// Returns the names of the fields of the action that are not canonical encodings.
+    fn non_canonical_fields(&self) -> impl Iterator<Item = &'static str> + '_ {
+        let checks: [(&'static str, bool); 6] = [
+            (
+                "cv_net",
+                ValueCommitment::from_bytes(self.cv_net_bytes())
+                    .is_some()
+                    .into(),
+            ),
+            (
+                "nf",
+                Nullifier::from_bytes(self.nullifier_bytes())
+                    .is_some()
+                    .into(),
+            ),
+            (
+                "rk",
+                redpallas::VerificationKey::<SpendAuth>::try_from(*self.rk_bytes()).is_ok(),
+            ),
+            (
+                "cmx",
+                ExtractedNoteCommitment::from_bytes(self.cmx_bytes())
+                    .is_some()
+                    .into(),
+            ),
+            (
+                "epk",
+                EphemeralPublicKey::from_bytes(self.epk_bytes())
+                    .is_some()
+                    .into(),
+            ),
+            (
+                "spend_auth_sig",
+                is_canonical_signature(self.spend_auth_sig),
+            ),
+        ];
+        checks
+            .into_iter()
+            .filter(|(_, canonical)| !canonical)
+            .map(|(field, _)| field)
+    }
+
+    /// Decodes the action.
+    pub fn to_action(&self) -> Result<Action<redpallas::Signature<SpendAuth>>, ParseError> {
+        let cv_net = Option::from(ValueCommitment::from_bytes(self.cv_net_bytes()))
+            .ok_or(ParseError::InvalidEncoding("cv_net"))?;
+        let nf = Option::from(Nullifier::from_bytes(self.nullifier_bytes()))
+            .ok_or(ParseError::InvalidEncoding("nf"))?;
+        let rk = redpallas::VerificationKey::try_from(*self.rk_bytes())
+            .map_err(|_| ParseError::InvalidEncoding("rk"))?;
+        let cmx = Option::from(ExtractedNoteCommitment::from_bytes(self.cmx_bytes()))
+            .ok_or(ParseError::InvalidEncoding("cmx"))?;
+
+        Ok(Action::from_parts(
+            nf,
+            rk,
+            cmx,
+            TransmittedNoteCiphertext {
+                epk_bytes: *self.epk_bytes(),
+                enc_ciphertext: *self.enc_ciphertext(),
+                out_ciphertext: *self.out_ciphertext(),
+            },
+            cv_net,
+            redpallas::Signature::from(*self.spend_auth_sig),
+        ))
+    }
+}
+
+/// A view of the encoding of an authorized bundle, borrowing its bytes.
+///
+/// Parsing a view only checks the structure of the encoding and the [`ParseLimits`],
+/// and performs no allocation. The fields of the bundle are only decoded on access.
+#[derive(Clone, Copy, Debug)]
+pub struct BundleView<'a> {
+    actions: &'a [u8],
+    flags: u8,
+    value_balance: i64,
+    burn: &'a [u8],
+    anchor: &'a [u8; 32],
+    proof: &'a [u8],
+    spend_auth_sigs: &'a [u8],
+    binding_signature: &'a [u8; 64],
+}
+
+impl<'a> BundleView<'a> {
+    /// Parses the encoding of a bundle at the start of `bytes`.
+    ///
+    /// Returns the view of the bundle along with the bytes following its encoding.
+    pub fn parse(bytes: &'a [u8], limits: &ParseLimits) -> Result<(Self, &'a [u8]), ParseError> {
+        let (view, rest) = Self::parse_structure(bytes, limits)?;
+        Flags::from_byte(view.flags).ok_or(ParseError::InvalidEncoding("flags"))?;
+        Ok((view, rest))
+    }
+
+    /// Parses the structure of the encoding of a bundle, without checking its flags.
+    fn parse_structure(
+        bytes: &'a [u8],
+        limits: &ParseLimits,
+    ) -> Result<(Self, &'a [u8]), ParseError> {
+        let mut cursor = Cursor(bytes);
+
+        let num_actions = check_limit(
+            cursor.take_compact_size()?,
+            limits.max_actions,
+            ParseError::TooManyActions,
+        )?;
+        if num_actions == 0 {
+            return Err(ParseError::NoActions);
+        }
+        let actions = cursor.take(encoded_size(
+            num_actions,
+            ACTION_SIZE,
+            0,
+            ParseError::TooManyActions,
+        )?)?;
+        let flags = cursor.take(1)?[0];
+        let value_balance = i64::from_le_bytes(*cursor.take_array()?);
+        let num_burns = check_limit(
+            cursor.take_compact_size()?,
+            limits.max_burns,
+            ParseError::TooManyBurns,
+        )?;
+        let burn = cursor.take(encoded_size(
+            num_burns,
+            BURN_ENTRY_SIZE,
+            0,
+            ParseError::TooManyBurns,
+        )?)?;
+        let anchor = cursor.take_array()?;
+        let proof_len = check_limit(
+            cursor.take_compact_size()?,
+            limits.max_proof_len,
+            ParseError::ProofTooLarge,
+        )?;
+        let proof = cursor.take(proof_len)?;
+        let spend_auth_sigs = cursor.take(encoded_size(
+            num_actions,
+            SIGNATURE_SIZE,
+            0,
+            ParseError::TooManyActions,
+        )?)?;
+        let binding_signature = cursor.take_array()?;
+
+        Ok((
+            BundleView {
+                actions,
+                flags,
+                value_balance,
+                burn,
+                anchor,
+                proof,
+                spend_auth_sigs,
+                binding_signature,
+            },
+            cursor.0,
+        ))
+    }
+
+    /// Returns the number of actions in the bundle.
+    pub fn num_actions(&self) -> usize {
+        self.actions.len() / ACTION_SIZE
+    }
+
+    /// Returns the actions of the bundle.
+    pub fn actions(&self) -> impl ExactSizeIterator<Item = ActionView<'a>> {
+        self.actions
+            .chunks_exact(ACTION_SIZE)
+            .zip(self.spend_auth_sigs.chunks_exact(SIGNATURE_SIZE))
+            .map(|(bytes, spend_auth_sig)| ActionView {
+                bytes: bytes.try_into().unwrap(),
+                spend_auth_sig: spend_auth_sig.try_into().unwrap(),
+            })
+    }
+
+    /// Returns the flags of the bundle.
+    pub fn flags(&self) -> Flags {
+        Flags::from_byte(self.flags).expect("checked by BundleView::parse")
+    }
+
+    /// Returns the value balance of the bundle.
+    pub fn value_balance(&self) -> i64 {
+        self.value_balance
+    }
+
+    /// Returns the encodings of the assets burnt by the bundle, along with the burnt
+    /// values.
+    pub fn burn(&self) -> impl ExactSizeIterator<Item = (&'a [u8; 32], u64)> {
+        self.burn.chunks_exact(BURN_ENTRY_SIZE).map(|entry| {
+            let (asset, value) = entry.split_at(32);
+            (
+                asset.try_into().unwrap(),
+                u64::from_le_bytes(value.try_into().unwrap()),
+            )
+        })
+    }
+
+    /// Returns the encoding of the anchor of the bundle.
+    pub fn anchor_bytes(&self) -> &'a [u8; 32] {
+        self.anchor
+    }
+
+    /// Returns the proof of the bundle.
+    pub fn proof_bytes(&self) -> &'a [u8] {
+        self.proof
+    }
+
+    /// Returns the encoding of the binding signature of the bundle.
+    pub fn binding_signature_bytes(&self) -> &'a [u8; 64] {
+        self.binding_signature
+    }
+
+    /// Returns the fields of the bundle that are not canonically encoded.
+    pub fn encoding_violations(&self) -> Vec<EncodingViolation> {
+        let mut violations = self
+            .actions()
+            .enumerate()
+            .flat_map(|(index, action)| {
+                action
+                    .non_canonical_fields()
+                    .map(move |field| EncodingViolation::Action { index, field })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        if Flags::from_byte(self.flags).is_none() {
+            violations.push(EncodingViolation::ReservedFlagBits(self.flags));
+        }
+
+        let mut burn = Vec::with_capacity(self.burn().len());
+        for (index, (asset, value)) in self.burn().enumerate() {
+            match Option::from(AssetBase::from_bytes(asset)) {
+                Some(asset) => burn.push((asset, NoteValue::from_raw(value))),
+                None => violations.push(EncodingViolation::BurnAsset(index)),
+            }
+            if i64::try_from(value).is_err() {
+                violations.push(EncodingViolation::BurnValue(index));
+            }
+        }
+        // The order of the burn entries can only be checked if all of their assets
+        // could be decoded.
+        if burn.len() == self.burn().len() {
+            if let Err(e) = validate_burn_list(&burn) {
+                violations.push(EncodingViolation::BurnList(e));
+            }
+        }
+
+        if Anchor::from_bytes(*self.anchor).is_none().into() {
+            violations.push(EncodingViolation::Anchor);
+        }
+        if !is_canonical_signature(self.binding_signature) {
+            violations.push(EncodingViolation::BindingSignature);
+        }
+
+        violations
+    }
+
+    /// Decodes the bundle.
+    pub fn to_bundle(&self) -> Result<Bundle<Authorized, i64>, ParseError> {
+        let actions = self
+            .actions()
+            .map(|action| action.to_action())
+            .collect::<Result<Vec<_>, _>>()?;
+        let burn = self
+            .burn()
+            .map(|(asset, value)| {
+                let asset = Option::from(AssetBase::from_bytes(asset))
+                    .ok_or(ParseError::InvalidEncoding("burn asset"))?;
+                let value =
+                    i64::try_from(value).map_err(|_| ParseError::InvalidEncoding("burn value"))?;
+                Ok((asset, value))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let anchor = Option::from(Anchor::from_bytes(*self.anchor))
+            .ok_or(ParseError::InvalidEncoding("anchor"))?;
+
+        Ok(Bundle::from_parts(
+            NonEmpty::from_vec(actions).ok_or(ParseError::NoActions)?,
+            self.flags(),
+            self.value_balance,
+            burn,
+            anchor,
+            Authorized::from_parts(
+                Proof::new(self.proof.to_vec()),
+                redpallas::Signature::from(*self.binding_signature),
+            ),
+        ))
+    }
+}
+
+/// Checks that all of the fields of the bundle encoded at the start of `bytes` are
+/// canonically encoded, and returns the fields that are not.
+///
+/// Unlike [`BundleView::parse`], this does not reject bundles whose flags have reserved
+/// bits set, but reports them as violations. An error is only returned if the structure
+/// of the encoding is invalid.
+pub fn check_encodings(
+    bytes: &[u8],
+    limits: &ParseLimits,
+) -> Result<Vec<EncodingViolation>, ParseError> {
+    let (view, _) = BundleView::parse_structure(bytes, limits)?;
+    Ok(view.encoding_violations())
+}
+
+/// Reads `n` more bytes from `reader` into `buf`, returning the offset at which they
+/// start.
+fn read_more<R: Read>(reader: &mut R, buf: &mut Vec<u8>, n: usize) -> io::Result<usize> {
+    let start = buf.len();
+    let end = start.checked_add(n).ok_or(ParseError::UnexpectedEnd)?;
+    buf.resize(end, 0);
+    reader.read_exact(&mut buf[start..])?;
+    Ok(start)
+}
+
+/// Reads a `CompactSize` from `reader` into `buf`, returning its value.
+pub(crate) fn read_compact_size<R: Read>(reader: &mut R, buf: &mut Vec<u8>) -> io::Result<u64> {
+    let start = read_more(reader, buf, 1)?;
+    let first = buf[start];
+    let tail_start = read_more(reader, buf, compact_size_tail_len(first))?;
+    Ok(decode_compact_size(first, &buf[tail_start..])?)
+}
+
+/// Reads the encoding of a bundle from `reader`, enforcing the given limits.
+///
+/// The encoding is read into a single buffer, which never grows beyond the size implied
+/// by the limits.
+pub fn read_bundle<R: Read>(
+    mut reader: R,
+    limits: &ParseLimits,
+) -> io::Result<Bundle<Authorized, i64>> {
+    let mut buf = vec![];
+
+    let num_actions = check_limit(
+        read_compact_size(&mut reader, &mut buf)?,
+        limits.max_actions,
+        ParseError::TooManyActions,
+    )?;
+    read_more(
+        &mut reader,
+        &mut buf,
+        encoded_size(num_actions, ACTION_SIZE, 1 + 8, ParseError::TooManyActions)?,
+    )?;
+    let num_burns = check_limit(
+        read_compact_size(&mut reader, &mut buf)?,
+        limits.max_burns,
+        ParseError::TooManyBurns,
+    )?;
+    read_more(
+        &mut reader,
+        &mut buf,
+        encoded_size(num_burns, BURN_ENTRY_SIZE, 32, ParseError::TooManyBurns)?,
+    )?;
+    let proof_len = check_limit(
+        read_compact_size(&mut reader, &mut buf)?,
+        limits.max_proof_len,
+        ParseError::ProofTooLarge,
+    )?;
+    // The proof is followed by the spend authorization signatures and the binding
+    // signature.
+    let proof_and_binding_len = proof_len
+        .checked_add(SIGNATURE_SIZE)
+        .ok_or(ParseError::ProofTooLarge(proof_len as u64))?;
+    read_more(
+        &mut reader,
+        &mut buf,
+        encoded_size(
+            num_actions,
+            SIGNATURE_SIZE,
+            proof_and_binding_len,
+            ParseError::TooManyActions,
+        )?,
+    )?;
+
+    let (view, _) = BundleView::parse(&buf, limits)?;
+    Ok(view.to_bundle()?)
+}
+
+/// Writes `n` as a `CompactSize`.
+pub(crate) fn write_compact_size<W: Write>(mut writer: W, n: usize) -> io::Result<()> {
+    match n {
+        0..=0xfc => writer.write_all(&[n as u8]),
+        0xfd..=0xffff => {
+            writer.write_all(&[0xfd])?;
+            writer.write_all(&(n as u16).to_le_bytes())
+        }
+        0x1_0000..=0xffff_ffff => {
+            writer.write_all(&[0xfe])?;
+            writer.write_all(&(n as u32).to_le_bytes())
+        }
+        _ => {
+            writer.write_all(&[0xff])?;
+            writer.write_all(&(n as u64).to_le_bytes())
+        }
+    }
+}
+
+/// Writes the encoding of `bundle` to `writer`, in the layout read by [`BundleView`]
+/// and [`read_bundle`].
+///
+/// Returns an error if the bundle burns a negative value.
+pub fn write_bundle<V: Copy + Into<i64>, W: Write>(
+    bundle: &Bundle<Authorized, V>,
+    mut writer: W,
+) -> io::Result<()> {
+    write_compact_size(&mut writer, bundle.actions().len())?;
+    for action in bundle.actions() {
+        writer.write_all(&action.cv_net().to_bytes())?;
+        writer.write_all(&action.nullifier().to_bytes())?;
+        writer.write_all(&<[u8; 32]>::from(action.rk()))?;
+        writer.write_all(&action.cmx().to_bytes())?;
+        writer.write_all(&action.encrypted_note().epk_bytes)?;
+        writer.write_all(&action.encrypted_note().enc_ciphertext)?;
+        writer.write_all(&action.encrypted_note().out_ciphertext)?;
+    }
+    writer.write_all(&[bundle.flags().to_byte()])?;
+    let value_balance: i64 = (*bundle.value_balance()).into();
+    writer.write_all(&value_balance.to_le_bytes())?;
+    write_compact_size(&mut writer, bundle.burn().len())?;
+    for (asset, value) in bundle.burn() {
+        let value = u64::try_from(Into::<i64>::into(*value))
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Negative burn value"))?;
+        writer.write_all(&asset.to_bytes())?;
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.write_all(&bundle.anchor().to_bytes())?;
+    let proof = bundle.authorization().proof().as_ref();
+    write_compact_size(&mut writer, proof.len())?;
+    writer.write_all(proof)?;
+    for action in bundle.actions() {
+        writer.write_all(&<[u8; 64]>::from(action.authorization()))?;
+    }
+    writer.write_all(&<[u8; 64]>::from(
+        bundle.authorization().binding_signature(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use group::{Group, GroupEncoding};
+    use pasta_curves::pallas;
+    use proptest::prelude::*;
+
+    use super::{
+        check_encodings, read_bundle, write_bundle, BundleView, EncodingViolation, ParseError,
+        ParseLimits, ACTION_SIZE,
+    };
+    use crate::bundle::{
+        burn_validation::BurnListError, testing::arb_bundle_i64, Authorized, Bundle,
+    };
+
+    fn encode(bundle: &Bundle<Authorized, i64>) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_bundle(bundle, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn oversized_counts_are_rejected() {
+        let limits = ParseLimits {
+            max_actions: usize::MAX,
+            max_burns: usize::MAX,
+            max_proof_len: usize::MAX,
+        };
+
+        // A count of actions whose encoding cannot fit in memory.
+        let mut bytes = vec![0xff];
+        bytes.extend_from_slice(&(usize::MAX as u64).to_le_bytes());
+        assert_eq!(
+            BundleView::parse(&bytes, &limits).unwrap_err(),
+            ParseError::TooManyActions(usize::MAX as u64)
+        );
+        assert_eq!(
+            read_bundle(&bytes[..], &limits).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn parse_roundtrip(bundle in arb_bundle_i64(3)) {
+            let mut bytes = encode(&bundle);
+            bytes.extend_from_slice(b"rest");
+
+            let limits = ParseLimits::default();
+            let (view, rest) = BundleView::parse(&bytes, &limits).unwrap();
+            assert_eq!(rest, b"rest");
+            assert_eq!(view.num_actions(), 3);
+            for (action, view) in bundle.actions().iter().zip(view.actions()) {
+                assert_eq!(view.enc_ciphertext(), &action.encrypted_note().enc_ciphertext);
+            }
+
+            let parsed = view.to_bundle().unwrap();
+            assert_eq!(encode(&parsed), &bytes[..bytes.len() - 4]);
+
+            let read = read_bundle(&bytes[..], &limits).unwrap();
+            assert_eq!(encode(&read), encode(&parsed));
+
+            assert_eq!(
+                BundleView::parse(&bytes[..100], &limits).unwrap_err(),
+                ParseError::UnexpectedEnd
+            );
+            let limits = ParseLimits { max_actions: 2, ..limits };
+            assert_eq!(
+                BundleView::parse(&bytes, &limits).unwrap_err(),
+                ParseError::TooManyActions(3)
+            );
+            assert!(read_bundle(&bytes[..], &limits).is_err());
+        }
+
+        #[test]
+        fn encoding_violations_are_reported(bundle in arb_bundle_i64(3)) {
+            let mut bytes = encode(&bundle);
+            let limits = ParseLimits::default();
+
+            // Arbitrary actions have identity ephemeral keys, so replace them.
+            let epk = pallas::Point::generator().to_bytes();
+            for i in 0..3 {
+                let offset = 1 + i * ACTION_SIZE + 128;
+                bytes[offset..offset + 32].copy_from_slice(&epk);
+            }
+
+            assert_eq!(check_encodings(&bytes, &limits).unwrap(), vec![]);
+
+            // Swap the first two burns.
+            if bundle.burn().len() >= 2 {
+                let mut swapped = bytes.clone();
+                let offset = 1 + 3 * ACTION_SIZE + 10;
+                swapped[offset..offset + 80].rotate_left(40);
+                assert_eq!(
+                    check_encodings(&swapped, &limits).unwrap(),
+                    vec![EncodingViolation::BurnList(BurnListError::Unsorted(1))]
+                );
+            }
+
+            // Set a reserved flag bit and make the ephemeral key of the second action
+            // non-canonical.
+            bytes[1 + 3 * ACTION_SIZE] |= 0b1000_0000;
+            bytes[1 + ACTION_SIZE + 128..1 + ACTION_SIZE + 160].copy_from_slice(&[0xff; 32]);
+            assert_eq!(
+                BundleView::parse(&bytes, &limits).unwrap_err(),
+                ParseError::InvalidEncoding("flags")
+            );
+
+            let violations = check_encodings(&bytes, &limits).unwrap();
+            assert_eq!(
+                violations,
+                vec![
+                    EncodingViolation::Action { index: 1, field: "epk" },
+                    EncodingViolation::ReservedFlagBits(bundle.flags().to_byte() | 0b1000_0000),
+                ]
+            );
+        }
+    }
+}
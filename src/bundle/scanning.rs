@@ -0,0 +1,184 @@
+//! Classification of the outputs received by a wallet.
+
+use zcash_note_encryption_zsa::try_note_decryption;
+
+use super::{Authorization, Bundle};
+use crate::{
+    keys::{IncomingViewingKey, ScanningKeys, Scope},
+    note::AssetBase,
+    note_encryption_v3::OrchardDomainV3,
+    Address, Note,
+};
+
+/// The kind of an output received by a wallet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputKind {
+    /// A note received by an external address of the wallet.
+    Transfer,
+    /// A note received by an internal address of the wallet, which is the change of a
+    /// transaction created by the wallet.
+    Change,
+    /// A note created by an issuance bundle.
+    Issuance,
+}
+
+/// An output of a bundle that was received by one of the keys of a wallet.
+#[derive(Clone, Debug)]
+pub struct ReceivedOutput {
+    index: usize,
+    kind: OutputKind,
+    ivk: IncomingViewingKey,
+    note: Note,
+    recipient: Address,
+    memo: Option<[u8; 512]>,
+}
+
+impl ReceivedOutput {
+    pub(crate) fn from_parts(
+        index: usize,
+        kind: OutputKind,
+        ivk: IncomingViewingKey,
+        note: Note,
+        recipient: Address,
+        memo: Option<[u8; 512]>,
+    ) -> Self {
+        ReceivedOutput {
+            index,
+            kind,
+            ivk,
+            note,
+            recipient,
+            memo,
+        }
+    }
+
+    /// Returns the index of the action, or of the note within its issue bundle, that
+    /// created the output.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the kind of the output.
+    pub fn kind(&self) -> OutputKind {
+        self.kind
+    }
+
+    /// Returns the incoming viewing key that received the output.
+    pub fn ivk(&self) -> &IncomingViewingKey {
+        &self.ivk
+    }
+
+    /// Returns the received note.
+    pub fn note(&self) -> &Note {
+        &self.note
+    }
+
+    /// Returns the asset of the received note.
+    pub fn asset(&self) -> AssetBase {
+        self.note.asset()
+    }
+
+    /// Returns the address that received the output.
+    pub fn recipient(&self) -> Address {
+        self.recipient
+    }
+
+    /// Returns the memo of the output, or `None` for issued notes, which have no memo.
+    pub fn memo(&self) -> Option<&[u8; 512]> {
+        self.memo.as_ref()
+    }
+}
+
+impl<T: Authorization, V> Bundle<T, V> {
+    /// Performs trial decryption of each action in the bundle with each of the incoming
+    /// viewing keys in `keys`, and returns the received outputs classified by whether
+    /// they are transfers or change.
+    pub fn decrypt_received_outputs(&self, keys: &ScanningKeys) -> Vec<ReceivedOutput> {
+        self.actions
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, action)| {
+                let domain = OrchardDomainV3::for_action(action);
+                keys.prepared_ivks()
+                    .iter()
+                    .find_map(|(ivk, scope, prepared_ivk)| {
+                        try_note_decryption(&domain, prepared_ivk, action).map(
+                            |(note, recipient, memo)| {
+                                let kind = match scope {
+                                    Scope::External => OutputKind::Transfer,
+                                    Scope::Internal => OutputKind::Change,
+                                };
+                                ReceivedOutput::from_parts(
+                                    idx,
+                                    kind,
+                                    ivk.clone(),
+                                    note,
+                                    recipient,
+                                    Some(memo),
+                                )
+                            },
+                        )
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "circuit"))]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::OutputKind;
+    use crate::{
+        builder::{Builder, BundleType},
+        constants::MERKLE_DEPTH_ORCHARD,
+        keys::{FullViewingKey, ScanningKeys, Scope, SpendingKey},
+        note::AssetBase,
+        tree::EMPTY_ROOTS,
+        value::NoteValue,
+    };
+
+    #[test]
+    fn received_outputs_are_classified_by_scope() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let external = fvk.address_at(0u32, Scope::External);
+        let internal = fvk.address_at(0u32, Scope::Internal);
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        for (recipient, value) in [(external, 5000), (internal, 3000)] {
+            builder
+                .add_output(
+                    None,
+                    recipient,
+                    NoteValue::from_raw(value),
+                    AssetBase::native(),
+                    None,
+                )
+                .unwrap();
+        }
+        let (bundle, meta) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+
+        let keys = ScanningKeys::from_full_viewing_keys(&[fvk]);
+        let mut received = bundle.decrypt_received_outputs(&keys);
+        received.sort_by_key(|output| output.note().value().inner());
+        assert_eq!(received.len(), 2);
+
+        assert_eq!(received[0].index(), meta.output_action_index(1).unwrap());
+        assert_eq!(received[0].kind(), OutputKind::Change);
+        assert_eq!(received[0].recipient(), internal);
+
+        assert_eq!(received[1].index(), meta.output_action_index(0).unwrap());
+        assert_eq!(received[1].kind(), OutputKind::Transfer);
+        assert_eq!(received[1].recipient(), external);
+
+        for output in &received {
+            assert_eq!(output.asset(), AssetBase::native());
+            assert!(output.memo().is_some());
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! Checking an authorized bundle before broadcasting it.
+
+use core::fmt;
+use std::collections::BTreeMap;
+
+use super::{
+    burn_validation::{validate_bundle_burn, validate_burn_list, BurnError, BurnListError},
+    verify_binding_signature, Authorized, Bundle, FailedCheck,
+};
+use crate::{circuit::VerifyingKey, value::NoteValue};
+
+/// A structural rule of the consensus rules that a bundle does not satisfy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StructuralError {
+    /// The action at `index` spends the same nullifier as the action at `first`.
+    DuplicateNullifier {
+        /// The index of the first action spending the nullifier.
+        first: usize,
+        /// The index of the duplicate action.
+        index: usize,
+    },
+    /// The bundle burns assets while ZSA transactions are disabled in its flags.
+    BurnWithoutZsa,
+    /// The burns of the bundle are invalid.
+    InvalidBurn(BurnError),
+    /// The burns of the bundle are valid, but do not form a canonical burn list.
+    InvalidBurnList(BurnListError),
+}
+
+impl fmt::Display for StructuralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructuralError::DuplicateNullifier { first, index } => write!(
+                f,
+                "Action {} spends the same nullifier as action {}",
+                index, first
+            ),
+            StructuralError::BurnWithoutZsa => {
+                f.write_str("The bundle burns assets with ZSA transactions disabled")
+            }
+            StructuralError::InvalidBurn(e) => write!(f, "Invalid burn: {}", e),
+            StructuralError::InvalidBurnList(e) => write!(f, "Invalid burn list: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StructuralError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StructuralError::InvalidBurn(e) => Some(e),
+            StructuralError::InvalidBurnList(e) => Some(e),
+            StructuralError::DuplicateNullifier { .. } | StructuralError::BurnWithoutZsa => None,
+        }
+    }
+}
+
+/// The outcome of [`Bundle::self_check`].
+#[derive(Debug)]
+pub struct SelfCheckReport {
+    /// The proof and signature checks that failed, in the order of [`FailedCheck`].
+    pub failed_checks: Vec<FailedCheck>,
+    /// The structural rules that the bundle does not satisfy.
+    pub structural_errors: Vec<StructuralError>,
+}
+
+impl SelfCheckReport {
+    /// Returns `true` if the bundle passed all of the checks of [`Bundle::self_check`].
+    pub fn is_valid(&self) -> bool {
+        self.failed_checks.is_empty() && self.structural_errors.is_empty()
+    }
+}
+
+impl<V: Copy + Into<i64>> Bundle<Authorized, V> {
+    /// Checks this bundle as a node would on its own, and reports all of the checks that
+    /// fail.
+    ///
+    /// This verifies the proof against `vk`, the spend authorization signatures and the
+    /// binding signature over `sighash`, that no nullifier is spent twice, and that the
+    /// burns are allowed by the flags of the bundle and form a valid burn list. Unlike the
+    /// [`BatchValidator`], it does not stop at the first failure, so that a wallet can
+    /// find the problems with a bundle before relaying the transaction containing it.
+    ///
+    /// Checks that depend on the chain state, such as the anchor and the nullifier set,
+    /// are not performed. Neither are the checks of the encoding of the bundle, which
+    /// can be performed on the serialized bundle with [`check_encodings`].
+    ///
+    /// [`BatchValidator`]: super::BatchValidator
+    /// [`check_encodings`]: super::parse::check_encodings
+    pub fn self_check(&self, vk: &VerifyingKey, sighash: &[u8; 32]) -> SelfCheckReport {
+        let mut failed_checks = vec![];
+        if self.verify_proof(vk).is_err() {
+            failed_checks.push(FailedCheck::Proof(0));
+        }
+        for (index, action) in self.actions().iter().enumerate() {
+            if action.rk().verify(sighash, action.authorization()).is_err() {
+                failed_checks.push(FailedCheck::SpendAuthSignature(index));
+            }
+        }
+        if verify_binding_signature(self, sighash).is_err() {
+            failed_checks.push(FailedCheck::BindingSignature);
+        }
+
+        let mut structural_errors = vec![];
+        let mut nullifiers = BTreeMap::new();
+        for (index, action) in self.actions().iter().enumerate() {
+            match nullifiers.get(action.nullifier()) {
+                Some(&first) => {
+                    structural_errors.push(StructuralError::DuplicateNullifier { first, index })
+                }
+                None => {
+                    nullifiers.insert(*action.nullifier(), index);
+                }
+            }
+        }
+        if !self.burn().is_empty() && !self.flags().zsa_enabled() {
+            structural_errors.push(StructuralError::BurnWithoutZsa);
+        }
+        let burn = self
+            .burn()
+            .iter()
+            .map(|(asset, value)| (*asset, Into::<i64>::into(*value)))
+            .collect();
+        match validate_bundle_burn(&burn) {
+            Ok(()) => {
+                // The burned values have been checked to be positive.
+                let burn = burn
+                    .into_iter()
+                    .map(|(asset, value)| (asset, NoteValue::from_raw(value as u64)))
+                    .collect::<Vec<_>>();
+                if let Err(e) = validate_burn_list(&burn) {
+                    structural_errors.push(StructuralError::InvalidBurnList(e));
+                }
+            }
+            Err(e) => structural_errors.push(StructuralError::InvalidBurn(e)),
+        }
+
+        SelfCheckReport {
+            failed_checks,
+            structural_errors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nonempty::NonEmpty;
+
+    use super::StructuralError;
+    use crate::{
+        bundle::{
+            batch::tests::bundles_with_failures,
+            burn_validation::{BurnError, BurnListError},
+            Bundle, FailedCheck,
+        },
+        note::AssetBase,
+    };
+
+    #[test]
+    fn all_failed_checks_are_reported() {
+        let (vk, bundles) = bundles_with_failures();
+        let reports: Vec<_> = bundles
+            .iter()
+            .map(|(bundle, sighash)| bundle.self_check(&vk, sighash))
+            .collect();
+
+        assert!(reports[0].is_valid());
+        assert_eq!(reports[1].failed_checks, vec![FailedCheck::Proof(0)]);
+        assert_eq!(
+            reports[2].failed_checks,
+            vec![FailedCheck::SpendAuthSignature(0)]
+        );
+        assert_eq!(
+            reports[3].failed_checks,
+            vec![FailedCheck::BindingSignature]
+        );
+        assert!(reports.iter().all(|r| r.structural_errors.is_empty()));
+
+        // Signatures over another sighash are all reported.
+        let (valid, _) = &bundles[0];
+        let report = valid.self_check(&vk, &[0; 32]);
+        assert_eq!(report.failed_checks.len(), valid.actions().len() + 1);
+        assert!(report.structural_errors.is_empty());
+    }
+
+    #[test]
+    fn structural_errors_are_reported() {
+        let (vk, bundles) = bundles_with_failures();
+        let (valid, sighash) = &bundles[0];
+        let with_parts = |actions, burn| {
+            Bundle::from_parts(
+                actions,
+                *valid.flags(),
+                *valid.value_balance(),
+                burn,
+                *valid.anchor(),
+                valid.authorization().clone(),
+            )
+        };
+
+        let first = valid.actions().first().clone();
+        let duplicated = with_parts(NonEmpty::from((first.clone(), vec![first])), vec![]);
+        assert_eq!(
+            duplicated.self_check(&vk, sighash).structural_errors,
+            vec![StructuralError::DuplicateNullifier { first: 0, index: 1 }]
+        );
+
+        // The bundle has ZSA transactions disabled.
+        let asset = AssetBase::random();
+        let burning = with_parts(valid.actions().clone(), vec![(asset, 5)]);
+        assert_eq!(
+            burning.self_check(&vk, sighash).structural_errors,
+            vec![StructuralError::BurnWithoutZsa]
+        );
+        let burning = with_parts(valid.actions().clone(), vec![(asset, 0)]);
+        assert_eq!(
+            burning.self_check(&vk, sighash).structural_errors,
+            vec![
+                StructuralError::BurnWithoutZsa,
+                StructuralError::InvalidBurn(BurnError::NonPositiveAmount)
+            ]
+        );
+
+        let mut burn = vec![(asset, 5), (AssetBase::random(), 5)];
+        burn.sort_by_key(|(asset, _)| asset.to_bytes());
+        burn.reverse();
+        let unsorted = with_parts(valid.actions().clone(), burn);
+        assert_eq!(
+            unsorted.self_check(&vk, sighash).structural_errors,
+            vec![
+                StructuralError::BurnWithoutZsa,
+                StructuralError::InvalidBurnList(BurnListError::Unsorted(1))
+            ]
+        );
+    }
+}
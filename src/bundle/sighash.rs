@@ -0,0 +1,181 @@
+//! Computation of the sighash that the signatures of a bundle are created over.
+//!
+//! The spend authorization and binding signatures of a bundle sign the sighash of the
+//! transaction that contains it. The sighash is usually computed by the crate that
+//! builds the transaction, and passed in as a `[u8; 32]`. A [`SighashProvider`] abstracts
+//! over that computation, and [`V6Sighash`] is a reference implementation for
+//! transactions whose only effects are an Orchard bundle and an issue bundle, so that
+//! self-contained tools can sign bundles without depending on a transaction crate.
+
+use blake2b_simd::{Hash as Blake2bHash, Params};
+
+use super::{
+    commitments::{hash_bundle_txid_empty, hash_issue_bundle_txid_empty},
+    Authorization, Bundle, BundleCommitment,
+};
+use crate::issuance::{IssueAuth, IssueBundle, IssueBundleCommitment};
+
+const ZCASH_TX_PERSONALIZATION_PREFIX: &[u8; 12] = b"ZcashTxHash_";
+const ZCASH_HEADERS_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxIdHeadersHash";
+const ZCASH_TRANSPARENT_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxIdTranspaHash";
+const ZCASH_SAPLING_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxIdSaplingHash";
+
+/// The version of v6 transactions, with the overwintered flag set.
+pub const V6_TX_VERSION: u32 = (1 << 31) | 6;
+/// The version group identifier of v6 transactions.
+pub const V6_VERSION_GROUP_ID: u32 = 0x7777_7777;
+
+/// A source of the sighash of the transaction that contains a bundle.
+pub trait SighashProvider {
+    /// Returns the sighash of the transaction containing the bundle and issue bundle
+    /// with the given commitments, or no such bundle for `None`.
+    fn sighash(
+        &self,
+        bundle: Option<BundleCommitment>,
+        issue_bundle: Option<IssueBundleCommitment>,
+    ) -> [u8; 32];
+
+    /// Returns the sighash of the transaction containing `bundle` and no issue bundle.
+    fn bundle_sighash<A: Authorization, V: Copy + Into<i64>>(
+        &self,
+        bundle: &Bundle<A, V>,
+    ) -> [u8; 32] {
+        self.sighash(Some(bundle.commitment()), None)
+    }
+
+    /// Returns the sighash of the transaction containing `issue_bundle`, and `bundle`
+    /// if given.
+    fn issue_bundle_sighash<A: Authorization, V: Copy + Into<i64>, IA: IssueAuth>(
+        &self,
+        bundle: Option<&Bundle<A, V>>,
+        issue_bundle: &IssueBundle<IA>,
+    ) -> [u8; 32] {
+        self.sighash(
+            bundle.map(|bundle| bundle.commitment()),
+            Some(issue_bundle.commitment()),
+        )
+    }
+}
+
+/// The sighash of a v6 transaction without transparent inputs or outputs and without a
+/// Sapling bundle, as defined in [ZIP 244] and extended with issuance by [ZIP 227].
+///
+/// For such a transaction, the signature digest of the shielded signatures is the
+/// transaction identifier digest, which commits to the header fields held by this type
+/// and to the Orchard and issue bundles.
+///
+/// [ZIP 244]: https://zips.z.cash/zip-0244
+/// [ZIP 227]: https://qed-it.github.io/zips/zip-0227
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct V6Sighash {
+    /// The transaction version, including the overwintered flag.
+    pub version: u32,
+    /// The version group identifier of the transaction.
+    pub version_group_id: u32,
+    /// The consensus branch identifier of the network upgrade the transaction targets.
+    pub consensus_branch_id: u32,
+    /// The lock time of the transaction.
+    pub lock_time: u32,
+    /// The height after which the transaction expires.
+    pub expiry_height: u32,
+}
+
+impl V6Sighash {
+    /// Constructs the sighash provider for a v6 transaction with the given header
+    /// fields.
+    pub fn new(consensus_branch_id: u32, lock_time: u32, expiry_height: u32) -> Self {
+        V6Sighash {
+            version: V6_TX_VERSION,
+            version_group_id: V6_VERSION_GROUP_ID,
+            consensus_branch_id,
+            lock_time,
+            expiry_height,
+        }
+    }
+
+    fn header_digest(&self) -> Blake2bHash {
+        let mut h = hasher(ZCASH_HEADERS_HASH_PERSONALIZATION);
+        h.update(&self.version.to_le_bytes());
+        h.update(&self.version_group_id.to_le_bytes());
+        h.update(&self.consensus_branch_id.to_le_bytes());
+        h.update(&self.lock_time.to_le_bytes());
+        h.update(&self.expiry_height.to_le_bytes());
+        h.finalize()
+    }
+}
+
+impl SighashProvider for V6Sighash {
+    fn sighash(
+        &self,
+        bundle: Option<BundleCommitment>,
+        issue_bundle: Option<IssueBundleCommitment>,
+    ) -> [u8; 32] {
+        let mut personal = [0; 16];
+        personal[..12].copy_from_slice(ZCASH_TX_PERSONALIZATION_PREFIX);
+        personal[12..].copy_from_slice(&self.consensus_branch_id.to_le_bytes());
+
+        let mut h = hasher(&personal);
+        h.update(self.header_digest().as_bytes());
+        h.update(
+            hasher(ZCASH_TRANSPARENT_HASH_PERSONALIZATION)
+                .finalize()
+                .as_bytes(),
+        );
+        h.update(
+            hasher(ZCASH_SAPLING_HASH_PERSONALIZATION)
+                .finalize()
+                .as_bytes(),
+        );
+        h.update(
+            bundle
+                .map_or_else(hash_bundle_txid_empty, |commitment| commitment.0)
+                .as_bytes(),
+        );
+        h.update(
+            issue_bundle
+                .map_or_else(hash_issue_bundle_txid_empty, |commitment| commitment.0)
+                .as_bytes(),
+        );
+        h.finalize().as_bytes().try_into().unwrap()
+    }
+}
+
+fn hasher(personal: &[u8; 16]) -> blake2b_simd::State {
+    Params::new().hash_length(32).personal(personal).to_state()
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{SighashProvider, V6Sighash};
+    use crate::bundle::testing::arb_bundle_i64;
+
+    #[test]
+    fn v6_sighash_without_bundles() {
+        // Computed independently from the definitions of ZIP 244 and ZIP 227.
+        assert_eq!(
+            hex::encode(V6Sighash::new(0x7777_7777, 0, 100).sighash(None, None)),
+            "3c2d3435e70bff48b3c6865734918fc34a38b420e80002586ee796f200728043"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn v6_sighash_commits_to_header_and_bundle(bundle in arb_bundle_i64(3)) {
+            let provider = V6Sighash::new(0x7777_7777, 0, 100);
+
+            let sighash = provider.bundle_sighash(&bundle);
+            prop_assert_eq!(sighash, provider.bundle_sighash(&bundle));
+            prop_assert_ne!(sighash, provider.sighash(None, None));
+            prop_assert_ne!(
+                sighash,
+                V6Sighash::new(0x7777_7777, 0, 101).bundle_sighash(&bundle)
+            );
+            prop_assert_ne!(
+                sighash,
+                V6Sighash::new(0x7777_7778, 0, 100).bundle_sighash(&bundle)
+            );
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Sizes of the encodings of bundles, for fee estimation.
+
+use super::{Authorized, Bundle};
+use crate::note_encryption_v3::ENC_CIPHERTEXT_SIZE_V3;
+
+/// The size of the fields of an action other than its encrypted note ciphertext:
+/// `cv_net`, `nf`, `rk`, `cmx`, `epk` and `out_ciphertext`.
+pub(super) const ACTION_FIXED_SIZE: usize = 5 * 32 + 80;
+
+/// The size of a burn entry: an asset base and a value.
+pub(super) const BURN_ENTRY_SIZE: usize = 32 + 8;
+
+/// The size of a RedPallas signature.
+pub(super) const SIGNATURE_SIZE: usize = 64;
+
+/// The weight of a byte of a signature or of the proof, relative to the other bytes of
+/// a bundle, reflecting the cost of verifying them.
+pub const VERIFICATION_WEIGHT_FACTOR: usize = 4;
+
+/// Returns the size of the `CompactSize` encoding of `n`.
+pub(crate) fn compact_size_len(n: usize) -> usize {
+    match n {
+        0..=0xfc => 1,
+        0xfd..=0xffff => 3,
+        0x10000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+impl<V> Bundle<Authorized, V> {
+    /// Returns the size of the encoding of the non-authorizing parts of this bundle,
+    /// along with the proof, and the size of the encoding of its signatures.
+    fn size_parts(&self) -> (usize, usize) {
+        let num_actions = self.actions().len();
+        let proof_len = self.authorization().proof().as_ref().len();
+
+        let data_size = compact_size_len(num_actions)
+            + num_actions * (ACTION_FIXED_SIZE + ENC_CIPHERTEXT_SIZE_V3)
+            + 1 // flags
+            + 8 // value balance
+            + compact_size_len(self.burn().len())
+            + self.burn().len() * BURN_ENTRY_SIZE
+            + 32 // anchor
+            + compact_size_len(proof_len)
+            + proof_len;
+        let signatures_size = (num_actions + 1) * SIGNATURE_SIZE;
+
+        (data_size, signatures_size)
+    }
+
+    /// Returns the size in bytes of the encoding of this bundle within a transaction.
+    ///
+    /// This is the length of the encoding written by [`write_bundle`], in the v6
+    /// transaction format, whatever the flags of the bundle.
+    ///
+    /// [`write_bundle`]: super::parse::write_bundle
+    pub fn serialized_size(&self) -> usize {
+        let (data_size, signatures_size) = self.size_parts();
+        data_size + signatures_size
+    }
+
+    /// Returns the weight of this bundle, for the purpose of mempool admission and fee
+    /// estimation.
+    ///
+    /// The weight is the [serialized size](Self::serialized_size) of the bundle, where
+    /// the bytes of the proof and of the signatures are counted
+    /// [`VERIFICATION_WEIGHT_FACTOR`] times.
+    pub fn weight(&self) -> usize {
+        let (data_size, signatures_size) = self.size_parts();
+        let proof_len = self.authorization().proof().as_ref().len();
+        data_size
+            + (VERIFICATION_WEIGHT_FACTOR - 1) * proof_len
+            + VERIFICATION_WEIGHT_FACTOR * signatures_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{compact_size_len, VERIFICATION_WEIGHT_FACTOR};
+    use crate::bundle::{parse::write_bundle, testing::arb_bundle_i64, Bundle, Flags};
+
+    #[test]
+    fn compact_size_lengths() {
+        assert_eq!(compact_size_len(0), 1);
+        assert_eq!(compact_size_len(0xfc), 1);
+        assert_eq!(compact_size_len(0xfd), 3);
+        assert_eq!(compact_size_len(0x10000), 5);
+    }
+
+    proptest! {
+        #[test]
+        fn serialized_size_matches_encoding(bundle in arb_bundle_i64(3)) {
+            let proof_len = bundle.authorization().proof().as_ref().len();
+            for flags in [Flags::ENABLED_WITHOUT_ZSA, Flags::ENABLED_WITH_ZSA] {
+                let bundle = Bundle::from_parts(
+                    bundle.actions().clone(),
+                    flags,
+                    *bundle.value_balance(),
+                    bundle.burn().to_vec(),
+                    *bundle.anchor(),
+                    bundle.authorization().clone(),
+                );
+                let mut bytes = vec![];
+                write_bundle(&bundle, &mut bytes).unwrap();
+
+                assert_eq!(bundle.serialized_size(), bytes.len());
+                assert_eq!(
+                    bundle.weight(),
+                    bytes.len() + (VERIFICATION_WEIGHT_FACTOR - 1) * (proof_len + 4 * 64)
+                );
+            }
+        }
+    }
+}
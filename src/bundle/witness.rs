@@ -0,0 +1,98 @@
+//! Hashes of the authorizing data of a bundle, for detecting malleated bundles.
+
+use blake2b_simd::Params;
+
+use super::{Authorized, Bundle};
+use crate::note::Nullifier;
+
+/// Personalization of the hash of a signature of a bundle.
+const ZCASH_ORCHARD_SIGNATURE_HASH_PERSONALIZATION: &[u8; 16] = b"ZTxAuthOrcSigWit";
+
+fn hash_signature(sig: [u8; 64]) -> [u8; 32] {
+    Params::new()
+        .hash_length(32)
+        .personal(ZCASH_ORCHARD_SIGNATURE_HASH_PERSONALIZATION)
+        .hash(&sig)
+        .as_bytes()
+        .try_into()
+        .unwrap()
+}
+
+/// The effects of an action along with a hash of its spend authorization signature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ActionWitnessHash {
+    /// The nullifier of the note spent by the action.
+    pub nullifier: Nullifier,
+    /// The encoding of the randomized validating key of the spend.
+    pub rk: [u8; 32],
+    /// The hash of the spend authorization signature of the action.
+    pub spend_auth_sig_hash: [u8; 32],
+}
+
+/// The hashes of the signatures of a bundle, returned by [`Bundle::witness_hashes`].
+///
+/// The signatures of a bundle are not covered by its [`Bundle::commitment`], so two
+/// bundles with the same effects can carry different signatures. A mempool that has seen
+/// a bundle spending a nullifier can compare these hashes with those of a new bundle
+/// spending the same nullifier, to detect that the new bundle only differs in its
+/// signatures without verifying them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WitnessHashes {
+    /// The hashes of the actions of the bundle, in order.
+    pub actions: Vec<ActionWitnessHash>,
+    /// The hash of the binding signature of the bundle.
+    pub binding_sig_hash: [u8; 32],
+}
+
+impl<V> Bundle<Authorized, V> {
+    /// Returns the hashes of the signatures of this bundle, along with the effects of
+    /// each action that its spend authorization signature is checked against.
+    pub fn witness_hashes(&self) -> WitnessHashes {
+        WitnessHashes {
+            actions: self
+                .actions()
+                .iter()
+                .map(|action| ActionWitnessHash {
+                    nullifier: *action.nullifier(),
+                    rk: action.rk().into(),
+                    spend_auth_sig_hash: hash_signature(action.authorization().into()),
+                })
+                .collect(),
+            binding_sig_hash: hash_signature(self.authorization().binding_signature().into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::bundle::{testing::arb_bundle, Authorized};
+
+    proptest! {
+        #[test]
+        fn witness_hashes(bundle in arb_bundle(3), other in arb_bundle(3)) {
+            let hashes = bundle.witness_hashes();
+            prop_assert_eq!(hashes.actions.len(), bundle.actions().len());
+            for (hash, action) in hashes.actions.iter().zip(bundle.actions()) {
+                prop_assert_eq!(&hash.nullifier, action.nullifier());
+                prop_assert_ne!(hash.spend_auth_sig_hash, hashes.binding_sig_hash);
+            }
+
+            // Replacing the binding signature only changes its hash.
+            let malleated = bundle.clone().map_authorization(
+                &mut (),
+                |_, _, sig| sig,
+                |_, auth| {
+                    Authorized::from_parts(
+                        auth.proof().clone(),
+                        other.authorization().binding_signature().clone(),
+                    )
+                },
+            );
+            let malleated_hashes = malleated.witness_hashes();
+            prop_assert_eq!(&malleated_hashes.actions, &hashes.actions);
+            prop_assert_ne!(malleated_hashes.binding_sig_hash, hashes.binding_sig_hash);
+        }
+    }
+}
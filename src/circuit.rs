@@ -1,9 +1,10 @@
 //! The Orchard Action circuit implementation.
 
 use core::fmt;
+use core::num::NonZeroUsize;
 
 use ff::Field;
-use group::{Curve, GroupEncoding};
+use group::{Curve, Group, GroupEncoding};
 use halo2_proofs::{
     circuit::{floor_planner, Layouter, Value},
     plonk::{
@@ -13,7 +14,6 @@ use halo2_proofs::{
     poly::Rotation,
     transcript::{Blake2bRead, Blake2bWrite},
 };
-use memuse::DynamicUsage;
 use pasta_curves::{arithmetic::CurveAffine, pallas, vesta};
 use rand::RngCore;
 
@@ -26,7 +26,7 @@ use self::{
     note_commit::{NoteCommitChip, NoteCommitConfig},
 };
 use crate::{
-    builder::SpendInfo,
+    builder::{ProofError, SpendInfo, MIN_ACTIONS},
     bundle::Flags,
     constants::{
         OrchardCommitDomains, OrchardFixedBases, OrchardFixedBasesFull, OrchardHashDomains,
@@ -70,9 +70,23 @@ pub mod gadget;
 mod note_commit;
 mod value_commit_orchard;
 
+pub use crate::Proof;
+
 /// Size of the Orchard circuit.
 const K: u32 = 11;
 
+/// Size of the extended evaluation domain of the Orchard circuit, in which the prover
+/// evaluates the polynomials of each circuit.
+const EXTENDED_K: u32 = K + 3;
+
+/// A rough heuristic for the number of polynomials of each circuit that the prover holds
+/// at once in the extended domain, covering the advice columns and the permutation and
+/// lookup argument polynomials.
+///
+/// This is neither measured nor derived from the columns of the circuit, and is only
+/// meant to overestimate the peak memory use of the prover.
+const PROVER_POLYS_PER_CIRCUIT: usize = 32;
+
 // Absolute offsets for public inputs.
 const ANCHOR: usize = 0;
 const CV_NET_X: usize = 1;
@@ -973,6 +987,27 @@ impl plonk::Circuit<pallas::Base> for Circuit {
     }
 }
 
+/// The public parameters of the commitment scheme used by the Orchard Action circuit.
+///
+/// Generating the parameters is a large part of the cost of building the proving and
+/// verifying keys; build them once with [`OrchardParams::new`] and share them between
+/// keys with [`ProvingKey::build_with_params`] and [`VerifyingKey::build_with_params`].
+#[derive(Clone, Debug)]
+pub struct OrchardParams(halo2_proofs::poly::commitment::Params<vesta::Affine>);
+
+impl OrchardParams {
+    /// Generates the parameters.
+    pub fn new() -> Self {
+        OrchardParams(halo2_proofs::poly::commitment::Params::new(K))
+    }
+}
+
+impl Default for OrchardParams {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// The verifying key for the Orchard Action circuit.
 #[derive(Debug)]
 pub struct VerifyingKey {
@@ -983,7 +1018,12 @@ pub struct VerifyingKey {
 impl VerifyingKey {
     /// Builds the verifying key.
     pub fn build() -> Self {
-        let params = halo2_proofs::poly::commitment::Params::new(K);
+        Self::build_with_params(&OrchardParams::new())
+    }
+
+    /// Builds the verifying key from previously generated parameters.
+    pub fn build_with_params(params: &OrchardParams) -> Self {
+        let params = params.0.clone();
         let circuit: Circuit = Default::default();
 
         let vk = plonk::keygen_vk(&params, &circuit).unwrap();
@@ -1002,7 +1042,12 @@ pub struct ProvingKey {
 impl ProvingKey {
     /// Builds the proving key.
     pub fn build() -> Self {
-        let params = halo2_proofs::poly::commitment::Params::new(K);
+        Self::build_with_params(&OrchardParams::new())
+    }
+
+    /// Builds the proving key from previously generated parameters.
+    pub fn build_with_params(params: &OrchardParams) -> Self {
+        let params = params.0.clone();
         let circuit: Circuit = Default::default();
 
         let vk = plonk::keygen_vk(&params, &circuit).unwrap();
@@ -1012,6 +1057,110 @@ impl ProvingKey {
     }
 }
 
+/// Options for creating the proof of a bundle with
+/// [`Bundle::create_proof_with_options`].
+///
+/// [`Bundle::create_proof_with_options`]: crate::Bundle::create_proof_with_options
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProverOptions {
+    /// The memory, in bytes, that creating a proof should stay within.
+    ///
+    /// Proofs that are estimated to need more memory, as by
+    /// [`ProverOptions::estimated_memory`], are rejected before the prover starts. The
+    /// proof is not created in chunks to fit the budget: a bundle that exceeds it must
+    /// be built with fewer actions.
+    pub max_memory_hint: Option<usize>,
+    /// The number of threads used to create a proof.
+    ///
+    /// If unset, or if a thread pool of this size cannot be created, the global thread
+    /// pool is used. This has no effect without the `multicore` feature.
+    pub threads: Option<NonZeroUsize>,
+}
+
+impl ProverOptions {
+    /// Returns an estimate of the memory, in bytes, used by the prover to create a proof
+    /// for `num_actions` actions.
+    ///
+    /// The estimate covers the polynomials that the prover evaluates for each action, and
+    /// excludes the proving key. It grows linearly with the number of actions, as all of
+    /// the actions of a bundle are proven together, and is a rough upper bound rather
+    /// than a measurement. It saturates at `usize::MAX`.
+    pub fn estimated_memory(num_actions: usize) -> usize {
+        num_actions
+            .saturating_mul(PROVER_POLYS_PER_CIRCUIT << EXTENDED_K)
+            .saturating_mul(32)
+    }
+
+    /// Returns the maximum number of actions of a bundle whose proof is estimated to fit
+    /// in the [`ProverOptions::max_memory_hint`], or `None` if there is no budget.
+    ///
+    /// A wallet can build its bundles with this as the [`BuilderConfig::max_actions`], so
+    /// that payments which would need larger bundles are rejected by the builder, and
+    /// can be split across several transactions before any proof is created.
+    ///
+    /// Returns [`ProofError::WouldExceedBudget`] if the budget cannot fit the two actions
+    /// that every bundle is padded to, as no bundle could then be proven.
+    ///
+    /// [`BuilderConfig::max_actions`]: crate::builder::BuilderConfig::max_actions
+    pub fn max_actions(&self) -> Result<Option<usize>, ProofError> {
+        match self.max_memory_hint {
+            None => Ok(None),
+            Some(budget) => {
+                let max_actions = budget / Self::estimated_memory(1);
+                if max_actions < MIN_ACTIONS {
+                    Err(ProofError::WouldExceedBudget {
+                        required: Self::estimated_memory(MIN_ACTIONS),
+                        budget,
+                    })
+                } else {
+                    Ok(Some(max_actions))
+                }
+            }
+        }
+    }
+
+    /// Returns the estimated memory for `num_actions` actions if it exceeds the
+    /// [`ProverOptions::max_memory_hint`].
+    pub(crate) fn exceeded_budget(&self, num_actions: usize) -> Option<usize> {
+        let required = Self::estimated_memory(num_actions);
+        self.max_memory_hint
+            .filter(|budget| required > *budget)
+            .map(|_| required)
+    }
+}
+
+/// An error that can occur when parsing an [`Instance`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstanceError {
+    /// The anchor is not a valid encoding.
+    InvalidAnchor,
+    /// The value commitment is not a valid encoding.
+    InvalidCvNet,
+    /// The nullifier is not a valid encoding.
+    InvalidNullifier,
+    /// The randomized verification key is not a valid encoding, or is the identity.
+    InvalidRk,
+    /// The note commitment is not a valid encoding.
+    InvalidCmx,
+    /// Unexpected bits are set in the flags.
+    InvalidFlags,
+}
+
+impl fmt::Display for InstanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceError::InvalidAnchor => f.write_str("Invalid anchor"),
+            InstanceError::InvalidCvNet => f.write_str("Invalid value commitment"),
+            InstanceError::InvalidNullifier => f.write_str("Invalid nullifier"),
+            InstanceError::InvalidRk => f.write_str("Invalid randomized verification key"),
+            InstanceError::InvalidCmx => f.write_str("Invalid note commitment"),
+            InstanceError::InvalidFlags => f.write_str("Invalid flags"),
+        }
+    }
+}
+
+impl std::error::Error for InstanceError {}
+
 /// Public inputs to the Orchard Action circuit.
 #[derive(Clone, Debug)]
 pub struct Instance {
@@ -1053,7 +1202,42 @@ impl Instance {
         }
     }
 
-    fn to_halo2_instance(&self) -> [[vesta::Scalar; 10]; 1] {
+    /// Parses an [`Instance`] from the encodings of its constituent parts, as they
+    /// appear in an encoded bundle.
+    ///
+    /// This allows proofs to be verified against public inputs decoded from the wire,
+    /// without reconstructing the [`Action`]s and [`Flags`] of the bundle.
+    ///
+    /// [`Action`]: crate::action::Action
+    pub fn from_bytes(
+        anchor: &[u8; 32],
+        cv_net: &[u8; 32],
+        nf_old: &[u8; 32],
+        rk: &[u8; 32],
+        cmx: &[u8; 32],
+        flags: u8,
+    ) -> Result<Self, InstanceError> {
+        let anchor =
+            Option::from(Anchor::from_bytes(*anchor)).ok_or(InstanceError::InvalidAnchor)?;
+        let cv_net =
+            Option::from(ValueCommitment::from_bytes(cv_net)).ok_or(InstanceError::InvalidCvNet)?;
+        let nf_old =
+            Option::from(Nullifier::from_bytes(nf_old)).ok_or(InstanceError::InvalidNullifier)?;
+        // The circuit requires rk to have affine coordinates.
+        if bool::from(pallas::Point::from_bytes(rk).map_or(1.into(), |p| p.is_identity())) {
+            return Err(InstanceError::InvalidRk);
+        }
+        let rk = VerificationKey::try_from(*rk).map_err(|_| InstanceError::InvalidRk)?;
+        let cmx = Option::from(ExtractedNoteCommitment::from_bytes(cmx))
+            .ok_or(InstanceError::InvalidCmx)?;
+        let flags = Flags::from_byte(flags).ok_or(InstanceError::InvalidFlags)?;
+
+        Ok(Self::from_parts(anchor, cv_net, nf_old, rk, cmx, flags))
+    }
+
+    /// Returns the public inputs of the Action circuit for this instance, in the form
+    /// expected by [`halo2_proofs::plonk::verify_proof`].
+    pub fn to_halo2_instance(&self) -> [[vesta::Scalar; 10]; 1] {
         let mut instance = [vesta::Scalar::zero(); 10];
 
         instance[ANCHOR] = self.anchor.inner();
@@ -1078,41 +1262,81 @@ impl Instance {
     }
 }
 
-/// A proof of the validity of an Orchard [`Bundle`].
+/// The encodings of the public inputs to the Orchard Action circuit, as they appear in an
+/// encoded bundle.
 ///
-/// [`Bundle`]: crate::bundle::Bundle
-#[derive(Clone)]
-pub struct Proof(Vec<u8>);
+/// This has a C-compatible layout, so that verifiers across an FFI boundary can pass
+/// instances without constructing any Rust object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct InstanceBytes {
+    /// The encoding of the anchor of the bundle.
+    pub anchor: [u8; 32],
+    /// The encoding of the value commitment of the action.
+    pub cv_net: [u8; 32],
+    /// The encoding of the nullifier of the note spent by the action.
+    pub nf_old: [u8; 32],
+    /// The encoding of the randomized verification key of the action.
+    pub rk: [u8; 32],
+    /// The encoding of the commitment to the note created by the action.
+    pub cmx: [u8; 32],
+    /// The encoding of the flags of the bundle.
+    pub flags: u8,
+}
 
-impl fmt::Debug for Proof {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if f.alternate() {
-            f.debug_tuple("Proof").field(&self.0).finish()
-        } else {
-            // By default, only show the proof length, not its contents.
-            f.debug_tuple("Proof")
-                .field(&format_args!("{} bytes", self.0.len()))
-                .finish()
+impl From<&Instance> for InstanceBytes {
+    fn from(instance: &Instance) -> Self {
+        InstanceBytes {
+            anchor: instance.anchor.to_bytes(),
+            cv_net: instance.cv_net.to_bytes(),
+            nf_old: instance.nf_old.to_bytes(),
+            rk: instance.rk.clone().into(),
+            cmx: instance.cmx.to_bytes(),
+            flags: Flags::from_parts(
+                instance.enable_spend,
+                instance.enable_output,
+                instance.enable_zsa,
+            )
+            .to_byte(),
         }
     }
 }
 
-impl AsRef<[u8]> for Proof {
-    fn as_ref(&self) -> &[u8] {
-        &self.0
+impl TryFrom<&InstanceBytes> for Instance {
+    type Error = InstanceError;
+
+    fn try_from(bytes: &InstanceBytes) -> Result<Self, Self::Error> {
+        Instance::from_bytes(
+            &bytes.anchor,
+            &bytes.cv_net,
+            &bytes.nf_old,
+            &bytes.rk,
+            &bytes.cmx,
+            bytes.flags,
+        )
     }
 }
 
-impl DynamicUsage for Proof {
-    fn dynamic_usage(&self) -> usize {
-        self.0.dynamic_usage()
-    }
+/// An error that can occur when verifying a proof with [`Proof::verify_raw`].
+#[derive(Debug)]
+pub enum VerifyRawError {
+    /// The instance at the given index could not be parsed.
+    Instance(usize, InstanceError),
+    /// The proof is invalid.
+    Proof(plonk::Error),
+}
 
-    fn dynamic_usage_bounds(&self) -> (usize, Option<usize>) {
-        self.0.dynamic_usage_bounds()
+impl fmt::Display for VerifyRawError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyRawError::Instance(index, e) => write!(f, "Instance {}: {}", index, e),
+            VerifyRawError::Proof(e) => write!(f, "Invalid proof: {}", e),
+        }
     }
 }
 
+impl std::error::Error for VerifyRawError {}
+
 impl Proof {
     /// Creates a proof for the given circuits and instances.
     pub fn create(
@@ -1140,6 +1364,32 @@ impl Proof {
         Ok(Proof(transcript.finalize()))
     }
 
+    /// Creates a proof for the given circuits and instances, with the given options.
+    ///
+    /// The memory budget of the options is not checked here: callers reject proofs that
+    /// would exceed it before constructing the circuits.
+    pub(crate) fn create_with_options(
+        pk: &ProvingKey,
+        circuits: &[Circuit],
+        instances: &[Instance],
+        options: &ProverOptions,
+        rng: impl RngCore + Send,
+    ) -> Result<Self, plonk::Error> {
+        #[cfg(feature = "multicore")]
+        if let Some(pool) = options.threads.and_then(|threads| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads.get())
+                .build()
+                .ok()
+        }) {
+            return pool.install(|| Self::create(pk, circuits, instances, rng));
+        }
+        #[cfg(not(feature = "multicore"))]
+        let _ = options;
+
+        Self::create(pk, circuits, instances, rng)
+    }
+
     /// Verifies this proof with the given instances.
     pub fn verify(&self, vk: &VerifyingKey, instances: &[Instance]) -> Result<(), plonk::Error> {
         let instances: Vec<_> = instances.iter().map(|i| i.to_halo2_instance()).collect();
@@ -1154,6 +1404,25 @@ impl Proof {
         plonk::verify_proof(&vk.params, &vk.vk, strategy, &instances, &mut transcript)
     }
 
+    /// Verifies this proof with instances given by their encodings.
+    ///
+    /// This is intended for verifiers behind an FFI boundary, such as C or WASM callers,
+    /// which only have access to the encoded public inputs of each action.
+    pub fn verify_raw(
+        &self,
+        vk: &VerifyingKey,
+        instances: &[InstanceBytes],
+    ) -> Result<(), VerifyRawError> {
+        let instances = instances
+            .iter()
+            .enumerate()
+            .map(|(index, bytes)| {
+                Instance::try_from(bytes).map_err(|e| VerifyRawError::Instance(index, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.verify(vk, &instances).map_err(VerifyRawError::Proof)
+    }
+
     /// Adds this proof to the given batch for verification with the given instances.
     ///
     /// Use this API if you want more control over how proof batches are processed. If you
@@ -1161,6 +1430,16 @@ impl Proof {
     ///
     /// [`bundle::BatchValidator`]: crate::bundle::BatchValidator
     pub fn add_to_batch(&self, batch: &mut BatchVerifier<vesta::Affine>, instances: Vec<Instance>) {
+        self.clone().into_batch(batch, instances)
+    }
+
+    /// Adds this proof to the given batch as in [`Proof::add_to_batch`], without copying
+    /// it.
+    pub(crate) fn into_batch(
+        self,
+        batch: &mut BatchVerifier<vesta::Affine>,
+        instances: Vec<Instance>,
+    ) {
         let instances = instances
             .iter()
             .map(|i| {
@@ -1171,12 +1450,7 @@ impl Proof {
             })
             .collect();
 
-        batch.add_proof(instances, self.0.clone());
-    }
-
-    /// Constructs a new Proof value.
-    pub fn new(bytes: Vec<u8>) -> Self {
-        Proof(bytes)
+        batch.add_proof(instances, self.0);
     }
 }
 
@@ -1190,7 +1464,10 @@ mod tests {
     use pasta_curves::pallas;
     use rand::{rngs::OsRng, RngCore};
 
-    use super::{Circuit, Instance, Proof, ProvingKey, VerifyingKey, K};
+    use super::{
+        Circuit, Instance, InstanceBytes, InstanceError, OrchardParams, Proof, ProvingKey,
+        VerifyingKey, K,
+    };
     use crate::builder::SpendInfo;
     use crate::bundle::Flags;
     use crate::note::commitment::NoteCommitTrapdoor;
@@ -1278,7 +1555,8 @@ mod tests {
             .map(|()| generate_dummy_circuit_instance(&mut rng))
             .unzip();
 
-        let vk = VerifyingKey::build();
+        let params = OrchardParams::new();
+        let vk = VerifyingKey::build_with_params(&params);
 
         // Test that the pinned verification key (representing the circuit)
         // is as expected.
@@ -1319,10 +1597,59 @@ mod tests {
             );
         }
 
-        let pk = ProvingKey::build();
+        let pk = ProvingKey::build_with_params(&params);
         let proof = Proof::create(&pk, &circuits, &instances, &mut rng).unwrap();
         assert!(proof.verify(&vk, &instances).is_ok());
         assert_eq!(proof.0.len(), expected_proof_size);
+
+        let instance_bytes: Vec<_> = instances.iter().map(InstanceBytes::from).collect();
+        assert!(proof.verify_raw(&vk, &instance_bytes).is_ok());
+    }
+
+    #[test]
+    fn instance_from_bytes() {
+        let (_, instance) = generate_dummy_circuit_instance(OsRng);
+        let flags = Flags::from_parts(
+            instance.enable_spend,
+            instance.enable_output,
+            instance.enable_zsa,
+        );
+
+        let parsed = Instance::from_bytes(
+            &instance.anchor.to_bytes(),
+            &instance.cv_net.to_bytes(),
+            &instance.nf_old.to_bytes(),
+            &instance.rk.clone().into(),
+            &instance.cmx.to_bytes(),
+            flags.to_byte(),
+        )
+        .unwrap();
+        assert_eq!(parsed.to_halo2_instance(), instance.to_halo2_instance());
+
+        assert_eq!(
+            Instance::from_bytes(
+                &instance.anchor.to_bytes(),
+                &instance.cv_net.to_bytes(),
+                &instance.nf_old.to_bytes(),
+                &pallas::Point::identity().to_bytes(),
+                &instance.cmx.to_bytes(),
+                flags.to_byte(),
+            )
+            .unwrap_err(),
+            InstanceError::InvalidRk
+        );
+        assert_eq!(
+            Instance::from_bytes(
+                &instance.anchor.to_bytes(),
+                &instance.cv_net.to_bytes(),
+                &instance.nf_old.to_bytes(),
+                &instance.rk.clone().into(),
+                &instance.cmx.to_bytes(),
+                0xff,
+            )
+            .unwrap_err(),
+            InstanceError::InvalidFlags
+        );
     }
 
     #[test]
@@ -0,0 +1,120 @@
+//! Items with the paths and signatures of the upstream `orchard` crate.
+//!
+//! Downstream crates written against the upstream crate can switch to this crate by
+//! importing these items instead, and only need to change the code that deals with
+//! non-native assets. All of the items here operate on the native asset.
+
+pub use crate::{Action, Address, Anchor, Bundle, Note};
+
+/// Note encryption with the upstream names.
+///
+/// Only the names match the upstream crate: the notes of this crate carry their asset,
+/// so their ciphertexts are not compatible with those of the upstream crate.
+pub mod note_encryption {
+    pub use crate::note_encryption_v3::{CompactAction, OrchardNoteEncryption};
+
+    /// The Orchard note encryption domain of this crate, under its upstream name.
+    ///
+    /// Unlike the upstream domain, its encrypted note ciphertexts are
+    /// [`ENC_CIPHERTEXT_SIZE_V3`] (612) bytes long instead of 580, as the note plaintext
+    /// includes the asset of the note. Code that stores or parses ciphertexts with a
+    /// fixed size must be updated accordingly.
+    ///
+    /// [`ENC_CIPHERTEXT_SIZE_V3`]: crate::note_encryption_v3::ENC_CIPHERTEXT_SIZE_V3
+    pub type OrchardDomain = crate::note_encryption_v3::OrchardDomainV3;
+}
+
+/// The bundle builder with the upstream signatures.
+#[cfg(feature = "circuit")]
+#[cfg_attr(docsrs, doc(cfg(feature = "circuit")))]
+pub mod builder {
+    use core::ops::{Deref, DerefMut};
+
+    pub use crate::builder::{
+        BuildError, BundleMetadata, BundleType, OutputError, SpendError, UnauthorizedBundle,
+    };
+    use crate::{
+        keys::OutgoingViewingKey, note::AssetBase, tree::Anchor, value::NoteValue, Address,
+    };
+
+    /// A [`crate::builder::Builder`] whose outputs are of the native asset.
+    ///
+    /// All of the methods of the wrapped builder are available through [`Deref`].
+    #[derive(Debug)]
+    pub struct Builder(crate::builder::Builder);
+
+    impl Builder {
+        /// Constructs a new empty builder for an Orchard bundle.
+        pub fn new(bundle_type: BundleType, anchor: Anchor) -> Self {
+            Builder(crate::builder::Builder::new(bundle_type, anchor))
+        }
+
+        /// Adds an address which will receive funds of the native asset in this bundle.
+        pub fn add_output(
+            &mut self,
+            ovk: Option<OutgoingViewingKey>,
+            recipient: Address,
+            value: NoteValue,
+            memo: Option<[u8; 512]>,
+        ) -> Result<(), OutputError> {
+            self.0
+                .add_output(ovk, recipient, value, AssetBase::native(), memo)
+        }
+
+        /// Returns the wrapped builder.
+        pub fn into_inner(self) -> crate::builder::Builder {
+            self.0
+        }
+    }
+
+    impl Deref for Builder {
+        type Target = crate::builder::Builder;
+
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl DerefMut for Builder {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.0
+        }
+    }
+}
+
+#[cfg(all(test, feature = "circuit"))]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::builder::{Builder, BundleType};
+    use crate::{
+        constants::MERKLE_DEPTH_ORCHARD,
+        keys::{FullViewingKey, Scope, SpendingKey},
+        tree::EMPTY_ROOTS,
+        value::NoteValue,
+    };
+
+    #[test]
+    fn upstream_builder_signatures() {
+        let mut rng = OsRng;
+
+        let sk = SpendingKey::random(&mut rng);
+        let fvk = FullViewingKey::from(&sk);
+        let recipient = fvk.address_at(0u32, Scope::External);
+
+        let mut builder = Builder::new(
+            BundleType::DEFAULT_VANILLA,
+            EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+        );
+        builder
+            .add_output(None, recipient, NoteValue::from_raw(5000), None)
+            .unwrap();
+
+        let (bundle, _) = builder
+            .into_inner()
+            .build::<i64>(&mut rng)
+            .unwrap()
+            .unwrap();
+        assert_eq!(bundle.value_balance(), &-5000);
+    }
+}
@@ -161,8 +161,7 @@ mod tests {
     };
     use group::{ff::PrimeField, Curve};
     use halo2_gadgets::sinsemilla::primitives::{CommitDomain, HashDomain};
-    use halo2_proofs::arithmetic::CurveAffine;
-    use halo2_proofs::pasta::pallas;
+    use pasta_curves::{arithmetic::CurveAffine, pallas};
     use rand::{self, rngs::OsRng, Rng};
 
     #[test]
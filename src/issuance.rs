@@ -1,26 +1,35 @@
 //! Structs related to issuance bundles and the associated logic.
+mod bridged;
+mod encoding;
+pub use bridged::{
+    BridgedAsset, BridgedAssetError, BRIDGED_ASSET_PREFIX, MAX_ORIGIN_CONTRACT_SIZE,
+};
+pub use encoding::IssueAuthEncoding;
+
 use blake2b_simd::Hash as Blake2bHash;
 use group::Group;
 use k256::schnorr;
 use nonempty::NonEmpty;
 use rand::RngCore;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::sync::Mutex;
 
 use crate::bundle::commitments::{hash_issue_bundle_auth_data, hash_issue_bundle_txid_data};
+use crate::bundle::{compact_size_len, OutputKind, ReceivedOutput};
 use crate::issuance::Error::{
     AssetBaseCannotBeIdentityPoint, IssueActionNotFound, IssueActionPreviouslyFinalizedAssetBase,
     IssueActionWithoutNoteNotFinalized, IssueBundleIkMismatchAssetBase,
     IssueBundleInvalidSignature, ValueSumOverflow, WrongAssetDescSize,
 };
-use crate::keys::{IssuanceAuthorizingKey, IssuanceValidatingKey};
+use crate::keys::{IssuanceAuthorizingKey, IssuanceValidatingKey, ScanningKeys};
 use crate::note::asset_base::is_asset_desc_of_valid_size;
 use crate::note::{AssetBase, Nullifier, Rho};
 
 use crate::value::{NoteValue, ValueSum};
 use crate::{Address, Note};
 
-use crate::supply_info::{AssetSupply, SupplyInfo};
+use crate::supply_info::{AssetStateReader, AssetSupply, SupplyInfo};
 
 /// A bundle of actions to be applied to the ledger.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -31,8 +40,55 @@ pub struct IssueBundle<T: IssueAuth> {
     actions: NonEmpty<IssueAction>,
     /// The authorization for this action.
     authorization: T,
+    /// The commitments to this bundle, computed on first use.
+    commitments: CommitmentCache,
+}
+
+/// The commitments to an [`IssueBundle`], cached so that repeated calls to
+/// [`IssueBundle::commitment`] and [`IssueBundle::authorizing_commitment`] do not rehash
+/// the bundle.
+///
+/// The cache is not part of the bundle: it is ignored when comparing bundles, and is
+/// cleared whenever the bundle is modified.
+#[derive(Debug, Default)]
+struct CommitmentCache {
+    /// The commitment to the effects of the bundle.
+    txid: Mutex<Option<Blake2bHash>>,
+    /// The commitment to the authorizing data of the bundle, which is only valid for the
+    /// authorization that it was computed with.
+    auth: Mutex<Option<Blake2bHash>>,
+}
+
+impl CommitmentCache {
+    /// Returns a cache for the same bundle contents with a different authorization.
+    ///
+    /// The commitment to the effects of the bundle does not depend on its authorization,
+    /// and is kept.
+    fn with_new_authorization(&self) -> Self {
+        CommitmentCache {
+            txid: Mutex::new(*self.txid.lock().unwrap()),
+            auth: Mutex::new(None),
+        }
+    }
+}
+
+impl Clone for CommitmentCache {
+    fn clone(&self) -> Self {
+        CommitmentCache {
+            txid: Mutex::new(*self.txid.lock().unwrap()),
+            auth: Mutex::new(*self.auth.lock().unwrap()),
+        }
+    }
 }
 
+impl PartialEq for CommitmentCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for CommitmentCache {}
+
 /// An issue action applied to the global ledger.
 ///
 /// Externally, this creates new zsa notes (adding a commitment to the global ledger).
@@ -46,6 +102,25 @@ pub struct IssueAction {
     finalize: bool,
 }
 
+/// The size of the encoding of an issued note: its recipient, value, asset, `rho` and
+/// `rseed`.
+const ISSUE_NOTE_SIZE: usize = 43 + 8 + 32 + 32 + 32;
+
+/// The size of the encoding of the issuance validating key and of the signature of an
+/// issue bundle.
+const ISSUE_BUNDLE_AUTH_SIZE: usize = 32 + 64;
+
+/// The contribution of an [`IssueAction`] to the size of its bundle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IssueActionSize {
+    /// The asset issued by the action, which identifies its asset description.
+    pub asset: AssetBase,
+    /// The number of notes issued by the action.
+    pub num_notes: usize,
+    /// The size in bytes of the encoding of the action.
+    pub serialized_size: usize,
+}
+
 /// The parameters required to add a Note into an IssueAction.
 #[derive(Debug)]
 pub struct IssueInfo {
@@ -153,6 +228,18 @@ impl IssueAction {
         ))
     }
 
+    /// Returns the size in bytes of the encoding of this action within an issue bundle.
+    ///
+    /// The action is encoded as its asset description and its notes, each prefixed by
+    /// their length, followed by its flags.
+    pub fn serialized_size(&self) -> usize {
+        compact_size_len(self.asset_desc.len())
+            + self.asset_desc.len()
+            + compact_size_len(self.notes.len())
+            + self.notes.len() * ISSUE_NOTE_SIZE
+            + 1
+    }
+
     /// Serialize `finalize` flag to a byte
     #[allow(clippy::bool_to_int_with_if)]
     pub fn flags(&self) -> u8 {
@@ -183,6 +270,13 @@ pub struct Signed {
     signature: schnorr::Signature,
 }
 
+impl Prepared {
+    /// Returns the sighash that the bundle will be signed over.
+    pub fn sighash(&self) -> &[u8; 32] {
+        &self.sighash
+    }
+}
+
 impl Signed {
     /// Returns the signature for this authorization.
     pub fn signature(&self) -> &schnorr::Signature {
@@ -211,6 +305,29 @@ impl<T: IssueAuth> IssueBundle<T> {
             .collect()
     }
 
+    /// Returns the notes of this bundle that were issued to one of the incoming viewing
+    /// keys in `keys`, with the index of each note among [`IssueBundle::get_all_notes`].
+    pub fn received_outputs(&self, keys: &ScanningKeys) -> Vec<ReceivedOutput> {
+        self.get_all_notes()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, note)| {
+                keys.ivks()
+                    .find(|ivk| ivk.diversifier_index(&note.recipient()).is_some())
+                    .map(|ivk| {
+                        ReceivedOutput::from_parts(
+                            idx,
+                            OutputKind::Issuance,
+                            ivk.clone(),
+                            note,
+                            note.recipient(),
+                            None,
+                        )
+                    })
+            })
+            .collect()
+    }
+
     /// Returns the authorization for this action.
     pub fn authorization(&self) -> &T {
         &self.authorization
@@ -232,8 +349,53 @@ impl<T: IssueAuth> IssueBundle<T> {
 
     /// Computes a commitment to the effects of this bundle, suitable for inclusion within
     /// a transaction ID.
+    ///
+    /// The commitment is cached, and is only recomputed after the bundle is modified.
     pub fn commitment(&self) -> IssueBundleCommitment {
-        IssueBundleCommitment(hash_issue_bundle_txid_data(self))
+        IssueBundleCommitment(
+            *self
+                .commitments
+                .txid
+                .lock()
+                .unwrap()
+                .get_or_insert_with(|| hash_issue_bundle_txid_data(self)),
+        )
+    }
+
+    /// Recomputes the commitment to the effects of this bundle, replacing the cached
+    /// value.
+    pub fn recompute_commitment(&self) -> IssueBundleCommitment {
+        let commitment = hash_issue_bundle_txid_data(self);
+        *self.commitments.txid.lock().unwrap() = Some(commitment);
+        IssueBundleCommitment(commitment)
+    }
+
+    /// Returns the size in bytes of the encoding of this bundle within a transaction,
+    /// once it is signed.
+    ///
+    /// This can be computed before the bundle is signed, as the signature has a fixed
+    /// size.
+    pub fn serialized_size(&self) -> usize {
+        compact_size_len(self.actions.len())
+            + self
+                .actions
+                .iter()
+                .map(IssueAction::serialized_size)
+                .sum::<usize>()
+            + ISSUE_BUNDLE_AUTH_SIZE
+    }
+
+    /// Returns the contribution of each action of this bundle to its
+    /// [serialized size](Self::serialized_size), in the order of the actions.
+    pub fn action_sizes(&self) -> Vec<IssueActionSize> {
+        self.actions
+            .iter()
+            .map(|action| IssueActionSize {
+                asset: AssetBase::derive(&self.ik, &action.asset_desc),
+                num_notes: action.notes.len(),
+                serialized_size: action.serialized_size(),
+            })
+            .collect()
     }
 
     /// Constructs an `IssueBundle` from its constituent parts.
@@ -246,6 +408,7 @@ impl<T: IssueAuth> IssueBundle<T> {
             ik,
             actions,
             authorization,
+            commitments: CommitmentCache::default(),
         }
     }
 }
@@ -304,6 +467,7 @@ impl IssueBundle<Unauthorized> {
                 ik,
                 actions: NonEmpty::new(action),
                 authorization: Unauthorized,
+                commitments: CommitmentCache::default(),
             },
             asset,
         ))
@@ -330,6 +494,7 @@ impl IssueBundle<Unauthorized> {
         }
 
         let asset = AssetBase::derive(&self.ik, &asset_desc);
+        self.commitments = CommitmentCache::default();
 
         let note = Note::new(
             recipient,
@@ -379,6 +544,7 @@ impl IssueBundle<Unauthorized> {
         {
             Some(issue_action) => {
                 issue_action.finalize = true;
+                self.commitments = CommitmentCache::default();
             }
             None => {
                 return Err(IssueActionNotFound);
@@ -394,11 +560,17 @@ impl IssueBundle<Unauthorized> {
             ik: self.ik,
             actions: self.actions,
             authorization: Prepared { sighash },
+            commitments: self.commitments.with_new_authorization(),
         }
     }
 }
 
 impl IssueBundle<Prepared> {
+    /// Returns the sighash that this bundle will be signed over.
+    pub fn sighash(&self) -> &[u8; 32] {
+        self.authorization.sighash()
+    }
+
     /// Sign the `IssueBundle`.
     /// The call makes sure that the provided `isk` matches the `ik` and the derived `asset` for each note in the bundle.
     pub fn sign(self, isk: &IssuanceAuthorizingKey) -> Result<IssueBundle<Signed>, Error> {
@@ -419,6 +591,7 @@ impl IssueBundle<Prepared> {
             ik: self.ik,
             actions: self.actions,
             authorization: Signed { signature },
+            commitments: self.commitments.with_new_authorization(),
         })
     }
 }
@@ -446,8 +619,38 @@ impl IssueBundle<Signed> {
     /// Computes a commitment to the authorizing data within for this bundle.
     ///
     /// This together with `IssueBundle::commitment` bind the entire bundle.
+    ///
+    /// The commitment is cached, and is only recomputed after the bundle is modified.
     pub fn authorizing_commitment(&self) -> IssueBundleAuthorizingCommitment {
-        IssueBundleAuthorizingCommitment(hash_issue_bundle_auth_data(self))
+        IssueBundleAuthorizingCommitment(
+            *self
+                .commitments
+                .auth
+                .lock()
+                .unwrap()
+                .get_or_insert_with(|| hash_issue_bundle_auth_data(self)),
+        )
+    }
+
+    /// Recomputes the commitment to the authorizing data within this bundle, replacing
+    /// the cached value.
+    pub fn recompute_authorizing_commitment(&self) -> IssueBundleAuthorizingCommitment {
+        let commitment = hash_issue_bundle_auth_data(self);
+        *self.commitments.auth.lock().unwrap() = Some(commitment);
+        IssueBundleAuthorizingCommitment(commitment)
+    }
+
+    /// Verifies the signature of this bundle over `sighash` against its `ik`.
+    ///
+    /// This is the first check of [`verify_issue_bundle`], and can be used to cheaply
+    /// reject bundles, such as in a mempool, before the full verification at block
+    /// validation. The other checks, of the sizes of the asset descriptions and of the
+    /// issued notes, are also independent of the asset state, but are not performed: only
+    /// the check that the issued assets have not been finalized needs the asset state.
+    pub fn verify_signature(&self, sighash: &[u8; 32]) -> Result<(), Error> {
+        self.ik
+            .verify(sighash, &self.authorization.signature)
+            .map_err(|_| IssueBundleInvalidSignature)
     }
 }
 
@@ -489,10 +692,170 @@ pub fn verify_issue_bundle(
     sighash: [u8; 32],
     finalized: &HashSet<AssetBase>, // The finalization set.
 ) -> Result<SupplyInfo, Error> {
-    bundle
-        .ik
-        .verify(&sighash, &bundle.authorization.signature)
-        .map_err(|_| IssueBundleInvalidSignature)?;
+    verify_issue_bundle_inner(bundle, sighash, |asset| finalized.contains(asset))
+}
+
+/// Validation for Orchard IssueBundles against the asset state recorded by a node.
+///
+/// This is equivalent to [`verify_issue_bundle`], with the finalization status of each
+/// asset read from `state`. The returned [`SupplyInfo`] can be applied to the state with
+/// [`AssetStateWriter::apply_supply_info`].
+///
+/// [`AssetStateWriter::apply_supply_info`]: crate::supply_info::AssetStateWriter::apply_supply_info
+pub fn verify_issue_bundle_with_state<S: AssetStateReader>(
+    bundle: &IssueBundle<Signed>,
+    sighash: [u8; 32],
+    state: &S,
+) -> Result<SupplyInfo, Error> {
+    verify_issue_bundle_inner(bundle, sighash, |asset| state.is_finalized(asset))
+}
+
+/// Rules on issuance that a deployment enforces in addition to the consensus rules,
+/// checked by [`verify_issue_bundle_with_policy`].
+pub trait IssuancePolicy {
+    /// Checks that the issuer with key `ik` may issue notes of `asset`, bringing the
+    /// supply of the asset to `total_supply`.
+    fn check_issuance(
+        &self,
+        ik: &IssuanceValidatingKey,
+        asset: &AssetBase,
+        total_supply: &AssetSupply,
+    ) -> Result<(), PolicyViolation>;
+}
+
+/// A violation of an [`IssuancePolicy`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyViolation {
+    /// The issuer is not allowed to issue assets.
+    IssuerNotAllowed,
+    /// The issuance would bring the supply of the asset above its cap.
+    CapExceeded {
+        /// The asset being issued.
+        asset: AssetBase,
+        /// The maximum supply of the asset.
+        cap: NoteValue,
+    },
+    /// A rule of a custom policy was violated.
+    Other(String),
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyViolation::IssuerNotAllowed => {
+                write!(f, "the issuer is not allowed to issue assets")
+            }
+            PolicyViolation::CapExceeded { cap, .. } => {
+                write!(
+                    f,
+                    "the issuance exceeds the maximum supply of {} of the asset",
+                    cap.inner()
+                )
+            }
+            PolicyViolation::Other(rule) => write!(f, "{}", rule),
+        }
+    }
+}
+
+/// An [`IssuancePolicy`] enforcing per-asset supply caps and an optional allowlist of
+/// issuers.
+#[derive(Clone, Debug, Default)]
+pub struct IssuanceRules {
+    caps: HashMap<AssetBase, NoteValue>,
+    allowed_issuers: Option<HashSet<[u8; 32]>>,
+}
+
+impl IssuanceRules {
+    /// Constructs rules that allow any issuance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Caps the total supply of `asset` at `cap`.
+    pub fn set_cap(&mut self, asset: AssetBase, cap: NoteValue) -> &mut Self {
+        self.caps.insert(asset, cap);
+        self
+    }
+
+    /// Allows the issuer with key `ik` to issue assets.
+    ///
+    /// Once an issuer has been allowed, issuance by any issuer that has not been allowed
+    /// is rejected.
+    pub fn allow_issuer(&mut self, ik: &IssuanceValidatingKey) -> &mut Self {
+        self.allowed_issuers
+            .get_or_insert_with(HashSet::new)
+            .insert(ik.to_bytes());
+        self
+    }
+}
+
+impl IssuancePolicy for IssuanceRules {
+    fn check_issuance(
+        &self,
+        ik: &IssuanceValidatingKey,
+        asset: &AssetBase,
+        total_supply: &AssetSupply,
+    ) -> Result<(), PolicyViolation> {
+        if let Some(allowed_issuers) = &self.allowed_issuers {
+            if !allowed_issuers.contains(&ik.to_bytes()) {
+                return Err(PolicyViolation::IssuerNotAllowed);
+            }
+        }
+        match self.caps.get(asset) {
+            Some(cap) if i128::from(total_supply.amount) > i128::from(*cap) => {
+                Err(PolicyViolation::CapExceeded {
+                    asset: *asset,
+                    cap: *cap,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Validation for Orchard IssueBundles against the asset state recorded by a node and
+/// the issuance policy of the deployment.
+///
+/// The bundle is first checked as in [`verify_issue_bundle_with_state`]. Then, for each
+/// asset issued by the bundle in order of [`AssetBase`], `policy` is checked with the
+/// supply of the asset recorded in `state` combined with the supply issued by the bundle.
+///
+/// # Errors
+///
+/// In addition to the errors of [`verify_issue_bundle_with_state`], returns
+/// [`Error::PolicyViolation`] for the first asset whose issuance violates `policy`.
+pub fn verify_issue_bundle_with_policy<S: AssetStateReader, P: IssuancePolicy + ?Sized>(
+    bundle: &IssueBundle<Signed>,
+    sighash: [u8; 32],
+    state: &S,
+    policy: &P,
+) -> Result<SupplyInfo, Error> {
+    let supply_info = verify_issue_bundle_with_state(bundle, sighash, state)?;
+
+    let mut assets: Vec<_> = supply_info.assets.iter().collect();
+    assets.sort_by_key(|(asset, _)| **asset);
+    for (asset, supply) in assets {
+        let total_supply = match state.asset_state(asset) {
+            Some(current) => AssetSupply::new(
+                (current.amount + supply.amount).ok_or(ValueSumOverflow)?,
+                current.is_finalized | supply.is_finalized,
+            ),
+            None => *supply,
+        };
+        policy
+            .check_issuance(bundle.ik(), asset, &total_supply)
+            .map_err(Error::PolicyViolation)?;
+    }
+
+    Ok(supply_info)
+}
+
+fn verify_issue_bundle_inner(
+    bundle: &IssueBundle<Signed>,
+    sighash: [u8; 32],
+    is_finalized: impl Fn(&AssetBase) -> bool,
+) -> Result<SupplyInfo, Error> {
+    bundle.verify_signature(&sighash)?;
 
     let supply_info =
         bundle
@@ -506,7 +869,7 @@ pub fn verify_issue_bundle(
                 let (asset, supply) = action.verify_supply(bundle.ik())?;
 
                 // Fail if the asset was previously finalized.
-                if finalized.contains(&asset) {
+                if is_finalized(&asset) {
                     return Err(IssueActionPreviouslyFinalizedAssetBase(asset));
                 }
 
@@ -540,6 +903,8 @@ pub enum Error {
 
     /// Overflow error occurred while calculating the value of the asset
     ValueSumOverflow,
+    /// The issuance violates the [`IssuancePolicy`] supplied by the caller.
+    PolicyViolation(PolicyViolation),
 }
 
 impl fmt::Display for Error {
@@ -581,6 +946,9 @@ impl fmt::Display for Error {
                     "overflow error occurred while calculating the value of the asset"
                 )
             }
+            Error::PolicyViolation(violation) => {
+                write!(f, "the issuance violates the policy: {}", violation)
+            }
         }
     }
 }
@@ -588,21 +956,30 @@ impl fmt::Display for Error {
 #[cfg(test)]
 mod tests {
     use super::{AssetSupply, IssueBundle, IssueInfo};
+    use crate::bundle::commitments::hash_issue_bundle_txid_data;
+    use crate::bundle::OutputKind;
+    use crate::issuance::testing::arb_valid_signed_issue_bundle;
     use crate::issuance::Error::{
         AssetBaseCannotBeIdentityPoint, IssueActionNotFound,
         IssueActionPreviouslyFinalizedAssetBase, IssueBundleIkMismatchAssetBase,
         IssueBundleInvalidSignature, WrongAssetDescSize,
     };
-    use crate::issuance::{verify_issue_bundle, IssueAction, Signed, Unauthorized};
+    use crate::issuance::{
+        verify_issue_bundle, verify_issue_bundle_with_policy, verify_issue_bundle_with_state,
+        IssuanceRules, IssueAction, PolicyViolation, Signed, Unauthorized,
+    };
     use crate::keys::{
-        FullViewingKey, IssuanceAuthorizingKey, IssuanceValidatingKey, Scope, SpendingKey,
+        FullViewingKey, IssuanceAuthorizingKey, IssuanceValidatingKey, ScanningKeys, Scope,
+        SpendingKey,
     };
     use crate::note::{AssetBase, Nullifier, Rho};
+    use crate::supply_info::{AssetStateReader, AssetStateWriter, InMemoryAssetState};
     use crate::value::{NoteValue, ValueSum};
     use crate::{Address, Note};
     use group::{Group, GroupEncoding};
     use nonempty::NonEmpty;
     use pasta_curves::pallas::{Point, Scalar};
+    use proptest::prelude::*;
     use rand::rngs::OsRng;
     use rand::RngCore;
     use std::collections::HashSet;
@@ -838,6 +1215,84 @@ mod tests {
         assert_eq!(action2.notes().first().unwrap().asset(), third_asset);
     }
 
+    #[test]
+    fn issue_bundle_received_outputs() {
+        let (mut rng, _, ik, other, _) = setup_params();
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(3u32, Scope::External);
+
+        let (mut bundle, asset) = IssueBundle::new(
+            ik,
+            String::from("Halo"),
+            Some(IssueInfo {
+                recipient: other,
+                value: NoteValue::from_raw(5),
+            }),
+            rng,
+        )
+        .unwrap();
+        bundle
+            .add_recipient(String::from("Halo"), recipient, NoteValue::from_raw(7), rng)
+            .unwrap();
+
+        let received = bundle.received_outputs(&ScanningKeys::from_full_viewing_keys(&[fvk]));
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].index(), 1);
+        assert_eq!(received[0].kind(), OutputKind::Issuance);
+        assert_eq!(received[0].asset(), asset);
+        assert_eq!(received[0].recipient(), recipient);
+        assert_eq!(received[0].note().value(), NoteValue::from_raw(7));
+        assert!(received[0].memo().is_none());
+    }
+
+    #[test]
+    fn issue_bundle_serialized_size() {
+        let (rng, isk, ik, recipient, sighash) = setup_params();
+
+        let (mut bundle, asset) = IssueBundle::new(
+            ik.clone(),
+            String::from("Size"),
+            Some(IssueInfo {
+                recipient,
+                value: NoteValue::from_raw(5),
+            }),
+            rng,
+        )
+        .unwrap();
+        bundle
+            .add_recipient(
+                String::from("Size"),
+                recipient,
+                NoteValue::from_raw(10),
+                rng,
+            )
+            .unwrap();
+        bundle
+            .add_recipient(
+                String::from("Other size"),
+                recipient,
+                NoteValue::from_raw(15),
+                rng,
+            )
+            .unwrap();
+
+        let sizes = bundle.action_sizes();
+        assert_eq!(sizes.len(), 2);
+        assert_eq!(sizes[0].asset, asset);
+        assert_eq!(sizes[0].num_notes, 2);
+        assert_eq!(sizes[0].serialized_size, 1 + 4 + 1 + 2 * 147 + 1);
+        assert_eq!(sizes[1].num_notes, 1);
+        assert_eq!(sizes[1].serialized_size, 1 + 10 + 1 + 147 + 1);
+
+        let size = bundle.serialized_size();
+        assert_eq!(
+            size,
+            1 + sizes[0].serialized_size + sizes[1].serialized_size + 96
+        );
+        let signed = bundle.prepare(sighash).sign(&isk).unwrap();
+        assert_eq!(signed.serialized_size(), size);
+    }
+
     #[test]
     fn issue_bundle_finalize_asset() {
         let (rng, _, ik, recipient, _) = setup_params();
@@ -917,6 +1372,52 @@ mod tests {
             .expect("signature should be valid");
     }
 
+    #[test]
+    fn issue_bundle_commitment_cache() {
+        let (mut rng, isk, ik, recipient, sighash) = setup_params();
+
+        let (mut bundle, _) = IssueBundle::new(
+            ik,
+            String::from("Cached"),
+            Some(IssueInfo {
+                recipient,
+                value: NoteValue::from_raw(5),
+            }),
+            &mut rng,
+        )
+        .unwrap();
+
+        let commitment: [u8; 32] = bundle.commitment().into();
+        assert_eq!(<[u8; 32]>::from(bundle.commitment()), commitment);
+
+        // Modifying the bundle invalidates the cached commitment.
+        bundle
+            .add_recipient(
+                String::from("Cached"),
+                recipient,
+                NoteValue::from_raw(7),
+                &mut rng,
+            )
+            .unwrap();
+        let commitment: [u8; 32] = bundle.commitment().into();
+        assert_eq!(
+            commitment,
+            hash_issue_bundle_txid_data(&bundle).as_bytes()[..]
+        );
+
+        bundle.finalize_action(String::from("Cached")).unwrap();
+        let commitment: [u8; 32] = bundle.commitment().into();
+        assert_eq!(<[u8; 32]>::from(bundle.recompute_commitment()), commitment);
+
+        // The commitment does not depend on the authorization.
+        let signed = bundle.prepare(sighash).sign(&isk).unwrap();
+        assert_eq!(<[u8; 32]>::from(signed.commitment()), commitment);
+        assert_eq!(
+            signed.authorizing_commitment().0,
+            signed.recompute_authorizing_commitment().0
+        );
+    }
+
     #[test]
     fn issue_bundle_invalid_isk_for_signature() {
         let (rng, _, ik, recipient, _) = setup_params();
@@ -1142,12 +1643,86 @@ mod tests {
         );
     }
 
+    #[test]
+    fn issue_bundle_verify_with_state() {
+        let (rng, isk, ik, recipient, sighash) = setup_params();
+
+        let (mut bundle, asset) = IssueBundle::new(
+            ik,
+            String::from("stateful"),
+            Some(IssueInfo {
+                recipient,
+                value: NoteValue::from_raw(5),
+            }),
+            rng,
+        )
+        .unwrap();
+        bundle.finalize_action(String::from("stateful")).unwrap();
+        let signed = bundle.prepare(sighash).sign(&isk).unwrap();
+
+        let mut state = InMemoryAssetState::new();
+        let supply_info = verify_issue_bundle_with_state(&signed, sighash, &state).unwrap();
+        state.apply_supply_info(&supply_info).unwrap();
+        assert!(state.is_finalized(&asset));
+
+        assert_eq!(
+            verify_issue_bundle_with_state(&signed, sighash, &state).unwrap_err(),
+            IssueActionPreviouslyFinalizedAssetBase(asset)
+        );
+    }
+
+    #[test]
+    fn issue_bundle_verify_with_policy() {
+        let (rng, isk, ik, recipient, sighash) = setup_params();
+
+        let (bundle, asset) = IssueBundle::new(
+            ik.clone(),
+            String::from("capped"),
+            Some(IssueInfo {
+                recipient,
+                value: NoteValue::from_raw(5),
+            }),
+            rng,
+        )
+        .unwrap();
+        let signed = bundle.prepare(sighash).sign(&isk).unwrap();
+
+        let mut state = InMemoryAssetState::new();
+        let mut rules = IssuanceRules::new();
+        rules
+            .set_cap(asset, NoteValue::from_raw(8))
+            .allow_issuer(&ik);
+
+        let supply_info =
+            verify_issue_bundle_with_policy(&signed, sighash, &state, &rules).unwrap();
+        state.apply_supply_info(&supply_info).unwrap();
+
+        // A second issuance of 5 brings the supply to 10, above the cap of 8.
+        assert_eq!(
+            verify_issue_bundle_with_policy(&signed, sighash, &state, &rules).unwrap_err(),
+            super::Error::PolicyViolation(PolicyViolation::CapExceeded {
+                asset,
+                cap: NoteValue::from_raw(8),
+            })
+        );
+
+        let mut rules = IssuanceRules::new();
+        rules.allow_issuer(&IssuanceValidatingKey::from(
+            &IssuanceAuthorizingKey::random(),
+        ));
+        assert_eq!(
+            verify_issue_bundle_with_policy(&signed, sighash, &state, &rules).unwrap_err(),
+            super::Error::PolicyViolation(PolicyViolation::IssuerNotAllowed)
+        );
+    }
+
     #[test]
     fn issue_bundle_verify_fail_bad_signature() {
         // we want to inject "bad" signatures for test purposes.
         impl IssueBundle<Signed> {
             pub fn set_authorization(&mut self, authorization: Signed) {
                 self.authorization = authorization;
+                self.commitments = self.commitments.with_new_authorization();
             }
         }
 
@@ -1180,6 +1755,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn issue_bundle_verify_signature() {
+        let (rng, isk, ik, recipient, sighash) = setup_params();
+
+        let (bundle, _) = IssueBundle::new(
+            ik,
+            String::from("signature"),
+            Some(IssueInfo {
+                recipient,
+                value: NoteValue::from_raw(5),
+            }),
+            rng,
+        )
+        .unwrap();
+        let signed = bundle.prepare(sighash).sign(&isk).unwrap();
+
+        assert_eq!(signed.verify_signature(&sighash), Ok(()));
+        assert_eq!(
+            signed.verify_signature(&[0; 32]).unwrap_err(),
+            IssueBundleInvalidSignature
+        );
+    }
+
     #[test]
     fn issue_bundle_verify_fail_wrong_sighash() {
         let (rng, isk, ik, recipient, random_sighash) = setup_params();
@@ -1340,13 +1938,13 @@ mod tests {
     fn issue_bundle_verify_fail_asset_base_identity_point() {
         let (isk, bundle, sighash) = identity_point_test_params(10, 20);
 
-        let signed = IssueBundle {
-            ik: bundle.ik,
-            actions: bundle.actions,
-            authorization: Signed {
+        let signed = IssueBundle::from_parts(
+            bundle.ik,
+            bundle.actions,
+            Signed {
                 signature: isk.try_sign(&sighash).unwrap(),
             },
-        };
+        );
 
         assert_eq!(
             verify_issue_bundle(&signed, sighash, &HashSet::new()).unwrap_err(),
@@ -1373,6 +1971,20 @@ mod tests {
             IssueAction::new_with_flags(String::from("Asset description"), vec![note], 2u8);
         assert!(action.is_none());
     }
+
+    proptest! {
+        #[test]
+        fn valid_signed_issue_bundles_verify(
+            (bundle, _, sighash) in arb_valid_signed_issue_bundle(3),
+        ) {
+            let supply_info = verify_issue_bundle(&bundle, sighash, &HashSet::new()).unwrap();
+            prop_assert_eq!(supply_info.assets.len(), 3);
+            for action in bundle.actions() {
+                let total: u64 = action.notes().iter().map(|note| note.value().inner()).sum();
+                prop_assert!(total <= 1 << 40);
+            }
+        }
+    }
 }
 
 /// Generators for property testing.
@@ -1380,9 +1992,12 @@ mod tests {
 #[cfg_attr(docsrs, doc(cfg(feature = "test-dependencies")))]
 pub mod testing {
     use crate::issuance::{IssueAction, IssueBundle, Prepared, Signed, Unauthorized};
-    use crate::keys::testing::arb_issuance_validating_key;
+    use crate::keys::testing::{arb_issuance_authorizing_key, arb_issuance_validating_key};
+    use crate::keys::{IssuanceAuthorizingKey, IssuanceValidatingKey};
     use crate::note::asset_base::testing::zsa_asset_base;
-    use crate::note::testing::arb_zsa_note;
+    use crate::note::testing::{arb_zsa_note, arb_zsa_note_with_value};
+    use crate::note::AssetBase;
+    use crate::value::NoteValue;
     use k256::schnorr;
     use nonempty::NonEmpty;
     use proptest::collection::vec;
@@ -1391,7 +2006,7 @@ pub mod testing {
 
     prop_compose! {
         /// Generate a uniformly distributed signature
-        pub(crate) fn arb_signature()(
+        pub fn arb_signature()(
             sig_bytes in vec(prop::num::u8::ANY, 64)
         ) -> schnorr::Signature {
             schnorr::Signature::try_from(sig_bytes.as_slice()).unwrap()
@@ -1423,11 +2038,7 @@ pub mod testing {
             ik in arb_issuance_validating_key()
         ) -> IssueBundle<Unauthorized> {
             let actions = NonEmpty::from_vec(actions).unwrap();
-            IssueBundle {
-                ik,
-                actions,
-                authorization: Unauthorized
-            }
+            IssueBundle::from_parts(ik, actions, Unauthorized)
         }
     }
 
@@ -1441,11 +2052,7 @@ pub mod testing {
             fake_sighash in prop::array::uniform32(prop::num::u8::ANY)
         ) -> IssueBundle<Prepared> {
             let actions = NonEmpty::from_vec(actions).unwrap();
-            IssueBundle {
-                ik,
-                actions,
-                authorization: Prepared { sighash: fake_sighash }
-            }
+            IssueBundle::from_parts(ik, actions, Prepared { sighash: fake_sighash })
         }
     }
 
@@ -1459,11 +2066,72 @@ pub mod testing {
             fake_sig in arb_signature(),
         ) -> IssueBundle<Signed> {
             let actions = NonEmpty::from_vec(actions).unwrap();
-            IssueBundle {
-                ik,
-                actions,
-                authorization: Signed { signature: fake_sig },
-            }
+            IssueBundle::from_parts(ik, actions, Signed { signature: fake_sig })
+        }
+    }
+
+    /// Generate an issue action for the asset with description `asset_desc` issued by `ik`,
+    /// containing between 1 and `max_notes` notes whose total value is at most `max_supply`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_notes` is zero.
+    pub fn arb_issue_action_with_supply_bounds(
+        ik: &IssuanceValidatingKey,
+        asset_desc: String,
+        max_notes: usize,
+        max_supply: u64,
+    ) -> impl Strategy<Value = IssueAction> {
+        assert!(
+            max_notes > 0,
+            "an issue action with notes needs at least one note"
+        );
+        let asset = AssetBase::derive(ik, &asset_desc);
+        (
+            vec(0..=max_supply / max_notes as u64, 1..=max_notes),
+            prop::bool::ANY,
+        )
+            .prop_flat_map(move |(values, finalize)| {
+                let notes = values
+                    .into_iter()
+                    .map(|value| arb_zsa_note_with_value(asset, NoteValue::from_raw(value)))
+                    .collect::<Vec<_>>();
+                (notes, Just(finalize))
+            })
+            .prop_map(move |(notes, finalize)| IssueAction {
+                asset_desc: asset_desc.clone(),
+                notes,
+                finalize,
+            })
+    }
+
+    prop_compose! {
+        /// Generate an issue bundle of `n_actions` distinct assets that satisfies the
+        /// consensus rules, along with the issuance authorizing key it is signed with and
+        /// the sighash it is signed over.
+        pub fn arb_valid_signed_issue_bundle(n_actions: usize)
+        (
+            isk in arb_issuance_authorizing_key(),
+            sighash in prop::array::uniform32(prop::num::u8::ANY),
+        )
+        (
+            actions in (0..n_actions)
+                .map(|i| arb_issue_action_with_supply_bounds(
+                    &IssuanceValidatingKey::from(&isk),
+                    format!("asset_desc {}", i),
+                    3,
+                    1 << 40,
+                ))
+                .collect::<Vec<_>>(),
+            isk in Just(isk),
+            sighash in Just(sighash),
+        ) -> (IssueBundle<Signed>, IssuanceAuthorizingKey, [u8; 32]) {
+            let bundle = IssueBundle::from_parts(
+                IssuanceValidatingKey::from(&isk),
+                NonEmpty::from_vec(actions).unwrap(),
+                Prepared { sighash },
+            );
+            (bundle.sign(&isk).unwrap(), isk, sighash)
         }
     }
 }
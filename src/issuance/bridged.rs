@@ -0,0 +1,229 @@
+//! Asset descriptions for assets bridged from other chains.
+
+use core::fmt;
+
+use rand::RngCore;
+
+use super::{Error, IssueBundle, IssueInfo, Unauthorized};
+use crate::{keys::IssuanceValidatingKey, note::AssetBase, value::NoteValue, Address};
+
+/// The prefix of the asset descriptions of bridged assets.
+pub const BRIDGED_ASSET_PREFIX: &str = "bridged/v1/";
+
+/// The maximum length in bytes of the address of the origin contract of a bridged asset.
+pub const MAX_ORIGIN_CONTRACT_SIZE: usize = 128;
+
+/// An asset wrapping an asset of another chain.
+///
+/// The asset description of a bridged asset is its canonical encoding
+/// `bridged/v1/<chain ID>/0x<origin contract>/<decimals>`, with the chain ID and the
+/// decimals in decimal without leading zeroes, and the address of the origin contract in
+/// lowercase hexadecimal. Each wrapped asset therefore has a single description, and a
+/// single [`AssetBase`] for a given issuer, whichever bridge constructs the description.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BridgedAsset {
+    chain_id: u64,
+    origin_contract: Vec<u8>,
+    decimals: u8,
+}
+
+impl BridgedAsset {
+    /// Constructs the description of the asset of the contract `origin_contract` on the
+    /// chain with identifier `chain_id`, whose amounts have `decimals` decimal places.
+    ///
+    /// Returns `None` if `origin_contract` is empty or longer than
+    /// [`MAX_ORIGIN_CONTRACT_SIZE`] bytes.
+    pub fn new(chain_id: u64, origin_contract: &[u8], decimals: u8) -> Option<Self> {
+        (!origin_contract.is_empty() && origin_contract.len() <= MAX_ORIGIN_CONTRACT_SIZE).then(
+            || BridgedAsset {
+                chain_id,
+                origin_contract: origin_contract.to_vec(),
+                decimals,
+            },
+        )
+    }
+
+    /// Returns the identifier of the chain the asset originates from.
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// Returns the address of the contract of the asset on its origin chain.
+    pub fn origin_contract(&self) -> &[u8] {
+        &self.origin_contract
+    }
+
+    /// Returns the number of decimal places of the amounts of the asset.
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    /// Returns the canonical asset description of this asset.
+    pub fn to_asset_desc(&self) -> String {
+        format!(
+            "{}{}/0x{}/{}",
+            BRIDGED_ASSET_PREFIX,
+            self.chain_id,
+            hex::encode(&self.origin_contract),
+            self.decimals
+        )
+    }
+
+    /// Parses a bridged asset from its asset description.
+    ///
+    /// Only the canonical encoding is accepted.
+    pub fn from_asset_desc(asset_desc: &str) -> Result<Self, BridgedAssetError> {
+        let fields = asset_desc
+            .strip_prefix(BRIDGED_ASSET_PREFIX)
+            .ok_or(BridgedAssetError::NotBridged)?;
+        let mut fields = fields.split('/');
+        let (chain_id, origin_contract, decimals) =
+            match (fields.next(), fields.next(), fields.next(), fields.next()) {
+                (Some(chain_id), Some(origin_contract), Some(decimals), None) => {
+                    (chain_id, origin_contract, decimals)
+                }
+                _ => return Err(BridgedAssetError::Malformed),
+            };
+
+        let chain_id = parse_canonical::<u64>(chain_id).ok_or(BridgedAssetError::Malformed)?;
+        let decimals = parse_canonical::<u8>(decimals).ok_or(BridgedAssetError::Malformed)?;
+        let origin_contract = origin_contract
+            .strip_prefix("0x")
+            .filter(|hex| hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')))
+            .and_then(|hex| hex::decode(hex).ok())
+            .ok_or(BridgedAssetError::Malformed)?;
+
+        BridgedAsset::new(chain_id, &origin_contract, decimals).ok_or(BridgedAssetError::Malformed)
+    }
+
+    /// Derives the [`AssetBase`] of this asset when issued by the issuer with key `ik`.
+    pub fn asset_base(&self, ik: &IssuanceValidatingKey) -> AssetBase {
+        AssetBase::derive(ik, &self.to_asset_desc())
+    }
+}
+
+/// Parses a decimal integer, rejecting encodings other than the one produced by
+/// `to_string`, such as those with leading zeroes or a sign.
+fn parse_canonical<T: core::str::FromStr + ToString>(s: &str) -> Option<T> {
+    s.parse::<T>().ok().filter(|value| value.to_string() == s)
+}
+
+/// An error returned when parsing the asset description of a [`BridgedAsset`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BridgedAssetError {
+    /// The asset description does not describe a bridged asset.
+    NotBridged,
+    /// The asset description is not a canonical bridged asset description.
+    Malformed,
+}
+
+impl fmt::Display for BridgedAssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BridgedAssetError::NotBridged => {
+                write!(f, "the asset description does not describe a bridged asset")
+            }
+            BridgedAssetError::Malformed => {
+                write!(f, "the bridged asset description is not canonical")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BridgedAssetError {}
+
+impl IssueBundle<Unauthorized> {
+    /// Constructs a new `IssueBundle` issuing the bridged asset `asset`, as in
+    /// [`IssueBundle::new`] with the canonical description of the asset.
+    pub fn new_bridged(
+        ik: IssuanceValidatingKey,
+        asset: &BridgedAsset,
+        issue_info: Option<IssueInfo>,
+        rng: impl RngCore,
+    ) -> Result<(IssueBundle<Unauthorized>, AssetBase), Error> {
+        IssueBundle::new(ik, asset.to_asset_desc(), issue_info, rng)
+    }
+
+    /// Adds a new note of the bridged asset `asset` to the bundle, as in
+    /// [`IssueBundle::add_recipient`] with the canonical description of the asset.
+    pub fn add_bridged_recipient(
+        &mut self,
+        asset: &BridgedAsset,
+        recipient: Address,
+        value: NoteValue,
+        rng: impl RngCore,
+    ) -> Result<AssetBase, Error> {
+        self.add_recipient(asset.to_asset_desc(), recipient, value, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::{BridgedAsset, BridgedAssetError};
+    use crate::{
+        issuance::{IssueBundle, IssueInfo},
+        keys::{FullViewingKey, IssuanceAuthorizingKey, IssuanceValidatingKey, Scope, SpendingKey},
+        value::NoteValue,
+    };
+
+    #[test]
+    fn bridged_asset_desc_round_trip() {
+        let contract = hex::decode("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap();
+        let asset = BridgedAsset::new(1, &contract, 6).unwrap();
+        let desc = asset.to_asset_desc();
+        assert_eq!(
+            desc,
+            "bridged/v1/1/0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48/6"
+        );
+        assert_eq!(BridgedAsset::from_asset_desc(&desc), Ok(asset));
+
+        for desc in [
+            "bridged/v1/01/0xa0b8/6",
+            "bridged/v1/1/0xA0B8/6",
+            "bridged/v1/1/a0b8/6",
+            "bridged/v1/1/0x/6",
+            "bridged/v1/1/0xa0b8/256",
+            "bridged/v1/1/0xa0b8/6/",
+            "bridged/v1/+1/0xa0b8/6",
+        ] {
+            assert_eq!(
+                BridgedAsset::from_asset_desc(desc),
+                Err(BridgedAssetError::Malformed)
+            );
+        }
+        assert_eq!(
+            BridgedAsset::from_asset_desc("USDC"),
+            Err(BridgedAssetError::NotBridged)
+        );
+    }
+
+    #[test]
+    fn issue_bridged_asset() {
+        let mut rng = OsRng;
+        let ik = IssuanceValidatingKey::from(&IssuanceAuthorizingKey::random());
+        let recipient =
+            FullViewingKey::from(&SpendingKey::random(&mut rng)).address_at(0u32, Scope::External);
+        let asset = BridgedAsset::new(10, &[0xab; 20], 18).unwrap();
+
+        let (mut bundle, asset_base) = IssueBundle::new_bridged(
+            ik.clone(),
+            &asset,
+            Some(IssueInfo {
+                recipient,
+                value: NoteValue::from_raw(5),
+            }),
+            rng,
+        )
+        .unwrap();
+        assert_eq!(asset_base, asset.asset_base(&ik));
+        assert_eq!(
+            bundle
+                .add_bridged_recipient(&asset, recipient, NoteValue::from_raw(7), rng)
+                .unwrap(),
+            asset_base
+        );
+        assert_eq!(bundle.actions().len(), 1);
+    }
+}
@@ -0,0 +1,230 @@
+//! Encoding of issue bundles, in each of their authorization states.
+//!
+//! An issue bundle is encoded as:
+//!
+//! - the number of actions as a `CompactSize`, followed by the actions: the length of the
+//!   asset description as a `CompactSize` and the asset description, the number of notes
+//!   as a `CompactSize` and the notes, and the flags of the action;
+//! - the issuance validating key;
+//! - the authorization of the bundle: nothing for an [`Unauthorized`] bundle, the sighash
+//!   for a [`Prepared`] bundle, and the signature for a [`Signed`] bundle.
+//!
+//! Each note is encoded as its recipient, value, asset, `rho` and `rseed`. An unsigned
+//! bundle can thus be transferred to an offline signing device, and the signed bundle
+//! returned, in the same encoding.
+
+use std::io::{self, Read, Write};
+
+use k256::schnorr;
+use nonempty::NonEmpty;
+
+use super::{IssueAction, IssueAuth, IssueBundle, Prepared, Signed, Unauthorized};
+use crate::{
+    bundle::{read_compact_size, write_compact_size},
+    keys::IssuanceValidatingKey,
+    note::{
+        asset_base::{is_asset_desc_of_valid_size, MAX_ASSET_DESCRIPTION_SIZE},
+        AssetBase, Note, RandomSeed, Rho,
+    },
+    value::NoteValue,
+    Address,
+};
+
+/// An authorization state of an [`IssueBundle`] that can be encoded.
+pub trait IssueAuthEncoding: IssueAuth + Sized {
+    /// Writes the encoding of this authorization.
+    fn write_auth<W: Write>(&self, writer: W) -> io::Result<()>;
+
+    /// Reads the encoding of an authorization.
+    fn read_auth<R: Read>(reader: R) -> io::Result<Self>;
+}
+
+impl IssueAuthEncoding for Unauthorized {
+    fn write_auth<W: Write>(&self, _: W) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read_auth<R: Read>(_: R) -> io::Result<Self> {
+        Ok(Unauthorized)
+    }
+}
+
+impl IssueAuthEncoding for Prepared {
+    fn write_auth<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.sighash)
+    }
+
+    fn read_auth<R: Read>(mut reader: R) -> io::Result<Self> {
+        Ok(Prepared {
+            sighash: read_array(&mut reader)?,
+        })
+    }
+}
+
+impl IssueAuthEncoding for Signed {
+    fn write_auth<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.signature.to_bytes())
+    }
+
+    fn read_auth<R: Read>(mut reader: R) -> io::Result<Self> {
+        let bytes: [u8; 64] = read_array(&mut reader)?;
+        let signature = schnorr::Signature::try_from(&bytes[..])
+            .map_err(|_| invalid_data("Invalid issuance signature"))?;
+        Ok(Signed { signature })
+    }
+}
+
+impl<T: IssueAuthEncoding> IssueBundle<T> {
+    /// Writes the encoding of this bundle.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_compact_size(&mut writer, self.actions.len())?;
+        for action in self.actions.iter() {
+            write_compact_size(&mut writer, action.asset_desc.len())?;
+            writer.write_all(action.asset_desc.as_bytes())?;
+            write_compact_size(&mut writer, action.notes.len())?;
+            for note in &action.notes {
+                writer.write_all(&note.recipient().to_raw_address_bytes())?;
+                writer.write_all(&note.value().to_bytes())?;
+                writer.write_all(&note.asset().to_bytes())?;
+                writer.write_all(&note.rho().to_bytes())?;
+                writer.write_all(note.rseed().as_bytes())?;
+            }
+            writer.write_all(&[action.flags()])?;
+        }
+        writer.write_all(&self.ik.to_bytes())?;
+        self.authorization.write_auth(writer)
+    }
+
+    /// Reads the encoding of a bundle written with [`IssueBundle::write`].
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let num_actions = read_len(&mut reader)?;
+        let mut actions = vec![];
+        for _ in 0..num_actions {
+            actions.push(read_action(&mut reader)?);
+        }
+        let actions = NonEmpty::from_vec(actions)
+            .ok_or_else(|| invalid_data("Issue bundle has no actions"))?;
+
+        let ik = IssuanceValidatingKey::from_bytes(&read_array::<_, 32>(&mut reader)?)
+            .ok_or_else(|| invalid_data("Invalid issuance validating key"))?;
+        let authorization = T::read_auth(&mut reader)?;
+
+        Ok(IssueBundle::from_parts(ik, actions, authorization))
+    }
+}
+
+fn read_action<R: Read>(reader: &mut R) -> io::Result<IssueAction> {
+    let desc_len = read_len(reader)?;
+    if desc_len > MAX_ASSET_DESCRIPTION_SIZE {
+        return Err(invalid_data("Invalid asset description"));
+    }
+    let mut asset_desc = vec![0; desc_len];
+    reader.read_exact(&mut asset_desc)?;
+    let asset_desc = String::from_utf8(asset_desc)
+        .ok()
+        .filter(|desc| is_asset_desc_of_valid_size(desc))
+        .ok_or_else(|| invalid_data("Invalid asset description"))?;
+
+    let num_notes = read_len(reader)?;
+    let mut notes = vec![];
+    for _ in 0..num_notes {
+        notes.push(read_note(reader)?);
+    }
+
+    let [flags] = read_array(reader)?;
+    IssueAction::new_with_flags(asset_desc, notes, flags)
+        .ok_or_else(|| invalid_data("Invalid issue action flags"))
+}
+
+fn read_note<R: Read>(reader: &mut R) -> io::Result<Note> {
+    let recipient = Option::from(Address::from_raw_address_bytes(&read_array(reader)?))
+        .ok_or_else(|| invalid_data("Invalid recipient"))?;
+    let value = NoteValue::from_bytes(read_array(reader)?);
+    let asset = Option::from(AssetBase::from_bytes(&read_array(reader)?))
+        .ok_or_else(|| invalid_data("Invalid asset"))?;
+    let rho: Rho = Option::from(Rho::from_bytes(&read_array(reader)?))
+        .ok_or_else(|| invalid_data("Invalid rho"))?;
+    let rseed = Option::from(RandomSeed::from_bytes(read_array(reader)?, &rho))
+        .ok_or_else(|| invalid_data("Invalid rseed"))?;
+    Option::from(Note::from_parts(recipient, value, asset, rho, rseed))
+        .ok_or_else(|| invalid_data("Invalid note"))
+}
+
+fn read_len<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let len = read_compact_size(reader, &mut vec![])?;
+    usize::try_from(len).map_err(|_| invalid_data("Length out of range"))
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::OsRng, RngCore};
+
+    use crate::{
+        issuance::{IssueBundle, IssueInfo, Prepared, Signed, Unauthorized},
+        keys::{FullViewingKey, IssuanceAuthorizingKey, IssuanceValidatingKey, Scope, SpendingKey},
+        value::NoteValue,
+    };
+
+    #[test]
+    fn issue_bundle_states_round_trip() {
+        let mut rng = OsRng;
+        let isk = IssuanceAuthorizingKey::random();
+        let ik = IssuanceValidatingKey::from(&isk);
+        let recipient =
+            FullViewingKey::from(&SpendingKey::random(&mut rng)).address_at(0u32, Scope::External);
+        let mut sighash = [0; 32];
+        rng.fill_bytes(&mut sighash);
+
+        let (mut bundle, _) = IssueBundle::new(
+            ik,
+            String::from("Offline"),
+            Some(IssueInfo {
+                recipient,
+                value: NoteValue::from_raw(5),
+            }),
+            rng,
+        )
+        .unwrap();
+        bundle
+            .add_recipient(
+                String::from("Other"),
+                recipient,
+                NoteValue::from_raw(7),
+                rng,
+            )
+            .unwrap();
+        bundle.finalize_action(String::from("Other")).unwrap();
+
+        let mut bytes = vec![];
+        bundle.write(&mut bytes).unwrap();
+        let unauthorized = IssueBundle::<Unauthorized>::read(&bytes[..]).unwrap();
+        assert_eq!(unauthorized.actions(), bundle.actions());
+        assert_eq!(unauthorized.commitment().0, bundle.commitment().0);
+
+        // The signing device receives the prepared bundle, and returns the signed one.
+        let mut bytes = vec![];
+        unauthorized.prepare(sighash).write(&mut bytes).unwrap();
+        let prepared = IssueBundle::<Prepared>::read(&bytes[..]).unwrap();
+        assert_eq!(prepared.sighash(), &sighash);
+
+        let mut bytes = vec![];
+        prepared.sign(&isk).unwrap().write(&mut bytes).unwrap();
+        let signed = IssueBundle::<Signed>::read(&bytes[..]).unwrap();
+        assert_eq!(bytes.len(), signed.serialized_size());
+        assert!(
+            crate::issuance::verify_issue_bundle(&signed, sighash, &Default::default()).is_ok()
+        );
+
+        assert!(IssueBundle::<Signed>::read(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
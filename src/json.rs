@@ -0,0 +1,346 @@
+//! A JSON encoding of bundles and issue bundles, for block explorers and debugging tools.
+//!
+//! Byte fields are hex-encoded, flags are named, and burns are listed as asset and value
+//! pairs. The types in this module implement [`serde::Serialize`] and
+//! [`serde::Deserialize`], and can be used with any serde data format.
+//!
+//! Bundles and actions can be imported back from their JSON encoding. Issue bundles can
+//! only be exported.
+
+use core::fmt;
+
+use nonempty::NonEmpty;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::Action,
+    bundle::{Authorized, Bundle, Flags},
+    issuance::{IssueAction, IssueBundle, Signed},
+    note::{AssetBase, ExtractedNoteCommitment, Note, Nullifier, TransmittedNoteCiphertext},
+    primitives::redpallas::{self, SpendAuth},
+    tree::Anchor,
+    value::ValueCommitment,
+    Proof,
+};
+
+/// An error that can occur when importing a bundle from its JSON encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsonError {
+    /// The named field is not valid hex, or has the wrong length.
+    InvalidHex(&'static str),
+    /// The named field does not encode a valid value.
+    InvalidEncoding(&'static str),
+    /// The bundle has no actions.
+    NoActions,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::InvalidHex(field) => write!(f, "Invalid hex encoding of {}", field),
+            JsonError::InvalidEncoding(field) => write!(f, "Invalid encoding of {}", field),
+            JsonError::NoActions => f.write_str("Bundle has no actions"),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+/// Decodes a hex-encoded field of `N` bytes.
+fn decode_hex<const N: usize>(s: &str, field: &'static str) -> Result<[u8; N], JsonError> {
+    hex::decode(s)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(JsonError::InvalidHex(field))
+}
+
+/// The JSON encoding of an authorized [`Action`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionJson {
+    /// The value commitment `cv_net`.
+    pub cv_net: String,
+    /// The nullifier of the spent note.
+    pub nullifier: String,
+    /// The randomized verification key for the spent note.
+    pub rk: String,
+    /// The extracted commitment of the output note.
+    pub cmx: String,
+    /// The ephemeral public key of the output note.
+    pub ephemeral_key: String,
+    /// The encrypted output note.
+    pub enc_ciphertext: String,
+    /// The encrypted output note for its sender.
+    pub out_ciphertext: String,
+    /// The spend authorization signature.
+    pub spend_auth_sig: String,
+}
+
+impl From<&Action<redpallas::Signature<SpendAuth>>> for ActionJson {
+    fn from(action: &Action<redpallas::Signature<SpendAuth>>) -> Self {
+        ActionJson {
+            cv_net: hex::encode(action.cv_net().to_bytes()),
+            nullifier: hex::encode(action.nullifier().to_bytes()),
+            rk: hex::encode(<[u8; 32]>::from(action.rk())),
+            cmx: hex::encode(action.cmx().to_bytes()),
+            ephemeral_key: hex::encode(action.encrypted_note().epk_bytes),
+            enc_ciphertext: hex::encode(action.encrypted_note().enc_ciphertext),
+            out_ciphertext: hex::encode(action.encrypted_note().out_ciphertext),
+            spend_auth_sig: hex::encode(<[u8; 64]>::from(action.authorization())),
+        }
+    }
+}
+
+impl ActionJson {
+    /// Decodes the action.
+    pub fn to_action(&self) -> Result<Action<redpallas::Signature<SpendAuth>>, JsonError> {
+        let cv_net = Option::from(ValueCommitment::from_bytes(&decode_hex(
+            &self.cv_net,
+            "cv_net",
+        )?))
+        .ok_or(JsonError::InvalidEncoding("cv_net"))?;
+        let nf = Option::from(Nullifier::from_bytes(&decode_hex(
+            &self.nullifier,
+            "nullifier",
+        )?))
+        .ok_or(JsonError::InvalidEncoding("nullifier"))?;
+        let rk = redpallas::VerificationKey::try_from(decode_hex::<32>(&self.rk, "rk")?)
+            .map_err(|_| JsonError::InvalidEncoding("rk"))?;
+        let cmx = Option::from(ExtractedNoteCommitment::from_bytes(&decode_hex(
+            &self.cmx, "cmx",
+        )?))
+        .ok_or(JsonError::InvalidEncoding("cmx"))?;
+
+        Ok(Action::from_parts(
+            nf,
+            rk,
+            cmx,
+            TransmittedNoteCiphertext {
+                epk_bytes: decode_hex(&self.ephemeral_key, "ephemeral_key")?,
+                enc_ciphertext: decode_hex(&self.enc_ciphertext, "enc_ciphertext")?,
+                out_ciphertext: decode_hex(&self.out_ciphertext, "out_ciphertext")?,
+            },
+            cv_net,
+            redpallas::Signature::from(decode_hex::<64>(&self.spend_auth_sig, "spend_auth_sig")?),
+        ))
+    }
+}
+
+/// The JSON encoding of the [`Flags`] of a bundle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlagsJson {
+    /// Whether spends are enabled.
+    pub spends_enabled: bool,
+    /// Whether outputs are enabled.
+    pub outputs_enabled: bool,
+    /// Whether ZSA are enabled.
+    pub zsa_enabled: bool,
+}
+
+impl From<&Flags> for FlagsJson {
+    fn from(flags: &Flags) -> Self {
+        FlagsJson {
+            spends_enabled: flags.spends_enabled(),
+            outputs_enabled: flags.outputs_enabled(),
+            zsa_enabled: flags.zsa_enabled(),
+        }
+    }
+}
+
+impl From<&FlagsJson> for Flags {
+    fn from(flags: &FlagsJson) -> Self {
+        Flags::from_byte(
+            u8::from(flags.spends_enabled)
+                | u8::from(flags.outputs_enabled) << 1
+                | u8::from(flags.zsa_enabled) << 2,
+        )
+        .expect("only known flags are set")
+    }
+}
+
+/// The JSON encoding of a burn of an asset.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BurnJson {
+    /// The burnt asset.
+    pub asset: String,
+    /// The burnt value.
+    pub value: i64,
+}
+
+/// The JSON encoding of an authorized [`Bundle`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleJson {
+    /// The actions of the bundle.
+    pub actions: Vec<ActionJson>,
+    /// The flags of the bundle.
+    pub flags: FlagsJson,
+    /// The net value moved out of the Orchard pool.
+    pub value_balance: i64,
+    /// The assets burnt by the bundle.
+    pub burn: Vec<BurnJson>,
+    /// The root of the note commitment tree that the spent notes are anchored to.
+    pub anchor: String,
+    /// The proof for the bundle.
+    pub proof: String,
+    /// The binding signature.
+    pub binding_sig: String,
+}
+
+impl<V: Copy + Into<i64>> From<&Bundle<Authorized, V>> for BundleJson {
+    fn from(bundle: &Bundle<Authorized, V>) -> Self {
+        BundleJson {
+            actions: bundle.actions().iter().map(ActionJson::from).collect(),
+            flags: bundle.flags().into(),
+            value_balance: (*bundle.value_balance()).into(),
+            burn: bundle
+                .burn()
+                .iter()
+                .map(|(asset, value)| BurnJson {
+                    asset: hex::encode(asset.to_bytes()),
+                    value: (*value).into(),
+                })
+                .collect(),
+            anchor: hex::encode(bundle.anchor().to_bytes()),
+            proof: hex::encode(bundle.authorization().proof().as_ref()),
+            binding_sig: hex::encode(<[u8; 64]>::from(bundle.authorization().binding_signature())),
+        }
+    }
+}
+
+impl BundleJson {
+    /// Decodes the bundle.
+    pub fn to_bundle(&self) -> Result<Bundle<Authorized, i64>, JsonError> {
+        let actions = self
+            .actions
+            .iter()
+            .map(ActionJson::to_action)
+            .collect::<Result<Vec<_>, _>>()?;
+        let burn = self
+            .burn
+            .iter()
+            .map(|burn| {
+                let asset = Option::from(AssetBase::from_bytes(&decode_hex(
+                    &burn.asset,
+                    "burn asset",
+                )?))
+                .ok_or(JsonError::InvalidEncoding("burn asset"))?;
+                Ok((asset, burn.value))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let anchor = Option::from(Anchor::from_bytes(decode_hex(&self.anchor, "anchor")?))
+            .ok_or(JsonError::InvalidEncoding("anchor"))?;
+        let proof = hex::decode(&self.proof).map_err(|_| JsonError::InvalidHex("proof"))?;
+
+        Ok(Bundle::from_parts(
+            NonEmpty::from_vec(actions).ok_or(JsonError::NoActions)?,
+            (&self.flags).into(),
+            self.value_balance,
+            burn,
+            anchor,
+            Authorized::from_parts(
+                Proof::new(proof),
+                redpallas::Signature::from(decode_hex::<64>(&self.binding_sig, "binding_sig")?),
+            ),
+        ))
+    }
+}
+
+/// The JSON encoding of an issued [`Note`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssueNoteJson {
+    /// The raw encoding of the recipient address.
+    pub recipient: String,
+    /// The issued value.
+    pub value: u64,
+    /// The issued asset.
+    pub asset: String,
+    /// The rho value of the note.
+    pub rho: String,
+    /// The seed randomness of the note.
+    pub rseed: String,
+}
+
+impl From<&Note> for IssueNoteJson {
+    fn from(note: &Note) -> Self {
+        IssueNoteJson {
+            recipient: hex::encode(note.recipient().to_raw_address_bytes()),
+            value: note.value().inner(),
+            asset: hex::encode(note.asset().to_bytes()),
+            rho: hex::encode(note.rho().to_bytes()),
+            rseed: hex::encode(note.rseed().as_bytes()),
+        }
+    }
+}
+
+/// The JSON encoding of an [`IssueAction`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssueActionJson {
+    /// The description of the issued asset.
+    pub asset_desc: String,
+    /// The issued notes.
+    pub notes: Vec<IssueNoteJson>,
+    /// Whether the action finalizes the issuance of the asset.
+    pub finalize: bool,
+}
+
+impl From<&IssueAction> for IssueActionJson {
+    fn from(action: &IssueAction) -> Self {
+        IssueActionJson {
+            asset_desc: action.asset_desc().to_string(),
+            notes: action.notes().iter().map(IssueNoteJson::from).collect(),
+            finalize: action.is_finalized(),
+        }
+    }
+}
+
+/// The JSON encoding of a signed [`IssueBundle`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssueBundleJson {
+    /// The issuance validating key.
+    pub ik: String,
+    /// The issuance actions.
+    pub actions: Vec<IssueActionJson>,
+    /// The issuance authorization signature.
+    pub signature: String,
+}
+
+impl From<&IssueBundle<Signed>> for IssueBundleJson {
+    fn from(bundle: &IssueBundle<Signed>) -> Self {
+        IssueBundleJson {
+            ik: hex::encode(bundle.ik().to_bytes()),
+            actions: bundle.actions().iter().map(IssueActionJson::from).collect(),
+            signature: hex::encode(bundle.authorization().signature().to_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{BundleJson, IssueBundleJson, JsonError};
+    use crate::{bundle::testing::arb_bundle_i64, issuance::testing::arb_signed_issue_bundle};
+
+    proptest! {
+        #[test]
+        fn bundle_json_round_trip(bundle in arb_bundle_i64(3)) {
+            let json = serde_json::to_string(&BundleJson::from(&bundle)).unwrap();
+            let parsed: BundleJson = serde_json::from_str(&json).unwrap();
+            let imported = parsed.to_bundle().unwrap();
+            prop_assert_eq!(BundleJson::from(&imported), parsed);
+
+            let mut invalid = BundleJson::from(&bundle);
+            invalid.anchor.pop();
+            prop_assert_eq!(invalid.to_bundle().err(), Some(JsonError::InvalidHex("anchor")));
+        }
+
+        #[test]
+        fn issue_bundle_json_export(bundle in arb_signed_issue_bundle(2)) {
+            let json = serde_json::to_value(IssueBundleJson::from(&bundle)).unwrap();
+            prop_assert_eq!(json["actions"].as_array().unwrap().len(), 2);
+            prop_assert_eq!(
+                json["ik"].as_str().unwrap(),
+                hex::encode(bundle.ik().to_bytes())
+            );
+        }
+    }
+}
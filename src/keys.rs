@@ -1,8 +1,10 @@
 //! Key structures for Orchard.
 
 use std::{
-    fmt::{Debug, Formatter},
+    collections::BTreeMap,
+    fmt::{self, Debug, Formatter},
     io::{self, Read, Write},
+    iter,
 };
 
 use aes::Aes256;
@@ -28,6 +30,8 @@ use zcash_note_encryption_zsa::EphemeralKeyBytes;
 
 use crate::{
     address::Address,
+    bundle::{Authorization, Bundle},
+    note::{DetectionTag, Note, Nullifier},
     primitives::redpallas::{self, SpendAuth, VerificationKey},
     spec::{
         commit_ivk, diversify_hash, extract_p, ka_orchard, ka_orchard_prepared, prf_nf, to_base,
@@ -35,11 +39,14 @@ use crate::{
         PreparedNonIdentityBase, PreparedNonZeroScalar, PrfExpand,
     },
     zip32::{
-        self, ExtendedSpendingKey, ZIP32_ORCHARD_PERSONALIZATION,
-        ZIP32_ORCHARD_PERSONALIZATION_FOR_ISSUANCE,
+        self, ExtendedSpendingKey, HardenedOnlyKey, IssuanceKeyFingerprint,
+        ZIP32_ORCHARD_PERSONALIZATION, ZIP32_ORCHARD_PERSONALIZATION_FOR_ISSUANCE,
     },
 };
 
+mod encoding;
+pub use encoding::{NetworkType, ViewingKeyEncodingError};
+
 // Preserve '::' which specifies the EXTERNAL 'zip32' crate
 #[rustfmt::skip]
 pub use ::zip32::{AccountId, ChildIndex, DiversifierIndex, Scope};
@@ -126,6 +133,57 @@ impl SpendingKey {
     }
 }
 
+/// Checks that `index` can be used as a hardened ZIP 32 child index.
+#[cfg(feature = "seed-helpers")]
+fn check_hardened_index(index: u32) -> Result<u32, zip32::Error> {
+    if index < (1 << 31) {
+        Ok(index)
+    } else {
+        Err(zip32::Error::InvalidChildIndex(index))
+    }
+}
+
+#[cfg(feature = "seed-helpers")]
+#[cfg_attr(docsrs, doc(cfg(feature = "seed-helpers")))]
+impl SpendingKey {
+    /// Derives the Orchard spending key for `account` from the 64-byte seed obtained from
+    /// a BIP 39 mnemonic phrase, on the network identified by its SLIP 44 `coin_type`.
+    ///
+    /// Unlike [`SpendingKey::from_zip32_seed`], this checks the seed length at compile
+    /// time and accepts the account as a plain integer, returning an error instead of
+    /// panicking if it is not a valid hardened index.
+    pub fn from_bip39_seed(
+        seed: &[u8; 64],
+        coin_type: u32,
+        account: u32,
+    ) -> Result<Self, zip32::Error> {
+        let account =
+            AccountId::try_from(account).map_err(|_| zip32::Error::InvalidChildIndex(account))?;
+        Self::from_zip32_seed(seed, check_hardened_index(coin_type)?, account)
+    }
+}
+
+#[cfg(feature = "seed-helpers")]
+#[cfg_attr(docsrs, doc(cfg(feature = "seed-helpers")))]
+impl IssuanceAuthorizingKey {
+    /// Derives the issuance key for `account` from the 64-byte seed obtained from a BIP 39
+    /// mnemonic phrase, on the network identified by its SLIP 44 `coin_type`.
+    ///
+    /// Returns an error instead of panicking if `coin_type` or `account` is not a valid
+    /// hardened index.
+    pub fn from_bip39_seed(
+        seed: &[u8; 64],
+        coin_type: u32,
+        account: u32,
+    ) -> Result<Self, zip32::Error> {
+        Self::from_zip32_seed(
+            seed,
+            check_hardened_index(coin_type)?,
+            check_hardened_index(account)?,
+        )
+    }
+}
+
 /// A spend authorizing key, used to create spend authorization signatures.
 /// This type enforces that the corresponding public point (ak^ℙ) has ỹ = 0.
 ///
@@ -271,23 +329,28 @@ impl IssuanceAuthorizingKey {
     }
 
     /// Derives the Orchard-ZSA issuance key for the given seed, coin type, and account.
+    ///
+    /// The key is derived along the hardened path `m / 227' / coin_type' / account'`, so
+    /// that an issuer can recover all their issuance keys from a single seed. Use
+    /// [`IssuanceValidatingKey::fingerprint`] to check that the expected key was derived.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the seed is shorter than 32 bytes or longer than 252 bytes.
     pub fn from_zip32_seed(
         seed: &[u8],
         coin_type: u32,
         account: u32,
     ) -> Result<Self, zip32::Error> {
-        // Call zip32 logic
         let path = &[
             ChildIndex::hardened(ZIP32_PURPOSE_FOR_ISSUANCE),
             ChildIndex::hardened(coin_type),
             ChildIndex::hardened(account),
         ];
 
-        // we are reusing zip32 logic for deriving the key, zip32 should be updated as discussed
-        let &isk_bytes =
-            ExtendedSpendingKey::from_path(seed, path, ZIP32_ORCHARD_PERSONALIZATION_FOR_ISSUANCE)?
-                .sk()
-                .to_bytes();
+        let isk_bytes =
+            HardenedOnlyKey::from_path(seed, path, ZIP32_ORCHARD_PERSONALIZATION_FOR_ISSUANCE)
+                .sk_bytes();
 
         IssuanceAuthorizingKey::from_bytes(isk_bytes).ok_or(zip32::Error::InvalidSpendingKey)
     }
@@ -351,6 +414,11 @@ impl IssuanceValidatingKey {
     pub fn verify(&self, msg: &[u8], signature: &Signature) -> Result<(), schnorr::Error> {
         self.0.verify_prehash(msg, signature)
     }
+
+    /// Returns the fingerprint of this issuance validating key.
+    pub fn fingerprint(&self) -> IssuanceKeyFingerprint {
+        self.into()
+    }
 }
 
 /// A key used to derive [`Nullifier`]s from [`Note`]s.
@@ -431,6 +499,52 @@ impl CommitIvkRandomness {
     }
 }
 
+/// The version of the encoding produced by [`FullViewingKey::to_versioned_bytes`].
+pub const FVK_ENCODING_VERSION: u8 = 1;
+
+/// The size of the encoding produced by [`FullViewingKey::to_versioned_bytes`].
+pub const FVK_VERSIONED_SIZE: usize = 1 + 96;
+
+/// An error that can occur when parsing the encoding of a full viewing key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FullViewingKeyError {
+    /// The encoding does not have the expected length.
+    InvalidLength(usize),
+    /// The encoding has an unsupported version.
+    UnsupportedVersion(u8),
+    /// `ak` is not a valid spend validating key.
+    InvalidAk,
+    /// `nk` is not a canonical field element.
+    InvalidNk,
+    /// `rivk` is not a canonical scalar.
+    InvalidRivk,
+    /// The incoming viewing key of one of the scopes is 0 or ⊥.
+    InvalidIvk,
+}
+
+impl fmt::Display for FullViewingKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FullViewingKeyError::InvalidLength(len) => {
+                write!(f, "Invalid full viewing key length {}", len)
+            }
+            FullViewingKeyError::UnsupportedVersion(version) => {
+                write!(
+                    f,
+                    "Unsupported full viewing key encoding version {}",
+                    version
+                )
+            }
+            FullViewingKeyError::InvalidAk => f.write_str("Invalid spend validating key"),
+            FullViewingKeyError::InvalidNk => f.write_str("Invalid nullifier deriving key"),
+            FullViewingKeyError::InvalidRivk => f.write_str("Invalid ivk commitment randomness"),
+            FullViewingKeyError::InvalidIvk => f.write_str("Invalid incoming viewing key"),
+        }
+    }
+}
+
+impl std::error::Error for FullViewingKeyError {}
+
 /// A key that provides the capability to view incoming and outgoing transactions.
 ///
 /// This key is useful anywhere you need to maintain accurate balance, but do not want the
@@ -516,6 +630,23 @@ impl FullViewingKey {
         .address(d)
     }
 
+    /// Returns an iterator over the payment addresses for this key, starting at index
+    /// `start` and ending at the maximum diversifier index.
+    ///
+    /// Every Orchard diversifier yields a valid address, so no index is skipped.
+    pub fn addresses(
+        &self,
+        start: impl Into<DiversifierIndex>,
+        scope: Scope,
+    ) -> impl Iterator<Item = (DiversifierIndex, Address)> {
+        let ivk = self.to_ivk(scope);
+        iter::successors(Some(start.into()), |j| {
+            let mut next = *j;
+            next.increment().ok().map(|()| next)
+        })
+        .map(move |j| (j, ivk.address_at(j)))
+    }
+
     /// Returns the scope of the given address, or `None` if the address is not derived
     /// from this full viewing key.
     pub fn scope_for_address(&self, address: &Address) -> Option<Scope> {
@@ -524,6 +655,15 @@ impl FullViewingKey {
             .find(|scope| self.to_ivk(*scope).diversifier_index(address).is_some())
     }
 
+    /// Returns `true` if `note` was sent to an internal address of this key, which makes
+    /// it the change of a transaction created by the wallet of this key.
+    ///
+    /// See [`Bundle::is_change_output`] to also check that the output was encrypted to
+    /// the internal outgoing viewing key.
+    pub fn is_change(&self, note: &Note) -> bool {
+        self.scope_for_address(&note.recipient()) == Some(Scope::Internal)
+    }
+
     /// Serializes the full viewing key as specified in [Zcash Protocol Spec § 5.6.4.4: Orchard Raw Full Viewing Keys][orchardrawfullviewingkeys]
     ///
     /// [orchardrawfullviewingkeys]: https://zips.z.cash/protocol/protocol.pdf#orchardfullviewingkeyencoding
@@ -561,18 +701,54 @@ impl FullViewingKey {
     ///
     /// [orchardrawfullviewingkeys]: https://zips.z.cash/protocol/protocol.pdf#orchardfullviewingkeyencoding
     pub fn from_bytes(bytes: &[u8; 96]) -> Option<Self> {
-        let ak = SpendValidatingKey::from_bytes(&bytes[..32])?;
-        let nk = NullifierDerivingKey::from_bytes(&bytes[32..64])?;
-        let rivk = CommitIvkRandomness::from_bytes(&bytes[64..])?;
+        Self::try_from_bytes(bytes).ok()
+    }
+
+    /// Parses a full viewing key from its "raw" encoding, returning the reason the
+    /// encoding is invalid on failure.
+    pub fn try_from_bytes(bytes: &[u8; 96]) -> Result<Self, FullViewingKeyError> {
+        let ak =
+            SpendValidatingKey::from_bytes(&bytes[..32]).ok_or(FullViewingKeyError::InvalidAk)?;
+        let nk = NullifierDerivingKey::from_bytes(&bytes[32..64])
+            .ok_or(FullViewingKeyError::InvalidNk)?;
+        let rivk = CommitIvkRandomness::from_bytes(&bytes[64..])
+            .ok_or(FullViewingKeyError::InvalidRivk)?;
 
         let fvk = FullViewingKey { ak, nk, rivk };
 
         // If either ivk is 0 or ⊥, this FVK is invalid.
-        let _: NonZeroPallasBase = Option::from(KeyAgreementPrivateKey::derive_inner(&fvk))?;
-        let _: NonZeroPallasBase =
-            Option::from(KeyAgreementPrivateKey::derive_inner(&fvk.derive_internal()))?;
+        for scoped in [&fvk, &fvk.derive_internal()] {
+            if KeyAgreementPrivateKey::derive_inner(scoped)
+                .is_none()
+                .into()
+            {
+                return Err(FullViewingKeyError::InvalidIvk);
+            }
+        }
+
+        Ok(fvk)
+    }
+
+    /// Serializes the full viewing key with a leading version byte.
+    ///
+    /// The layout is `FVK_ENCODING_VERSION || ak || nk || rivk`, where the last 96 bytes
+    /// are the raw encoding returned by [`FullViewingKey::to_bytes`].
+    pub fn to_versioned_bytes(&self) -> [u8; FVK_VERSIONED_SIZE] {
+        let mut result = [0u8; FVK_VERSIONED_SIZE];
+        result[0] = FVK_ENCODING_VERSION;
+        result[1..].copy_from_slice(&self.to_bytes());
+        result
+    }
 
-        Some(fvk)
+    /// Parses a full viewing key from the encoding produced by
+    /// [`FullViewingKey::to_versioned_bytes`].
+    pub fn from_versioned_bytes(bytes: &[u8]) -> Result<Self, FullViewingKeyError> {
+        let bytes = <&[u8; FVK_VERSIONED_SIZE]>::try_from(bytes)
+            .map_err(|_| FullViewingKeyError::InvalidLength(bytes.len()))?;
+        if bytes[0] != FVK_ENCODING_VERSION {
+            return Err(FullViewingKeyError::UnsupportedVersion(bytes[0]));
+        }
+        Self::try_from_bytes(bytes[1..].try_into().unwrap())
     }
 
     /// Derives an internal full viewing key from a full viewing key, as specified in
@@ -602,6 +778,68 @@ impl FullViewingKey {
             Scope::Internal => OutgoingViewingKey::from_fvk(&self.derive_internal()),
         }
     }
+
+    /// Derives a [`DetectionKey`] for this full viewing key.
+    pub fn to_detection_key(&self) -> DetectionKey {
+        DetectionKey(self.nk)
+    }
+}
+
+/// A key that can detect which actions spend the notes of a wallet, without being able
+/// to decrypt notes or learn their values, assets or memos.
+///
+/// It is intended for outsourcing the detection of spends to a semi-trusted server: the
+/// wallet hands the server its detection key along with the [`DetectionTag`] of each of
+/// its notes, and the server links them to the nullifiers revealed in bundles. Neither
+/// an [`IncomingViewingKey`] nor an [`OutgoingViewingKey`] can be derived from it.
+///
+/// [`DetectionTag`]: crate::note::DetectionTag
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DetectionKey(NullifierDerivingKey);
+
+impl DetectionKey {
+    /// Serializes the detection key to its canonical byte representation.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    /// Parses a detection key from its canonical byte representation.
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        NullifierDerivingKey::from_bytes(bytes).map(DetectionKey)
+    }
+
+    /// Derives the nullifier of the note with the given detection tag.
+    pub fn nullifier(&self, tag: &DetectionTag) -> Nullifier {
+        tag.nullifier(&self.0)
+    }
+
+    /// Detects the actions of `bundle` that spend any of the notes with the given
+    /// detection tags.
+    ///
+    /// Returns the index of each such action along with the index of the tag of the
+    /// note it spends.
+    pub fn detect_spends<T: Authorization, V>(
+        &self,
+        bundle: &Bundle<T, V>,
+        tags: &[DetectionTag],
+    ) -> Vec<(usize, usize)> {
+        let nullifiers: BTreeMap<Nullifier, usize> = tags
+            .iter()
+            .enumerate()
+            .map(|(tag_idx, tag)| (self.nullifier(tag), tag_idx))
+            .collect();
+
+        bundle
+            .actions()
+            .iter()
+            .enumerate()
+            .filter_map(|(action_idx, action)| {
+                nullifiers
+                    .get(action.nullifier())
+                    .map(|tag_idx| (action_idx, *tag_idx))
+            })
+            .collect()
+    }
 }
 
 /// A key that provides the capability to derive a sequence of diversifiers.
@@ -865,6 +1103,74 @@ impl AsRef<[u8; 32]> for OutgoingViewingKey {
     }
 }
 
+/// A set of viewing keys that have been prepared once for scanning many bundles.
+///
+/// Preparing an [`IncomingViewingKey`] for trial decryption is expensive, so wallets that
+/// scan many bundles should construct a `ScanningKeys` once and pass it to
+/// [`Bundle::decrypt_outputs_with_scanning_keys`] and
+/// [`Bundle::recover_outputs_with_scanning_keys`].
+#[derive(Clone, Debug)]
+pub struct ScanningKeys {
+    ivks: Vec<(IncomingViewingKey, Scope, PreparedIncomingViewingKey)>,
+    ovks: Vec<OutgoingViewingKey>,
+}
+
+impl ScanningKeys {
+    /// Prepares the given incoming and outgoing viewing keys for scanning.
+    ///
+    /// The incoming viewing keys are treated as keys of the external scope.
+    pub fn new(ivks: &[IncomingViewingKey], ovks: &[OutgoingViewingKey]) -> Self {
+        let ivks: Vec<_> = ivks
+            .iter()
+            .map(|ivk| (ivk.clone(), Scope::External))
+            .collect();
+        Self::with_scopes(&ivks, ovks)
+    }
+
+    /// Prepares the given incoming viewing keys, along with the scope that each of them
+    /// was derived for, and outgoing viewing keys for scanning.
+    pub fn with_scopes(ivks: &[(IncomingViewingKey, Scope)], ovks: &[OutgoingViewingKey]) -> Self {
+        ScanningKeys {
+            ivks: ivks
+                .iter()
+                .map(|(ivk, scope)| (ivk.clone(), *scope, PreparedIncomingViewingKey::new(ivk)))
+                .collect(),
+            ovks: ovks.to_vec(),
+        }
+    }
+
+    /// Prepares the incoming and outgoing viewing keys of both scopes of each of the
+    /// given full viewing keys for scanning.
+    pub fn from_full_viewing_keys(fvks: &[FullViewingKey]) -> Self {
+        let scoped = || {
+            fvks.iter()
+                .flat_map(|fvk| [Scope::External, Scope::Internal].map(|scope| (fvk, scope)))
+        };
+        let ivks: Vec<_> = scoped()
+            .map(|(fvk, scope)| (fvk.to_ivk(scope), scope))
+            .collect();
+        let ovks: Vec<_> = scoped().map(|(fvk, scope)| fvk.to_ovk(scope)).collect();
+        Self::with_scopes(&ivks, &ovks)
+    }
+
+    /// Returns the incoming viewing keys, in the order they will be tried.
+    pub fn ivks(&self) -> impl Iterator<Item = &IncomingViewingKey> {
+        self.ivks.iter().map(|(ivk, _, _)| ivk)
+    }
+
+    /// Returns the outgoing viewing keys, in the order they will be tried.
+    pub fn ovks(&self) -> &[OutgoingViewingKey] {
+        &self.ovks
+    }
+
+    /// Returns the incoming viewing keys along with their scopes and prepared forms.
+    pub(crate) fn prepared_ivks(
+        &self,
+    ) -> &[(IncomingViewingKey, Scope, PreparedIncomingViewingKey)] {
+        &self.ivks
+    }
+}
+
 /// The diversified transmission key for a given payment address.
 ///
 /// Defined in [Zcash Protocol Spec § 4.2.3: Orchard Key Components][orchardkeycomponents].
@@ -1139,6 +1445,61 @@ mod tests {
         assert!(SpendValidatingKey::from_bytes(&[0; 32]).is_none());
     }
 
+    #[test]
+    fn address_iterator() {
+        let fvk = FullViewingKey::from(&SpendingKey::from_bytes([5; 32]).unwrap());
+        let addresses: Vec<_> = fvk.addresses(7u32, Scope::Internal).take(3).collect();
+        for (offset, (j, address)) in addresses.into_iter().enumerate() {
+            assert_eq!(j, DiversifierIndex::from(7 + offset as u32));
+            assert_eq!(address, fvk.address_at(j, Scope::Internal));
+        }
+
+        let last = DiversifierIndex::from([0xff; 11]);
+        assert_eq!(fvk.addresses(last, Scope::External).count(), 1);
+    }
+
+    #[test]
+    fn fvk_versioned_encoding() {
+        let fvk = FullViewingKey::from(&SpendingKey::from_bytes([7; 32]).unwrap());
+        let bytes = fvk.to_versioned_bytes();
+        assert_eq!(bytes[0], FVK_ENCODING_VERSION);
+        assert_eq!(&bytes[1..], &fvk.to_bytes()[..]);
+        assert_eq!(FullViewingKey::from_versioned_bytes(&bytes), Ok(fvk));
+
+        assert_eq!(
+            FullViewingKey::from_versioned_bytes(&bytes[1..]),
+            Err(FullViewingKeyError::InvalidLength(96))
+        );
+
+        let mut versioned = bytes;
+        versioned[0] = 2;
+        assert_eq!(
+            FullViewingKey::from_versioned_bytes(&versioned),
+            Err(FullViewingKeyError::UnsupportedVersion(2))
+        );
+
+        let mut ak = bytes;
+        ak[1..33].fill(0);
+        assert_eq!(
+            FullViewingKey::from_versioned_bytes(&ak),
+            Err(FullViewingKeyError::InvalidAk)
+        );
+
+        let mut nk = bytes;
+        nk[33..65].fill(0xff);
+        assert_eq!(
+            FullViewingKey::from_versioned_bytes(&nk),
+            Err(FullViewingKeyError::InvalidNk)
+        );
+
+        let mut rivk = bytes;
+        rivk[65..].fill(0xff);
+        assert_eq!(
+            FullViewingKey::from_versioned_bytes(&rivk),
+            Err(FullViewingKeyError::InvalidRivk)
+        );
+    }
+
     #[test]
     fn parsers_reject_invalid() {
         assert!(bool::from(
@@ -1193,6 +1554,24 @@ mod tests {
         }
     }
 
+    proptest! {
+        #[test]
+        fn detection_key_derives_nullifiers(
+            sk in arb_spending_key(),
+            note in crate::note::testing::arb_native_note(),
+        ) {
+            let fvk = FullViewingKey::from(&sk);
+            let dk = fvk.to_detection_key();
+            assert_eq!(DetectionKey::from_bytes(&dk.to_bytes()), Some(dk.clone()));
+
+            let split_note = note.create_split_note(&mut OsRng);
+            for note in [note, split_note] {
+                let tag = DetectionTag::from_bytes(&note.detection_tag().to_bytes()).unwrap();
+                assert_eq!(dk.nullifier(&tag), note.nullifier(&fvk));
+            }
+        }
+    }
+
     #[test]
     fn test_vectors() {
         for tv in crate::test_vectors::keys::test_vectors() {
@@ -1272,4 +1651,43 @@ mod tests {
             assert!(ik.verify(&message, &signature).is_ok());
         }
     }
+
+    #[test]
+    fn issuance_key_zip32_test_vectors() {
+        use crate::test_vectors::issuance_keys::{test_vectors, SEED};
+
+        for tv in test_vectors() {
+            let isk =
+                IssuanceAuthorizingKey::from_zip32_seed(&SEED, tv.coin_type, tv.account).unwrap();
+            assert_eq!(isk.to_bytes(), tv.isk);
+
+            let ik = IssuanceValidatingKey::from(&isk);
+            assert_eq!(ik.to_bytes(), tv.ik);
+            assert_eq!(ik.fingerprint().as_bytes(), &tv.ik_fingerprint);
+            assert_eq!(ik.fingerprint().tag(), tv.ik_fingerprint[..4]);
+        }
+    }
+
+    #[cfg(feature = "seed-helpers")]
+    #[test]
+    fn bip39_seed_helpers() {
+        let seed = [7u8; 64];
+
+        let sk = SpendingKey::from_bip39_seed(&seed, 133, 5).unwrap();
+        let expected = SpendingKey::from_zip32_seed(&seed, 133, 5u32.try_into().unwrap()).unwrap();
+        assert_eq!(sk.to_bytes(), expected.to_bytes());
+
+        let isk = IssuanceAuthorizingKey::from_bip39_seed(&seed, 133, 5).unwrap();
+        let expected = IssuanceAuthorizingKey::from_zip32_seed(&seed, 133, 5).unwrap();
+        assert_eq!(isk.to_bytes(), expected.to_bytes());
+
+        assert_eq!(
+            SpendingKey::from_bip39_seed(&seed, 133, 1 << 31).err(),
+            Some(zip32::Error::InvalidChildIndex(1 << 31))
+        );
+        assert_eq!(
+            IssuanceAuthorizingKey::from_bip39_seed(&seed, 1 << 31, 0).err(),
+            Some(zip32::Error::InvalidChildIndex(1 << 31))
+        );
+    }
 }
@@ -0,0 +1,286 @@
+//! Human-readable encodings of viewing keys and addresses.
+
+use core::fmt;
+
+use bech32::{FromBase32, ToBase32, Variant};
+
+use super::{IncomingViewingKey, OutgoingViewingKey};
+use crate::Address;
+
+/// The network that an encoded key, address or asset identifier is intended for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NetworkType {
+    /// The main network.
+    Main,
+    /// The test network.
+    Test,
+    /// A local regression-testing network.
+    Regtest,
+}
+
+impl NetworkType {
+    pub(crate) const ALL: [NetworkType; 3] =
+        [NetworkType::Main, NetworkType::Test, NetworkType::Regtest];
+
+    /// Returns the human-readable part of the Bech32m encoding of Orchard incoming
+    /// viewing keys for this network.
+    pub fn ivk_hrp(&self) -> &'static str {
+        match self {
+            NetworkType::Main => "zivkorchard",
+            NetworkType::Test => "zivktestorchard",
+            NetworkType::Regtest => "zivkregtestorchard",
+        }
+    }
+
+    /// Returns the human-readable part of the Bech32m encoding of Orchard outgoing
+    /// viewing keys for this network.
+    pub fn ovk_hrp(&self) -> &'static str {
+        match self {
+            NetworkType::Main => "zovkorchard",
+            NetworkType::Test => "zovktestorchard",
+            NetworkType::Regtest => "zovkregtestorchard",
+        }
+    }
+
+    /// Returns the human-readable part of the Bech32m encoding of Orchard payment
+    /// addresses for this network.
+    pub fn address_hrp(&self) -> &'static str {
+        match self {
+            NetworkType::Main => "zo",
+            NetworkType::Test => "ztestorchard",
+            NetworkType::Regtest => "zregtestorchard",
+        }
+    }
+
+    /// Returns the human-readable part of the Bech32m encoding of asset identifiers for
+    /// this network.
+    ///
+    /// The identifiers of the main network use [`ASSET_ID_HRP`].
+    ///
+    /// [`ASSET_ID_HRP`]: crate::note::ASSET_ID_HRP
+    pub fn asset_id_hrp(&self) -> &'static str {
+        match self {
+            NetworkType::Main => "zsa",
+            NetworkType::Test => "zsatest",
+            NetworkType::Regtest => "zsaregtest",
+        }
+    }
+}
+
+/// An error that can occur when parsing the encoding of a viewing key or an address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ViewingKeyEncodingError {
+    /// The string is not a valid Bech32m encoding.
+    InvalidEncoding,
+    /// The encoding is of a value of the expected type, but for another network.
+    WrongNetwork(NetworkType),
+    /// The human-readable part is not that of a value of the expected type.
+    UnknownHrp(String),
+    /// The encoded bytes are not a valid key or address.
+    InvalidKey,
+    /// The string is not the canonical (lowercase) encoding of the value.
+    NonCanonical,
+}
+
+impl fmt::Display for ViewingKeyEncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViewingKeyEncodingError::InvalidEncoding => f.write_str("Invalid Bech32m encoding"),
+            ViewingKeyEncodingError::WrongNetwork(network) => {
+                write!(f, "Value is encoded for the {:?} network", network)
+            }
+            ViewingKeyEncodingError::UnknownHrp(hrp) => {
+                write!(f, "Unknown human-readable part {}", hrp)
+            }
+            ViewingKeyEncodingError::InvalidKey => f.write_str("Invalid key"),
+            ViewingKeyEncodingError::NonCanonical => f.write_str("Non-canonical encoding"),
+        }
+    }
+}
+
+impl std::error::Error for ViewingKeyEncodingError {}
+
+fn encode(hrp: &str, bytes: &[u8]) -> String {
+    bech32::encode(hrp, bytes.to_base32(), Variant::Bech32m)
+        .expect("the human-readable part is valid")
+}
+
+/// Decodes the payload of a Bech32m string with the human-readable part `hrp(network)`.
+fn decode<const N: usize>(
+    network: NetworkType,
+    hrp: fn(&NetworkType) -> &'static str,
+    s: &str,
+) -> Result<[u8; N], ViewingKeyEncodingError> {
+    let (found, data, variant) =
+        bech32::decode(s).map_err(|_| ViewingKeyEncodingError::InvalidEncoding)?;
+    if variant != Variant::Bech32m {
+        return Err(ViewingKeyEncodingError::InvalidEncoding);
+    }
+    if found != hrp(&network) {
+        return Err(
+            match NetworkType::ALL.into_iter().find(|n| hrp(n) == found) {
+                Some(other) => ViewingKeyEncodingError::WrongNetwork(other),
+                None => ViewingKeyEncodingError::UnknownHrp(found),
+            },
+        );
+    }
+    Vec::<u8>::from_base32(&data)
+        .map_err(|_| ViewingKeyEncodingError::InvalidEncoding)?
+        .try_into()
+        .map_err(|_| ViewingKeyEncodingError::InvalidKey)
+}
+
+impl IncomingViewingKey {
+    /// Encodes this key as a Bech32m string for the given network.
+    pub fn encode(&self, network: NetworkType) -> String {
+        encode(network.ivk_hrp(), &self.to_bytes())
+    }
+
+    /// Parses an incoming viewing key from its Bech32m encoding for the given network.
+    ///
+    /// Keys encoded for other networks are rejected, as are encodings other than the
+    /// one produced by [`IncomingViewingKey::encode`].
+    pub fn decode(network: NetworkType, s: &str) -> Result<Self, ViewingKeyEncodingError> {
+        let bytes = decode::<64>(network, NetworkType::ivk_hrp, s)?;
+        let ivk = Option::<IncomingViewingKey>::from(IncomingViewingKey::from_bytes(&bytes))
+            .ok_or(ViewingKeyEncodingError::InvalidKey)?;
+        if ivk.encode(network) != s {
+            return Err(ViewingKeyEncodingError::NonCanonical);
+        }
+        Ok(ivk)
+    }
+}
+
+impl OutgoingViewingKey {
+    /// Encodes this key as a Bech32m string for the given network.
+    pub fn encode(&self, network: NetworkType) -> String {
+        encode(network.ovk_hrp(), self.as_ref())
+    }
+
+    /// Parses an outgoing viewing key from its Bech32m encoding for the given network.
+    ///
+    /// Keys encoded for other networks are rejected, as are encodings other than the
+    /// one produced by [`OutgoingViewingKey::encode`].
+    pub fn decode(network: NetworkType, s: &str) -> Result<Self, ViewingKeyEncodingError> {
+        let ovk = OutgoingViewingKey::from(decode::<32>(network, NetworkType::ovk_hrp, s)?);
+        if ovk.encode(network) != s {
+            return Err(ViewingKeyEncodingError::NonCanonical);
+        }
+        Ok(ovk)
+    }
+}
+
+impl Address {
+    /// Encodes this address as a Bech32m string of its raw encoding for the given
+    /// network.
+    pub fn encode(&self, network: NetworkType) -> String {
+        encode(network.address_hrp(), &self.to_raw_address_bytes())
+    }
+
+    /// Parses an address from its Bech32m encoding for the given network.
+    ///
+    /// Addresses encoded for other networks are rejected, as are encodings other than the
+    /// one produced by [`Address::encode`].
+    pub fn decode(network: NetworkType, s: &str) -> Result<Self, ViewingKeyEncodingError> {
+        let bytes = decode::<43>(network, NetworkType::address_hrp, s)?;
+        let address = Option::<Address>::from(Address::from_raw_address_bytes(&bytes))
+            .ok_or(ViewingKeyEncodingError::InvalidKey)?;
+        if address.encode(network) != s {
+            return Err(ViewingKeyEncodingError::NonCanonical);
+        }
+        Ok(address)
+    }
+
+    /// Parses an address from its Bech32m encoding for any network, and returns the
+    /// network that it is encoded for.
+    pub fn decode_any(s: &str) -> Result<(NetworkType, Self), ViewingKeyEncodingError> {
+        match Address::decode(NetworkType::Main, s) {
+            Err(ViewingKeyEncodingError::WrongNetwork(network)) => {
+                Address::decode(network, s).map(|address| (network, address))
+            }
+            result => result.map(|address| (NetworkType::Main, address)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NetworkType, ViewingKeyEncodingError};
+    use crate::{
+        keys::{FullViewingKey, IncomingViewingKey, OutgoingViewingKey, Scope, SpendingKey},
+        Address,
+    };
+
+    #[test]
+    fn viewing_key_encodings() {
+        let fvk = FullViewingKey::from(&SpendingKey::from_bytes([3; 32]).unwrap());
+        let ivk = fvk.to_ivk(Scope::External);
+        let ovk = fvk.to_ovk(Scope::External);
+
+        for network in [NetworkType::Main, NetworkType::Test, NetworkType::Regtest] {
+            let encoded = ivk.encode(network);
+            assert!(encoded.starts_with(network.ivk_hrp()));
+            assert_eq!(
+                IncomingViewingKey::decode(network, &encoded),
+                Ok(ivk.clone())
+            );
+
+            let encoded = ovk.encode(network);
+            assert!(encoded.starts_with(network.ovk_hrp()));
+            assert_eq!(
+                OutgoingViewingKey::decode(network, &encoded).map(|ovk| *ovk.as_ref()),
+                Ok(*ovk.as_ref())
+            );
+        }
+
+        let test_ivk = ivk.encode(NetworkType::Test);
+        assert_eq!(
+            IncomingViewingKey::decode(NetworkType::Main, &test_ivk),
+            Err(ViewingKeyEncodingError::WrongNetwork(NetworkType::Test))
+        );
+        assert_eq!(
+            IncomingViewingKey::decode(NetworkType::Test, &test_ivk.to_uppercase()),
+            Err(ViewingKeyEncodingError::NonCanonical)
+        );
+
+        let main_ovk = ovk.encode(NetworkType::Main);
+        assert_eq!(
+            IncomingViewingKey::decode(NetworkType::Main, &main_ovk),
+            Err(ViewingKeyEncodingError::UnknownHrp(
+                "zovkorchard".to_owned()
+            ))
+        );
+
+        let mut corrupted = main_ovk.into_bytes();
+        let last = corrupted.last_mut().unwrap();
+        *last = if *last == b'q' { b'p' } else { b'q' };
+        assert_eq!(
+            OutgoingViewingKey::decode(NetworkType::Main, &String::from_utf8(corrupted).unwrap()),
+            Err(ViewingKeyEncodingError::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn address_encodings() {
+        let fvk = FullViewingKey::from(&SpendingKey::from_bytes([5; 32]).unwrap());
+        let address = fvk.address_at(0u32, Scope::External);
+
+        for network in [NetworkType::Main, NetworkType::Test, NetworkType::Regtest] {
+            let encoded = address.encode(network);
+            assert!(encoded.starts_with(network.address_hrp()));
+            assert_eq!(Address::decode(network, &encoded), Ok(address));
+            assert_eq!(Address::decode_any(&encoded), Ok((network, address)));
+        }
+
+        assert_eq!(
+            Address::decode(NetworkType::Main, &address.encode(NetworkType::Regtest)),
+            Err(ViewingKeyEncodingError::WrongNetwork(NetworkType::Regtest))
+        );
+        assert_eq!(
+            Address::decode_any(&fvk.to_ivk(Scope::External).encode(NetworkType::Main)),
+            Err(ViewingKeyEncodingError::UnknownHrp(
+                "zivkorchard".to_owned()
+            ))
+        );
+    }
+}
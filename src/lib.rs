@@ -16,31 +16,97 @@
 #![deny(missing_docs)]
 #![deny(unsafe_code)]
 
+use core::fmt;
+
+use memuse::DynamicUsage;
+
 mod action;
 mod address;
+#[cfg(feature = "borsh")]
+mod borsh_impls;
+#[cfg(feature = "circuit")]
+#[cfg_attr(docsrs, doc(cfg(feature = "circuit")))]
 pub mod builder;
 pub mod bundle;
+#[cfg(feature = "circuit")]
+#[cfg_attr(docsrs, doc(cfg(feature = "circuit")))]
 pub mod circuit;
+pub mod compat;
 mod constants;
 pub mod issuance;
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub mod json;
 pub mod keys;
+#[cfg(feature = "memo-bundle")]
+#[cfg_attr(docsrs, doc(cfg(feature = "memo-bundle")))]
+pub mod memo_bundle;
 pub mod note;
 pub mod supply_info;
 // pub mod note_encryption; // disabled until backward compatability is implemented.
 pub mod note_encryption_v3;
 pub mod primitives;
 mod spec;
+#[cfg(feature = "circuit")]
+#[cfg_attr(docsrs, doc(cfg(feature = "circuit")))]
+pub mod swap_bundle;
 pub mod tree;
 pub mod value;
+pub mod verification;
+#[cfg(feature = "zebra")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zebra")))]
+pub mod zebra;
 pub mod zip32;
 
-#[cfg(test)]
-mod test_vectors;
+#[cfg(any(test, feature = "test-vectors"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-vectors")))]
+pub mod test_vectors;
 
-pub use action::Action;
+pub use action::{Action, ActionError};
 pub use address::Address;
 pub use bundle::Bundle;
-pub use circuit::Proof;
 pub use constants::MERKLE_DEPTH_ORCHARD as NOTE_COMMITMENT_TREE_DEPTH;
 pub use note::Note;
 pub use tree::Anchor;
+
+/// A proof of the validity of an Orchard [`Bundle`].
+///
+/// [`Bundle`]: crate::bundle::Bundle
+#[derive(Clone)]
+pub struct Proof(Vec<u8>);
+
+impl fmt::Debug for Proof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.debug_tuple("Proof").field(&self.0).finish()
+        } else {
+            // By default, only show the proof length, not its contents.
+            f.debug_tuple("Proof")
+                .field(&format_args!("{} bytes", self.0.len()))
+                .finish()
+        }
+    }
+}
+
+impl AsRef<[u8]> for Proof {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl DynamicUsage for Proof {
+    fn dynamic_usage(&self) -> usize {
+        self.0.dynamic_usage()
+    }
+
+    fn dynamic_usage_bounds(&self) -> (usize, Option<usize>) {
+        self.0.dynamic_usage_bounds()
+    }
+}
+
+impl Proof {
+    /// Constructs a new Proof value.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Proof(bytes)
+    }
+}
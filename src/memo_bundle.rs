@@ -0,0 +1,181 @@
+//! Prototype of memos of arbitrary length, encrypted separately from their notes.
+//!
+//! The note plaintext of an Orchard action has room for a memo of exactly 512 bytes. A
+//! longer memo can instead be encrypted into a [`DetachedMemo`], which is carried
+//! alongside the bundle, and referenced from the memo field of the note plaintext by a
+//! [`MemoReference`]. The reference holds the key of the detached memo, so only the
+//! recipient and the holder of the outgoing viewing key of the note can decrypt it.
+//!
+//! This is an experimental format, and is not part of any consensus rules.
+
+use core::fmt;
+
+use blake2b_simd::Params;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Nonce,
+};
+use rand::{CryptoRng, RngCore};
+
+const MEMO_KDF_PERSONALIZATION: &[u8; 16] = b"Zcash_OrchMemoKD";
+
+/// The first byte of a memo field holding a [`MemoReference`].
+///
+/// ZIP 302 reserves memos whose first byte is between `0xF7` and `0xFE` for future use.
+pub const MEMO_REFERENCE_TAG: u8 = 0xF7;
+
+/// A reference to a [`DetachedMemo`], encoded into the memo field of a note plaintext.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoReference {
+    key: [u8; 32],
+    len: u32,
+}
+
+impl MemoReference {
+    /// Returns the length of the detached memo that this reference points to.
+    pub fn memo_len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Encodes this reference as a memo field.
+    ///
+    /// The encoding is [`MEMO_REFERENCE_TAG`], followed by the key of the memo and the
+    /// length of the memo as a little-endian 32-bit integer, padded with zeroes.
+    pub fn to_memo(&self) -> [u8; 512] {
+        let mut memo = [0; 512];
+        memo[0] = MEMO_REFERENCE_TAG;
+        memo[1..33].copy_from_slice(&self.key);
+        memo[33..37].copy_from_slice(&self.len.to_le_bytes());
+        memo
+    }
+
+    /// Parses a reference from a memo field, returning `None` if the memo does not hold
+    /// a reference.
+    pub fn from_memo(memo: &[u8; 512]) -> Option<Self> {
+        if memo[0] != MEMO_REFERENCE_TAG || memo[37..].iter().any(|b| *b != 0) {
+            return None;
+        }
+        Some(MemoReference {
+            key: memo[1..33].try_into().unwrap(),
+            len: u32::from_le_bytes(memo[33..37].try_into().unwrap()),
+        })
+    }
+}
+
+/// A memo of arbitrary length, encrypted under the key held by its [`MemoReference`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DetachedMemo {
+    ciphertext: Vec<u8>,
+}
+
+impl DetachedMemo {
+    /// Encrypts `memo` under a fresh key, returning the encrypted memo and the reference
+    /// to it, which is to be used as the memo of the output note.
+    ///
+    /// Returns `None` if the memo is longer than `u32::MAX` bytes.
+    pub fn encrypt(
+        memo: &[u8],
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Option<(Self, MemoReference)> {
+        let len = u32::try_from(memo.len()).ok()?;
+        let mut key = [0; 32];
+        rng.fill_bytes(&mut key);
+
+        let ciphertext = cipher(&key)
+            .encrypt(&Nonce::default(), memo)
+            .expect("memo is not too long");
+        Some((DetachedMemo { ciphertext }, MemoReference { key, len }))
+    }
+
+    /// Parses an encrypted memo from its byte encoding.
+    pub fn from_bytes(ciphertext: Vec<u8>) -> Self {
+        DetachedMemo { ciphertext }
+    }
+
+    /// Returns the byte encoding of this memo.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.ciphertext
+    }
+
+    /// Decrypts this memo with the key held by `reference`.
+    pub fn decrypt(&self, reference: &MemoReference) -> Result<Vec<u8>, DetachedMemoError> {
+        let memo = cipher(&reference.key)
+            .decrypt(&Nonce::default(), &self.ciphertext[..])
+            .map_err(|_| DetachedMemoError::Decryption)?;
+        if memo.len() != reference.memo_len() {
+            return Err(DetachedMemoError::LengthMismatch);
+        }
+        Ok(memo)
+    }
+
+    /// Decrypts this memo with the reference held by the memo field of a decrypted
+    /// note.
+    pub fn decrypt_with_memo_field(&self, memo: &[u8; 512]) -> Result<Vec<u8>, DetachedMemoError> {
+        let reference = MemoReference::from_memo(memo).ok_or(DetachedMemoError::NotAReference)?;
+        self.decrypt(&reference)
+    }
+}
+
+/// Derives the cipher for the detached memo with the given key.
+///
+/// As the key is freshly generated for each memo, the memo can be encrypted with the
+/// all-zero nonce.
+fn cipher(key: &[u8; 32]) -> ChaCha20Poly1305 {
+    let encryption_key = Params::new()
+        .hash_length(32)
+        .personal(MEMO_KDF_PERSONALIZATION)
+        .hash(key);
+    ChaCha20Poly1305::new_from_slice(encryption_key.as_bytes()).unwrap()
+}
+
+/// An error that can occur when decrypting a [`DetachedMemo`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DetachedMemoError {
+    /// The memo field of the note does not hold a [`MemoReference`].
+    NotAReference,
+    /// The memo was not encrypted with the key of the reference.
+    Decryption,
+    /// The decrypted memo does not have the length given by the reference.
+    LengthMismatch,
+}
+
+impl fmt::Display for DetachedMemoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetachedMemoError::NotAReference => {
+                f.write_str("The memo field does not reference a detached memo")
+            }
+            DetachedMemoError::Decryption => f.write_str("Failed to decrypt the detached memo"),
+            DetachedMemoError::LengthMismatch => {
+                f.write_str("The detached memo does not have the referenced length")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DetachedMemoError {}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::{DetachedMemo, DetachedMemoError, MemoReference};
+
+    #[test]
+    fn detached_memo_round_trip() {
+        let memo = vec![7; 2000];
+        let (detached, reference) = DetachedMemo::encrypt(&memo, OsRng).unwrap();
+        assert_eq!(reference.memo_len(), 2000);
+
+        let memo_field = reference.to_memo();
+        assert_eq!(MemoReference::from_memo(&memo_field), Some(reference));
+        assert_eq!(detached.decrypt_with_memo_field(&memo_field), Ok(memo));
+
+        let (_, other) = DetachedMemo::encrypt(b"other", OsRng).unwrap();
+        assert_eq!(detached.decrypt(&other), Err(DetachedMemoError::Decryption));
+        assert_eq!(
+            detached.decrypt_with_memo_field(&[0xF6; 512]),
+            Err(DetachedMemoError::NotAReference)
+        );
+    }
+}
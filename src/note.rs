@@ -9,7 +9,7 @@ use rand::RngCore;
 use subtle::{Choice, ConditionallySelectable, CtOption};
 
 use crate::{
-    keys::{EphemeralSecretKey, FullViewingKey, Scope, SpendingKey},
+    keys::{EphemeralSecretKey, FullViewingKey, NullifierDerivingKey, Scope, SpendingKey},
     spec::{to_base, to_scalar, NonZeroPallasScalar, PrfExpand},
     value::NoteValue,
     Address,
@@ -19,7 +19,7 @@ pub(crate) mod commitment;
 pub use self::commitment::{ExtractedNoteCommitment, NoteCommitment};
 
 pub(crate) mod nullifier;
-pub use self::nullifier::Nullifier;
+pub use self::nullifier::{Nullifier, NullifierIndex, NullifierSet};
 
 /// The randomness used to construct a note.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -59,8 +59,13 @@ impl Rho {
     }
 }
 
+mod storage;
+pub use self::storage::{NoteStorageError, NOTE_STORAGE_SIZE, NOTE_STORAGE_VERSION};
+
 pub(crate) mod asset_base;
-pub use self::asset_base::AssetBase;
+pub use self::asset_base::{
+    asset_digest, encode_asset_id, AssetBase, AssetDescHasher, AssetIdError, ASSET_ID_HRP,
+};
 
 /// The ZIP 212 seed randomness for a note.
 #[derive(Copy, Clone, Debug)]
@@ -300,6 +305,17 @@ impl Note {
         self.commitment_inner().unwrap()
     }
 
+    /// Returns `true` if `cmx` is the commitment to this note.
+    ///
+    /// The proof of a bundle only shows that the note commitment of each action commits to
+    /// a valid note, not that the ciphertext of the action encrypts that note. A note
+    /// obtained from a ciphertext by other means than the decryption functions of this
+    /// crate, which perform this check, must be checked against the note commitment of
+    /// its action before it is trusted, as only the committed note can be spent.
+    pub fn verify_commitment(&self, cmx: &ExtractedNoteCommitment) -> bool {
+        ExtractedNoteCommitment::from(self.commitment()) == *cmx
+    }
+
     /// Derives the commitment to this note.
     ///
     /// This is the internal fallible API, used to check at construction time that the
@@ -336,6 +352,21 @@ impl Note {
         )
     }
 
+    /// Returns the [`DetectionTag`] of this note, which allows the holder of a
+    /// [`DetectionKey`] to detect when this note is spent.
+    ///
+    /// [`DetectionKey`]: crate::keys::DetectionKey
+    pub fn detection_tag(&self) -> DetectionTag {
+        let selected_rseed = self.rseed_split_note.unwrap_or(self.rseed);
+
+        DetectionTag {
+            rho: self.rho,
+            psi: selected_rseed.psi(&self.rho),
+            cm: self.commitment(),
+            is_split_note: self.rseed_split_note.is_some().into(),
+        }
+    }
+
     /// Create a split note which has the same values than the input note except for
     /// `rseed_split_note` which is equal to a random seed.
     pub fn create_split_note(self, rng: &mut impl RngCore) -> Self {
@@ -346,6 +377,62 @@ impl Note {
     }
 }
 
+/// The public parts of a note that, combined with a [`DetectionKey`], allow its nullifier
+/// to be derived.
+///
+/// A detection tag does not reveal the value, asset or recipient of the note: these are
+/// hidden by the note commitment.
+///
+/// [`DetectionKey`]: crate::keys::DetectionKey
+#[derive(Clone, Debug)]
+pub struct DetectionTag {
+    rho: Rho,
+    psi: pallas::Base,
+    cm: NoteCommitment,
+    is_split_note: bool,
+}
+
+impl DetectionTag {
+    /// Derives the nullifier of the tagged note with the given nullifier deriving key.
+    pub(crate) fn nullifier(&self, nk: &NullifierDerivingKey) -> Nullifier {
+        Nullifier::derive(
+            nk,
+            self.rho.0,
+            self.psi,
+            self.cm.clone(),
+            Choice::from(u8::from(self.is_split_note)),
+        )
+    }
+
+    /// Serializes the detection tag to its canonical byte representation.
+    pub fn to_bytes(&self) -> [u8; 97] {
+        let mut bytes = [0u8; 97];
+        bytes[0..32].copy_from_slice(&self.rho.to_bytes());
+        bytes[32..64].copy_from_slice(&self.psi.to_repr());
+        bytes[64..96].copy_from_slice(&self.cm.0.to_bytes());
+        bytes[96] = u8::from(self.is_split_note);
+        bytes
+    }
+
+    /// Parses a detection tag from its canonical byte representation.
+    pub fn from_bytes(bytes: &[u8; 97]) -> Option<Self> {
+        let rho = Option::from(Rho::from_bytes(bytes[0..32].try_into().unwrap()))?;
+        let psi = Option::from(pallas::Base::from_repr(bytes[32..64].try_into().unwrap()))?;
+        let cm = Option::from(pallas::Point::from_bytes(bytes[64..96].try_into().unwrap()))?;
+        let is_split_note = match bytes[96] {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        Some(DetectionTag {
+            rho,
+            psi,
+            cm: NoteCommitment(cm),
+            is_split_note,
+        })
+    }
+}
+
 /// An encrypted note.
 #[derive(Clone)]
 pub struct TransmittedNoteCiphertext {
@@ -358,6 +445,14 @@ pub struct TransmittedNoteCiphertext {
     pub out_ciphertext: [u8; 80],
 }
 
+impl TransmittedNoteCiphertext {
+    /// Returns references to the ephemeral key, the encrypted note ciphertext and the
+    /// outgoing ciphertext, in that order, without copying them.
+    pub fn as_parts(&self) -> (&[u8; 32], &[u8; 612], &[u8; 80]) {
+        (&self.epk_bytes, &self.enc_ciphertext, &self.out_ciphertext)
+    }
+}
+
 impl fmt::Debug for TransmittedNoteCiphertext {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("TransmittedNoteCiphertext")
@@ -430,6 +525,24 @@ pub mod testing {
         }
     }
 
+    prop_compose! {
+        /// Generate an arbitrary zsa note with the given value
+        pub fn arb_zsa_note_with_value(asset: AssetBase, value: NoteValue)(
+            recipient in arb_address(),
+            rho in arb_nullifier().prop_map(Rho::from_nf_old),
+            rseed in arb_rseed(),
+        ) -> Note {
+            Note {
+                recipient,
+                value,
+                asset,
+                rho,
+                rseed,
+                rseed_split_note: CtOption::new(rseed, 0u8.into()),
+            }
+        }
+    }
+
     prop_compose! {
         /// Generate an arbitrary zsa note
         pub fn arb_zsa_note(asset: AssetBase)(
@@ -1,15 +1,20 @@
-use blake2b_simd::{Hash as Blake2bHash, Params};
+use core::cmp::Ordering;
+use core::fmt;
+
+use bech32::{FromBase32, ToBase32, Variant};
+use blake2b_simd::{Hash as Blake2bHash, Params, State as Blake2bState};
 use group::{Group, GroupEncoding};
-use halo2_proofs::arithmetic::CurveExt;
+use pasta_curves::arithmetic::CurveExt;
 use pasta_curves::pallas;
 use std::hash::{Hash, Hasher};
+use std::io;
 
 use subtle::{Choice, ConstantTimeEq, CtOption};
 
 use crate::constants::fixed_bases::{
     NATIVE_ASSET_BASE_V_BYTES, VALUE_COMMITMENT_PERSONALIZATION, ZSA_ASSET_BASE_PERSONALIZATION,
 };
-use crate::keys::{IssuanceAuthorizingKey, IssuanceValidatingKey};
+use crate::keys::{IssuanceAuthorizingKey, IssuanceValidatingKey, NetworkType};
 
 /// Note type identifier.
 #[derive(Clone, Copy, Debug, Eq)]
@@ -17,9 +22,54 @@ pub struct AssetBase(pallas::Point);
 
 pub const MAX_ASSET_DESCRIPTION_SIZE: usize = 512;
 
+/// The human-readable part of the encoding of asset identifiers on the main network.
+pub const ASSET_ID_HRP: &str = "zsa";
+
+/// An error that can occur when parsing an asset identifier string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AssetIdError {
+    /// The string is not a valid Bech32m encoding.
+    InvalidEncoding,
+    /// The string is the identifier of an asset on another network.
+    WrongNetwork(NetworkType),
+    /// The string does not have the human-readable part of asset identifiers.
+    WrongHrp(String),
+    /// The encoded bytes are not the canonical encoding of a valid asset base.
+    InvalidAssetBase,
+    /// The string is not in canonical (lowercase) form.
+    NonCanonical,
+}
+
+impl fmt::Display for AssetIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetIdError::InvalidEncoding => f.write_str("Invalid Bech32m encoding"),
+            AssetIdError::WrongNetwork(network) => {
+                write!(f, "Asset identifier for the {:?} network", network)
+            }
+            AssetIdError::WrongHrp(hrp) => write!(f, "Unexpected human-readable part {}", hrp),
+            AssetIdError::InvalidAssetBase => f.write_str("Invalid asset base"),
+            AssetIdError::NonCanonical => f.write_str("Non-canonical asset identifier"),
+        }
+    }
+}
+
+impl std::error::Error for AssetIdError {}
+
 /// Personalization for the ZSA asset digest generator
 pub const ZSA_ASSET_DIGEST_PERSONALIZATION: &[u8; 16] = b"ZSA-Asset-Digest";
 
+/// Encodes the identifier of the asset issued by `ik` with description `asset_desc`.
+///
+/// `EncodeAssetId(ik, asset_desc) = version_byte || ik || asset_desc`
+///
+/// This is the first step of the derivation of an [`AssetBase`], followed by
+/// [`asset_digest`] and [`AssetBase::from_asset_digest`].
+pub fn encode_asset_id(ik: &IssuanceValidatingKey, asset_desc: &str) -> Vec<u8> {
+    let version_byte = [0x00];
+    [&version_byte[..], &ik.to_bytes(), asset_desc.as_bytes()].concat()
+}
+
 ///    AssetDigest for the ZSA asset
 ///
 ///    Defined in [Transfer and Burn of Zcash Shielded Assets][AssetDigest].
@@ -34,6 +84,72 @@ pub fn asset_digest(asset_id: Vec<u8>) -> Blake2bHash {
         .finalize()
 }
 
+/// An incremental hasher deriving an [`AssetBase`] from an asset description provided
+/// in chunks, for example while reading it from a file.
+///
+/// It also implements [`io::Write`], so that the description can be copied into it
+/// with [`io::copy`].
+#[derive(Clone, Debug)]
+pub struct AssetDescHasher {
+    state: Blake2bState,
+    len: usize,
+}
+
+impl AssetDescHasher {
+    /// Starts hashing the description of an asset issued by `ik`.
+    pub fn new(ik: &IssuanceValidatingKey) -> Self {
+        let mut state = Params::new()
+            .hash_length(64)
+            .personal(ZSA_ASSET_DIGEST_PERSONALIZATION)
+            .to_state();
+        state.update(&encode_asset_id(ik, ""));
+        AssetDescHasher { state, len: 0 }
+    }
+
+    /// Adds a chunk of the asset description to the hash.
+    pub fn update(&mut self, data: &[u8]) -> &mut Self {
+        self.state.update(data);
+        self.len += data.len();
+        self
+    }
+
+    /// Completes the derivation of the asset base.
+    ///
+    /// Returns `None` if the description is empty or longer than
+    /// [`MAX_ASSET_DESCRIPTION_SIZE`] bytes, or if the derived asset base is the
+    /// identity point.
+    pub fn finalize(&self) -> Option<AssetBase> {
+        if self.len == 0 || self.len > MAX_ASSET_DESCRIPTION_SIZE {
+            return None;
+        }
+        let asset_digest = self.state.finalize();
+        AssetBase::from_asset_digest(
+            asset_digest
+                .as_bytes()
+                .try_into()
+                .expect("the asset digest is 64 bytes"),
+        )
+        .into()
+    }
+}
+
+impl io::Write for AssetDescHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.len + buf.len() > MAX_ASSET_DESCRIPTION_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "asset description is too long",
+            ));
+        }
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 impl AssetBase {
     /// Deserialize the asset_id from a byte array.
     pub fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self> {
@@ -45,6 +161,83 @@ impl AssetBase {
         self.0.to_bytes()
     }
 
+    /// Encodes the asset base as a Bech32m string with the [`ASSET_ID_HRP`]
+    /// human-readable part, for display to users.
+    ///
+    /// This is the identifier of the asset on the main network, as encoded by
+    /// [`AssetBase::to_network_asset_id_string`].
+    pub fn to_asset_id_string(&self) -> String {
+        self.to_network_asset_id_string(NetworkType::Main)
+    }
+
+    /// Parses an asset base from its Bech32m encoding on the main network.
+    ///
+    /// See [`AssetBase::from_network_asset_id_string`].
+    pub fn from_asset_id_string(s: &str) -> Result<Self, AssetIdError> {
+        Self::from_network_asset_id_string(NetworkType::Main, s)
+    }
+
+    /// Encodes the asset base as a Bech32m string with the human-readable part of asset
+    /// identifiers on `network`, for display to users.
+    pub fn to_network_asset_id_string(&self, network: NetworkType) -> String {
+        bech32::encode(
+            network.asset_id_hrp(),
+            self.to_bytes().to_base32(),
+            Variant::Bech32m,
+        )
+        .expect("the human-readable part is valid")
+    }
+
+    /// Parses an asset base from its Bech32m encoding on `network`.
+    ///
+    /// Only the canonical encoding produced by [`AssetBase::to_network_asset_id_string`]
+    /// is accepted: the string must be lowercase, and must encode the canonical bytes of
+    /// an asset base other than the identity. Identifiers of other networks are rejected.
+    pub fn from_network_asset_id_string(
+        network: NetworkType,
+        s: &str,
+    ) -> Result<Self, AssetIdError> {
+        let (hrp, data, variant) = bech32::decode(s).map_err(|_| AssetIdError::InvalidEncoding)?;
+        if variant != Variant::Bech32m {
+            return Err(AssetIdError::InvalidEncoding);
+        }
+        if hrp != network.asset_id_hrp() {
+            return Err(
+                match NetworkType::ALL
+                    .into_iter()
+                    .find(|n| n.asset_id_hrp() == hrp)
+                {
+                    Some(other) => AssetIdError::WrongNetwork(other),
+                    None => AssetIdError::WrongHrp(hrp),
+                },
+            );
+        }
+        let bytes: [u8; 32] = Vec::<u8>::from_base32(&data)
+            .map_err(|_| AssetIdError::InvalidEncoding)?
+            .try_into()
+            .map_err(|_| AssetIdError::InvalidAssetBase)?;
+        let asset = Option::<AssetBase>::from(AssetBase::from_bytes(&bytes))
+            .filter(|asset| asset.to_bytes() == bytes && !bool::from(asset.0.is_identity()))
+            .ok_or(AssetIdError::InvalidAssetBase)?;
+
+        if asset.to_network_asset_id_string(network) != s {
+            return Err(AssetIdError::NonCanonical);
+        }
+        Ok(asset)
+    }
+
+    /// Maps an asset digest, as computed by [`asset_digest`], to the corresponding
+    /// asset base.
+    ///
+    /// `AssetBase = ZSAValueBase(AssetDigest)`
+    ///
+    /// Returns `None` if the result is the identity point, which happens with negligible
+    /// probability.
+    pub fn from_asset_digest(asset_digest: &[u8; 64]) -> CtOption<Self> {
+        let asset_base = pallas::Point::hash_to_curve(ZSA_ASSET_BASE_PERSONALIZATION)(asset_digest);
+        CtOption::new(AssetBase(asset_base), !asset_base.is_identity())
+    }
+
     /// Note type derivation$.
     ///
     /// Defined in [Transfer and Burn of Zcash Shielded Assets][AssetBase].
@@ -61,23 +254,16 @@ impl AssetBase {
             "The asset_desc string is not of valid size"
         );
 
-        // EncodeAssetId(ik, asset_desc) = version_byte || ik || asset_desc
-        let version_byte = [0x00];
-        let encode_asset_id = [&version_byte[..], &ik.to_bytes(), asset_desc.as_bytes()].concat();
-
-        let asset_digest = asset_digest(encode_asset_id);
-
-        let asset_base =
-            pallas::Point::hash_to_curve(ZSA_ASSET_BASE_PERSONALIZATION)(asset_digest.as_bytes());
+        let asset_digest = asset_digest(encode_asset_id(ik, asset_desc));
 
         // this will happen with negligible probability.
-        assert!(
-            bool::from(!asset_base.is_identity()),
-            "The Asset Base is the identity point, which is invalid."
-        );
-
-        // AssetBase = ZSAValueBase(AssetDigest)
-        AssetBase(asset_base)
+        AssetBase::from_asset_digest(
+            asset_digest
+                .as_bytes()
+                .try_into()
+                .expect("the asset digest is 64 bytes"),
+        )
+        .expect("The Asset Base is the identity point, which is invalid.")
     }
 
     /// Note type for the "native" currency (zec), maintains backward compatibility with Orchard untyped notes.
@@ -108,10 +294,15 @@ impl AssetBase {
     }
 }
 
+/// Hashes the canonical encoding of the asset base, as returned by
+/// [`AssetBase::to_bytes`].
+///
+/// Equal asset bases have the same encoding, so this is consistent with [`PartialEq`],
+/// and the input to the hasher does not depend on the internal representation of the
+/// point.
 impl Hash for AssetBase {
     fn hash<H: Hasher>(&self, h: &mut H) {
         h.write(&self.to_bytes());
-        h.finish();
     }
 }
 
@@ -120,9 +311,33 @@ pub fn is_asset_desc_of_valid_size(asset_desc: &str) -> bool {
     !asset_desc.is_empty() && asset_desc.bytes().len() <= MAX_ASSET_DESCRIPTION_SIZE
 }
 
+impl ConstantTimeEq for AssetBase {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
 impl PartialEq for AssetBase {
     fn eq(&self, other: &Self) -> bool {
-        bool::from(self.0.ct_eq(&other.0))
+        self.ct_eq(other).into()
+    }
+}
+
+/// Orders asset bases by their canonical encodings, as returned by
+/// [`AssetBase::to_bytes`], compared lexicographically.
+///
+/// This order is stable across platforms and versions of this crate, so it can be used
+/// for sorted structures whose contents are committed to. Each comparison encodes both
+/// asset bases, so sorting many assets is faster with their encodings as keys.
+impl Ord for AssetBase {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_bytes().cmp(&other.to_bytes())
+    }
+}
+
+impl PartialOrd for AssetBase {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
@@ -195,4 +410,146 @@ pub mod testing {
             assert_eq!(calculated_asset_base, test_vector_asset_base);
         }
     }
+
+    #[test]
+    fn test_vectors_incremental() {
+        let test_vectors = crate::test_vectors::asset_base::test_vectors();
+
+        for tv in test_vectors {
+            let ik = IssuanceValidatingKey::from_bytes(&tv.key).unwrap();
+
+            let mut hasher = super::AssetDescHasher::new(&ik);
+            // Empty descriptions are rejected.
+            assert!(hasher.finalize().is_none());
+            for chunk in tv.description.chunks(100) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize().unwrap().to_bytes(), tv.asset_base);
+
+            let mut writer = super::AssetDescHasher::new(&ik);
+            std::io::copy(&mut &tv.description[..], &mut writer).unwrap();
+            assert_eq!(writer.finalize().unwrap().to_bytes(), tv.asset_base);
+
+            // Descriptions longer than the maximum size are rejected.
+            assert!(std::io::Write::write_all(&mut writer, b"x").is_err());
+            assert!(hasher.update(b"x").finalize().is_none());
+        }
+    }
+
+    #[test]
+    fn test_vectors_step_by_step() {
+        let test_vectors = crate::test_vectors::asset_base::test_vectors();
+
+        for tv in test_vectors {
+            let ik = IssuanceValidatingKey::from_bytes(&tv.key).unwrap();
+            let description = std::str::from_utf8(&tv.description).unwrap();
+
+            let encoded = super::encode_asset_id(&ik, description);
+            assert_eq!(encoded[0], 0x00);
+            assert_eq!(&encoded[1..33], &tv.key[..]);
+            assert_eq!(&encoded[33..], &tv.description[..]);
+
+            let digest = super::asset_digest(encoded);
+            let asset_base =
+                AssetBase::from_asset_digest(digest.as_bytes().try_into().unwrap()).unwrap();
+
+            assert_eq!(asset_base.to_bytes(), tv.asset_base);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use core::cmp::Ordering;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    use subtle::ConstantTimeEq;
+
+    use super::{testing::arb_asset_base, AssetBase, AssetIdError};
+    use crate::keys::NetworkType;
+
+    proptest! {
+        #[test]
+        fn asset_id_string_round_trip(asset in arb_asset_base()) {
+            let encoded = asset.to_asset_id_string();
+            prop_assert!(encoded.starts_with("zsa1"));
+            prop_assert_eq!(AssetBase::from_asset_id_string(&encoded), Ok(asset));
+            prop_assert_eq!(
+                AssetBase::from_asset_id_string(&encoded.to_uppercase()),
+                Err(AssetIdError::NonCanonical)
+            );
+
+            let encoded = asset.to_network_asset_id_string(NetworkType::Test);
+            prop_assert!(encoded.starts_with("zsatest1"));
+            prop_assert_eq!(
+                AssetBase::from_network_asset_id_string(NetworkType::Test, &encoded),
+                Ok(asset)
+            );
+            prop_assert_eq!(
+                AssetBase::from_asset_id_string(&encoded),
+                Err(AssetIdError::WrongNetwork(NetworkType::Test))
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn asset_base_order_and_hash(a in arb_asset_base(), b in arb_asset_base()) {
+            prop_assert_eq!(a.cmp(&b), a.to_bytes().cmp(&b.to_bytes()));
+            prop_assert_eq!(a == b, bool::from(a.ct_eq(&b)));
+            prop_assert_eq!(a.cmp(&b) == Ordering::Equal, a == b);
+
+            let hash = |asset: &AssetBase| {
+                let mut hasher = DefaultHasher::new();
+                asset.hash(&mut hasher);
+                hasher.finish()
+            };
+            prop_assert_eq!(hash(&a), hash(&AssetBase::from_bytes(&a.to_bytes()).unwrap()));
+        }
+    }
+
+    #[test]
+    fn asset_id_string_rejects_invalid_inputs() {
+        let encoded = AssetBase::native().to_asset_id_string();
+
+        // Corrupted checksum.
+        let mut corrupted = encoded.clone();
+        let last = if corrupted.pop() == Some('q') {
+            'p'
+        } else {
+            'q'
+        };
+        corrupted.push(last);
+        assert_eq!(
+            AssetBase::from_asset_id_string(&corrupted),
+            Err(AssetIdError::InvalidEncoding)
+        );
+
+        // Wrong human-readable part.
+        let wrong_hrp = bech32::encode(
+            "zec",
+            bech32::ToBase32::to_base32(&AssetBase::native().to_bytes()),
+            bech32::Variant::Bech32m,
+        )
+        .unwrap();
+        assert_eq!(
+            AssetBase::from_asset_id_string(&wrong_hrp),
+            Err(AssetIdError::WrongHrp("zec".to_string()))
+        );
+
+        // The identity is not a valid asset base.
+        let identity = bech32::encode(
+            "zsa",
+            bech32::ToBase32::to_base32(&[0u8; 32]),
+            bech32::Variant::Bech32m,
+        )
+        .unwrap();
+        assert_eq!(
+            AssetBase::from_asset_id_string(&identity),
+            Err(AssetIdError::InvalidAssetBase)
+        );
+    }
 }
@@ -1,13 +1,16 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
 use group::{ff::PrimeField, Group};
-use halo2_proofs::arithmetic::CurveExt;
 use memuse::DynamicUsage;
-use pasta_curves::pallas;
+use pasta_curves::{arithmetic::CurveExt, pallas};
 use rand::RngCore;
 use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
 
-use super::NoteCommitment;
+use super::{Note, NoteCommitment};
 use crate::{
-    keys::NullifierDerivingKey,
+    bundle::Bundle,
+    keys::{FullViewingKey, NullifierDerivingKey},
     spec::{extract_p, mod_r_p},
 };
 
@@ -76,6 +79,189 @@ impl ConstantTimeEq for Nullifier {
     }
 }
 
+/// A map from the nullifiers of the notes of a wallet to identifiers of the notes.
+///
+/// This is used with [`Bundle::spent_nullifiers_matching`] to detect which of the notes
+/// of a wallet are spent by a bundle.
+///
+/// [`Bundle::spent_nullifiers_matching`]: crate::Bundle::spent_nullifiers_matching
+#[derive(Clone, Debug)]
+pub struct NullifierIndex<Id> {
+    notes: BTreeMap<Nullifier, Id>,
+}
+
+impl<Id> Default for NullifierIndex<Id> {
+    fn default() -> Self {
+        NullifierIndex {
+            notes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<Id> NullifierIndex<Id> {
+    /// Constructs an empty index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the nullifier of a note to the index, returning the identifier previously
+    /// associated with the nullifier, if any.
+    pub fn insert(&mut self, nf: Nullifier, id: Id) -> Option<Id> {
+        self.notes.insert(nf, id)
+    }
+
+    /// Removes a nullifier from the index, returning its identifier if it was present.
+    pub fn remove(&mut self, nf: &Nullifier) -> Option<Id> {
+        self.notes.remove(nf)
+    }
+
+    /// Returns the identifier of the note with the given nullifier, if it is indexed.
+    pub fn get(&self, nf: &Nullifier) -> Option<&Id> {
+        self.notes.get(nf)
+    }
+
+    /// Returns the number of indexed nullifiers.
+    pub fn len(&self) -> usize {
+        self.notes.len()
+    }
+
+    /// Returns `true` if the index contains no nullifiers.
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+    }
+
+    /// Returns `true` if any action of `bundle` spends a note in the index.
+    pub fn contains_any<A, V>(&self, bundle: &Bundle<A, V>) -> bool {
+        bundle
+            .actions()
+            .iter()
+            .any(|action| self.notes.contains_key(action.nullifier()))
+    }
+}
+
+impl<Id> FromIterator<(Nullifier, Id)> for NullifierIndex<Id> {
+    fn from_iter<I: IntoIterator<Item = (Nullifier, Id)>>(iter: I) -> Self {
+        NullifierIndex {
+            notes: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<Id> Extend<(Nullifier, Id)> for NullifierIndex<Id> {
+    fn extend<I: IntoIterator<Item = (Nullifier, Id)>>(&mut self, iter: I) {
+        self.notes.extend(iter)
+    }
+}
+
+/// A set of nullifiers, stored as a sorted array.
+///
+/// This is intended for checking whether the bundles of a block spend any of a large
+/// number of tracked notes. Unlike [`NullifierIndex`], it does not associate
+/// identifiers with the nullifiers, and it takes 32 bytes per nullifier. Adding
+/// nullifiers to the set re-sorts it, so it is best constructed in a single batch.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NullifierSet {
+    nullifiers: Vec<Nullifier>,
+}
+
+impl NullifierSet {
+    /// Constructs an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Constructs the set of the nullifiers of the given notes, which are all spendable
+    /// by `fvk`.
+    pub fn from_notes<'a>(notes: impl IntoIterator<Item = &'a Note>, fvk: &FullViewingKey) -> Self {
+        notes.into_iter().map(|note| note.nullifier(fvk)).collect()
+    }
+
+    /// Returns `true` if the set contains `nf`.
+    pub fn contains(&self, nf: &Nullifier) -> bool {
+        self.nullifiers.binary_search(nf).is_ok()
+    }
+
+    /// Returns `true` if any action of `bundle` spends a note whose nullifier is in the
+    /// set.
+    pub fn contains_any<A, V>(&self, bundle: &Bundle<A, V>) -> bool {
+        bundle
+            .actions()
+            .iter()
+            .any(|action| self.contains(action.nullifier()))
+    }
+
+    /// Returns the number of nullifiers in the set.
+    pub fn len(&self) -> usize {
+        self.nullifiers.len()
+    }
+
+    /// Returns `true` if the set contains no nullifiers.
+    pub fn is_empty(&self) -> bool {
+        self.nullifiers.is_empty()
+    }
+
+    /// Returns the nullifiers in the set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = &Nullifier> {
+        self.nullifiers.iter()
+    }
+
+    /// Serializes the set as the number of nullifiers, as a little-endian 32-bit
+    /// integer, followed by the nullifiers in ascending order.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let len = u32::try_from(self.nullifiers.len()).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Too many nullifiers in set")
+        })?;
+        writer.write_all(&len.to_le_bytes())?;
+        self.nullifiers
+            .iter()
+            .try_for_each(|nf| writer.write_all(&nf.to_bytes()))
+    }
+
+    /// Parses a set serialized with [`NullifierSet::write`].
+    ///
+    /// Returns an error if a nullifier is not canonically encoded, or if the nullifiers
+    /// are not in strictly ascending order.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut len = [0; 4];
+        reader.read_exact(&mut len)?;
+        let len = u32::from_le_bytes(len) as usize;
+
+        let mut nullifiers: Vec<Nullifier> = Vec::new();
+        for _ in 0..len {
+            let mut bytes = [0; 32];
+            reader.read_exact(&mut bytes)?;
+            let nf = Option::from(Nullifier::from_bytes(&bytes)).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "Invalid nullifier encoding")
+            })?;
+            if nullifiers.last().map_or(false, |last| *last >= nf) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Nullifiers are not in ascending order",
+                ));
+            }
+            nullifiers.push(nf);
+        }
+        Ok(NullifierSet { nullifiers })
+    }
+}
+
+impl FromIterator<Nullifier> for NullifierSet {
+    fn from_iter<I: IntoIterator<Item = Nullifier>>(iter: I) -> Self {
+        let mut nullifiers: Vec<_> = iter.into_iter().collect();
+        nullifiers.sort_unstable();
+        nullifiers.dedup();
+        NullifierSet { nullifiers }
+    }
+}
+
+impl Extend<Nullifier> for NullifierSet {
+    fn extend<I: IntoIterator<Item = Nullifier>>(&mut self, iter: I) {
+        self.nullifiers.extend(iter);
+        self.nullifiers.sort_unstable();
+        self.nullifiers.dedup();
+    }
+}
+
 /// Generators for property testing.
 #[cfg(any(test, feature = "test-dependencies"))]
 #[cfg_attr(docsrs, doc(cfg(feature = "test-dependencies")))]
@@ -98,3 +284,63 @@ pub mod testing {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{testing::arb_nullifier, NullifierIndex, NullifierSet};
+    use crate::bundle::testing::arb_bundle;
+
+    proptest! {
+        #[test]
+        fn nullifier_set_contains_any(
+            bundle in arb_bundle(3),
+            others in prop::collection::vec(arb_nullifier(), 0..10),
+        ) {
+            let spent = *bundle.actions().first().nullifier();
+
+            let set: NullifierSet = others.iter().copied().collect();
+            let index: NullifierIndex<()> = others.iter().map(|nf| (*nf, ())).collect();
+            prop_assert_eq!(
+                set.contains_any(&bundle),
+                bundle.actions().iter().any(|action| others.contains(action.nullifier()))
+            );
+            prop_assert_eq!(set.contains_any(&bundle), index.contains_any(&bundle));
+
+            let mut set = set;
+            set.extend([spent]);
+            prop_assert!(set.contains(&spent));
+            prop_assert!(set.contains_any(&bundle));
+            prop_assert!(set.iter().zip(set.iter().skip(1)).all(|(a, b)| a < b));
+
+            let mut bytes = vec![];
+            set.write(&mut bytes).unwrap();
+            prop_assert_eq!(bytes.len(), 4 + 32 * set.len());
+            prop_assert_eq!(NullifierSet::read(&bytes[..]).unwrap(), set);
+        }
+
+        #[test]
+        fn spent_nullifiers_are_matched(bundle in arb_bundle(3), unspent in arb_nullifier()) {
+            prop_assume!(bundle.actions().iter().all(|action| action.nullifier() != &unspent));
+
+            let index: NullifierIndex<&str> = [
+                (*bundle.actions()[1].nullifier(), "spent"),
+                (unspent, "unspent"),
+            ]
+            .into_iter()
+            .collect();
+            prop_assert_eq!(bundle.spent_nullifiers_matching(&index), vec![(1, &"spent")]);
+            prop_assert!(bundle
+                .spent_nullifiers_matching(&NullifierIndex::<()>::new())
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn nullifier_set_rejects_unsorted_encoding() {
+        let mut bytes = 2u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[0; 64]);
+        assert!(NullifierSet::read(&bytes[..]).is_err());
+    }
+}
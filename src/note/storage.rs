@@ -0,0 +1,186 @@
+//! Encoding of received notes for storage in wallet databases.
+
+use core::fmt;
+
+use subtle::CtOption;
+
+use super::{AssetBase, ExtractedNoteCommitment, Note, RandomSeed, Rho};
+use crate::{value::NoteValue, Address};
+
+/// The version of the storage encoding produced by [`Note::to_storage_bytes`].
+pub const NOTE_STORAGE_VERSION: u8 = 1;
+
+/// The size of the storage encoding of a note.
+///
+/// `version || recipient || value || asset || rho || rseed || split_flag ||
+/// rseed_split_note || cmx`
+pub const NOTE_STORAGE_SIZE: usize = 1 + 43 + 8 + 32 + 32 + 32 + 1 + 32 + 32;
+
+/// An error that can occur when parsing the storage encoding of a note.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteStorageError {
+    /// The encoding has an unsupported version.
+    UnsupportedVersion(u8),
+    /// The recipient is not a valid Orchard address.
+    InvalidRecipient,
+    /// The asset is not a valid asset base.
+    InvalidAsset,
+    /// The rho value is not a canonical field element.
+    InvalidRho,
+    /// A random seed is not valid for the rho value of the note.
+    InvalidRandomSeed,
+    /// The split note flag is not 0 or 1, or the split note seed of a note that is not
+    /// a split note is not zero.
+    InvalidSplitNote,
+    /// The fields of the note do not have a valid note commitment.
+    InvalidNote,
+    /// The note commitment of the fields does not match the stored one.
+    CommitmentMismatch,
+}
+
+impl fmt::Display for NoteStorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NoteStorageError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported note encoding version {}", version)
+            }
+            NoteStorageError::InvalidRecipient => f.write_str("Invalid recipient"),
+            NoteStorageError::InvalidAsset => f.write_str("Invalid asset"),
+            NoteStorageError::InvalidRho => f.write_str("Invalid rho"),
+            NoteStorageError::InvalidRandomSeed => f.write_str("Invalid random seed"),
+            NoteStorageError::InvalidSplitNote => f.write_str("Invalid split note fields"),
+            NoteStorageError::InvalidNote => f.write_str("Note has no valid commitment"),
+            NoteStorageError::CommitmentMismatch => {
+                f.write_str("Note commitment does not match the stored one")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NoteStorageError {}
+
+impl Note {
+    /// Serializes this note for storage in a wallet database.
+    ///
+    /// The encoding is versioned, and includes the extracted note commitment of the note
+    /// so that its integrity is checked by [`Note::from_storage_bytes`].
+    pub fn to_storage_bytes(&self) -> [u8; NOTE_STORAGE_SIZE] {
+        let mut bytes = [0u8; NOTE_STORAGE_SIZE];
+        bytes[0] = NOTE_STORAGE_VERSION;
+        bytes[1..44].copy_from_slice(&self.recipient.to_raw_address_bytes());
+        bytes[44..52].copy_from_slice(&self.value.to_bytes());
+        bytes[52..84].copy_from_slice(&self.asset.to_bytes());
+        bytes[84..116].copy_from_slice(&self.rho.to_bytes());
+        bytes[116..148].copy_from_slice(self.rseed.as_bytes());
+        if let Some(rseed_split_note) = Option::<RandomSeed>::from(self.rseed_split_note) {
+            bytes[148] = 1;
+            bytes[149..181].copy_from_slice(rseed_split_note.as_bytes());
+        }
+        bytes[181..213]
+            .copy_from_slice(&ExtractedNoteCommitment::from(self.commitment()).to_bytes());
+        bytes
+    }
+
+    /// Parses a note from its storage encoding, as produced by
+    /// [`Note::to_storage_bytes`].
+    ///
+    /// Each field is validated, and the note commitment of the parsed note must match the
+    /// stored one.
+    pub fn from_storage_bytes(bytes: &[u8; NOTE_STORAGE_SIZE]) -> Result<Self, NoteStorageError> {
+        if bytes[0] != NOTE_STORAGE_VERSION {
+            return Err(NoteStorageError::UnsupportedVersion(bytes[0]));
+        }
+
+        let recipient: Address = Option::from(Address::from_raw_address_bytes(
+            bytes[1..44].try_into().unwrap(),
+        ))
+        .ok_or(NoteStorageError::InvalidRecipient)?;
+        let value = NoteValue::from_bytes(bytes[44..52].try_into().unwrap());
+        let asset: AssetBase =
+            Option::from(AssetBase::from_bytes(bytes[52..84].try_into().unwrap()))
+                .ok_or(NoteStorageError::InvalidAsset)?;
+        let rho: Rho = Option::from(Rho::from_bytes(bytes[84..116].try_into().unwrap()))
+            .ok_or(NoteStorageError::InvalidRho)?;
+        let rseed: RandomSeed = Option::from(RandomSeed::from_bytes(
+            bytes[116..148].try_into().unwrap(),
+            &rho,
+        ))
+        .ok_or(NoteStorageError::InvalidRandomSeed)?;
+        let rseed_split_note = match bytes[148] {
+            0 if bytes[149..181].iter().all(|b| *b == 0) => CtOption::new(rseed, 0u8.into()),
+            1 => {
+                let rseed_split_note: RandomSeed = Option::from(RandomSeed::from_bytes(
+                    bytes[149..181].try_into().unwrap(),
+                    &rho,
+                ))
+                .ok_or(NoteStorageError::InvalidRandomSeed)?;
+                CtOption::new(rseed_split_note, 1u8.into())
+            }
+            _ => return Err(NoteStorageError::InvalidSplitNote),
+        };
+
+        let note = Note {
+            recipient,
+            value,
+            asset,
+            rho,
+            rseed,
+            rseed_split_note,
+        };
+        let cm: super::NoteCommitment =
+            Option::from(note.commitment_inner()).ok_or(NoteStorageError::InvalidNote)?;
+        if ExtractedNoteCommitment::from(cm).to_bytes() != bytes[181..213] {
+            return Err(NoteStorageError::CommitmentMismatch);
+        }
+
+        Ok(note)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use rand::rngs::OsRng;
+
+    use super::NoteStorageError;
+    use crate::{
+        note::{testing::arb_note, Note},
+        value::testing::arb_note_value,
+    };
+
+    proptest! {
+        #[test]
+        fn note_storage_round_trip(
+            note in arb_note_value().prop_flat_map(arb_note),
+            split in prop::bool::ANY,
+        ) {
+            let note = if split { note.create_split_note(&mut OsRng) } else { note };
+            let bytes = note.to_storage_bytes();
+            let parsed = Note::from_storage_bytes(&bytes).unwrap();
+            prop_assert_eq!(parsed, note);
+            prop_assert_eq!(parsed.to_storage_bytes(), bytes);
+
+            let mut versioned = bytes;
+            versioned[0] = 2;
+            prop_assert_eq!(
+                Note::from_storage_bytes(&versioned),
+                Err(NoteStorageError::UnsupportedVersion(2))
+            );
+
+            // Changing the value leaves a valid note, but breaks the commitment.
+            let mut tampered = bytes;
+            tampered[44] ^= 1;
+            prop_assert_eq!(
+                Note::from_storage_bytes(&tampered),
+                Err(NoteStorageError::CommitmentMismatch)
+            );
+
+            let mut flagged = bytes;
+            flagged[148] = 2;
+            prop_assert_eq!(
+                Note::from_storage_bytes(&flagged),
+                Err(NoteStorageError::InvalidSplitNote)
+            );
+        }
+    }
+}
@@ -1,21 +1,29 @@
 //! In-band secret distribution for Orchard bundles.
 
 use blake2b_simd::{Hash, Params};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Nonce,
+};
 use core::fmt;
 use group::ff::PrimeField;
+use subtle::ConstantTimeEq;
 use zcash_note_encryption_zsa::{
-    BatchDomain, Domain, EphemeralKeyBytes, OutPlaintextBytes, OutgoingCipherKey, ShieldedOutput,
-    AEAD_TAG_SIZE, MEMO_SIZE, OUT_PLAINTEXT_SIZE,
+    BatchDomain, Domain, EphemeralKeyBytes, OutPlaintextBytes, ShieldedOutput, AEAD_TAG_SIZE,
+    MEMO_SIZE, OUT_PLAINTEXT_SIZE,
 };
 
+pub use zcash_note_encryption_zsa::OutgoingCipherKey;
+
 use crate::note::AssetBase;
 use crate::{
-    action::Action,
+    action::{Action, ActionError, OUT_CIPHERTEXT_SIZE},
     keys::{
         DiversifiedTransmissionKey, Diversifier, EphemeralPublicKey, EphemeralSecretKey,
         OutgoingViewingKey, PreparedEphemeralPublicKey, PreparedIncomingViewingKey, SharedSecret,
     },
-    note::{ExtractedNoteCommitment, Nullifier, RandomSeed, Rho},
+    note::{ExtractedNoteCommitment, Nullifier, RandomSeed, Rho, TransmittedNoteCiphertext},
+    primitives::redpallas::{self, SpendAuth},
     value::{NoteValue, ValueCommitment},
     Address, Note,
 };
@@ -117,10 +125,16 @@ impl AsRef<[u8]> for CompactNoteCiphertextBytes {
     }
 }
 
+/// Derives the outgoing cipher key of an output, with which its `out_ciphertext` can be
+/// decrypted.
+///
+/// The key is specific to a single output, so it can be given to a third party to
+/// reveal that output without revealing `ovk`.
+///
 /// Defined in [Zcash Protocol Spec § 5.4.2: Pseudo Random Functions][concreteprfs].
 ///
 /// [concreteprfs]: https://zips.z.cash/protocol/nu5.pdf#concreteprfs
-pub(crate) fn prf_ock_orchard(
+pub fn prf_ock_orchard(
     ovk: &OutgoingViewingKey,
     cv: &ValueCommitment,
     cmx_bytes: &[u8; 32],
@@ -177,7 +191,7 @@ where
             let bytes = plaintext.0[COMPACT_NOTE_SIZE_V2..COMPACT_NOTE_SIZE_V3]
                 .try_into()
                 .unwrap();
-            AssetBase::from_bytes(bytes).unwrap()
+            Option::from(AssetBase::from_bytes(bytes))?
         }
         _ => panic!("invalid note version"),
     };
@@ -397,17 +411,14 @@ impl fmt::Debug for CompactAction {
     }
 }
 
-impl<T> From<&Action<T>> for CompactAction
-where
-    Action<T>: ShieldedOutput<OrchardDomainV3>,
-{
+impl<T> From<&Action<T>> for CompactAction {
     fn from(action: &Action<T>) -> Self {
         CompactAction {
             nullifier: *action.nullifier(),
             cmx: *action.cmx(),
-            ephemeral_key: action.ephemeral_key(),
+            ephemeral_key: EphemeralKeyBytes(*action.epk_bytes()),
             enc_ciphertext: CompactNoteCiphertextBytes(
-                action.encrypted_note().enc_ciphertext[..COMPACT_NOTE_SIZE_V3]
+                action.enc_ciphertext_bytes()[..COMPACT_NOTE_SIZE_V3]
                     .try_into()
                     .unwrap(),
             ),
@@ -415,6 +426,44 @@ where
     }
 }
 
+impl<T> Action<T> {
+    /// Returns the compact form of this action, as served to light clients.
+    pub fn to_compact(&self) -> CompactAction {
+        CompactAction::from(self)
+    }
+}
+
+/// An error returned when reconstructing an [`Action`] from a [`CompactAction`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompactActionError {
+    /// A part of the action is invalid: only [`ActionError::EncCiphertextSize`] and
+    /// [`ActionError::OutCiphertextSize`] are returned.
+    Action(ActionError),
+    /// The compact part of the encrypted note ciphertext differs from the ciphertext of
+    /// the compact action.
+    CiphertextMismatch,
+}
+
+impl fmt::Display for CompactActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompactActionError::Action(e) => e.fmt(f),
+            CompactActionError::CiphertextMismatch => {
+                f.write_str("Encrypted note ciphertext does not match the compact action")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompactActionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CompactActionError::Action(e) => Some(e),
+            CompactActionError::CiphertextMismatch => None,
+        }
+    }
+}
+
 impl ShieldedOutput<OrchardDomainV3> for CompactAction {
     fn ephemeral_key(&self) -> EphemeralKeyBytes {
         EphemeralKeyBytes(self.ephemeral_key.0)
@@ -458,36 +507,240 @@ impl CompactAction {
     pub fn rho(&self) -> Rho {
         Rho::from_nf_old(self.nullifier)
     }
+
+    /// Reconstructs the full action from this compact action and the parts of the
+    /// action that it omits.
+    ///
+    /// `enc_ciphertext` is the full encrypted note ciphertext of the action, which must
+    /// start with the compact ciphertext of this compact action.
+    pub fn to_action<T>(
+        &self,
+        rk: redpallas::VerificationKey<SpendAuth>,
+        cv_net: ValueCommitment,
+        enc_ciphertext: &[u8],
+        out_ciphertext: &[u8],
+        authorization: T,
+    ) -> Result<Action<T>, CompactActionError> {
+        let enc_ciphertext: [u8; ENC_CIPHERTEXT_SIZE_V3] =
+            enc_ciphertext.try_into().map_err(|_| {
+                CompactActionError::Action(ActionError::EncCiphertextSize(enc_ciphertext.len()))
+            })?;
+        let out_ciphertext: [u8; OUT_CIPHERTEXT_SIZE] =
+            out_ciphertext.try_into().map_err(|_| {
+                CompactActionError::Action(ActionError::OutCiphertextSize(out_ciphertext.len()))
+            })?;
+        if enc_ciphertext[..COMPACT_NOTE_SIZE_V3] != self.enc_ciphertext.0[..] {
+            return Err(CompactActionError::CiphertextMismatch);
+        }
+
+        Ok(Action::from_parts(
+            self.nullifier,
+            rk,
+            self.cmx,
+            TransmittedNoteCiphertext {
+                epk_bytes: self.ephemeral_key.0,
+                enc_ciphertext,
+                out_ciphertext,
+            },
+            cv_net,
+            authorization,
+        ))
+    }
+}
+
+/// The reason for which the trial decryption of an output failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteDecryptionError {
+    /// The ephemeral key of the output is not a valid encoding of a curve point.
+    InvalidEphemeralKey,
+    /// The output does not carry its full note ciphertext.
+    MissingCiphertext,
+    /// The note ciphertext could not be authenticated. This is the expected error for
+    /// outputs that were not sent to the key, but also occurs if the ciphertext is
+    /// malformed.
+    NotForThisKey,
+    /// The note plaintext has an unsupported version.
+    UnsupportedVersion(u8),
+    /// The note plaintext does not hold a valid encoding of an asset.
+    InvalidAsset,
+    /// The note plaintext does not describe a valid note.
+    InvalidNote,
+    /// The decrypted note does not match the note commitment of the output.
+    CommitmentMismatch,
+    /// The ephemeral key of the output was not derived from the decrypted note, as
+    /// required by [ZIP 212].
+    ///
+    /// [ZIP 212]: https://zips.z.cash/zip-0212
+    EphemeralKeyMismatch,
+}
+
+impl fmt::Display for NoteDecryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NoteDecryptionError::InvalidEphemeralKey => f.write_str("Invalid ephemeral key"),
+            NoteDecryptionError::MissingCiphertext => {
+                f.write_str("The output does not have a full note ciphertext")
+            }
+            NoteDecryptionError::NotForThisKey => {
+                f.write_str("The note ciphertext could not be decrypted with this key")
+            }
+            NoteDecryptionError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported note plaintext version {:#04x}", version)
+            }
+            NoteDecryptionError::InvalidAsset => {
+                f.write_str("The note plaintext has an invalid asset")
+            }
+            NoteDecryptionError::InvalidNote => f.write_str("The note plaintext is invalid"),
+            NoteDecryptionError::CommitmentMismatch => {
+                f.write_str("The decrypted note does not match the note commitment")
+            }
+            NoteDecryptionError::EphemeralKeyMismatch => {
+                f.write_str("The ephemeral key was not derived from the decrypted note")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NoteDecryptionError {}
+
+/// Trial-decrypts `output` as in [`try_note_decryption`], returning the reason for which
+/// decryption failed.
+///
+/// This is slower than [`try_note_decryption`], and is intended for diagnosing outputs
+/// that a wallet expected to be able to decrypt, rather than for scanning. Both reject
+/// notes whose ephemeral key was not derived from their seed randomness, as required by
+/// [ZIP 212].
+///
+/// [`try_note_decryption`]: zcash_note_encryption_zsa::try_note_decryption
+/// [ZIP 212]: https://zips.z.cash/zip-0212
+pub fn try_note_decryption_detailed<Output: ShieldedOutput<OrchardDomainV3>>(
+    domain: &OrchardDomainV3,
+    ivk: &PreparedIncomingViewingKey,
+    output: &Output,
+) -> Result<(Note, Address, [u8; MEMO_SIZE]), NoteDecryptionError> {
+    let ephemeral_key = output.ephemeral_key();
+    let epk =
+        OrchardDomainV3::epk(&ephemeral_key).ok_or(NoteDecryptionError::InvalidEphemeralKey)?;
+    let shared_secret = OrchardDomainV3::ka_agree_dec(ivk, &OrchardDomainV3::prepare_epk(epk));
+    let key = OrchardDomainV3::kdf(shared_secret, &ephemeral_key);
+
+    let enc_ciphertext = output
+        .enc_ciphertext()
+        .ok_or(NoteDecryptionError::MissingCiphertext)?;
+    let plaintext = ChaCha20Poly1305::new_from_slice(key.as_bytes())
+        .unwrap()
+        .decrypt(&Nonce::default(), enc_ciphertext.as_ref())
+        .map_err(|_| NoteDecryptionError::NotForThisKey)?;
+
+    let asset = match note_version(&plaintext) {
+        Some(0x02) => AssetBase::native(),
+        Some(_) => Option::from(AssetBase::from_bytes(
+            plaintext[COMPACT_NOTE_SIZE_V2..COMPACT_NOTE_SIZE_V3]
+                .try_into()
+                .unwrap(),
+        ))
+        .ok_or(NoteDecryptionError::InvalidAsset)?,
+        None => return Err(NoteDecryptionError::UnsupportedVersion(plaintext[0])),
+    };
+    let diversifier = Diversifier::from_bytes(plaintext[1..12].try_into().unwrap());
+    let value = NoteValue::from_bytes(plaintext[12..20].try_into().unwrap());
+    let rseed = Option::from(RandomSeed::from_bytes(
+        plaintext[20..COMPACT_NOTE_SIZE_V2].try_into().unwrap(),
+        &domain.rho,
+    ))
+    .ok_or(NoteDecryptionError::InvalidNote)?;
+    let recipient = Address::from_parts(
+        diversifier,
+        DiversifiedTransmissionKey::derive(ivk, &diversifier),
+    );
+    let note: Note = Option::from(Note::from_parts(recipient, value, asset, domain.rho, rseed))
+        .ok_or(NoteDecryptionError::InvalidNote)?;
+
+    if ExtractedNoteCommitment::from(note.commitment()).to_bytes() != output.cmstar_bytes() {
+        return Err(NoteDecryptionError::CommitmentMismatch);
+    }
+    // As in `try_note_decryption`, the ephemeral key must be derived from the note.
+    let derived_epk = OrchardDomainV3::ka_derive_public(&note, &note.esk());
+    if !bool::from(derived_epk.to_bytes().0.ct_eq(&ephemeral_key.0)) {
+        return Err(NoteDecryptionError::EphemeralKeyMismatch);
+    }
+
+    Ok((
+        note,
+        recipient,
+        plaintext[COMPACT_NOTE_SIZE_V3..].try_into().unwrap(),
+    ))
 }
 
 #[cfg(test)]
 mod tests {
+    use chacha20poly1305::{
+        aead::{Aead, KeyInit},
+        ChaCha20Poly1305, Nonce,
+    };
     use proptest::prelude::*;
     use rand::rngs::OsRng;
     use zcash_note_encryption_zsa::{
-        try_compact_note_decryption, try_note_decryption, try_output_recovery_with_ovk, Domain,
-        EphemeralKeyBytes,
+        try_compact_note_decryption, try_note_decryption, try_output_recovery_with_ock,
+        try_output_recovery_with_ovk, Domain, EphemeralKeyBytes, ShieldedOutput, MEMO_SIZE,
     };
 
     use super::{
-        note_version, orchard_parse_note_plaintext_without_memo, prf_ock_orchard, CompactAction,
-        OrchardDomainV3, OrchardNoteEncryption,
+        note_version, orchard_parse_note_plaintext_without_memo, prf_ock_orchard,
+        try_note_decryption_detailed, CompactAction, CompactActionError, NoteDecryptionError,
+        OrchardDomainV3, OrchardNoteEncryption, COMPACT_NOTE_SIZE_V3,
     };
     use crate::{
-        action::Action,
+        action::{testing::arb_action, Action, ActionError},
         keys::{
-            DiversifiedTransmissionKey, Diversifier, EphemeralSecretKey, IncomingViewingKey,
-            OutgoingViewingKey, PreparedIncomingViewingKey,
+            DiversifiedTransmissionKey, Diversifier, EphemeralSecretKey, FullViewingKey,
+            IncomingViewingKey, OutgoingViewingKey, PreparedIncomingViewingKey, Scope, SpendingKey,
         },
         note::{
             testing::arb_note, AssetBase, ExtractedNoteCommitment, Nullifier, RandomSeed, Rho,
             TransmittedNoteCiphertext,
         },
         primitives::redpallas,
-        value::{NoteValue, ValueCommitment},
+        value::{NoteValue, ValueCommitTrapdoor, ValueCommitment, ValueSum},
         Address, Note,
     };
 
+    proptest! {
+        #[test]
+        fn compact_action_round_trip(
+            action in arb_action(NoteValue::from_raw(10), NoteValue::from_raw(5))
+        ) {
+            let compact = action.to_compact();
+            prop_assert_eq!(compact.nullifier(), *action.nullifier());
+            prop_assert_eq!(compact.cmstar_bytes(), action.cmx().to_bytes());
+
+            let full = |enc: &[u8]| {
+                compact.to_action(
+                    action.rk().clone(),
+                    action.cv_net().clone(),
+                    enc,
+                    action.out_ciphertext_bytes(),
+                    action.authorization().clone(),
+                )
+            };
+            let converted = full(action.enc_ciphertext_bytes()).unwrap();
+            prop_assert_eq!(converted.enc_ciphertext_bytes(), action.enc_ciphertext_bytes());
+            prop_assert_eq!(converted.epk_bytes(), action.epk_bytes());
+            prop_assert_eq!(converted.cv_net().to_bytes(), action.cv_net().to_bytes());
+
+            prop_assert_eq!(
+                full(&action.enc_ciphertext_bytes()[..COMPACT_NOTE_SIZE_V3]).unwrap_err(),
+                CompactActionError::Action(ActionError::EncCiphertextSize(COMPACT_NOTE_SIZE_V3))
+            );
+            let mut tampered = *action.enc_ciphertext_bytes();
+            tampered[0] ^= 1;
+            prop_assert_eq!(
+                full(&tampered).unwrap_err(),
+                CompactActionError::CiphertextMismatch
+            );
+        }
+    }
+
     proptest! {
         #[test]
         fn test_encoding_roundtrip(
@@ -616,6 +869,15 @@ mod tests {
                 None => panic!("Output recovery failed"),
             }
 
+            match try_output_recovery_with_ock(&domain, &ock, &action, &tv.c_out) {
+                Some((decrypted_note, decrypted_to, decrypted_memo)) => {
+                    assert_eq!(decrypted_note, note);
+                    assert_eq!(decrypted_to, recipient);
+                    assert_eq!(&decrypted_memo[..], &tv.memo[..]);
+                }
+                None => panic!("Output recovery with ock failed"),
+            }
+
             //
             // Test encryption
             //
@@ -629,4 +891,102 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn detailed_decryption_errors() {
+        let mut rng = OsRng;
+
+        let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+        let recipient = fvk.address_at(0u32, Scope::External);
+        let ivk = PreparedIncomingViewingKey::new(&fvk.to_ivk(Scope::External));
+        let other_ivk = PreparedIncomingViewingKey::new(
+            &FullViewingKey::from(&SpendingKey::random(&mut rng)).to_ivk(Scope::External),
+        );
+
+        let nf_old = Nullifier::dummy(&mut rng);
+        let rho = Rho::from_nf_old(nf_old);
+        let domain = OrchardDomainV3 { rho };
+        let note = Note::new(
+            recipient,
+            NoteValue::from_raw(10),
+            AssetBase::random(),
+            rho,
+            &mut rng,
+        );
+        let plaintext = OrchardDomainV3::note_plaintext_bytes(&note, &[0; MEMO_SIZE]).0;
+
+        // Encrypts `plaintext` to the recipient with `esk`, in an action committing to `note`.
+        let encrypt = |esk: &EphemeralSecretKey, plaintext: &[u8], cmx| {
+            let ephemeral_key = OrchardDomainV3::ka_derive_public(&note, esk).to_bytes();
+            let key = esk.agree(recipient.pk_d()).kdf_orchard(&ephemeral_key);
+            let enc_ciphertext = ChaCha20Poly1305::new_from_slice(key.as_bytes())
+                .unwrap()
+                .encrypt(&Nonce::default(), plaintext)
+                .unwrap();
+            Action::from_parts(
+                nf_old,
+                redpallas::VerificationKey::dummy(),
+                cmx,
+                TransmittedNoteCiphertext {
+                    epk_bytes: ephemeral_key.0,
+                    enc_ciphertext: enc_ciphertext.try_into().unwrap(),
+                    out_ciphertext: [0; 80],
+                },
+                ValueCommitment::derive(
+                    ValueSum::zero(),
+                    ValueCommitTrapdoor::zero(),
+                    AssetBase::native(),
+                ),
+                (),
+            )
+        };
+        let cmx = ExtractedNoteCommitment::from(note.commitment());
+
+        let action = encrypt(&note.esk(), &plaintext, cmx);
+        assert_eq!(
+            try_note_decryption_detailed(&domain, &ivk, &action)
+                .map(|(decrypted_note, _, _)| decrypted_note),
+            Ok(note)
+        );
+        assert_eq!(
+            try_note_decryption_detailed(&domain, &other_ivk, &action),
+            Err(NoteDecryptionError::NotForThisKey)
+        );
+
+        let mut unsupported = plaintext;
+        unsupported[0] = 0x04;
+        assert_eq!(
+            try_note_decryption_detailed(&domain, &ivk, &encrypt(&note.esk(), &unsupported, cmx)),
+            Err(NoteDecryptionError::UnsupportedVersion(0x04))
+        );
+
+        // The encoding of a point with an x-coordinate that is not a field element.
+        let mut invalid_asset = plaintext;
+        invalid_asset[52..84].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            try_note_decryption_detailed(&domain, &ivk, &encrypt(&note.esk(), &invalid_asset, cmx)),
+            Err(NoteDecryptionError::InvalidAsset)
+        );
+
+        let other_cmx = ExtractedNoteCommitment::from_bytes(&[0; 32]).unwrap();
+        assert_eq!(
+            try_note_decryption_detailed(
+                &domain,
+                &ivk,
+                &encrypt(&note.esk(), &plaintext, other_cmx)
+            ),
+            Err(NoteDecryptionError::CommitmentMismatch)
+        );
+
+        // An ephemeral key derived from another note to the same recipient.
+        let other_note = Note::new(recipient, note.value(), note.asset(), rho, &mut rng);
+        assert_eq!(
+            try_note_decryption_detailed(
+                &domain,
+                &ivk,
+                &encrypt(&other_note.esk(), &plaintext, cmx)
+            ),
+            Err(NoteDecryptionError::EphemeralKeyMismatch)
+        );
+    }
 }
@@ -2,6 +2,7 @@
 
 use core::cmp::{Ord, Ordering, PartialOrd};
 
+use blake2b_simd::Params;
 use pasta_curves::pallas;
 use rand::{CryptoRng, RngCore};
 
@@ -59,8 +60,82 @@ impl<T: SigType> SigningKey<T> {
     pub fn sign<R: RngCore + CryptoRng>(&self, rng: R, msg: &[u8]) -> Signature<T> {
         Signature(self.0.sign(rng, msg))
     }
+
+    /// Creates a signature of type `T` on `msg` using this `SigningKey`, with a nonce
+    /// derived deterministically from this key, `msg` and `aux_rand`.
+    ///
+    /// This is intended for signers without a reliable source of randomness, and for
+    /// reproducible tests. `aux_rand` should be fresh randomness when any is available,
+    /// as it protects against side-channel attacks on the nonce derivation; it may be
+    /// empty otherwise. Signing the same message twice with the same `aux_rand` produces
+    /// the same signature.
+    pub fn sign_deterministic(&self, msg: &[u8], aux_rand: &[u8]) -> Signature<T> {
+        let seed = Params::new()
+            .hash_length(64)
+            .personal(NONCE_SEED_PERSONALIZATION)
+            .to_state()
+            .update(&<[u8; 32]>::from(self))
+            .update(&(aux_rand.len() as u64).to_le_bytes())
+            .update(aux_rand)
+            .update(msg)
+            .finalize();
+        let mut rng = NonceRng {
+            seed: *seed.as_array(),
+            counter: 0,
+        };
+        self.sign(&mut rng, msg)
+    }
+}
+
+/// Personalization for the derivation of the seed of deterministic signing nonces.
+const NONCE_SEED_PERSONALIZATION: &[u8; 16] = b"Orchard_RPNonces";
+
+/// Personalization for the expansion of the seed of deterministic signing nonces.
+const NONCE_EXPAND_PERSONALIZATION: &[u8; 16] = b"Orchard_RPExpand";
+
+/// The source of the randomness consumed by [`SigningKey::sign`] when signing
+/// deterministically, which expands a secret seed with BLAKE2b.
+struct NonceRng {
+    seed: [u8; 64],
+    counter: u64,
 }
 
+impl RngCore for NonceRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(64) {
+            let block = Params::new()
+                .hash_length(64)
+                .personal(NONCE_EXPAND_PERSONALIZATION)
+                .to_state()
+                .update(&self.seed)
+                .update(&self.counter.to_le_bytes())
+                .finalize();
+            self.counter += 1;
+            chunk.copy_from_slice(&block.as_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// The output of `NonceRng` is unpredictable without the signing key.
+impl CryptoRng for NonceRng {}
+
 /// A RedPallas verification key.
 #[derive(Clone, Debug)]
 pub struct VerificationKey<T: SigType>(pub(crate) reddsa::VerificationKey<T>);
@@ -223,3 +298,35 @@ pub mod testing {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{
+        testing::{arb_binding_signing_key, arb_spendauth_signing_key},
+        VerificationKey,
+    };
+
+    proptest! {
+        #[test]
+        fn deterministic_signatures(
+            spend_sk in arb_spendauth_signing_key(),
+            binding_sk in arb_binding_signing_key(),
+            msg in prop::collection::vec(any::<u8>(), 0..100),
+            aux_rand in prop::collection::vec(any::<u8>(), 0..40),
+        ) {
+            let sig = spend_sk.sign_deterministic(&msg, &aux_rand);
+            prop_assert!(VerificationKey::from(&spend_sk).verify(&msg, &sig).is_ok());
+            prop_assert_eq!(&sig, &spend_sk.sign_deterministic(&msg, &aux_rand));
+
+            let mut other_aux_rand = aux_rand.clone();
+            other_aux_rand.push(0);
+            prop_assert_ne!(&sig, &spend_sk.sign_deterministic(&msg, &other_aux_rand));
+
+            let sig = binding_sk.sign_deterministic(&msg, &aux_rand);
+            prop_assert!(VerificationKey::from(&binding_sk).verify(&msg, &sig).is_ok());
+            prop_assert_eq!(sig, binding_sk.sign_deterministic(&msg, &aux_rand));
+        }
+    }
+}
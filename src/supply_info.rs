@@ -75,6 +75,77 @@ impl Default for SupplyInfo {
     }
 }
 
+/// Read access to the recorded supply of each asset, as maintained by a node.
+pub trait AssetStateReader {
+    /// Returns the recorded supply of `asset`, or `None` if it has never been issued.
+    fn asset_state(&self, asset: &AssetBase) -> Option<AssetSupply>;
+
+    /// Returns `true` if `asset` has been finalized.
+    fn is_finalized(&self, asset: &AssetBase) -> bool {
+        self.asset_state(asset)
+            .map_or(false, |supply| supply.is_finalized)
+    }
+}
+
+/// Write access to the recorded supply of each asset, as maintained by a node.
+pub trait AssetStateWriter: AssetStateReader {
+    /// Records the supply of `asset`.
+    fn set_asset_state(&mut self, asset: AssetBase, supply: AssetSupply);
+
+    /// Applies the supply changes of a verified issue bundle, as returned by
+    /// [`verify_issue_bundle`], to the recorded state.
+    ///
+    /// No state is modified if the supply of any asset overflows.
+    ///
+    /// [`verify_issue_bundle`]: crate::issuance::verify_issue_bundle
+    fn apply_supply_info(&mut self, supply_info: &SupplyInfo) -> Result<(), Error> {
+        let updated = supply_info
+            .assets
+            .iter()
+            .map(|(asset, supply)| {
+                let supply = match self.asset_state(asset) {
+                    Some(current) => AssetSupply::new(
+                        (current.amount + supply.amount).ok_or(Error::ValueSumOverflow)?,
+                        current.is_finalized | supply.is_finalized,
+                    ),
+                    None => *supply,
+                };
+                Ok((*asset, supply))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        for (asset, supply) in updated {
+            self.set_asset_state(asset, supply);
+        }
+        Ok(())
+    }
+}
+
+/// An in-memory record of the supply of each asset.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryAssetState {
+    assets: HashMap<AssetBase, AssetSupply>,
+}
+
+impl InMemoryAssetState {
+    /// Creates a new, empty `InMemoryAssetState` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AssetStateReader for InMemoryAssetState {
+    fn asset_state(&self, asset: &AssetBase) -> Option<AssetSupply> {
+        self.assets.get(asset).copied()
+    }
+}
+
+impl AssetStateWriter for InMemoryAssetState {
+    fn set_asset_state(&mut self, asset: AssetBase, supply: AssetSupply) {
+        self.assets.insert(asset, supply);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -190,4 +261,47 @@ mod tests {
         assert!(finalization_set.contains(&asset1));
         assert!(finalization_set.contains(&asset3));
     }
+
+    #[test]
+    fn test_in_memory_asset_state() {
+        let asset1 = create_test_asset("Asset 1");
+        let asset2 = create_test_asset("Asset 2");
+
+        let mut supply_info = SupplyInfo::new();
+        assert!(supply_info
+            .add_supply(asset1, AssetSupply::new(ValueSum::from_raw(10), false))
+            .is_ok());
+        assert!(supply_info
+            .add_supply(asset2, AssetSupply::new(ValueSum::from_raw(20), true))
+            .is_ok());
+
+        let mut state = InMemoryAssetState::new();
+        assert!(state.apply_supply_info(&supply_info).is_ok());
+        assert!(state.apply_supply_info(&supply_info).is_ok());
+
+        assert_eq!(
+            state.asset_state(&asset1),
+            Some(AssetSupply::new(ValueSum::from_raw(20), false))
+        );
+        assert!(!state.is_finalized(&asset1));
+        assert!(state.is_finalized(&asset2));
+        assert_eq!(state.asset_state(&create_test_asset("Asset 3")), None);
+
+        let mut overflow = SupplyInfo::new();
+        assert!(overflow
+            .add_supply(asset2, AssetSupply::new(ValueSum::from_raw(1), false))
+            .is_ok());
+        let max = (ValueSum::from_raw(i64::MAX) + i64::MAX).unwrap();
+        assert!(overflow
+            .add_supply(asset1, AssetSupply::new(max, false))
+            .is_ok());
+        assert_eq!(
+            state.apply_supply_info(&overflow),
+            Err(Error::ValueSumOverflow)
+        );
+        assert_eq!(
+            state.asset_state(&asset2),
+            Some(AssetSupply::new(ValueSum::from_raw(40), true))
+        );
+    }
 }
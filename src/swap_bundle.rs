@@ -0,0 +1,408 @@
+//! Structs related to swap bundles, made of several independently authorized action groups.
+//!
+//! An action group is a set of actions that is proven and whose spends are authorized
+//! independently of the other action groups of a transaction, as described in
+//! [ZIP-228: Asset Swaps for Zcash Shielded Assets][zip228]. This lets several parties
+//! each contribute their own action group, which are then combined into a [`SwapBundle`]
+//! balanced by a single binding signature.
+//!
+//! [zip228]: https://qed-it.github.io/zips/zip-0228
+
+use core::fmt;
+
+use halo2_proofs::plonk;
+use rand::{CryptoRng, RngCore};
+
+use crate::{
+    address::Address,
+    builder::{BuildError, Builder, BundleType, OutputError, SpendError},
+    bundle::{
+        commitments::hash_swap_bundle_txid_data, derive_bvk, Authorization, Bundle,
+        BundleCommitment,
+    },
+    circuit::{Proof, ProvingKey, VerifyingKey},
+    keys::{FullViewingKey, OutgoingViewingKey, SpendAuthorizingKey},
+    note::{AssetBase, Note},
+    primitives::redpallas::{self, Binding, SpendAuth},
+    tree::{Anchor, MerklePath},
+    value::{NoteValue, OverflowError, ValueCommitTrapdoor},
+};
+
+/// An error that can occur when combining action groups into a [`SwapBundle`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SwapError {
+    /// The sum of the value balances of the action groups overflowed.
+    ValueBalance(OverflowError),
+    /// The assets moved by the action groups do not balance.
+    Unbalanced,
+}
+
+impl fmt::Display for SwapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SwapError::ValueBalance(_) => {
+                f.write_str("The value balance of the swap bundle overflowed")
+            }
+            SwapError::Unbalanced => f.write_str("The action groups of the swap do not balance"),
+        }
+    }
+}
+
+impl std::error::Error for SwapError {}
+
+/// One party's side of an atomic swap.
+///
+/// A party spends its notes of the asset it gives, and creates outputs for the asset it
+/// receives as well as for its change. Its action group does not balance on its own, and
+/// only the [`SwapBundle`] combining it with the action groups of the counterparties
+/// must balance. Each party signs only the spends of its own action group.
+#[derive(Debug)]
+pub struct SwapOrder {
+    builder: Builder,
+}
+
+impl SwapOrder {
+    /// Constructs a new empty order whose spends use the given anchor.
+    pub fn new(anchor: Anchor) -> Self {
+        SwapOrder {
+            builder: Builder::new(BundleType::DEFAULT_ZSA, anchor),
+        }
+    }
+
+    /// Adds a note given away by this party. See [`Builder::add_spend`].
+    pub fn add_spend(
+        &mut self,
+        fvk: FullViewingKey,
+        note: Note,
+        merkle_path: MerklePath,
+    ) -> Result<(), SpendError> {
+        self.builder.add_spend(fvk, note, merkle_path)
+    }
+
+    /// Adds an output receiving funds, either the asset obtained in the swap or change.
+    /// See [`Builder::add_output`].
+    pub fn add_output(
+        &mut self,
+        ovk: Option<OutgoingViewingKey>,
+        recipient: Address,
+        value: NoteValue,
+        asset: AssetBase,
+        memo: Option<[u8; 512]>,
+    ) -> Result<(), OutputError> {
+        self.builder.add_output(ovk, recipient, value, asset, memo)
+    }
+
+    /// Builds, proves and authorizes the action group of this party.
+    ///
+    /// `signing_keys` must contain the spend authorizing keys of all the notes spent by
+    /// this party.
+    pub fn build<V: Copy + Into<i64> + TryFrom<i64>, R: RngCore + CryptoRng>(
+        self,
+        mut rng: R,
+        pk: &ProvingKey,
+        signing_keys: &[SpendAuthorizingKey],
+    ) -> Result<ActionGroup<V>, BuildError> {
+        let (bundle, _) = self
+            .builder
+            .build_unbalanced::<V>(&mut rng)?
+            .expect("bundles are padded to contain at least two actions");
+        bundle
+            .create_proof(pk, &mut rng)?
+            .build_action_group(&mut rng, signing_keys)
+    }
+}
+
+/// Authorizing data for an action group, ready to be combined into a [`SwapBundle`].
+///
+/// Unlike [`Authorized`], this does not contain a binding signature; the action groups
+/// of a swap bundle share a single binding signature.
+///
+/// [`Authorized`]: crate::bundle::Authorized
+#[derive(Debug, Clone)]
+pub struct ActionGroupAuthorized {
+    proof: Proof,
+}
+
+impl Authorization for ActionGroupAuthorized {
+    type SpendAuth = redpallas::Signature<SpendAuth>;
+}
+
+impl ActionGroupAuthorized {
+    /// Constructs the authorizing data for an action group from its proof.
+    pub fn from_parts(proof: Proof) -> Self {
+        ActionGroupAuthorized { proof }
+    }
+
+    /// Return the proof component of the authorizing data.
+    pub fn proof(&self) -> &Proof {
+        &self.proof
+    }
+}
+
+/// A proven action group whose spends are authorized, along with the binding signing key
+/// needed to include it in a [`SwapBundle`].
+///
+/// The spend authorization signatures of an action group are computed over its own
+/// [`Bundle::commitment`], so that each party can authorize its action group without
+/// knowing the other action groups of the swap.
+#[derive(Debug)]
+pub struct ActionGroup<V> {
+    action_group: Bundle<ActionGroupAuthorized, V>,
+    bsk: redpallas::SigningKey<Binding>,
+}
+
+impl<V> ActionGroup<V> {
+    /// Constructs an action group from its authorized actions and its binding signing key.
+    pub(crate) fn from_parts(
+        action_group: Bundle<ActionGroupAuthorized, V>,
+        bsk: redpallas::SigningKey<Binding>,
+    ) -> Self {
+        ActionGroup { action_group, bsk }
+    }
+
+    /// Returns the actions of this action group.
+    pub fn action_group(&self) -> &Bundle<ActionGroupAuthorized, V> {
+        &self.action_group
+    }
+}
+
+/// A bundle made of several action groups, balanced by a joint binding signature.
+#[derive(Debug)]
+pub struct SwapBundle<V> {
+    action_groups: Vec<Bundle<ActionGroupAuthorized, V>>,
+    value_balance: V,
+    binding_signature: redpallas::Signature<Binding>,
+}
+
+impl<V: Copy + Into<i64> + TryFrom<i64>> SwapBundle<V> {
+    /// Combines the given action groups into a swap bundle, and signs `sighash` with the
+    /// sum of their binding signing keys.
+    ///
+    /// The value balance of the swap bundle is the sum of the value balances of the
+    /// action groups. Returns an error if that sum overflows, or if the non-native assets
+    /// moved by the action groups do not balance.
+    pub fn new<R: RngCore + CryptoRng>(
+        rng: R,
+        action_groups: Vec<ActionGroup<V>>,
+        sighash: [u8; 32],
+    ) -> Result<Self, SwapError> {
+        let value_balance = action_groups
+            .iter()
+            .try_fold(0i64, |acc, group| {
+                acc.checked_add((*group.action_group.value_balance()).into())
+            })
+            .ok_or(SwapError::ValueBalance(OverflowError))?;
+        let value_balance =
+            V::try_from(value_balance).map_err(|_| SwapError::ValueBalance(OverflowError))?;
+
+        let bsk = action_groups
+            .iter()
+            .map(|group| {
+                Option::from(ValueCommitTrapdoor::from_bytes((&group.bsk).into()))
+                    .expect("binding signing keys are canonical scalars")
+            })
+            .fold(ValueCommitTrapdoor::zero(), |acc, rcv| acc + &rcv)
+            .into_bsk();
+
+        let swap_bundle = SwapBundle {
+            action_groups: action_groups
+                .into_iter()
+                .map(|group| group.action_group)
+                .collect(),
+            value_balance,
+            binding_signature: bsk.sign(rng, &sighash),
+        };
+
+        // The binding signing key only matches the binding validating key if every asset
+        // is balanced across the action groups.
+        if redpallas::VerificationKey::from(&bsk) != swap_bundle.binding_validating_key() {
+            return Err(SwapError::Unbalanced);
+        }
+
+        Ok(swap_bundle)
+    }
+}
+
+impl<V> SwapBundle<V> {
+    /// Returns the action groups of this swap bundle.
+    pub fn action_groups(&self) -> &[Bundle<ActionGroupAuthorized, V>] {
+        &self.action_groups
+    }
+
+    /// Returns the total net value moved into or out of the pool by this swap bundle.
+    pub fn value_balance(&self) -> &V {
+        &self.value_balance
+    }
+
+    /// Returns the binding signature of this swap bundle.
+    pub fn binding_signature(&self) -> &redpallas::Signature<Binding> {
+        &self.binding_signature
+    }
+
+    /// Verifies the proofs of every action group of this swap bundle.
+    pub fn verify_proofs(&self, vk: &VerifyingKey) -> Result<(), plonk::Error> {
+        self.action_groups.iter().try_for_each(|group| {
+            group
+                .authorization()
+                .proof()
+                .verify(vk, &group.to_instances())
+        })
+    }
+}
+
+impl<V: Copy + Into<i64>> SwapBundle<V> {
+    /// Computes a commitment to the effects of this swap bundle, suitable for inclusion
+    /// within a transaction ID.
+    pub fn commitment(&self) -> BundleCommitment {
+        BundleCommitment(hash_swap_bundle_txid_data(self))
+    }
+
+    /// Returns the binding validating key for this swap bundle, derived from the actions
+    /// and burns of all of its action groups.
+    pub fn binding_validating_key(&self) -> redpallas::VerificationKey<Binding> {
+        derive_bvk(
+            self.action_groups
+                .iter()
+                .flat_map(|group| group.actions().iter()),
+            self.value_balance,
+            self.action_groups
+                .iter()
+                .flat_map(|group| group.burn().iter().cloned()),
+        )
+    }
+
+    /// Verifies the spend authorization signatures of every action group, and the binding
+    /// signature of this swap bundle over `sighash`.
+    pub fn verify_signatures(&self, sighash: &[u8; 32]) -> Result<(), reddsa::Error> {
+        for group in &self.action_groups {
+            let group_sighash: [u8; 32] = group.commitment().into();
+            for action in group.actions().iter() {
+                action.rk().verify(&group_sighash, action.authorization())?;
+            }
+        }
+
+        self.binding_validating_key()
+            .verify(sighash, &self.binding_signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use super::{SwapBundle, SwapError, SwapOrder};
+    use crate::{
+        builder::{Builder, BundleType},
+        circuit::{ProvingKey, VerifyingKey},
+        constants::MERKLE_DEPTH_ORCHARD,
+        keys::{
+            FullViewingKey, IssuanceAuthorizingKey, IssuanceValidatingKey, Scope,
+            SpendAuthorizingKey, SpendingKey,
+        },
+        note::{AssetBase, Note, Nullifier, Rho},
+        tree::{MerklePath, EMPTY_ROOTS},
+        value::NoteValue,
+    };
+
+    #[test]
+    fn action_groups_share_a_binding_signature() {
+        let pk = ProvingKey::build();
+        let vk = VerifyingKey::build();
+        let mut rng = OsRng;
+
+        let action_groups = [3u64, 4]
+            .into_iter()
+            .map(|value| {
+                let fvk = FullViewingKey::from(&SpendingKey::random(&mut rng));
+                let mut builder = Builder::new(
+                    BundleType::DEFAULT_VANILLA,
+                    EMPTY_ROOTS[MERKLE_DEPTH_ORCHARD].into(),
+                );
+                builder
+                    .add_output(
+                        None,
+                        fvk.address_at(0u32, Scope::External),
+                        NoteValue::from_raw(value),
+                        AssetBase::native(),
+                        None,
+                    )
+                    .unwrap();
+                let (bundle, _) = builder.build::<i64>(&mut rng).unwrap().unwrap();
+                bundle
+                    .create_proof(&pk, &mut rng)
+                    .unwrap()
+                    .build_action_group(&mut rng, &[])
+                    .unwrap()
+            })
+            .collect();
+
+        let sighash = [42u8; 32];
+        let swap_bundle = SwapBundle::new(&mut rng, action_groups, sighash).unwrap();
+        assert_eq!(swap_bundle.value_balance(), &(-7));
+        assert!(swap_bundle.verify_signatures(&sighash).is_ok());
+        assert!(swap_bundle.verify_signatures(&[0u8; 32]).is_err());
+        assert!(swap_bundle.verify_proofs(&vk).is_ok());
+    }
+
+    /// Builds the order of a party holding a note of `give` worth `value`, which it
+    /// exchanges for a note of `receive` of the same value.
+    fn order(
+        rng: &mut OsRng,
+        pk: &ProvingKey,
+        give: AssetBase,
+        receive: AssetBase,
+        value: u64,
+    ) -> super::ActionGroup<i64> {
+        let sk = SpendingKey::random(rng);
+        let fvk = FullViewingKey::from(&sk);
+        let address = fvk.address_at(0u32, Scope::External);
+
+        let note = Note::new(
+            address,
+            NoteValue::from_raw(value),
+            give,
+            Rho::from_nf_old(Nullifier::dummy(rng)),
+            &mut *rng,
+        );
+        let merkle_path = MerklePath::dummy(rng);
+        let anchor = merkle_path.root(note.commitment().into());
+
+        let mut order = SwapOrder::new(anchor);
+        order.add_spend(fvk, note, merkle_path).unwrap();
+        order
+            .add_output(None, address, NoteValue::from_raw(value), receive, None)
+            .unwrap();
+        order
+            .build(&mut *rng, pk, &[SpendAuthorizingKey::from(&sk)])
+            .unwrap()
+    }
+
+    #[test]
+    fn two_party_swap() {
+        let pk = ProvingKey::build();
+        let vk = VerifyingKey::build();
+        let mut rng = OsRng;
+
+        let asset = |desc: &str| {
+            let ik = IssuanceValidatingKey::from(&IssuanceAuthorizingKey::random());
+            AssetBase::derive(&ik, desc)
+        };
+        let (asset_x, asset_y) = (asset("X"), asset("Y"));
+
+        let sighash = [7u8; 32];
+        let party_a = order(&mut rng, &pk, asset_x, asset_y, 10);
+        let party_b = order(&mut rng, &pk, asset_y, asset_x, 10);
+        let swap_bundle = SwapBundle::new(&mut rng, vec![party_a, party_b], sighash).unwrap();
+        assert_eq!(swap_bundle.value_balance(), &0);
+        assert!(swap_bundle.verify_signatures(&sighash).is_ok());
+        assert!(swap_bundle.verify_proofs(&vk).is_ok());
+
+        // A party receiving more than its counterparty gives does not balance.
+        let party_a = order(&mut rng, &pk, asset_x, asset_y, 10);
+        let party_b = order(&mut rng, &pk, asset_y, asset_x, 11);
+        assert!(matches!(
+            SwapBundle::new(&mut rng, vec![party_a, party_b], sighash),
+            Err(SwapError::Unbalanced)
+        ));
+    }
+}
@@ -1,7 +1,23 @@
+//! Test vectors for the Orchard protocol.
+//!
+//! The [`generate`] module deterministically generates vectors that other
+//! implementations can use as fixtures.
+
+#[cfg(test)]
 pub(crate) mod asset_base;
+#[cfg(test)]
 pub(crate) mod commitment_tree;
+#[cfg(test)]
 pub(crate) mod issuance_auth_sig;
+#[cfg(test)]
+pub(crate) mod issuance_keys;
+#[cfg(test)]
 pub(crate) mod keys;
+#[cfg(test)]
 pub(crate) mod merkle_path;
+#[cfg(test)]
 pub(crate) mod note_encryption;
+#[cfg(test)]
 pub(crate) mod note_encryption_v3;
+
+pub mod generate;
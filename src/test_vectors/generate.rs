@@ -0,0 +1,462 @@
+//! Deterministic generation of test vectors, for other implementations of the protocol.
+//!
+//! All vectors are derived from a seed, so that fixtures can be regenerated from a single
+//! source. Byte fields are hex-encoded, and every vector type implements
+//! [`serde::Serialize`] so that the vectors can be written in any serde data format.
+
+use blake2b_simd::Params;
+use ff::{FromUniformBytes, PrimeField};
+use nonempty::NonEmpty;
+use pasta_curves::pallas;
+use rand::{CryptoRng, RngCore};
+use serde::Serialize;
+
+use crate::{
+    action::Action,
+    bundle::{Authorization, Bundle, Flags},
+    issuance::{IssueBundle, IssueInfo},
+    keys::{
+        FullViewingKey, IssuanceAuthorizingKey, IssuanceValidatingKey, Scope, SpendValidatingKey,
+        SpendingKey,
+    },
+    note::{
+        asset_digest, encode_asset_id, AssetBase, ExtractedNoteCommitment, Note, Nullifier, Rho,
+        TransmittedNoteCiphertext,
+    },
+    note_encryption_v3::OrchardNoteEncryption,
+    tree::Anchor,
+    value::{NoteValue, ValueCommitTrapdoor, ValueCommitment, ValueSum},
+};
+
+/// Personalization for the expansion of the seed of the generator.
+const GENERATOR_PERSONALIZATION: &[u8; 16] = b"Orchard_TVectors";
+
+/// A deterministic source of randomness for one test vector, expanding the seed, the
+/// kind and the index of the vector with BLAKE2b.
+///
+/// Its output is stable across versions of this crate and of its dependencies.
+struct VectorRng {
+    seed: [u8; 64],
+    counter: u64,
+}
+
+impl VectorRng {
+    fn new(seed: &[u8], kind: &str, index: usize) -> Self {
+        let seed = Params::new()
+            .hash_length(64)
+            .personal(GENERATOR_PERSONALIZATION)
+            .to_state()
+            .update(&(seed.len() as u64).to_le_bytes())
+            .update(seed)
+            .update(&(kind.len() as u64).to_le_bytes())
+            .update(kind.as_bytes())
+            .update(&(index as u64).to_le_bytes())
+            .finalize();
+        VectorRng {
+            seed: *seed.as_array(),
+            counter: 0,
+        }
+    }
+
+    fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        let mut bytes = [0; N];
+        self.fill_bytes(&mut bytes);
+        bytes
+    }
+}
+
+impl RngCore for VectorRng {
+    fn next_u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.bytes())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.bytes())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(64) {
+            let block = Params::new()
+                .hash_length(64)
+                .personal(GENERATOR_PERSONALIZATION)
+                .to_state()
+                .update(&self.seed)
+                .update(&self.counter.to_le_bytes())
+                .finalize();
+            self.counter += 1;
+            chunk.copy_from_slice(&block.as_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// The generated keys are only used in test vectors.
+impl CryptoRng for VectorRng {}
+
+/// Generates an issuance key from `rng`.
+fn issuance_key(rng: &mut VectorRng) -> IssuanceAuthorizingKey {
+    loop {
+        if let Some(isk) = IssuanceAuthorizingKey::from_bytes(rng.bytes()) {
+            break isk;
+        }
+    }
+}
+
+/// The two flavors of note encryption test vectors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum NoteFlavor {
+    /// A note of the native asset.
+    Native,
+    /// A note of an issued asset.
+    Zsa,
+}
+
+/// A test vector for the commitment and the encryption of a note.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct NoteEncryptionVector {
+    /// Whether the note is of the native asset.
+    pub flavor: NoteFlavor,
+    /// The spending key of the recipient.
+    pub sk: String,
+    /// The raw encoding of the recipient address.
+    pub recipient: String,
+    /// The outgoing viewing key of the sender.
+    pub ovk: String,
+    /// The value of the note.
+    pub value: u64,
+    /// The asset of the note.
+    pub asset: String,
+    /// The rho value of the note.
+    pub rho: String,
+    /// The seed randomness of the note.
+    pub rseed: String,
+    /// The memo of the note.
+    pub memo: String,
+    /// The value commitment trapdoor of the action.
+    pub rcv: String,
+    /// The value commitment of the action.
+    pub cv_net: String,
+    /// The extracted note commitment.
+    pub cmx: String,
+    /// The ephemeral public key.
+    pub ephemeral_key: String,
+    /// The encrypted note ciphertext.
+    pub enc_ciphertext: String,
+    /// The outgoing ciphertext.
+    pub out_ciphertext: String,
+}
+
+/// A test vector for the derivation of an asset base.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AssetBaseVector {
+    /// The issuance validating key.
+    pub ik: String,
+    /// The asset description.
+    pub asset_desc: String,
+    /// The encoded asset identifier.
+    pub asset_id: String,
+    /// The asset digest.
+    pub asset_digest: String,
+    /// The asset base.
+    pub asset_base: String,
+}
+
+/// A test vector for the commitments to, and the signature of, an issue bundle.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct IssueBundleVector {
+    /// The issuance authorizing key.
+    pub isk: String,
+    /// The issuance validating key.
+    pub ik: String,
+    /// The asset description.
+    pub asset_desc: String,
+    /// The issued notes, in their storage encoding.
+    pub notes: Vec<String>,
+    /// The sighash signed by the issuer.
+    pub sighash: String,
+    /// The commitment to the issue bundle.
+    pub commitment: String,
+    /// The commitment to the authorizing data of the issue bundle.
+    pub auth_commitment: String,
+    /// The issuance authorization signature.
+    pub signature: String,
+}
+
+/// A test vector for the commitment to a bundle.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BundleDigestVector {
+    /// The actions of the bundle, each as the note encryption vector it was built from.
+    pub actions: Vec<NoteEncryptionVector>,
+    /// The nullifiers of the actions.
+    pub nullifiers: Vec<String>,
+    /// The randomized verification keys of the actions.
+    pub rks: Vec<String>,
+    /// The flags byte of the bundle.
+    pub flags: u8,
+    /// The value balance of the bundle.
+    pub value_balance: i64,
+    /// The anchor of the bundle.
+    pub anchor: String,
+    /// The commitment to the bundle.
+    pub commitment: String,
+}
+
+/// All the test vectors generated from a seed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TestVectors {
+    /// Note encryption and note commitment vectors.
+    pub note_encryption: Vec<NoteEncryptionVector>,
+    /// Asset base derivation vectors.
+    pub asset_base: Vec<AssetBaseVector>,
+    /// Issue bundle vectors.
+    pub issue_bundle: Vec<IssueBundleVector>,
+    /// Bundle digest vectors.
+    pub bundle_digest: Vec<BundleDigestVector>,
+}
+
+/// Generates `count` vectors of each kind from `seed`.
+pub fn generate(seed: &[u8], count: usize) -> TestVectors {
+    TestVectors {
+        note_encryption: (0..count)
+            .map(|i| note_encryption_vector(seed, i).0)
+            .collect(),
+        asset_base: (0..count).map(|i| asset_base_vector(seed, i)).collect(),
+        issue_bundle: (0..count).map(|i| issue_bundle_vector(seed, i)).collect(),
+        bundle_digest: (0..count).map(|i| bundle_digest_vector(seed, i)).collect(),
+    }
+}
+
+/// Generates the note encryption vector with the given index, along with the action it
+/// describes. Even indices produce native notes, and odd indices produce ZSA notes.
+fn note_encryption_vector(seed: &[u8], index: usize) -> (NoteEncryptionVector, Action<()>) {
+    let mut rng = VectorRng::new(seed, "note_encryption", index);
+
+    let flavor = if index % 2 == 0 {
+        NoteFlavor::Native
+    } else {
+        NoteFlavor::Zsa
+    };
+    let asset = match flavor {
+        NoteFlavor::Native => AssetBase::native(),
+        NoteFlavor::Zsa => {
+            let ik = IssuanceValidatingKey::from(&issuance_key(&mut rng));
+            AssetBase::derive(&ik, "test vector asset")
+        }
+    };
+
+    let sk = SpendingKey::random(&mut rng);
+    let fvk = FullViewingKey::from(&sk);
+    let recipient = fvk.address_at(0u32, Scope::External);
+    let ovk = fvk.to_ovk(Scope::External);
+    let value = NoteValue::from_raw(rng.next_u64() >> 14);
+    let nf_old = Nullifier::dummy(&mut rng);
+    let note = Note::new(recipient, value, asset, Rho::from_nf_old(nf_old), &mut rng);
+    let memo: [u8; 512] = rng.bytes();
+
+    let rcv = ValueCommitTrapdoor::random(&mut rng);
+    let cv_net = ValueCommitment::derive(
+        ValueSum::from_raw(-i64::try_from(value.inner()).unwrap()),
+        rcv,
+        asset,
+    );
+    let cmx = ExtractedNoteCommitment::from(note.commitment());
+
+    let encryptor = OrchardNoteEncryption::new(Some(ovk.clone()), note, memo);
+    let encrypted_note = TransmittedNoteCiphertext {
+        epk_bytes: encryptor.epk().to_bytes().0,
+        enc_ciphertext: encryptor.encrypt_note_plaintext().0,
+        out_ciphertext: encryptor.encrypt_outgoing_plaintext(&cv_net, &cmx, &mut rng),
+    };
+
+    let ak = SpendValidatingKey::from(fvk);
+    let rk = ak.randomize(&pallas_scalar(&mut rng));
+
+    let vector = NoteEncryptionVector {
+        flavor,
+        sk: hex::encode(sk.to_bytes()),
+        recipient: hex::encode(recipient.to_raw_address_bytes()),
+        ovk: hex::encode(ovk.as_ref()),
+        value: value.inner(),
+        asset: hex::encode(asset.to_bytes()),
+        rho: hex::encode(note.rho().to_bytes()),
+        rseed: hex::encode(note.rseed().as_bytes()),
+        memo: hex::encode(memo),
+        rcv: hex::encode(rcv.inner().to_repr()),
+        cv_net: hex::encode(cv_net.to_bytes()),
+        cmx: hex::encode(cmx.to_bytes()),
+        ephemeral_key: hex::encode(encrypted_note.epk_bytes),
+        enc_ciphertext: hex::encode(encrypted_note.enc_ciphertext),
+        out_ciphertext: hex::encode(encrypted_note.out_ciphertext),
+    };
+    let action = Action::from_parts(nf_old, rk, cmx, encrypted_note, cv_net, ());
+
+    (vector, action)
+}
+
+/// Samples a Pallas scalar from `rng`.
+fn pallas_scalar(rng: &mut VectorRng) -> pallas::Scalar {
+    pallas::Scalar::from_uniform_bytes(&rng.bytes())
+}
+
+/// Generates the asset base vector with the given index.
+fn asset_base_vector(seed: &[u8], index: usize) -> AssetBaseVector {
+    let mut rng = VectorRng::new(seed, "asset_base", index);
+
+    let ik = IssuanceValidatingKey::from(&issuance_key(&mut rng));
+    let asset_desc = format!("Test vector asset {}", index);
+    let asset_id = encode_asset_id(&ik, &asset_desc);
+    let digest = asset_digest(asset_id.clone());
+    let asset_base = AssetBase::derive(&ik, &asset_desc);
+
+    AssetBaseVector {
+        ik: hex::encode(ik.to_bytes()),
+        asset_desc,
+        asset_id: hex::encode(asset_id),
+        asset_digest: hex::encode(digest.as_bytes()),
+        asset_base: hex::encode(asset_base.to_bytes()),
+    }
+}
+
+/// Generates the issue bundle vector with the given index.
+fn issue_bundle_vector(seed: &[u8], index: usize) -> IssueBundleVector {
+    let mut rng = VectorRng::new(seed, "issue_bundle", index);
+
+    let isk = issuance_key(&mut rng);
+    let ik = IssuanceValidatingKey::from(&isk);
+    let recipient =
+        FullViewingKey::from(&SpendingKey::random(&mut rng)).address_at(0u32, Scope::External);
+    let asset_desc = format!("Issued test vector asset {}", index);
+    let (bundle, _) = IssueBundle::new(
+        ik.clone(),
+        asset_desc.clone(),
+        Some(IssueInfo {
+            recipient,
+            value: NoteValue::from_raw(rng.next_u64() >> 14),
+        }),
+        &mut rng,
+    )
+    .expect("the asset description is valid");
+    let commitment = bundle.commitment();
+    let sighash: [u8; 32] = rng.bytes();
+    let signed = bundle
+        .prepare(sighash)
+        .sign(&isk)
+        .expect("the issuance key matches the bundle");
+
+    IssueBundleVector {
+        isk: hex::encode(isk.to_bytes()),
+        ik: hex::encode(ik.to_bytes()),
+        asset_desc,
+        notes: signed
+            .get_all_notes()
+            .iter()
+            .map(|note| hex::encode(note.to_storage_bytes()))
+            .collect(),
+        sighash: hex::encode(sighash),
+        commitment: hex::encode(commitment.0.as_bytes()),
+        auth_commitment: hex::encode(signed.authorizing_commitment().0.as_bytes()),
+        signature: hex::encode(signed.authorization().signature().to_bytes()),
+    }
+}
+
+/// The authorization of the bundles of the bundle digest vectors, which do not commit to
+/// any authorizing data.
+#[derive(Debug)]
+struct Unauthorized;
+
+impl Authorization for Unauthorized {
+    type SpendAuth = ();
+}
+
+/// Generates the bundle digest vector with the given index, made of a native and a ZSA
+/// action.
+fn bundle_digest_vector(seed: &[u8], index: usize) -> BundleDigestVector {
+    let mut rng = VectorRng::new(seed, "bundle_digest", index);
+
+    let (vectors, actions): (Vec<_>, Vec<_>) = (0..2)
+        .map(|i| note_encryption_vector(&rng.bytes::<32>(), i))
+        .unzip();
+    let nullifiers = actions
+        .iter()
+        .map(|action| hex::encode(action.nullifier().to_bytes()))
+        .collect();
+    let rks = actions
+        .iter()
+        .map(|action| hex::encode(<[u8; 32]>::from(action.rk())))
+        .collect();
+    let value_balance = -vectors
+        .iter()
+        .map(|vector| i64::try_from(vector.value).unwrap())
+        .sum::<i64>();
+    let anchor = Anchor::from(pallas::Base::from_uniform_bytes(&rng.bytes()));
+
+    let bundle = Bundle::from_parts(
+        NonEmpty::from_vec(actions).expect("there are two actions"),
+        Flags::ENABLED_WITH_ZSA,
+        value_balance,
+        vec![],
+        anchor,
+        Unauthorized,
+    );
+
+    BundleDigestVector {
+        actions: vectors,
+        nullifiers,
+        rks,
+        flags: bundle.flags().to_byte(),
+        value_balance,
+        anchor: hex::encode(anchor.to_bytes()),
+        commitment: hex::encode(bundle.commitment().0.as_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate, NoteFlavor};
+
+    #[test]
+    fn generation_is_deterministic() {
+        let vectors = generate(b"seed", 2);
+        assert_eq!(vectors, generate(b"seed", 2));
+        assert_ne!(vectors, generate(b"other seed", 2));
+
+        assert_eq!(vectors.note_encryption[0].flavor, NoteFlavor::Native);
+        assert_eq!(vectors.note_encryption[1].flavor, NoteFlavor::Zsa);
+        assert_eq!(vectors.bundle_digest[0].actions.len(), 2);
+    }
+
+    #[test]
+    fn asset_base_vectors_are_stable() {
+        // Computed independently from the seed expansion, BIP 340 key derivation and
+        // asset digest definitions.
+        let vectors = generate(b"seed", 2).asset_base;
+        let expected = [
+            (
+                "910cee5d6d6dd8abf84e25d2cced45224243c5038ef5d7865eee273dd38f4862",
+                "Test vector asset 0",
+                "5a7b93642d40674c26c103a41974bb1b4f1ef0583a0bf206a4d488723cc3331b\
+                 068fd22eecea9aba4be498415770748b91f83362833aaf6436931bb1b7270300",
+            ),
+            (
+                "c72ac7e8d343dc5142291dbd5a3167d08dbee870c4da2f0e41244cd5cd2702ee",
+                "Test vector asset 1",
+                "32fe3f2dd1b71fdeeff0db8517e7cb6033d551db21c605e8eab952d370291a7f\
+                 6ceaef9244467bd7490ac20b67fae354c96f624b8fb3764d8f6f21d608feb85f",
+            ),
+        ];
+
+        for (vector, (ik, asset_desc, asset_digest)) in vectors.iter().zip(expected) {
+            assert_eq!(vector.ik, ik);
+            assert_eq!(vector.asset_desc, asset_desc);
+            assert_eq!(
+                vector.asset_id,
+                format!("00{}{}", ik, hex::encode(asset_desc))
+            );
+            assert_eq!(vector.asset_digest, asset_digest);
+        }
+    }
+}
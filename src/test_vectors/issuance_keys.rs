@@ -0,0 +1,77 @@
+// Derived from the seed [0x00, 0x01, ..., 0x1f] along the path m / 227' / coin_type' / account',
+// using an independent implementation of the derivation.
+
+pub(crate) struct TestVector {
+    pub(crate) coin_type: u32,
+    pub(crate) account: u32,
+    pub(crate) isk: [u8; 32],
+    pub(crate) ik: [u8; 32],
+    pub(crate) ik_fingerprint: [u8; 32],
+}
+
+pub(crate) const SEED: [u8; 32] = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+    0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+];
+
+pub(crate) fn test_vectors() -> Vec<TestVector> {
+    vec![
+        TestVector {
+            coin_type: 133,
+            account: 0,
+            isk: [
+                0xe1, 0x84, 0x39, 0x0f, 0x92, 0x85, 0x50, 0xf4, 0xf9, 0xf2, 0xc3, 0x67, 0xb0, 0x56,
+                0xa6, 0x42, 0x29, 0xb8, 0x37, 0xdb, 0x3d, 0xbe, 0x0a, 0xb5, 0x87, 0xa1, 0xa8, 0xcf,
+                0x3e, 0xcf, 0x61, 0xd2,
+            ],
+            ik: [
+                0x59, 0xc5, 0xa9, 0x92, 0x30, 0x52, 0x9a, 0x52, 0xf4, 0x9d, 0x72, 0x68, 0x37, 0x1e,
+                0x67, 0x69, 0x4f, 0xe9, 0x83, 0xe5, 0x6b, 0x28, 0x29, 0xa0, 0x45, 0xcc, 0x0b, 0x70,
+                0x25, 0x8c, 0xc8, 0x92,
+            ],
+            ik_fingerprint: [
+                0x71, 0xc5, 0x00, 0x3b, 0xb5, 0xde, 0xa7, 0xe9, 0x5f, 0x95, 0x10, 0x17, 0xae, 0xac,
+                0x3e, 0x96, 0x8b, 0x3c, 0x7f, 0xae, 0xfc, 0xf0, 0x2e, 0x6d, 0xdc, 0x3c, 0x73, 0x8c,
+                0x01, 0xb7, 0x9c, 0x15,
+            ],
+        },
+        TestVector {
+            coin_type: 133,
+            account: 1,
+            isk: [
+                0x2f, 0xc5, 0x80, 0x30, 0xcd, 0xf1, 0x7e, 0x9e, 0x99, 0x79, 0xc6, 0xeb, 0x99, 0x7f,
+                0xe0, 0x74, 0x37, 0x79, 0x7b, 0x8d, 0x59, 0x23, 0x22, 0x9f, 0xa5, 0xe2, 0xae, 0xee,
+                0x69, 0x9c, 0xe7, 0x0b,
+            ],
+            ik: [
+                0x31, 0x0b, 0x62, 0x0f, 0xaf, 0x57, 0x8e, 0xdd, 0x5d, 0xd7, 0xe2, 0x85, 0xd1, 0xf9,
+                0xad, 0xb2, 0x86, 0xfd, 0x13, 0xf9, 0x7e, 0xa9, 0x98, 0xf8, 0x17, 0x0b, 0x29, 0xc6,
+                0x75, 0xf7, 0xc5, 0x52,
+            ],
+            ik_fingerprint: [
+                0xbf, 0xf3, 0x83, 0x90, 0xb8, 0x84, 0xf7, 0xe1, 0x71, 0xaa, 0x4c, 0x59, 0xd6, 0xf3,
+                0x41, 0x51, 0x94, 0x8c, 0xaa, 0x88, 0x59, 0x92, 0x30, 0x30, 0xc0, 0x46, 0x45, 0x8a,
+                0x99, 0x5d, 0x7a, 0xf2,
+            ],
+        },
+        TestVector {
+            coin_type: 1,
+            account: 0,
+            isk: [
+                0x92, 0xc7, 0x80, 0x36, 0xe1, 0xf9, 0x7c, 0x37, 0x19, 0x69, 0x5b, 0xd8, 0xc4, 0x80,
+                0x39, 0x5c, 0x18, 0xa1, 0x8e, 0x02, 0xc8, 0xd0, 0x79, 0xa4, 0x5e, 0x38, 0xf2, 0x46,
+                0x0e, 0xb1, 0x63, 0x3c,
+            ],
+            ik: [
+                0x1a, 0xde, 0x58, 0x6c, 0xe8, 0xf0, 0xa4, 0xbf, 0x84, 0xf9, 0xcd, 0xbc, 0xb1, 0xf2,
+                0x09, 0xf9, 0xad, 0xc6, 0x46, 0xd8, 0xe3, 0x8a, 0xe7, 0x85, 0xbe, 0x85, 0x08, 0xcf,
+                0x0d, 0xc9, 0x5c, 0x84,
+            ],
+            ik_fingerprint: [
+                0x64, 0x07, 0xfe, 0x0a, 0x01, 0xa1, 0xc5, 0x81, 0x31, 0x74, 0x9b, 0xa2, 0x15, 0x8b,
+                0x48, 0xed, 0x5c, 0xc0, 0x43, 0xe6, 0x42, 0x5d, 0xbf, 0x8c, 0x61, 0xe7, 0xaa, 0x34,
+                0x4c, 0x8a, 0xd2, 0xf6,
+            ],
+        },
+    ]
+}
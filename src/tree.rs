@@ -22,6 +22,9 @@ use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
 use subtle::{Choice, ConditionallySelectable, CtOption};
 
+mod witness;
+pub use witness::{WitnessSource, WitnessTracker, WitnessTrackerError};
+
 // The uncommitted leaf is defined as pallas::Base(2).
 // <https://zips.z.cash/protocol/protocol.pdf#thmuncommittedorchard>
 lazy_static! {
@@ -64,7 +67,21 @@ impl Anchor {
     /// may only be used for coinbase bundles or in circumstances where Orchard
     /// functionality is not active.
     pub fn empty_tree() -> Anchor {
-        Anchor(MerkleHashOrchard::empty_root(Level::from(MERKLE_DEPTH_ORCHARD as u8)).0)
+        Self::empty_tree_of_depth(MERKLE_DEPTH_ORCHARD)
+    }
+
+    /// The anchor of the empty note commitment tree of the given depth.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `depth` is greater than [`MERKLE_DEPTH_ORCHARD`].
+    pub fn empty_tree_of_depth(depth: usize) -> Anchor {
+        assert!(
+            depth <= MERKLE_DEPTH_ORCHARD,
+            "trees can be at most {} levels deep",
+            MERKLE_DEPTH_ORCHARD
+        );
+        Anchor(MerkleHashOrchard::empty_root(Level::from(depth as u8)).0)
     }
 
     pub(crate) fn inner(&self) -> pallas::Base {
@@ -84,21 +101,25 @@ impl Anchor {
 
 /// The Merkle path from a leaf of the note commitment tree
 /// to its anchor.
+///
+/// The depth of the tree defaults to the consensus depth [`MERKLE_DEPTH_ORCHARD`], which
+/// is the only depth supported by the Action circuit. Paths in shallower trees, such as
+/// those of tests or private deployments, can be used to compute and check anchors. As
+/// leaf positions are 32-bit, trees can be at most [`MERKLE_DEPTH_ORCHARD`] levels deep.
 #[derive(Debug, Clone)]
-pub struct MerklePath {
+pub struct MerklePath<const DEPTH: usize = MERKLE_DEPTH_ORCHARD> {
     position: u32,
-    auth_path: [MerkleHashOrchard; MERKLE_DEPTH_ORCHARD],
+    auth_path: [MerkleHashOrchard; DEPTH],
 }
 
 #[cfg(any(test, feature = "test-dependencies"))]
 #[cfg_attr(docsrs, doc(cfg(feature = "test-dependencies")))]
-impl From<(incrementalmerkletree::Position, Vec<MerkleHashOrchard>)> for MerklePath {
+impl<const DEPTH: usize> From<(incrementalmerkletree::Position, Vec<MerkleHashOrchard>)>
+    for MerklePath<DEPTH>
+{
     fn from(path: (incrementalmerkletree::Position, Vec<MerkleHashOrchard>)) -> Self {
         let position: u64 = path.0.into();
-        Self {
-            position: position as u32,
-            auth_path: path.1.try_into().unwrap(),
-        }
+        Self::from_parts(position as u32, path.1.try_into().unwrap())
     }
 }
 
@@ -126,9 +147,20 @@ impl MerklePath {
     pub(crate) fn new(position: u32, auth_path: [pallas::Base; MERKLE_DEPTH_ORCHARD]) -> Self {
         Self::from_parts(position, auth_path.map(MerkleHashOrchard))
     }
+}
+
+impl<const DEPTH: usize> MerklePath<DEPTH> {
+    const DEPTH_IS_SUPPORTED: () = assert!(
+        DEPTH <= MERKLE_DEPTH_ORCHARD,
+        "trees can be at most MERKLE_DEPTH_ORCHARD levels deep"
+    );
 
     /// Instantiates a new Merkle path given a leaf position and authentication path.
-    pub fn from_parts(position: u32, auth_path: [MerkleHashOrchard; MERKLE_DEPTH_ORCHARD]) -> Self {
+    ///
+    /// Only the low `DEPTH` bits of `position` are used.
+    #[allow(clippy::let_unit_value)]
+    pub fn from_parts(position: u32, auth_path: [MerkleHashOrchard; DEPTH]) -> Self {
+        let () = Self::DEPTH_IS_SUPPORTED;
         Self {
             position,
             auth_path,
@@ -159,12 +191,12 @@ impl MerklePath {
     }
 
     /// Returns the position of the leaf using this Merkle path.
-    pub(crate) fn position(&self) -> u32 {
+    pub fn position(&self) -> u32 {
         self.position
     }
 
     /// Returns the authentication path.
-    pub(crate) fn auth_path(&self) -> [MerkleHashOrchard; MERKLE_DEPTH_ORCHARD] {
+    pub(crate) fn auth_path(&self) -> [MerkleHashOrchard; DEPTH] {
         self.auth_path
     }
 }
@@ -288,7 +320,10 @@ pub mod testing {
 #[cfg(test)]
 mod tests {
     use {
-        crate::tree::{MerkleHashOrchard, EMPTY_ROOTS},
+        crate::{
+            note::ExtractedNoteCommitment,
+            tree::{Anchor, MerkleHashOrchard, MerklePath, EMPTY_ROOTS},
+        },
         bridgetree::{BridgeTree, Frontier as BridgeFrontier},
         group::ff::PrimeField,
         incrementalmerkletree::Level,
@@ -333,6 +368,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn shallow_merkle_paths() {
+        assert_eq!(Anchor::empty_tree_of_depth(4), Anchor::from(EMPTY_ROOTS[4]));
+
+        for (i, tv) in crate::test_vectors::merkle_path::test_vectors()
+            .into_iter()
+            .enumerate()
+        {
+            let root = Anchor::from_bytes(tv.root).unwrap();
+            for j in 0..=i {
+                let path = MerklePath::<4>::from_parts(
+                    j as u32,
+                    tv.paths[j].map(|v| MerkleHashOrchard::from_bytes(&v).unwrap()),
+                );
+                let cmx = ExtractedNoteCommitment::from_bytes(&tv.leaves[j]).unwrap();
+                assert_eq!(path.root(cmx), root);
+            }
+        }
+    }
+
     #[test]
     fn empty_roots_incremental() {
         use incrementalmerkletree::Hashable;
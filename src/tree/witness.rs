@@ -0,0 +1,331 @@
+//! Tracking of the witnesses of notes as the note commitment tree grows.
+
+use core::fmt;
+use std::collections::BTreeMap;
+
+use incrementalmerkletree::{Hashable, Level};
+
+use super::{Anchor, MerkleHashOrchard, MerklePath};
+use crate::{constants::MERKLE_DEPTH_ORCHARD, note::ExtractedNoteCommitment};
+
+/// An error that can occur when updating a [`WitnessTracker`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WitnessTrackerError {
+    /// The tree has no room for more leaves.
+    TreeFull,
+    /// The witness does not lead to the current root of the tracked tree.
+    StaleWitness,
+}
+
+impl fmt::Display for WitnessTrackerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessTrackerError::TreeFull => f.write_str("Note commitment tree is full"),
+            WitnessTrackerError::StaleWitness => {
+                f.write_str("Witness does not lead to the current root of the tree")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WitnessTrackerError {}
+
+/// A source of up-to-date witnesses for notes, such as a wallet's note commitment tree.
+pub trait WitnessSource {
+    /// Returns the Merkle path of the leaf at `position` to the given anchor, or `None`
+    /// if the source cannot produce it.
+    fn witness_at(&self, position: u32, anchor: &Anchor) -> Option<MerklePath>;
+}
+
+/// The rightmost leaf of a tree, along with the roots of the complete subtrees to its
+/// left.
+#[derive(Clone, Debug)]
+struct Frontier<const DEPTH: usize> {
+    position: u32,
+    leaf: MerkleHashOrchard,
+    /// The left sibling of the path from the leaf to the root at each level where the
+    /// path is a right child. The entries at other levels are unused.
+    ommers: [MerkleHashOrchard; DEPTH],
+}
+
+impl<const DEPTH: usize> Frontier<DEPTH> {
+    /// Returns the roots of the subtrees of each height containing the leaf, with the
+    /// leaves that have not been appended yet taken to be empty. The last entry is the
+    /// root of the tree.
+    fn subtree_roots(&self) -> Vec<MerkleHashOrchard> {
+        let mut roots = Vec::with_capacity(DEPTH + 1);
+        roots.push(self.leaf);
+        for (l, ommer) in self.ommers.iter().enumerate() {
+            let level = Level::from(l as u8);
+            let node = roots[l];
+            roots.push(if self.position & (1 << l) == 0 {
+                MerkleHashOrchard::combine(level, &node, &MerkleHashOrchard::empty_root(level))
+            } else {
+                MerkleHashOrchard::combine(level, ommer, &node)
+            });
+        }
+        roots
+    }
+}
+
+/// A note commitment tree that keeps the witnesses of selected leaves current as new
+/// leaves are appended.
+///
+/// This allows wallets that do not store the full tree to keep the witnesses of their
+/// spendable notes up to date between blocks, by appending the note commitments of each
+/// block in order.
+#[derive(Clone, Debug)]
+pub struct WitnessTracker<const DEPTH: usize = MERKLE_DEPTH_ORCHARD> {
+    frontier: Option<Frontier<DEPTH>>,
+    witnesses: BTreeMap<u32, [MerkleHashOrchard; DEPTH]>,
+}
+
+impl<const DEPTH: usize> Default for WitnessTracker<DEPTH> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const DEPTH: usize> WitnessTracker<DEPTH> {
+    /// Creates a tracker for the empty tree.
+    pub fn new() -> Self {
+        WitnessTracker {
+            frontier: None,
+            witnesses: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a tracker for the tree whose last leaf is `cmx`, with the given witness of
+    /// that leaf, and starts tracking the witness.
+    pub fn from_last_leaf(cmx: ExtractedNoteCommitment, path: &MerklePath<DEPTH>) -> Self {
+        let leaf = MerkleHashOrchard::from_cmx(&cmx);
+        let position = path.position();
+        let auth_path = path.auth_path();
+
+        // The complete subtrees to the left of the leaf are exactly the left siblings of
+        // its path.
+        let frontier = Frontier {
+            position,
+            leaf,
+            ommers: auth_path,
+        };
+
+        WitnessTracker {
+            frontier: Some(frontier),
+            witnesses: BTreeMap::from([(
+                position,
+                Self::witness_of_last_leaf(position, auth_path),
+            )]),
+        }
+    }
+
+    /// Returns the number of leaves in the tree.
+    pub fn size(&self) -> u64 {
+        self.frontier
+            .as_ref()
+            .map_or(0, |frontier| u64::from(frontier.position) + 1)
+    }
+
+    /// Returns the root of the tree.
+    pub fn root(&self) -> Anchor {
+        match &self.frontier {
+            None => Anchor::empty_tree_of_depth(DEPTH),
+            Some(frontier) => frontier.subtree_roots()[DEPTH].into(),
+        }
+    }
+
+    /// Appends a note commitment to the tree, updating the tracked witnesses, and
+    /// returns its position.
+    pub fn append(&mut self, cmx: ExtractedNoteCommitment) -> Result<u32, WitnessTrackerError> {
+        let leaf = MerkleHashOrchard::from_cmx(&cmx);
+        let frontier = match self.frontier.take() {
+            None => Frontier {
+                position: 0,
+                leaf,
+                ommers: [MerkleHashOrchard::empty_leaf(); DEPTH],
+            },
+            Some(frontier) => {
+                let position = u64::from(frontier.position) + 1;
+                if position >> DEPTH != 0 {
+                    self.frontier = Some(frontier);
+                    return Err(WitnessTrackerError::TreeFull);
+                }
+                let position = position as u32;
+
+                // The subtree that ends with the previous leaf is complete at the level
+                // of the lowest set bit of the new position.
+                let mut ommers = frontier.ommers;
+                let level = position.trailing_zeros() as usize;
+                ommers[level] = frontier.subtree_roots()[level];
+
+                Frontier {
+                    position,
+                    leaf,
+                    ommers,
+                }
+            }
+        };
+
+        // The new leaf only changes the sibling of each witness at the level where the
+        // paths of the witnessed leaf and of the new leaf meet.
+        let position = frontier.position;
+        if !self.witnesses.is_empty() {
+            let roots = frontier.subtree_roots();
+            for (witnessed, auth_path) in self.witnesses.iter_mut() {
+                let level = (31 - (witnessed ^ position).leading_zeros()) as usize;
+                auth_path[level] = roots[level];
+            }
+        }
+
+        self.frontier = Some(frontier);
+        Ok(position)
+    }
+
+    /// Starts tracking the witness of the most recently appended leaf, and returns its
+    /// position, or `None` if the tree is empty.
+    pub fn mark(&mut self) -> Option<u32> {
+        let frontier = self.frontier.as_ref()?;
+        let position = frontier.position;
+        self.witnesses.insert(
+            position,
+            Self::witness_of_last_leaf(position, frontier.ommers),
+        );
+        Some(position)
+    }
+
+    /// Starts tracking a witness of the leaf `cmx` that is current with respect to the
+    /// tracked tree.
+    pub fn track(
+        &mut self,
+        cmx: ExtractedNoteCommitment,
+        path: &MerklePath<DEPTH>,
+    ) -> Result<(), WitnessTrackerError> {
+        if u64::from(path.position()) >= self.size() || path.root(cmx) != self.root() {
+            return Err(WitnessTrackerError::StaleWitness);
+        }
+        self.witnesses.insert(path.position(), path.auth_path());
+        Ok(())
+    }
+
+    /// Stops tracking the witness of the leaf at `position`.
+    pub fn forget(&mut self, position: u32) {
+        self.witnesses.remove(&position);
+    }
+
+    /// Returns the current witness of the leaf at `position`, if it is tracked.
+    pub fn witness(&self, position: u32) -> Option<MerklePath<DEPTH>> {
+        self.witnesses
+            .get(&position)
+            .map(|auth_path| MerklePath::from_parts(position, *auth_path))
+    }
+
+    /// Returns the positions of the tracked witnesses.
+    pub fn tracked_positions(&self) -> impl Iterator<Item = u32> + '_ {
+        self.witnesses.keys().copied()
+    }
+
+    /// Returns the authentication path of the last leaf of a tree at `position`, given
+    /// the left siblings of its path.
+    fn witness_of_last_leaf(
+        position: u32,
+        mut ommers: [MerkleHashOrchard; DEPTH],
+    ) -> [MerkleHashOrchard; DEPTH] {
+        for (l, node) in ommers.iter_mut().enumerate() {
+            if position & (1 << l) == 0 {
+                *node = MerkleHashOrchard::empty_root(Level::from(l as u8));
+            }
+        }
+        ommers
+    }
+}
+
+impl WitnessSource for WitnessTracker {
+    fn witness_at(&self, position: u32, anchor: &Anchor) -> Option<MerklePath> {
+        if self.root() == *anchor {
+            self.witness(position)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bridgetree::BridgeTree;
+
+    use super::{WitnessTracker, WitnessTrackerError};
+    use crate::{
+        note::ExtractedNoteCommitment,
+        tree::{Anchor, MerkleHashOrchard},
+    };
+
+    #[test]
+    fn witnesses_match_bridgetree() {
+        let leaves = crate::test_vectors::merkle_path::test_vectors()
+            .last()
+            .unwrap()
+            .leaves;
+
+        let mut tree = BridgeTree::<MerkleHashOrchard, u32, 4>::new(100);
+        let mut tracker = WitnessTracker::<4>::new();
+        assert_eq!(tracker.root(), Anchor::empty_tree_of_depth(4));
+
+        for (i, leaf) in leaves.iter().enumerate() {
+            let cmx = ExtractedNoteCommitment::from_bytes(leaf).unwrap();
+            tree.append(MerkleHashOrchard::from_cmx(&cmx));
+            assert_eq!(tracker.append(cmx), Ok(i as u32));
+            if i % 3 == 0 {
+                tree.mark().unwrap();
+                assert_eq!(tracker.mark(), Some(i as u32));
+            }
+
+            assert_eq!(tracker.size(), i as u64 + 1);
+            assert_eq!(tracker.root(), tree.root(0).unwrap().into());
+            for position in tracker.tracked_positions() {
+                let path = tracker.witness(position).unwrap();
+                assert_eq!(
+                    tree.witness(u64::from(position).into(), 0).ok(),
+                    Some(path.auth_path().to_vec())
+                );
+            }
+        }
+
+        let cmx = ExtractedNoteCommitment::from_bytes(&leaves[0]).unwrap();
+        assert_eq!(tracker.append(cmx), Err(WitnessTrackerError::TreeFull));
+    }
+
+    #[test]
+    fn resume_from_last_leaf() {
+        let leaves = crate::test_vectors::merkle_path::test_vectors()
+            .last()
+            .unwrap()
+            .leaves
+            .map(|leaf| ExtractedNoteCommitment::from_bytes(&leaf).unwrap());
+
+        let mut tracker = WitnessTracker::<4>::new();
+        for cmx in &leaves[..5] {
+            tracker.append(*cmx).unwrap();
+        }
+        let position = tracker.mark().unwrap();
+        let path = tracker.witness(position).unwrap();
+
+        let mut resumed = WitnessTracker::from_last_leaf(leaves[4], &path);
+        assert_eq!(resumed.root(), tracker.root());
+        assert_eq!(resumed.track(leaves[4], &path), Ok(()),);
+
+        for cmx in &leaves[5..] {
+            tracker.append(*cmx).unwrap();
+            resumed.append(*cmx).unwrap();
+        }
+        assert_eq!(resumed.root(), tracker.root());
+        assert_eq!(
+            resumed.witness(position).unwrap().root(leaves[4]),
+            tracker.root()
+        );
+
+        assert_eq!(
+            resumed.track(leaves[4], &path),
+            Err(WitnessTrackerError::StaleWitness)
+        );
+    }
+}
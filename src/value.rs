@@ -37,14 +37,19 @@
 //! [`Builder::add_output`]: crate::builder::Builder::add_output
 //! [Rust documentation]: https://doc.rust-lang.org/stable/std/primitive.i64.html
 
+mod format;
+pub use format::{ParseValueError, ValueFormatter, NATIVE_DECIMALS};
+
 use core::fmt::{self, Debug};
 use core::iter::Sum;
 use core::ops::{Add, RangeInclusive, Sub};
+use std::collections::HashMap;
 use std::ops::Neg;
 
 use bitvec::{array::BitArray, order::Lsb0};
 use ff::{Field, PrimeField};
 use group::{Curve, Group, GroupEncoding};
+#[cfg(feature = "circuit")]
 use halo2_proofs::plonk::Assigned;
 use pasta_curves::{
     arithmetic::{CurveAffine, CurveExt},
@@ -58,6 +63,7 @@ use crate::{
     primitives::redpallas::{self, Binding},
 };
 
+#[cfg(feature = "circuit")]
 use crate::builder::BuildError;
 use crate::note::AssetBase;
 
@@ -125,6 +131,7 @@ impl NoteValue {
     }
 }
 
+#[cfg(feature = "circuit")]
 impl From<&NoteValue> for Assigned<pallas::Base> {
     fn from(v: &NoteValue) -> Self {
         pallas::Base::from(v.inner()).into()
@@ -190,10 +197,11 @@ impl ValueSum {
         )
     }
 
+    #[cfg(feature = "circuit")]
     pub(crate) fn into<V: TryFrom<i64>>(self) -> Result<V, BuildError> {
         i64::try_from(self)
-            .map_err(BuildError::ValueSum)
-            .and_then(|i| V::try_from(i).map_err(|_| BuildError::ValueSum(OverflowError)))
+            .map_err(BuildError::from)
+            .and_then(|i| V::try_from(i).map_err(|_| BuildError::from(OverflowError)))
     }
 }
 
@@ -248,7 +256,7 @@ impl From<ValueSum> for i128 {
 }
 
 /// The blinding factor for a [`ValueCommitment`].
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct ValueCommitTrapdoor(pallas::Scalar);
 
 impl ValueCommitTrapdoor {
@@ -339,6 +347,12 @@ impl<'a> Sum<&'a ValueCommitment> for ValueCommitment {
 }
 
 impl ValueCommitment {
+    /// Returns the sum of the given value commitments, which commits to the sum of their
+    /// values under the sum of their trapdoors.
+    pub fn sum<'a>(cvs: impl IntoIterator<Item = &'a ValueCommitment>) -> Self {
+        cvs.into_iter().sum()
+    }
+
     /// Derives a `ValueCommitment` by $\mathsf{ValueCommit^{Orchard}}$.
     ///
     /// Defined in [Zcash Protocol Spec § 5.4.8.3: Homomorphic Pedersen commitments (Sapling and Orchard)][concretehomomorphiccommit].
@@ -348,6 +362,40 @@ impl ValueCommitment {
     pub fn derive(value: ValueSum, rcv: ValueCommitTrapdoor, asset: AssetBase) -> Self {
         let hasher = pallas::Point::hash_to_curve(VALUE_COMMITMENT_PERSONALIZATION);
         let R = hasher(&VALUE_COMMITMENT_R_BYTES);
+
+        Self::commit(asset.cv_base(), R, value, rcv)
+    }
+
+    /// Derives the `ValueCommitment`s of many `(value, rcv, asset)` openings at once.
+    ///
+    /// This is equivalent to calling [`ValueCommitment::derive`] on each opening, but
+    /// derives the generators of the commitments (which requires hashing to the curve)
+    /// only once for the whole batch, and once per distinct asset, instead of once per
+    /// commitment.
+    #[allow(non_snake_case)]
+    pub fn derive_batch(
+        openings: impl IntoIterator<Item = (ValueSum, ValueCommitTrapdoor, AssetBase)>,
+    ) -> Vec<Self> {
+        let hasher = pallas::Point::hash_to_curve(VALUE_COMMITMENT_PERSONALIZATION);
+        let R = hasher(&VALUE_COMMITMENT_R_BYTES);
+        let mut bases = HashMap::new();
+
+        openings
+            .into_iter()
+            .map(|(value, rcv, asset)| {
+                let V_zsa = *bases.entry(asset).or_insert_with(|| asset.cv_base());
+                Self::commit(V_zsa, R, value, rcv)
+            })
+            .collect()
+    }
+
+    #[allow(non_snake_case)]
+    fn commit(
+        V_zsa: pallas::Point,
+        R: pallas::Point,
+        value: ValueSum,
+        rcv: ValueCommitTrapdoor,
+    ) -> Self {
         let abs_value = u64::try_from(value.0.abs()).expect("value must be in valid range");
 
         let value = if value.0.is_negative() {
@@ -356,8 +404,6 @@ impl ValueCommitment {
             pallas::Scalar::from(abs_value)
         };
 
-        let V_zsa = asset.cv_base();
-
         ValueCommitment(V_zsa * value + R * rcv.0)
     }
 
@@ -469,7 +515,7 @@ mod tests {
 
     use super::{
         testing::{arb_note_value_bounded, arb_trapdoor, arb_value_sum_bounded},
-        OverflowError, ValueCommitTrapdoor, ValueCommitment, ValueSum, MAX_NOTE_VALUE,
+        NoteValue, OverflowError, ValueCommitTrapdoor, ValueCommitment, ValueSum, MAX_NOTE_VALUE,
     };
     use crate::primitives::redpallas;
 
@@ -550,5 +596,43 @@ mod tests {
             check_binding_signature(&native_values, &asset_values, &neg_trapdoors, &[]);
             check_binding_signature(&native_values, &asset_values, &neg_trapdoors, &burn_values);
         }
+
+        #[test]
+        fn value_commitment_sum_is_homomorphic(
+            (asset, values) in arb_asset_base().prop_flat_map(|asset|
+                (Just(asset), prop::collection::vec((arb_value_sum_bounded(NoteValue::from_raw(MAX_NOTE_VALUE / 10)), arb_trapdoor()), 0..10))
+            )
+        ) {
+            let cvs: Vec<_> = values
+                .iter()
+                .map(|(value, rcv)| ValueCommitment::derive(*value, rcv.clone(), asset))
+                .collect();
+            let total = values
+                .iter()
+                .map(|(value, _)| value)
+                .sum::<Result<ValueSum, OverflowError>>()
+                .unwrap();
+            let rcv = values.iter().map(|(_, rcv)| rcv).sum::<ValueCommitTrapdoor>();
+
+            prop_assert_eq!(
+                ValueCommitment::sum(&cvs).to_bytes(),
+                ValueCommitment::derive(total, rcv, asset).to_bytes()
+            );
+        }
+
+        #[test]
+        fn derive_batch_matches_derive(
+            openings in prop::collection::vec(
+                (arb_value_sum_bounded(NoteValue::from_raw(MAX_NOTE_VALUE)), arb_trapdoor(), arb_asset_base()),
+                0..10,
+            )
+        ) {
+            let batch = ValueCommitment::derive_batch(openings.clone());
+
+            prop_assert_eq!(batch.len(), openings.len());
+            for (cv, (value, rcv, asset)) in batch.iter().zip(openings) {
+                prop_assert_eq!(cv.to_bytes(), ValueCommitment::derive(value, rcv, asset).to_bytes());
+            }
+        }
     }
 }
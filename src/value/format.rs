@@ -0,0 +1,186 @@
+//! Formatting and parsing of note values in units of their assets.
+
+use core::fmt;
+use std::collections::HashMap;
+
+use super::NoteValue;
+use crate::note::AssetBase;
+
+/// The number of decimal places of the native asset, whose smallest unit is the zatoshi.
+pub const NATIVE_DECIMALS: u8 = 8;
+
+/// Formats and parses [`NoteValue`]s as decimal amounts of their assets.
+///
+/// A note value is an integer number of the smallest unit of its asset. The formatter
+/// holds the number of decimal places of each asset, so that for example a native value
+/// of `150_000_000` is formatted as `1.5`. The native asset has [`NATIVE_DECIMALS`]
+/// decimal places, and assets without an entry in the table have none.
+#[derive(Clone, Debug)]
+pub struct ValueFormatter {
+    decimals: HashMap<AssetBase, u8>,
+}
+
+impl Default for ValueFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ValueFormatter {
+    /// Constructs a formatter that only knows the decimals of the native asset.
+    pub fn new() -> Self {
+        let mut decimals = HashMap::new();
+        decimals.insert(AssetBase::native(), NATIVE_DECIMALS);
+        ValueFormatter { decimals }
+    }
+
+    /// Sets the number of decimal places of `asset`.
+    pub fn set_decimals(&mut self, asset: AssetBase, decimals: u8) -> &mut Self {
+        self.decimals.insert(asset, decimals);
+        self
+    }
+
+    /// Returns the number of decimal places of `asset`.
+    pub fn decimals(&self, asset: &AssetBase) -> u8 {
+        self.decimals.get(asset).copied().unwrap_or(0)
+    }
+
+    /// Formats `value` as a decimal amount of `asset`.
+    ///
+    /// Trailing zeroes of the fractional part are omitted, along with the decimal point
+    /// if the amount is whole.
+    pub fn format(&self, value: NoteValue, asset: &AssetBase) -> String {
+        let decimals = usize::from(self.decimals(asset));
+        let digits = format!("{:0>width$}", value.inner(), width = decimals + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - decimals);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            whole.to_string()
+        } else {
+            format!("{}.{}", whole, fraction)
+        }
+    }
+
+    /// Parses a decimal amount of `asset` into a note value.
+    ///
+    /// The amount must consist of digits, optionally followed by a decimal point and at
+    /// most as many digits as `asset` has decimal places.
+    pub fn parse(&self, amount: &str, asset: &AssetBase) -> Result<NoteValue, ParseValueError> {
+        let decimals = usize::from(self.decimals(asset));
+        let (whole, fraction) = match amount.split_once('.') {
+            Some((whole, fraction)) if !fraction.is_empty() => (whole, fraction),
+            Some(_) => return Err(ParseValueError::InvalidFormat),
+            None => (amount, ""),
+        };
+        if whole.is_empty()
+            || !whole
+                .bytes()
+                .chain(fraction.bytes())
+                .all(|b| b.is_ascii_digit())
+        {
+            return Err(ParseValueError::InvalidFormat);
+        }
+        if fraction.len() > decimals {
+            return Err(ParseValueError::TooManyDecimals);
+        }
+
+        let padding = core::iter::repeat(0).take(decimals - fraction.len());
+        whole
+            .bytes()
+            .chain(fraction.bytes())
+            .map(|b| u64::from(b - b'0'))
+            .chain(padding)
+            .try_fold(0u64, |value, digit| {
+                value.checked_mul(10)?.checked_add(digit)
+            })
+            .map(NoteValue::from_raw)
+            .ok_or(ParseValueError::Overflow)
+    }
+}
+
+/// An error returned when parsing an amount with a [`ValueFormatter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseValueError {
+    /// The amount is not a decimal number.
+    InvalidFormat,
+    /// The amount has more decimal places than its asset.
+    TooManyDecimals,
+    /// The amount does not fit in a note value.
+    Overflow,
+}
+
+impl fmt::Display for ParseValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseValueError::InvalidFormat => f.write_str("The amount is not a decimal number"),
+            ParseValueError::TooManyDecimals => {
+                f.write_str("The amount has more decimal places than its asset")
+            }
+            ParseValueError::Overflow => f.write_str("The amount does not fit in a note value"),
+        }
+    }
+}
+
+impl std::error::Error for ParseValueError {}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{ParseValueError, ValueFormatter};
+    use crate::{
+        note::AssetBase,
+        value::{testing::arb_note_value, NoteValue},
+    };
+
+    #[test]
+    fn native_values() {
+        let formatter = ValueFormatter::new();
+        let native = AssetBase::native();
+
+        assert_eq!(
+            formatter.format(NoteValue::from_raw(150_000_000), &native),
+            "1.5"
+        );
+        assert_eq!(
+            formatter.format(NoteValue::from_raw(1), &native),
+            "0.00000001"
+        );
+        assert_eq!(formatter.format(NoteValue::from_raw(0), &native), "0");
+        assert_eq!(
+            formatter.parse("1.5", &native),
+            Ok(NoteValue::from_raw(150_000_000))
+        );
+        assert_eq!(
+            formatter.parse("2", &native),
+            Ok(NoteValue::from_raw(200_000_000))
+        );
+
+        for amount in ["", ".5", "1.", "1,5", "-1", "1.5e3"] {
+            assert_eq!(
+                formatter.parse(amount, &native),
+                Err(ParseValueError::InvalidFormat)
+            );
+        }
+        assert_eq!(
+            formatter.parse("0.000000001", &native),
+            Err(ParseValueError::TooManyDecimals)
+        );
+        assert_eq!(
+            formatter.parse("184467440737.09551616", &native),
+            Err(ParseValueError::Overflow)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn format_parse_round_trip(value in arb_note_value(), decimals in 0u8..30) {
+            let asset = AssetBase::random();
+            let mut formatter = ValueFormatter::new();
+            formatter.set_decimals(asset, decimals);
+
+            let amount = formatter.format(value, &asset);
+            prop_assert_eq!(formatter.parse(&amount, &asset), Ok(value));
+        }
+    }
+}
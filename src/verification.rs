@@ -0,0 +1,160 @@
+//! Consensus verification of issuance, burns and asset supply, for use by nodes.
+//!
+//! This module gathers the checks that a node applies to the issuance and burning of
+//! assets, so that they can be used without depending on the layout of the rest of the
+//! crate:
+//!
+//! - [`verify_issue_bundle`] and [`verify_issue_bundle_with_state`] check a single issue
+//!   bundle, and [`verify_issue_bundles`] checks the issue bundles of a block in order.
+//!   [`verify_issue_bundle_with_policy`] additionally checks an [`IssuancePolicy`] of the
+//!   deployment, such as the caps and allowlist of [`IssuanceRules`].
+//! - [`validate_bundle_burn`] and [`validate_burn_list`] check the burns of a bundle, and
+//!   [`burn_totals`] sums the burns of the bundles of a block.
+//! - [`SupplyInfo`] holds the supply changes of verified issue bundles, which can be
+//!   recorded with an [`AssetStateWriter`].
+
+pub use crate::bundle::burn_validation::{
+    burn_totals, validate_bundle_burn, validate_burn_list, BurnError, BurnListError, BurnTotalError,
+};
+pub use crate::issuance::{
+    verify_issue_bundle, verify_issue_bundle_with_policy, verify_issue_bundle_with_state,
+    Error as IssuanceError, IssuancePolicy, IssuanceRules, PolicyViolation,
+};
+pub use crate::supply_info::{
+    AssetStateReader, AssetStateWriter, AssetSupply, InMemoryAssetState, SupplyInfo,
+};
+
+use crate::{
+    issuance::{IssueBundle, Signed},
+    note::AssetBase,
+};
+
+/// The recorded asset state, updated with the supply changes of the issue bundles of a
+/// block that have been verified so far.
+struct PendingState<'a, S> {
+    state: &'a S,
+    pending: &'a SupplyInfo,
+}
+
+impl<S: AssetStateReader> AssetStateReader for PendingState<'_, S> {
+    fn asset_state(&self, asset: &AssetBase) -> Option<AssetSupply> {
+        match (
+            self.state.asset_state(asset),
+            self.pending.assets.get(asset),
+        ) {
+            // An overflow of the total supply is reported when the supply changes are
+            // combined, so it can be ignored here.
+            (Some(current), Some(pending)) => Some(AssetSupply::new(
+                (current.amount + pending.amount).unwrap_or(current.amount),
+                current.is_finalized | pending.is_finalized,
+            )),
+            (current, pending) => current.or_else(|| pending.copied()),
+        }
+    }
+
+    fn is_finalized(&self, asset: &AssetBase) -> bool {
+        self.state.is_finalized(asset)
+            || self
+                .pending
+                .assets
+                .get(asset)
+                .map_or(false, |supply| supply.is_finalized)
+    }
+}
+
+/// Verifies the issue bundles of a block, in order, against the asset state recorded
+/// before the block.
+///
+/// Each bundle is checked as in [`verify_issue_bundle_with_state`], taking into account
+/// the assets finalized by the preceding bundles of the block. Returns the combined
+/// supply changes of all of the bundles, which can be recorded with
+/// [`AssetStateWriter::apply_supply_info`] once the block is accepted.
+///
+/// # Errors
+///
+/// Returns the index of the first invalid bundle along with its error, or the index of
+/// the bundle at which the supply of an asset overflows.
+pub fn verify_issue_bundles<'a, S: AssetStateReader>(
+    bundles: impl IntoIterator<Item = (&'a IssueBundle<Signed>, [u8; 32])>,
+    state: &S,
+) -> Result<SupplyInfo, (usize, IssuanceError)> {
+    let mut block_supply = SupplyInfo::new();
+    for (index, (bundle, sighash)) in bundles.into_iter().enumerate() {
+        let pending = PendingState {
+            state,
+            pending: &block_supply,
+        };
+        let supply_info =
+            verify_issue_bundle_with_state(bundle, sighash, &pending).map_err(|e| (index, e))?;
+        for (asset, supply) in supply_info.assets {
+            block_supply
+                .add_supply(asset, supply)
+                .map_err(|e| (index, e))?;
+        }
+    }
+    Ok(block_supply)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::OsRng, RngCore};
+
+    use super::{verify_issue_bundles, InMemoryAssetState, IssuanceError};
+    use crate::{
+        issuance::{IssueBundle, IssueInfo},
+        keys::{FullViewingKey, IssuanceAuthorizingKey, IssuanceValidatingKey, Scope, SpendingKey},
+        note::AssetBase,
+        value::{NoteValue, ValueSum},
+    };
+
+    #[test]
+    fn assets_finalized_earlier_in_the_block() {
+        let mut rng = OsRng;
+        let isk = IssuanceAuthorizingKey::random();
+        let ik = IssuanceValidatingKey::from(&isk);
+        let recipient =
+            FullViewingKey::from(&SpendingKey::random(&mut rng)).address_at(0u32, Scope::External);
+        let mut sighash = [0u8; 32];
+        rng.fill_bytes(&mut sighash);
+
+        let issue = |value, finalize: bool| {
+            let (mut bundle, _) = IssueBundle::new(
+                ik.clone(),
+                String::from("Block asset"),
+                Some(IssueInfo {
+                    recipient,
+                    value: NoteValue::from_raw(value),
+                }),
+                OsRng,
+            )
+            .unwrap();
+            if finalize {
+                bundle.finalize_action(String::from("Block asset")).unwrap();
+            }
+            bundle.prepare(sighash).sign(&isk).unwrap()
+        };
+        let asset = AssetBase::derive(&ik, "Block asset");
+        let state = InMemoryAssetState::new();
+
+        let first = issue(3, false);
+        let second = issue(4, true);
+        let supply_info =
+            verify_issue_bundles([(&first, sighash), (&second, sighash)], &state).unwrap();
+        let supply = supply_info.assets[&asset];
+        assert_eq!(supply.amount, ValueSum::from_raw(7));
+        assert!(supply.is_finalized);
+
+        let third = issue(5, false);
+        assert_eq!(
+            verify_issue_bundles(
+                [(&first, sighash), (&second, sighash), (&third, sighash)],
+                &state
+            )
+            .unwrap_err(),
+            (
+                2,
+                IssuanceError::IssueActionPreviouslyFinalizedAssetBase(asset)
+            )
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Borrowed views of bundles in the shape of the transaction structures of Zebra.
+//!
+//! Zebra stores the Orchard part of a transaction as shielded data holding the
+//! transaction-level fields of the bundle and, for each action, its value commitment,
+//! nullifier, randomized key, note commitment, ciphertext parts and spend authorization
+//! signature. The types in this module expose an authorized [`Bundle`] in that shape
+//! without copying, and convert it back into a [`Bundle`].
+
+use nonempty::NonEmpty;
+
+use crate::{
+    action::Action,
+    bundle::{Authorized, Bundle, Flags},
+    note::{AssetBase, ExtractedNoteCommitment, Nullifier, TransmittedNoteCiphertext},
+    primitives::redpallas::{self, Binding, SpendAuth},
+    tree::Anchor,
+    value::ValueCommitment,
+    Proof,
+};
+
+/// The fields of an authorized action, borrowed from its bundle.
+#[derive(Clone, Copy, Debug)]
+pub struct ActionData<'a> {
+    /// The value commitment of the action.
+    pub cv: &'a ValueCommitment,
+    /// The nullifier of the spent note.
+    pub nullifier: &'a Nullifier,
+    /// The randomized validating key of the spend.
+    pub rk: &'a redpallas::VerificationKey<SpendAuth>,
+    /// The commitment to the output note.
+    pub cm_x: &'a ExtractedNoteCommitment,
+    /// The encoding of the ephemeral key of the output note.
+    pub ephemeral_key: &'a [u8; 32],
+    /// The encrypted output note.
+    pub enc_ciphertext: &'a [u8; 612],
+    /// The encryption of the output note for the holder of the outgoing viewing key.
+    pub out_ciphertext: &'a [u8; 80],
+    /// The spend authorization signature.
+    pub spend_auth_sig: &'a redpallas::Signature<SpendAuth>,
+}
+
+impl<'a> From<&'a Action<redpallas::Signature<SpendAuth>>> for ActionData<'a> {
+    fn from(action: &'a Action<redpallas::Signature<SpendAuth>>) -> Self {
+        let (ephemeral_key, enc_ciphertext, out_ciphertext) = action.encrypted_note().as_parts();
+        ActionData {
+            cv: action.cv_net(),
+            nullifier: action.nullifier(),
+            rk: action.rk(),
+            cm_x: action.cmx(),
+            ephemeral_key,
+            enc_ciphertext,
+            out_ciphertext,
+            spend_auth_sig: action.authorization(),
+        }
+    }
+}
+
+impl ActionData<'_> {
+    /// Constructs the action with these fields.
+    pub fn to_action(&self) -> Action<redpallas::Signature<SpendAuth>> {
+        Action::from_parts(
+            *self.nullifier,
+            self.rk.clone(),
+            *self.cm_x,
+            TransmittedNoteCiphertext {
+                epk_bytes: *self.ephemeral_key,
+                enc_ciphertext: *self.enc_ciphertext,
+                out_ciphertext: *self.out_ciphertext,
+            },
+            self.cv.clone(),
+            self.spend_auth_sig.clone(),
+        )
+    }
+}
+
+/// The fields of an authorized bundle, borrowed from it.
+#[derive(Clone, Debug)]
+pub struct ShieldedData<'a, V> {
+    /// The flags of the bundle.
+    pub flags: Flags,
+    /// The net value moved out of the shielded pool by the bundle.
+    pub value_balance: &'a V,
+    /// The assets burnt by the bundle.
+    pub burn: &'a [(AssetBase, V)],
+    /// The anchor shared by all of the spends of the bundle.
+    pub shared_anchor: &'a Anchor,
+    /// The proof of the bundle.
+    pub proof: &'a Proof,
+    /// The actions of the bundle.
+    pub actions: NonEmpty<ActionData<'a>>,
+    /// The binding signature of the bundle.
+    pub binding_sig: &'a redpallas::Signature<Binding>,
+}
+
+impl<'a, V> From<&'a Bundle<Authorized, V>> for ShieldedData<'a, V> {
+    fn from(bundle: &'a Bundle<Authorized, V>) -> Self {
+        ShieldedData {
+            flags: *bundle.flags(),
+            value_balance: bundle.value_balance(),
+            burn: bundle.burn(),
+            shared_anchor: bundle.anchor(),
+            proof: bundle.authorization().proof(),
+            actions: NonEmpty::from_vec(bundle.actions().iter().map(ActionData::from).collect())
+                .expect("bundles have at least one action"),
+            binding_sig: bundle.authorization().binding_signature(),
+        }
+    }
+}
+
+impl<V: Clone> ShieldedData<'_, V> {
+    /// Constructs the bundle with these fields.
+    pub fn to_bundle(&self) -> Bundle<Authorized, V> {
+        Bundle::from_parts(
+            NonEmpty::from_vec(self.actions.iter().map(ActionData::to_action).collect())
+                .expect("shielded data has at least one action"),
+            self.flags,
+            self.value_balance.clone(),
+            self.burn.to_vec(),
+            *self.shared_anchor,
+            Authorized::from_parts(self.proof.clone(), self.binding_sig.clone()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::ShieldedData;
+    use crate::bundle::testing::arb_bundle_i64;
+
+    proptest! {
+        #[test]
+        fn shielded_data_round_trip(bundle in arb_bundle_i64(3)) {
+            let shielded_data = ShieldedData::from(&bundle);
+            prop_assert_eq!(shielded_data.actions.len(), bundle.actions().len());
+
+            let converted = shielded_data.to_bundle();
+            prop_assert_eq!(
+                converted.commitment().0.as_bytes(),
+                bundle.commitment().0.as_bytes()
+            );
+            prop_assert_eq!(
+                converted.authorizing_commitment().0.as_bytes(),
+                bundle.authorizing_commitment().0.as_bytes()
+            );
+        }
+    }
+}
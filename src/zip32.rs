@@ -7,13 +7,14 @@ use subtle::{Choice, ConstantTimeEq, CtOption};
 use zip32::ChainCode;
 
 use crate::{
-    keys::{FullViewingKey, SpendingKey},
+    keys::{FullViewingKey, IssuanceValidatingKey, SpendingKey},
     spec::PrfExpand,
 };
 
 pub use zip32::ChildIndex;
 
 const ZIP32_ORCHARD_FVFP_PERSONALIZATION: &[u8; 16] = b"ZcashOrchardFVFP";
+const ZIP32_ISSUANCE_KEY_FP_PERSONALIZATION: &[u8; 16] = b"ZSAIssuanceKeyFP";
 /// Personalization for the master extended spending key
 pub const ZIP32_ORCHARD_PERSONALIZATION: &[u8; 16] = b"ZcashIP32Orchard";
 /// Personalization for the master extended issuance key
@@ -70,6 +71,42 @@ impl FvkTag {
     }
 }
 
+/// A fingerprint of an issuance validating key, which identifies the key an issuer
+/// derived from their seed without revealing the key itself.
+///
+/// The fingerprint is the BLAKE2b-256 hash of the encoding of the key, personalized with
+/// `"ZSAIssuanceKeyFP"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IssuanceKeyFingerprint([u8; 32]);
+
+impl From<&IssuanceValidatingKey> for IssuanceKeyFingerprint {
+    fn from(ik: &IssuanceValidatingKey) -> Self {
+        let mut h = Blake2bParams::new()
+            .hash_length(32)
+            .personal(ZIP32_ISSUANCE_KEY_FP_PERSONALIZATION)
+            .to_state();
+        h.update(&ik.to_bytes());
+        let mut fp = [0u8; 32];
+        fp.copy_from_slice(h.finalize().as_bytes());
+        IssuanceKeyFingerprint(fp)
+    }
+}
+
+impl IssuanceKeyFingerprint {
+    /// Returns the raw bytes of this fingerprint.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Returns the first four bytes of this fingerprint, which are short enough to be
+    /// displayed to users.
+    pub fn tag(&self) -> [u8; 4] {
+        let mut tag = [0u8; 4];
+        tag.copy_from_slice(&self.0[..4]);
+        tag
+    }
+}
+
 /// The derivation index associated with a key.
 ///
 /// Master keys are never derived via the ZIP 32 child derivation process, but they have
@@ -245,6 +282,76 @@ impl ExtendedSpendingKey {
     }
 }
 
+/// A hardened-only extended key, used to derive issuance keys.
+///
+/// The derivation follows [ZIP32: Orchard child key derivation][orchardchildkey], except
+/// that intermediate keys are not required to be valid Orchard spending keys: only the
+/// final key is validated, by the consumer of [`HardenedOnlyKey::sk_bytes`].
+///
+/// [orchardchildkey]: https://zips.z.cash/zip-0032#orchard-child-key-derivation
+#[derive(Clone)]
+pub(crate) struct HardenedOnlyKey {
+    sk: [u8; 32],
+    chain_code: ChainCode,
+}
+
+impl HardenedOnlyKey {
+    /// Derives the key at the given path from the master key generated from `seed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the seed is shorter than 32 bytes or longer than 252 bytes.
+    pub(crate) fn from_path(seed: &[u8], path: &[ChildIndex], personalization: &[u8; 16]) -> Self {
+        path.iter()
+            .fold(Self::master(seed, personalization), |xsk, i| {
+                xsk.derive_child(*i)
+            })
+    }
+
+    fn master(seed: &[u8], personalization: &[u8; 16]) -> Self {
+        assert!(seed.len() >= 32 && seed.len() <= 252);
+        // I := BLAKE2b-512(personalization, seed)
+        let I = Blake2bParams::new()
+            .hash_length(64)
+            .personal(personalization)
+            .to_state()
+            .update(seed)
+            .finalize();
+        Self::from_I(I.as_bytes())
+    }
+
+    fn derive_child(&self, index: ChildIndex) -> Self {
+        // I := PRF^Expand(c_par, [0x81] || sk_par || I2LEOSP(i))
+        let I: [u8; 64] = PrfExpand::ORCHARD_ZIP32_CHILD.with(
+            self.chain_code.as_bytes(),
+            &self.sk,
+            &index.index().to_le_bytes(),
+        );
+        Self::from_I(&I)
+    }
+
+    #[allow(non_snake_case)]
+    fn from_I(I: &[u8]) -> Self {
+        // I_L is used as the key, and I_R as the chain code.
+        HardenedOnlyKey {
+            sk: I[..32].try_into().unwrap(),
+            chain_code: ChainCode::new(I[32..].try_into().unwrap()),
+        }
+    }
+
+    /// Returns the raw bytes of this key.
+    pub(crate) fn sk_bytes(&self) -> [u8; 32] {
+        self.sk
+    }
+}
+
+impl fmt::Debug for HardenedOnlyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Do not leak the key material.
+        f.debug_struct("HardenedOnlyKey").finish_non_exhaustive()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
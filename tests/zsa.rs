@@ -3,7 +3,7 @@ mod builder;
 use crate::builder::verify_bundle;
 use bridgetree::BridgeTree;
 use incrementalmerkletree::Hashable;
-use orchard::bundle::Authorized;
+use orchard::bundle::{Authorized, BurnReceipt, BurnReceiptError};
 use orchard::issuance::{verify_issue_bundle, IssueBundle, IssueInfo, Signed, Unauthorized};
 use orchard::keys::{IssuanceAuthorizingKey, IssuanceValidatingKey};
 use orchard::note::{AssetBase, ExtractedNoteCommitment};
@@ -254,7 +254,8 @@ fn build_and_verify_bundle(
             .map_err(|err| err.to_string())?;
         assets_to_burn
             .into_iter()
-            .try_for_each(|(asset, value)| builder.add_burn(asset, value))?;
+            .try_for_each(|(asset, value)| builder.add_burn(asset, value))
+            .map_err(|err| err.to_string())?;
         build_and_sign_bundle(builder, rng, keys.pk(), keys.sk())
     };
 
@@ -555,3 +556,54 @@ fn zsa_issue_and_transfer() {
         Err(error) => assert_eq!(error, "Burning is not possible for zero values"),
     }
 }
+
+/// Burn a ZSA asset and check the receipt of the burn against the bundle
+#[test]
+fn zsa_burn_receipts() {
+    let keys = prepare_keys();
+    let (zsa_note_1, zsa_note_2) = issue_zsa_notes("zsa_asset", &keys);
+    let (merkle_path1, _, anchor) = build_merkle_path_with_two_leaves(&zsa_note_1, &zsa_note_2);
+    let asset = zsa_note_1.asset();
+
+    let mut builder = Builder::new(BundleType::DEFAULT_ZSA, anchor);
+    builder
+        .add_spend(keys.fvk().clone(), zsa_note_1, merkle_path1)
+        .unwrap();
+    builder
+        .add_output(
+            None,
+            keys.recipient,
+            NoteValue::from_raw(zsa_note_1.value().inner() - 3),
+            asset,
+            None,
+        )
+        .unwrap();
+    builder.add_burn(asset, NoteValue::from_raw(3)).unwrap();
+    let bundle = build_and_sign_bundle(builder, OsRng, keys.pk(), keys.sk());
+    let sighash: [u8; 32] = bundle.commitment().into();
+
+    let receipts = bundle.burn_receipts(&sighash);
+    assert_eq!(receipts.len(), 1);
+    let receipt = receipts[0];
+    assert_eq!(receipt.asset(), asset);
+    assert_eq!(receipt.amount(), NoteValue::from_raw(3));
+    assert_eq!(receipt.verify(&bundle), Ok(()));
+    assert_eq!(BurnReceipt::from_bytes(&receipt.to_bytes()), Some(receipt));
+
+    // A receipt for another transaction is rejected.
+    let other = BurnReceipt::new(&bundle, &asset, &[0; 32]).unwrap();
+    assert_eq!(
+        other.verify(&bundle),
+        Err(BurnReceiptError::InvalidBindingSignature)
+    );
+
+    // A receipt for another amount is rejected.
+    let mut bytes = receipt.to_bytes();
+    bytes[32..40].copy_from_slice(&4u64.to_le_bytes());
+    assert_eq!(
+        BurnReceipt::from_bytes(&bytes).unwrap().verify(&bundle),
+        Err(BurnReceiptError::BurnNotFound)
+    );
+
+    assert!(BurnReceipt::new(&bundle, &AssetBase::native(), &sighash).is_none());
+}